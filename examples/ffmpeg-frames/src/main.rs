@@ -0,0 +1,33 @@
+use anyhow::Result;
+use onvif_cam_rs::builder::camera::CameraBuilder;
+use onvif_cam_rs::client;
+use onvif_cam_rs::device::camera::Camera;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+
+    let mut devices = client::discover().await?;
+    let mut camera = Camera::new(devices.remove(0));
+    camera.build_all().await?;
+
+    // Decoding blocks, so it runs outside of the async runtime threads
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        let mut reader = camera.open_ffmpeg(true)?;
+
+        for _ in 0..100 {
+            match reader.next_frame()? {
+                Some(frame) => println!(
+                    "{}x{} RGB frame, pts {:?}",
+                    frame.width, frame.height, frame.pts
+                ),
+                None => break,
+            }
+        }
+
+        Ok(())
+    })
+    .await??;
+
+    Ok(())
+}
@@ -0,0 +1,36 @@
+use anyhow::Result;
+use onvif_cam_rs::builder::camera::CameraBuilder;
+use onvif_cam_rs::client;
+use onvif_cam_rs::device::camera::Camera;
+use onvif_cam_rs::rtsp::stream::Frame;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+
+    let mut devices = client::discover().await?;
+    let mut camera = Camera::new(devices.remove(0));
+    camera.build_all().await?;
+
+    // No FFmpeg or OpenCV needed, frames come straight from the built-in RTSP client
+    let mut stream = camera.open_stream(None).await?;
+
+    for _ in 0..100 {
+        match stream.next_frame().await? {
+            Some(Frame::Video(video)) => println!(
+                "{:?} access unit: {} bytes, keyframe: {}",
+                video.codec,
+                video.data.len(),
+                video.keyframe
+            ),
+            Some(Frame::Metadata(metadata)) => {
+                println!("Metadata: {}", String::from_utf8_lossy(&metadata.data))
+            }
+            None => break,
+        }
+    }
+
+    stream.teardown().await?;
+
+    Ok(())
+}
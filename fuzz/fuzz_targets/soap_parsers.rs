@@ -0,0 +1,19 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use onvif_cam_rs::device::EventCapabilities;
+use onvif_cam_rs::soap;
+
+// Every parser must return, Ok or Err, for any bytes a device may send
+fuzz_target!(|data: &[u8]| {
+    let _ = soap::parse_fault(data);
+    let _ = soap::parse_probe_match(data);
+    let _ = soap::parse_probe_matches(data);
+    let _ = soap::parse_discovery_message(data);
+    let _ = soap::parse_capabilities(data);
+    let _ = soap::parse_device_info(data);
+    let _ = soap::parse_profiles(data);
+    let _ = soap::parse_stream_uri(data);
+    let _ = soap::parse_services(data);
+    let _ = soap::parse_service_capabilities::<EventCapabilities>(data);
+});
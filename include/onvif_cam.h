@@ -0,0 +1,58 @@
+/*
+ * C interface of onvif-cam-rs, built with the `ffi` feature:
+ *
+ *     cargo rustc --release --features ffi --lib --crate-type cdylib
+ *
+ * Functions returning a pointer return NULL on failure and functions
+ * returning int return -1, onvif_last_error() then tells why. Strings
+ * returned by the library are freed with onvif_string_free(). All calls
+ * block until the device answered.
+ */
+
+#ifndef ONVIF_CAM_H
+#define ONVIF_CAM_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct OnvifCamera OnvifCamera;
+typedef struct OnvifEvents OnvifEvents;
+
+/* Called from a library thread with the event topic and the event as
+ * JSON: {"topic", "utc_time", "source": {...}, "data": {...}}. When the
+ * subscription fails, events stop after a last call with a NULL topic
+ * and {"error": "..."} */
+typedef void (*OnvifEventCallback)(const char *topic, const char *event_json, void *user_data);
+
+/* Message of the last failure on the calling thread, owned by the library */
+const char *onvif_last_error(void);
+void onvif_string_free(char *s);
+
+/* Device service URLs of the devices on the local network, one per line */
+char *onvif_discover(void);
+
+/* url is a device service URL, e.g. "http://192.168.1.64/onvif/device_service" */
+OnvifCamera *onvif_camera_new(const char *url);
+/* No credentials when username is NULL */
+OnvifCamera *onvif_camera_new_with_credentials(const char *url, const char *username,
+                                               const char *password);
+void onvif_camera_free(OnvifCamera *camera);
+
+/* profile may be NULL for the first media profile of the camera */
+char *onvif_camera_stream_uri(const OnvifCamera *camera, const char *profile);
+/* Fails without moving when seconds is not finite, stops the camera
+ * once asked to move */
+int onvif_camera_ptz_move(const OnvifCamera *camera, const char *profile,
+                          float pan, float tilt, float zoom, double seconds);
+
+OnvifEvents *onvif_events_subscribe(const OnvifCamera *camera, OnvifEventCallback callback,
+                                    void *user_data);
+/* No callback is made once this returns */
+void onvif_events_free(OnvifEvents *events);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
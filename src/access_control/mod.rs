@@ -0,0 +1,115 @@
+use crate::client::{self, Messages};
+use crate::device::camera::Camera;
+use crate::utils::xml::XmlNode;
+
+use anyhow::{anyhow, Result};
+use log::info;
+
+/// A point where access to an area is controlled, e.g. a door with a card
+/// reader
+#[derive(Default, Debug, Clone)]
+#[rustfmt::skip]
+pub struct AccessPointInfo {
+    pub token:         String,
+    pub name:          String,
+    pub description:   Option<String>,
+    /// Type of the controlled entity, e.g. "tdc:Door"
+    pub entity_type:   Option<String>,
+    /// Token of the controlled entity, a door token for doors
+    pub entity:        String,
+}
+
+#[derive(Default, Debug, Clone, Copy)]
+pub struct AccessPointState {
+    pub enabled: bool,
+}
+
+/// Access Control service URL of a built camera
+pub fn access_control_url(camera: &Camera) -> Result<url::Url> {
+    match &camera.services.access_control {
+        Some(url) => Ok(camera.base.authorize(&url.parse()?)),
+        None => Err(anyhow!(
+            "[AccessControl] Device has no Access Control service"
+        )),
+    }
+}
+
+/// Lists every access point of the device, following the pages the
+/// device splits the list into
+///
+/// # Arguments
+///
+/// * `onvif_url` - The Access Control service URL (see `Services::access_control`)
+pub async fn get_access_point_info_list(onvif_url: url::Url) -> Result<Vec<AccessPointInfo>> {
+    let mut access_points = Vec::new();
+    let mut start_reference = None;
+
+    loop {
+        let msg = Messages::GetAccessPointInfoList { start_reference };
+
+        let response = client::send(onvif_url.clone(), msg).await?;
+        let response = response.error_for_status()?.bytes().await?;
+        let root = XmlNode::parse(&response[..])?;
+
+        access_points.extend(root.find_all("AccessPointInfo").into_iter().map(|a| {
+            AccessPointInfo {
+                token: a.attr("token").unwrap_or_default().to_string(),
+                name: a.child_text("Name").unwrap_or_default(),
+                description: a.child_text("Description"),
+                entity_type: a.child_text("EntityType"),
+                entity: a.child_text("Entity").unwrap_or_default(),
+            }
+        }));
+
+        start_reference = root
+            .find("NextStartReference")
+            .map(|n| n.text.trim().to_string());
+
+        if start_reference.as_deref().is_none_or(str::is_empty) {
+            break;
+        }
+    }
+
+    info!("Access points found: {}", access_points.len());
+
+    Ok(access_points)
+}
+
+pub async fn get_access_point_state(onvif_url: url::Url, token: &str) -> Result<AccessPointState> {
+    let msg = Messages::GetAccessPointState {
+        token: token.to_string(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+    let state = root.find("AccessPointState").ok_or_else(|| {
+        anyhow!("[AccessControl][get_access_point_state] No AccessPointState in response")
+    })?;
+
+    Ok(AccessPointState {
+        enabled: state
+            .child_text("Enabled")
+            .is_some_and(|e| e.trim().eq_ignore_ascii_case("true")),
+    })
+}
+
+/// Lets the access point grant access again after disable_access_point()
+pub async fn enable_access_point(onvif_url: url::Url, token: &str) -> Result<()> {
+    let msg = Messages::EnableAccessPoint {
+        token: token.to_string(),
+    };
+
+    client::send(onvif_url, msg).await?.error_for_status()?;
+    Ok(())
+}
+
+/// Stops the access point from granting access until it is enabled again
+pub async fn disable_access_point(onvif_url: url::Url, token: &str) -> Result<()> {
+    let msg = Messages::DisableAccessPoint {
+        token: token.to_string(),
+    };
+
+    client::send(onvif_url, msg).await?.error_for_status()?;
+    Ok(())
+}
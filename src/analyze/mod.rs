@@ -0,0 +1,323 @@
+use crate::builder::camera::CameraBuilder;
+use crate::client::fixtures::{self, Exchange, Fixture};
+use crate::device::camera::Camera;
+use crate::device::StreamTransport;
+use crate::events::{self, PullPointSubscription};
+use crate::soap::parse_probe_match;
+use crate::utils::xml::XmlNode;
+use crate::{media, ptz, recording, search, system};
+
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// Where replayed requests are sent, the host is never contacted
+const DUMP_URL: &str = "http://dump.invalid/onvif/device_service";
+
+/// A SOAP answer saved from a device, e.g. the body of an HTTP response
+/// exported from Wireshark
+#[derive(Debug, Clone, Serialize)]
+#[rustfmt::skip]
+pub struct SoapDump {
+    /// File name or any label identifying the dump in the report
+    pub source:      String,
+    /// First element of the SOAP body, e.g. "GetProfilesResponse"
+    pub element:     String,
+    pub body:        String,
+}
+
+impl SoapDump {
+    /// Takes the payload as saved. Anything before the first `<`, such as
+    /// HTTP headers left in a Wireshark export, is skipped.
+    pub fn new(source: &str, payload: &str) -> Result<Self> {
+        let start = payload
+            .find('<')
+            .ok_or_else(|| anyhow!("[Analyze][SoapDump] {source} does not contain XML"))?;
+        let body = payload[start..].to_string();
+
+        let root = XmlNode::parse(body.as_bytes())
+            .with_context(|| format!("[Analyze][SoapDump] {source} is not well formed XML"))?;
+        let element = root
+            .find("Body")
+            .and_then(|b| b.children.first())
+            .map(|e| e.name.clone())
+            .ok_or_else(|| anyhow!("[Analyze][SoapDump] {source} has no SOAP body"))?;
+
+        Ok(SoapDump {
+            source: source.to_string(),
+            element,
+            body,
+        })
+    }
+
+    /// The operation that produced this answer, e.g. "GetProfiles"
+    pub fn operation(&self) -> &str {
+        self.element
+            .strip_suffix("Response")
+            .unwrap_or(&self.element)
+    }
+}
+
+/// What the crate made of one dump
+#[derive(Debug, Clone, Serialize)]
+#[rustfmt::skip]
+pub struct Extraction {
+    pub source:      String,
+    pub operation:   String,
+    /// Debug output of the parsed value
+    pub extracted:   Option<String>,
+    /// Parser error, or the panic message when a parser panicked
+    pub error:       Option<String>,
+}
+
+/// A file that could not be read as a SOAP message
+#[derive(Debug, Clone, Serialize)]
+pub struct UnreadableDump {
+    pub source: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[rustfmt::skip]
+pub struct AnalysisReport {
+    pub extractions:   Vec<Extraction>,
+    /// Dumps of operations this crate does not parse
+    pub unsupported:   Vec<SoapDump>,
+    pub unreadable:    Vec<UnreadableDump>,
+}
+
+impl AnalysisReport {
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+
+        for extraction in &self.extractions {
+            let _ = writeln!(text, "== {} ({})", extraction.operation, extraction.source);
+
+            match (&extraction.extracted, &extraction.error) {
+                (Some(extracted), _) => {
+                    let _ = writeln!(text, "{extracted}\n");
+                }
+                (_, Some(error)) => {
+                    let _ = writeln!(text, "ERROR: {error}\n");
+                }
+                _ => {}
+            }
+        }
+
+        for dump in &self.unsupported {
+            let _ = writeln!(
+                text,
+                "Not parsed by this crate: {} ({})",
+                dump.element, dump.source
+            );
+        }
+
+        for dump in &self.unreadable {
+            let _ = writeln!(text, "Unreadable: {}: {}", dump.source, dump.error);
+        }
+
+        text
+    }
+}
+
+/// Reads a single dump or every `.xml` file of a directory
+pub fn load_dumps(path: impl AsRef<Path>) -> Result<(Vec<SoapDump>, Vec<UnreadableDump>)> {
+    let path = path.as_ref();
+    let mut files = Vec::new();
+
+    if path.is_dir() {
+        for entry in fs::read_dir(path)
+            .with_context(|| format!("[Analyze][load_dumps] Unable to read {}", path.display()))?
+        {
+            let file = entry?.path();
+
+            if file
+                .extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("xml"))
+            {
+                files.push(file);
+            }
+        }
+
+        files.sort();
+    } else {
+        files.push(path.to_path_buf());
+    }
+
+    let mut dumps = Vec::new();
+    let mut unreadable = Vec::new();
+
+    for file in files {
+        let source = file.display().to_string();
+        let payload = fs::read(&file)
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+            .map_err(anyhow::Error::from)
+            .and_then(|payload| SoapDump::new(&source, &payload));
+
+        match payload {
+            Ok(dump) => dumps.push(dump),
+            Err(e) => unreadable.push(UnreadableDump {
+                source,
+                error: format!("{e:#}"),
+            }),
+        }
+    }
+
+    Ok((dumps, unreadable))
+}
+
+/// Runs the parsers of this crate over saved SOAP answers and reports
+/// what each would have extracted, without any device. Each dump is
+/// replayed through client::fixtures to the same function that would
+/// have sent the request, so the real parsing code runs.
+///
+/// Replay is process wide, see client::fixtures, so nothing else should
+/// talk to devices while the analysis runs.
+///
+/// # Examples
+///
+/// ```no_run
+/// # async fn run() -> anyhow::Result<()> {
+/// let report = onvif_cam_rs::analyze::analyze_path("captures/").await?;
+/// println!("{}", report.to_text());
+/// # Ok(())
+/// # }
+/// ```
+pub async fn analyze(dumps: Vec<SoapDump>) -> AnalysisReport {
+    let mut report = AnalysisReport::default();
+
+    for dump in dumps {
+        if !is_supported(&dump.element) {
+            report.unsupported.push(dump);
+            continue;
+        }
+
+        let fixture = Fixture {
+            exchanges: vec![Exchange {
+                operation: dump.operation().to_string(),
+                path: String::new(),
+                request: String::new(),
+                status: 200,
+                response: dump.body.clone(),
+            }],
+            ..Default::default()
+        };
+
+        let player = fixtures::replay(fixture);
+        let element = dump.element.clone();
+        let body = dump.body.clone();
+
+        // Some parsers index into what they expect to find, a panic is
+        // reported like any other error
+        let result = match tokio::spawn(extract(element, body)).await {
+            Ok(result) => result.map_err(|e| format!("{e:#}")),
+            Err(e) if e.is_panic() => Err(format!("Parser panicked: {}", panic_message(e))),
+            Err(e) => Err(e.to_string()),
+        };
+
+        drop(player);
+
+        report.extractions.push(Extraction {
+            operation: dump.operation().to_string(),
+            source: dump.source,
+            extracted: result.as_ref().ok().cloned(),
+            error: result.err(),
+        });
+    }
+
+    report
+}
+
+/// Loads the dumps at `path` (a file or a directory) and analyzes them
+pub async fn analyze_path(path: impl AsRef<Path>) -> Result<AnalysisReport> {
+    let (dumps, unreadable) = load_dumps(path)?;
+    let mut report = analyze(dumps).await;
+    report.unreadable = unreadable;
+
+    Ok(report)
+}
+
+fn is_supported(element: &str) -> bool {
+    matches!(
+        element,
+        "ProbeMatches"
+            | "GetCapabilitiesResponse"
+            | "GetDeviceInformationResponse"
+            | "GetProfilesResponse"
+            | "GetServicesResponse"
+            | "GetStreamUriResponse"
+            | "GetSnapshotUriResponse"
+            | "GetSystemDateAndTimeResponse"
+            | "CreatePullPointSubscriptionResponse"
+            | "PullMessagesResponse"
+            | "GetPresetsResponse"
+            | "GetRecordingsResponse"
+            | "GetRecordingJobsResponse"
+            | "GetStorageConfigurationsResponse"
+            | "GetRecordingSummaryResponse"
+    )
+}
+
+/// Calls the function that would have sent the request answered by the
+/// dump and formats what it returned
+async fn extract(element: String, body: String) -> Result<String> {
+    let url: url::Url = DUMP_URL.parse()?;
+
+    let extracted = match element.as_str() {
+        "ProbeMatches" => format!("{:#?}", parse_probe_match(body.as_bytes())?),
+        "GetCapabilitiesResponse" => format!("{:#?}", Camera::set_capabilities(url).await?),
+        "GetDeviceInformationResponse" => format!("{:#?}", Camera::set_device_info(url).await?),
+        "GetProfilesResponse" => format!("{:#?}", Camera::set_profiles(url).await?),
+        "GetServicesResponse" => format!("{:#?}", Camera::set_services(url).await?),
+        "GetStreamUriResponse" => format!(
+            "{:#?}",
+            media::get_stream_uri(url, "", StreamTransport::RtspOverTcp).await?
+        ),
+        "GetSnapshotUriResponse" => format!("{:#?}", media::get_snapshot_uri(url, "").await?),
+        "GetSystemDateAndTimeResponse" => {
+            format!("{:#?}", system::get_system_date_and_time(url).await?)
+        }
+        "CreatePullPointSubscriptionResponse" => {
+            format!("{:#?}", events::create_pull_point_subscription(url).await?)
+        }
+        "PullMessagesResponse" => {
+            let subscription = PullPointSubscription {
+                address: url,
+                termination_time: None,
+            };
+            format!(
+                "{:#?}",
+                subscription.pull(Duration::from_secs(1), 100).await?
+            )
+        }
+        "GetPresetsResponse" => format!("{:#?}", ptz::get_presets(url, "").await?),
+        "GetRecordingsResponse" => format!("{:#?}", recording::get_recordings(url).await?),
+        "GetRecordingJobsResponse" => format!("{:#?}", recording::get_recording_jobs(url).await?),
+        "GetStorageConfigurationsResponse" => {
+            format!("{:#?}", recording::get_storage_configurations(url).await?)
+        }
+        "GetRecordingSummaryResponse" => {
+            format!("{:#?}", search::get_recording_summary(url).await?)
+        }
+        other => return Err(anyhow!("[Analyze][extract] No parser for {other}")),
+    };
+
+    Ok(extracted)
+}
+
+fn panic_message(error: tokio::task::JoinError) -> String {
+    let panic = error.into_panic();
+
+    panic
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
@@ -0,0 +1,549 @@
+use crate::client::{self, DiscoverOptions, Rediscovered};
+use crate::device::camera::Camera;
+use crate::device::{deserialize_profiles, Device, MediaProfile, StreamUri};
+
+use anyhow::{anyhow, Context, Result};
+use fs2::FileExt;
+use futures::future::join_all;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// File name of the device cache
+pub const CACHE_FILE: &str = "devices_found.json";
+
+/// File name of the version 0 text cache, written to the working directory
+pub const LEGACY_CACHE_FILE: &str = "devices_found.txt";
+
+/// Directory created under the platform data dir to hold the cache
+const CACHE_DIR: &str = "onvif-cam-rs";
+
+/// Version of the cache format written by this crate.
+/// Version 0 is the legacy `IP: ... ONVIF: ...` text file.
+pub const CACHE_VERSION: u32 = 1;
+
+/// Everything the cache keeps about a single device. Credentials are never
+/// stored, only a reference (e.g. a keyring entry or env var name) that the
+/// application resolves itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[rustfmt::skip]
+pub struct CachedDevice {
+    pub device:            Device,
+    pub stream:            StreamUri,
+    #[serde(default, deserialize_with = "deserialize_profiles")]
+    pub profiles:          Vec<MediaProfile>,
+    pub credentials_ref:   Option<String>,
+    #[serde(default)]
+    pub stale:             bool,
+    #[serde(default)]
+    pub labels:            BTreeSet<String>,
+}
+
+/// What to do with cached devices that no longer answer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StalePolicy {
+    /// Keep the entry with `stale` set
+    Mark,
+    /// Remove the entry from the cache
+    Drop,
+}
+
+/// Devices found on previous runs, persisted as JSON so discovery via
+/// UDP broadcast can be skipped
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[rustfmt::skip]
+pub struct DeviceCache {
+    pub version:   u32,
+    pub devices:   Vec<CachedDevice>,
+}
+
+impl Default for DeviceCache {
+    fn default() -> Self {
+        DeviceCache {
+            version: CACHE_VERSION,
+            devices: Vec::new(),
+        }
+    }
+}
+
+impl From<&Camera> for CachedDevice {
+    fn from(camera: &Camera) -> Self {
+        CachedDevice {
+            device: camera.base.clone(),
+            stream: camera.stream.clone(),
+            profiles: camera.profiles.clone(),
+            credentials_ref: None,
+            stale: false,
+            labels: camera.labels.clone(),
+        }
+    }
+}
+
+impl From<&CachedDevice> for Camera {
+    fn from(cached: &CachedDevice) -> Self {
+        let mut camera = Camera::new(cached.device.clone());
+        camera.stream = cached.stream.clone();
+        camera.profiles = cached.profiles.clone();
+        camera.labels = cached.labels.clone();
+        camera
+    }
+}
+
+impl DeviceCache {
+    pub fn from_cameras(cameras: &[Camera]) -> Self {
+        DeviceCache {
+            devices: cameras.iter().map(CachedDevice::from).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Reads a cache file, migrating older formats to the current version.
+    /// A malformed file or one written by a newer version of the crate is
+    /// reported as an error instead of being guessed at.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Err(anyhow!("[Cache][load] No cache at {}", path.display()));
+        }
+
+        let lock = CacheLock::shared(path)?;
+        let cache = Self::load_unlocked(path);
+        if let Some(lock) = lock {
+            lock.release()?;
+        }
+
+        cache
+    }
+
+    fn load_unlocked(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("[Cache][load] Unable to read {}", path.display()))?;
+
+        Self::migrate(&contents)
+            .with_context(|| format!("[Cache][load] Unable to load cache at {}", path.display()))
+    }
+
+    /// Parses cache contents of any known version into the current format
+    pub fn migrate(contents: &str) -> Result<Self> {
+        let value: Value = match serde_json::from_str(contents) {
+            Ok(value) => value,
+            Err(_) if contents.contains("ONVIF:") => {
+                info!("Migrating legacy text cache to version {CACHE_VERSION}");
+                return Self::from_legacy_text(contents);
+            }
+            Err(e) => return Err(anyhow!("[Cache][migrate] Malformed cache: {e}")),
+        };
+
+        let version = value
+            .get("version")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| anyhow!("[Cache][migrate] Cache has no version"))?;
+
+        // Each future format change adds a step here upgrading
+        // version N to N + 1, applied until CACHE_VERSION is reached
+        match u32::try_from(version) {
+            Ok(CACHE_VERSION) => Ok(serde_json::from_value(value)?),
+            Ok(v) if v < CACHE_VERSION => {
+                Err(anyhow!("[Cache][migrate] Unknown cache version {v}"))
+            }
+            _ => Err(anyhow!(
+                "[Cache][migrate] Cache version {version} was written by a newer version of this crate"
+            )),
+        }
+    }
+
+    /// Converts the version 0 text format, one device per line:
+    /// `IP: <rtsp url or empty> ONVIF: <onvif url>`
+    fn from_legacy_text(contents: &str) -> Result<Self> {
+        let mut devices = Vec::new();
+
+        for (num, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let (ip, onvif) = line
+                .split_once("ONVIF:")
+                .ok_or_else(|| anyhow!("[Cache][migrate] Line {} has no ONVIF url", num + 1))?;
+
+            let url_onvif = onvif
+                .trim()
+                .parse()
+                .with_context(|| format!("[Cache][migrate] Bad ONVIF url on line {}", num + 1))?;
+
+            let uri = ip.trim().trim_start_matches("IP:").trim();
+
+            devices.push(CachedDevice {
+                device: Device::new(url_onvif),
+                stream: StreamUri {
+                    uri: (!uri.is_empty()).then(|| uri.to_string()),
+                    ..Default::default()
+                },
+                profiles: Vec::new(),
+                credentials_ref: None,
+                stale: false,
+                labels: BTreeSet::new(),
+            });
+        }
+
+        Ok(DeviceCache {
+            devices,
+            ..Default::default()
+        })
+    }
+
+    /// Writes the cache, creating any missing parent directories.
+    /// The file is written to a temporary file and renamed over the old
+    /// one while holding an exclusive lock, so concurrent writers from other
+    /// processes or tasks can never leave a half written cache behind.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let lock = CacheLock::exclusive(path)?;
+        let result = self.save_unlocked(path);
+        lock.release()?;
+
+        result
+    }
+
+    fn save_unlocked(&self, path: &Path) -> Result<()> {
+        let contents = serde_json::to_string_pretty(self)?;
+        let tmp_path = sibling_path(path, &format!("tmp.{}", std::process::id()));
+
+        let mut tmp = File::create(&tmp_path)
+            .with_context(|| format!("[Cache][save] Unable to write {}", tmp_path.display()))?;
+        tmp.write_all(contents.as_bytes())?;
+        tmp.sync_all()?;
+
+        fs::rename(&tmp_path, path)
+            .with_context(|| format!("[Cache][save] Unable to replace {}", path.display()))?;
+
+        Ok(())
+    }
+
+    /// Loads, modifies and saves the cache under a single exclusive lock so
+    /// updates from concurrent writers are not lost. A missing cache starts
+    /// out empty.
+    pub fn update<F>(path: impl AsRef<Path>, modify: F) -> Result<Self>
+    where
+        F: FnOnce(&mut DeviceCache),
+    {
+        let path = path.as_ref();
+        let lock = CacheLock::exclusive(path)?;
+
+        let result = match path.exists() {
+            true => Self::load_unlocked(path),
+            false => Ok(DeviceCache::default()),
+        }
+        .and_then(|mut cache| {
+            modify(&mut cache);
+            cache.save_unlocked(path)?;
+            Ok(cache)
+        });
+
+        lock.release()?;
+
+        result
+    }
+
+    /// Pings every cached device with GetSystemDateAndTime and marks or
+    /// drops the ones that do not answer within `wait`, since addresses
+    /// may have changed since the cache was written.
+    /// Returns the number of stale devices found.
+    pub async fn validate(&mut self, wait: Duration, policy: StalePolicy) -> usize {
+        let pings = self
+            .devices
+            .iter()
+            .map(|d| client::ping(d.device.url_onvif.clone(), wait));
+
+        let results = join_all(pings).await;
+        let mut stale = 0;
+
+        for (device, result) in self.devices.iter_mut().zip(results) {
+            device.stale = result.is_err();
+
+            if let Err(e) = result {
+                warn!("Cached device is stale: {e}");
+                stale += 1;
+            }
+        }
+
+        if policy == StalePolicy::Drop {
+            self.devices.retain(|d| !d.stale);
+        }
+
+        stale
+    }
+
+    /// Runs discovery again and merges the answers into the cache, see
+    /// client::rediscover(). Moved devices keep their cached profiles and
+    /// stream URI, the URI following the device to its new host, and are
+    /// no longer stale. New devices are added without either.
+    pub async fn rediscover(&mut self, options: DiscoverOptions) -> Result<Vec<Rediscovered>> {
+        let mut devices: Vec<Device> = self.devices.iter().map(|d| d.device.clone()).collect();
+        let changes = client::rediscover(&mut devices, options).await?;
+
+        // Known devices keep their positions, new ones come after them
+        let mut devices = devices.into_iter();
+
+        for (cached, device) in self.devices.iter_mut().zip(devices.by_ref()) {
+            if cached.device.url_onvif != device.url_onvif {
+                info!(
+                    "Cached device moved from {} to {}",
+                    cached.device.url_onvif, device.url_onvif
+                );
+                move_stream(
+                    &mut cached.stream,
+                    &cached.device.url_onvif,
+                    &device.url_onvif,
+                );
+                cached.stale = false;
+            }
+
+            cached.device = device;
+        }
+
+        for device in devices {
+            self.devices.push(CachedDevice::from(&Camera::new(device)));
+        }
+
+        Ok(changes)
+    }
+
+    pub fn cameras(&self) -> Vec<Camera> {
+        self.devices.iter().map(Camera::from).collect()
+    }
+}
+
+/// Points a stream URI on the old host of a device at its new host
+fn move_stream(stream: &mut StreamUri, from: &url::Url, to: &url::Url) {
+    let Some(uri) = &stream.uri else {
+        return;
+    };
+    let Ok(mut uri) = url::Url::parse(uri) else {
+        return;
+    };
+
+    if uri.host_str() == from.host_str() && uri.set_host(to.host_str()).is_ok() {
+        stream.uri = Some(uri.to_string());
+    }
+}
+
+/// Advisory lock held on `<cache>.lock` while the cache is read or written.
+/// A separate lock file is used because saving renames a new file over
+/// the cache itself. Only writers create it, so reading never touches the
+/// file system. The locks block, async callers take them through
+/// spawn_blocking().
+struct CacheLock {
+    file: File,
+}
+
+impl CacheLock {
+    /// A shared lock for reading, None when no writer ever created the
+    /// lock file, e.g. for a cache on a read-only file system
+    fn shared(path: &Path) -> Result<Option<Self>> {
+        let lock_path = sibling_path(path, "lock");
+        let file = match File::open(&lock_path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!("[Cache][lock] Unable to open {}", lock_path.display())
+                })
+            }
+        };
+
+        file.lock_shared()?;
+        Ok(Some(CacheLock { file }))
+    }
+
+    /// An exclusive lock for writing, creating the cache directory and
+    /// lock file when missing
+    fn exclusive(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("[Cache][lock] Unable to create {}", parent.display()))?;
+        }
+
+        let lock_path = sibling_path(path, "lock");
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .with_context(|| format!("[Cache][lock] Unable to open {}", lock_path.display()))?;
+
+        file.lock_exclusive()?;
+        Ok(CacheLock { file })
+    }
+
+    fn release(self) -> Result<()> {
+        Ok(self.file.unlock()?)
+    }
+}
+
+/// `devices_found.json` -> `devices_found.json.<extension>`
+fn sibling_path(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(extension);
+    path.with_file_name(name)
+}
+
+/// Default location of the cache inside the platform data directory,
+/// e.g. `~/.local/share/onvif-cam-rs/devices_found.json` on Linux or
+/// `%APPDATA%\onvif-cam-rs\devices_found.json` on Windows.
+/// Falls back to the current directory when no data dir is known.
+pub fn default_path() -> PathBuf {
+    match dirs::data_dir() {
+        Some(dir) => dir.join(CACHE_DIR).join(CACHE_FILE),
+        None => PathBuf::from(CACHE_FILE),
+    }
+}
+
+/// The legacy text cache to migrate when there is no cache at `path`
+fn legacy_path(path: &Path) -> Option<PathBuf> {
+    if path.exists() {
+        return None;
+    }
+
+    [
+        path.with_file_name(LEGACY_CACHE_FILE),
+        PathBuf::from(LEGACY_CACHE_FILE),
+    ]
+    .into_iter()
+    .find(|legacy| legacy.exists())
+}
+
+/// Loads a legacy text cache and saves it at `path` in the current format
+fn migrate_legacy(legacy: &Path, path: &Path) -> Result<DeviceCache> {
+    info!("Migrating {} to {}", legacy.display(), path.display());
+
+    let cache = DeviceCache::load(legacy)?;
+    cache.save(path)?;
+
+    Ok(cache)
+}
+
+/// Loads the cache at `path` and validates every entry, see DeviceCache::validate()
+pub async fn load_validated(
+    path: impl AsRef<Path>,
+    wait: Duration,
+    policy: StalePolicy,
+) -> Result<DeviceCache> {
+    let path = path.as_ref().to_path_buf();
+    let mut cache = tokio::task::spawn_blocking(move || DeviceCache::load(path)).await??;
+    cache.validate(wait, policy).await;
+
+    Ok(cache)
+}
+
+/// Returns the devices stored in the cache at `path`, or runs discovery
+/// and saves the result there when the cache is missing or unreadable.
+/// A missing cache is first migrated from a legacy `devices_found.txt`
+/// next to it or in the working directory.
+pub async fn load_or_discover(path: impl AsRef<Path>) -> Result<Vec<Device>> {
+    let path = path.as_ref().to_path_buf();
+
+    let loaded = {
+        let path = path.clone();
+        tokio::task::spawn_blocking(move || match legacy_path(&path) {
+            Some(legacy) => migrate_legacy(&legacy, &path),
+            None => DeviceCache::load(path),
+        })
+        .await?
+    };
+
+    match loaded {
+        Ok(cache) if !cache.devices.is_empty() => {
+            info!(
+                "Loaded {} devices from {}",
+                cache.devices.len(),
+                path.display()
+            );
+            return Ok(cache.devices.into_iter().map(|d| d.device).collect());
+        }
+        Ok(_) => info!("Cache at {} is empty, discovering", path.display()),
+        Err(e) => warn!("{e:#}, discovering"),
+    }
+
+    let devices = client::discover().await?;
+    let cameras: Vec<Camera> = devices.iter().cloned().map(Camera::new).collect();
+    let cache = DeviceCache::from_cameras(&cameras);
+    tokio::task::spawn_blocking(move || cache.save(path)).await??;
+
+    Ok(devices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrate_legacy_text() {
+        let cache = DeviceCache::migrate(
+            "IP: rtsp://192.168.1.64/Streaming/101 ONVIF: http://192.168.1.64/onvif/device_service\n\
+             \n\
+             IP:  ONVIF: http://192.168.1.65/onvif/device_service\n",
+        )
+        .unwrap();
+
+        assert_eq!(cache.version, CACHE_VERSION);
+        assert_eq!(cache.devices.len(), 2);
+        assert_eq!(
+            cache.devices[0].device.url_onvif.as_str(),
+            "http://192.168.1.64/onvif/device_service"
+        );
+        assert_eq!(
+            cache.devices[0].stream.uri.as_deref(),
+            Some("rtsp://192.168.1.64/Streaming/101")
+        );
+        assert_eq!(cache.devices[1].stream.uri, None);
+    }
+
+    #[test]
+    fn migrate_legacy_text_bad_url() {
+        assert!(DeviceCache::migrate("IP: ONVIF: not a url").is_err());
+    }
+
+    #[test]
+    fn migrate_current_version_round_trip() {
+        let cache = DeviceCache::migrate(
+            "IP: rtsp://192.168.1.64/Streaming/101 ONVIF: http://192.168.1.64/onvif/device_service",
+        )
+        .unwrap();
+        let json = serde_json::to_string(&cache).unwrap();
+        let migrated = DeviceCache::migrate(&json).unwrap();
+
+        assert_eq!(migrated.devices.len(), 1);
+        assert_eq!(
+            migrated.devices[0].device.url_onvif,
+            cache.devices[0].device.url_onvif
+        );
+        assert_eq!(migrated.devices[0].stream.uri, cache.devices[0].stream.uri);
+    }
+
+    #[test]
+    fn migrate_rejects_unknown_versions() {
+        let newer = format!(r#"{{"version": {}, "devices": []}}"#, CACHE_VERSION + 1);
+
+        assert!(DeviceCache::migrate(&newer).is_err());
+        assert!(DeviceCache::migrate(r#"{"version": 0, "devices": []}"#).is_err());
+        assert!(DeviceCache::migrate(r#"{"devices": []}"#).is_err());
+        assert!(DeviceCache::migrate("{ not json").is_err());
+    }
+
+    #[test]
+    fn migrate_rejects_versions_beyond_u32() {
+        // Truncated to u32 this would read as the current version
+        let version = (1u64 << 32) + u64::from(CACHE_VERSION);
+        let cache = format!(r#"{{"version": {version}, "devices": []}}"#);
+
+        let error = DeviceCache::migrate(&cache).unwrap_err();
+        assert!(error.to_string().contains("newer version"));
+    }
+}
@@ -0,0 +1,221 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Bodies longer than this are cut in audit entries
+pub const AUDIT_BODY_LIMIT: usize = 4096;
+
+/// Elements whose text never reaches an audit sink
+const REDACTED_ELEMENTS: [&str; 2] = ["Password", "Nonce"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditDirection {
+    Request,
+    Response,
+}
+
+/// One SOAP message sent to or received from a device
+#[derive(Debug, Clone, Serialize)]
+#[rustfmt::skip]
+pub struct AuditEntry {
+    pub timestamp:   DateTime<Utc>,
+    pub direction:   AuditDirection,
+    /// Host and port of the device, credentials are never part of it
+    pub device:      String,
+    /// First element of the SOAP body of the request, e.g. "GetProfiles"
+    pub action:      String,
+    /// HTTP status of responses
+    pub status:      Option<u16>,
+    /// Passwords and nonces redacted, cut after AUDIT_BODY_LIMIT bytes
+    pub body:        String,
+}
+
+/// Receives every SOAP message going through client::send() and
+/// send_timeout() once set with set_audit_sink(), each retry and
+/// authentication attempt included. Closures taking an AuditEntry are
+/// sinks too.
+pub trait AuditSink: Send + Sync {
+    fn record(&self, entry: &AuditEntry);
+}
+
+impl<F: Fn(&AuditEntry) + Send + Sync> AuditSink for F {
+    fn record(&self, entry: &AuditEntry) {
+        self(entry)
+    }
+}
+
+/// Appends audit entries to a file, one JSON object per line
+pub struct AuditLog {
+    file: Mutex<File>,
+}
+
+impl AuditLog {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("[Audit][open] Unable to open {}", path.display()))?;
+
+        Ok(AuditLog {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl AuditSink for AuditLog {
+    fn record(&self, entry: &AuditEntry) {
+        let Ok(line) = serde_json::to_string(entry) else {
+            return;
+        };
+
+        // A full disk should not fail the request being audited
+        let _ = writeln!(self.file.lock().unwrap(), "{line}");
+    }
+}
+
+static SINK: Mutex<Option<Arc<dyn AuditSink>>> = Mutex::new(None);
+
+/// Sends every SOAP message from now on to `sink`, None stops auditing
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::client::{self, AuditLog};
+/// # fn run() -> anyhow::Result<()> {
+/// client::set_audit_sink(Some(Box::new(AuditLog::open("soap-audit.jsonl")?)));
+/// # Ok(())
+/// # }
+/// ```
+pub fn set_audit_sink(sink: Option<Box<dyn AuditSink>>) {
+    *SINK.lock().unwrap() = sink.map(Arc::from);
+}
+
+pub(crate) fn enabled() -> bool {
+    SINK.lock().unwrap().is_some()
+}
+
+/// Hands a message to the audit sink, if any
+pub(crate) fn record(
+    direction: AuditDirection,
+    onvif_url: &url::Url,
+    action: &str,
+    status: Option<u16>,
+    body: &str,
+) {
+    // The sink runs without the lock held so it may log through the client
+    let Some(sink) = SINK.lock().unwrap().clone() else {
+        return;
+    };
+
+    sink.record(&AuditEntry {
+        timestamp: Utc::now(),
+        direction,
+        device: super::stats::host(onvif_url),
+        action: action.to_string(),
+        status,
+        body: truncate(redact(body)),
+    });
+}
+
+/// `body` with the text of Password and Nonce elements, whatever their
+/// namespace prefix, replaced
+pub fn redact(body: &str) -> String {
+    let mut redacted = String::with_capacity(body.len());
+    let mut rest = body;
+
+    while let Some(open) = rest.find('<') {
+        let Some(close) = rest[open..].find('>').map(|close| open + close) else {
+            break;
+        };
+
+        let tag = &rest[open + 1..close];
+        let name = tag.split_whitespace().next().unwrap_or_default();
+        let local_name = name.rsplit(':').next().unwrap_or_default();
+        redacted.push_str(&rest[..=close]);
+        rest = &rest[close + 1..];
+
+        let opening = !tag.starts_with('/') && !tag.ends_with('/');
+        let secret = opening && REDACTED_ELEMENTS.contains(&local_name);
+        if secret {
+            let end = rest.find('<').unwrap_or(rest.len());
+            redacted.push_str("[redacted]");
+            rest = &rest[end..];
+        }
+    }
+
+    redacted.push_str(rest);
+    redacted
+}
+
+fn truncate(mut body: String) -> String {
+    if body.len() <= AUDIT_BODY_LIMIT {
+        return body;
+    }
+
+    let mut end = AUDIT_BODY_LIMIT;
+    while !body.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let cut = body.len() - end;
+    body.truncate(end);
+    body + &format!("...[{cut} more bytes]")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redact_password_and_nonce() {
+        let body = r#"<wsse:UsernameToken><wsse:Username>admin</wsse:Username><wsse:Password Type="PasswordDigest">c2VjcmV0</wsse:Password><wsse:Nonce>bm9uY2U=</wsse:Nonce></wsse:UsernameToken>"#;
+
+        assert_eq!(
+            redact(body),
+            r#"<wsse:UsernameToken><wsse:Username>admin</wsse:Username><wsse:Password Type="PasswordDigest">[redacted]</wsse:Password><wsse:Nonce>[redacted]</wsse:Nonce></wsse:UsernameToken>"#
+        );
+    }
+
+    #[test]
+    fn redact_any_prefix_and_no_prefix() {
+        assert_eq!(
+            redact("<tt:Password>secret</tt:Password><Password>secret</Password>"),
+            "<tt:Password>[redacted]</tt:Password><Password>[redacted]</Password>"
+        );
+    }
+
+    #[test]
+    fn redact_keeps_other_elements() {
+        let body = "<tds:User><tt:Username>admin</tt:Username><tt:PasswordPolicy/><tt:UserLevel>User</tt:UserLevel></tds:User>";
+
+        assert_eq!(redact(body), body);
+    }
+
+    #[test]
+    fn redact_unterminated_tag() {
+        assert_eq!(
+            redact("<Password>secret</Pass"),
+            "<Password>[redacted]</Pass"
+        );
+        assert_eq!(redact("text <Password"), "text <Password");
+    }
+
+    #[test]
+    fn truncate_on_char_boundary() {
+        let body = "é".repeat(AUDIT_BODY_LIMIT);
+        let truncated = truncate(body.clone());
+
+        assert!(truncated.len() < body.len());
+        assert!(truncated.ends_with(&format!(
+            "...[{} more bytes]",
+            body.len() - AUDIT_BODY_LIMIT
+        )));
+    }
+}
@@ -0,0 +1,90 @@
+use super::{post, soap_msg, stats, Messages};
+use crate::system::parse_system_date_and_time;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, TimeDelta, Utc};
+use log::{debug, warn};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::timeout;
+use url::Url;
+use uuid::Uuid;
+
+/// How long the device clock is read for before signing without it
+const CLOCK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Device clock minus ours, per device host and port
+static OFFSETS: Mutex<BTreeMap<String, TimeDelta>> = Mutex::new(BTreeMap::new());
+
+/// How far the clock of the device at `onvif_url` is ahead of ours, as
+/// measured before its first authenticated request. None until then.
+pub fn clock_offset(onvif_url: &Url) -> Option<TimeDelta> {
+    OFFSETS
+        .lock()
+        .unwrap()
+        .get(&stats::host(onvif_url))
+        .copied()
+}
+
+/// Drops the measured clock offset of a device so the next authenticated
+/// request measures it again, e.g. after its clock was set
+pub fn forget_clock_offset(onvif_url: &Url) {
+    OFFSETS.lock().unwrap().remove(&stats::host(onvif_url));
+}
+
+/// Measures the clock offset of the device at `onvif_url` unless it is
+/// known already. Devices reject UsernameTokens created too far from their
+/// own time, so the offset is read with GetSystemDateAndTime, which needs
+/// no credentials, before the first authenticated request and kept for
+/// the following ones.
+pub(crate) async fn measure_clock(client: &reqwest::Client, onvif_url: &Url) {
+    if clock_offset(onvif_url).is_some() {
+        return;
+    }
+
+    let offset = match measure_offset(client, onvif_url).await {
+        Ok(Some(offset)) => {
+            debug!("[Client][measure_clock] Clock of {onvif_url} is {offset} ahead");
+            offset
+        }
+        // Asking again would not help, e.g. devices wanting credentials
+        // for GetSystemDateAndTime too
+        Ok(None) => {
+            warn!("[Client][measure_clock] {onvif_url} did not tell its time");
+            TimeDelta::zero()
+        }
+        Err(e) => {
+            warn!("[Client][measure_clock] Unable to read the clock of {onvif_url}: {e}");
+            return;
+        }
+    };
+
+    OFFSETS
+        .lock()
+        .unwrap()
+        .insert(stats::host(onvif_url), offset);
+}
+
+/// The current time on the device clock, for the Created timestamp of a
+/// UsernameToken. Our own clock when the offset could not be measured.
+pub(crate) fn device_now(onvif_url: &Url) -> DateTime<Utc> {
+    Utc::now() + clock_offset(onvif_url).unwrap_or_default()
+}
+
+/// Device time minus our time halfway through the round trip, None when
+/// the device answered without its time
+async fn measure_offset(client: &reqwest::Client, onvif_url: &Url) -> Result<Option<TimeDelta>> {
+    let soap_msg = soap_msg(&Messages::GetSystemDateAndTime, Uuid::new_v4());
+
+    let sent = Utc::now();
+    let response = timeout(CLOCK_TIMEOUT, post(client, onvif_url, &soap_msg, None))
+        .await
+        .map_err(|_| anyhow!("no answer"))??;
+    let body = response.bytes().await?;
+    let received = Utc::now();
+
+    Ok(parse_system_date_and_time(&body)
+        .ok()
+        .map(|device_time| device_time - (sent + (received - sent) / 2)))
+}
@@ -0,0 +1,105 @@
+use super::{rebuilt_response, stats};
+use crate::auth::AuthScheme;
+use crate::soap::fault_code;
+
+use anyhow::Result;
+use reqwest::{header, Response, StatusCode};
+use std::fmt;
+
+/// Authentication failures of client::send() and send_timeout(), so callers
+/// can ask for credentials rather than treat them as any other failure.
+/// They come inside anyhow::Error:
+///
+/// ```no_run
+/// # use onvif_cam_rs::client::{self, Messages};
+/// # async fn run(onvif_url: url::Url) -> anyhow::Result<()> {
+/// match client::send(onvif_url, Messages::GetProfiles).await {
+///     Err(e) if matches!(e.downcast_ref(), Some(client::Error::AuthRequired { .. })) => {
+///         println!("Please enter the camera password");
+///     }
+///     other => println!("{:?}", other?.status()),
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The device wants credentials and none were given. `schemes` are
+    /// those it asked for, see AuthScheme.
+    AuthRequired {
+        device: String,
+        schemes: Vec<AuthScheme>,
+    },
+    /// The device refused the credentials given
+    AuthFailed {
+        device: String,
+        schemes: Vec<AuthScheme>,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::AuthRequired { device, schemes } => {
+                write!(
+                    f,
+                    "[Client][send] {device} requires credentials ({schemes:?})"
+                )
+            }
+            Error::AuthFailed { device, .. } => {
+                write!(f, "[Client][send] {device} refused the credentials")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// `response` unless it refuses the request for want of credentials: a
+/// 401, or a fault with the NotAuthorized subcode, which are then turned
+/// into Error::AuthRequired or, when credentials were sent, AuthFailed
+pub(crate) async fn check_authorized(
+    onvif_url: &url::Url,
+    response: Response,
+    with_credentials: bool,
+) -> Result<Response> {
+    let status = response.status();
+    if status.is_success() || status.is_informational() || status.is_redirection() {
+        return Ok(response);
+    }
+
+    let mut schemes: Vec<AuthScheme> = response
+        .headers()
+        .get_all(header::WWW_AUTHENTICATE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .filter_map(challenged_scheme)
+        .collect();
+
+    let headers = response.headers().clone();
+    let body = response.bytes().await?;
+
+    if fault_code(&body).is_some_and(|code| code.ends_with("NotAuthorized")) {
+        schemes.push(AuthScheme::UsernameToken);
+    } else if status != StatusCode::UNAUTHORIZED {
+        return rebuilt_response(status, &headers, body);
+    }
+
+    let device = stats::host(onvif_url);
+    Err(match with_credentials {
+        true => Error::AuthFailed { device, schemes },
+        false => Error::AuthRequired { device, schemes },
+    }
+    .into())
+}
+
+/// The scheme of a WWW-Authenticate header value
+fn challenged_scheme(value: &str) -> Option<AuthScheme> {
+    let scheme = value.split_whitespace().next()?;
+
+    match scheme.to_ascii_lowercase().as_str() {
+        "digest" => Some(AuthScheme::Digest),
+        "basic" => Some(AuthScheme::Basic),
+        _ => None,
+    }
+}
@@ -0,0 +1,224 @@
+use super::{discover_with, probe_socket, DiscoverOptions};
+use crate::device::{Device, DeviceTypes};
+
+use anyhow::{anyhow, Result};
+use simple_dns::rdata::RData;
+use simple_dns::{Name, Packet, Question, CLASS, TYPE};
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::time::Instant;
+use tokio::net::UdpSocket;
+use tokio::time::timeout;
+
+const MDNS_ADDR: (Ipv4Addr, u16) = (Ipv4Addr::new(224, 0, 0, 251), 5353);
+const MDNS_SERVICE: &str = "_onvif._tcp.local";
+const SSDP_ADDR: (Ipv4Addr, u16) = (Ipv4Addr::new(239, 255, 255, 250), 1900);
+const SSDP_SEARCH: &str = "M-SEARCH * HTTP/1.1\r\n\
+    HOST: 239.255.255.250:1900\r\n\
+    MAN: \"ssdp:discover\"\r\n\
+    MX: 2\r\n\
+    ST: upnp:rootdevice\r\n\r\n";
+
+/// Path of the device service when the announcement does not give one
+const DEVICE_SERVICE: &str = "/onvif/device_service";
+
+/// WS-Discovery, then mDNS and SSDP for devices that ignore WS-Discovery
+/// probes. Devices found by mDNS or SSDP at a host WS-Discovery already
+/// found are left out. Each method collects answers for the timeout of
+/// `options`, one after the other so a fixed listen port can be reused.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::client::{self, DiscoverOptions};
+/// # async fn run() -> anyhow::Result<()> {
+/// let devices = client::discover_with_fallback(DiscoverOptions::default()).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn discover_with_fallback(options: DiscoverOptions) -> Result<Vec<Device>> {
+    // No answer to the probes is what the fallback is for
+    let mut devices = discover_with(options.clone()).await.unwrap_or_default();
+
+    let mut fallback = discover_mdns(&options).await?;
+    fallback.extend(discover_ssdp(&options).await?);
+
+    for device in fallback {
+        let host = device.url_onvif.host();
+
+        if !devices.iter().any(|d| d.url_onvif.host() == host) {
+            devices.push(device);
+        }
+    }
+
+    if devices.is_empty() {
+        return Err(anyhow!(
+            "[OnvifClient][discover_with_fallback] Unable to find any devices."
+        ));
+    }
+
+    Ok(devices)
+}
+
+/// Devices announcing the `_onvif._tcp` service over mDNS. The instance
+/// name becomes the name scope of the device.
+pub async fn discover_mdns(options: &DiscoverOptions) -> Result<Vec<Device>> {
+    let socket = probe_socket(options).await?;
+
+    let mut query = Packet::new_query(0);
+    query.questions.push(Question::new(
+        Name::new_unchecked(MDNS_SERVICE),
+        TYPE::PTR.into(),
+        CLASS::IN.into(),
+        true,
+    ));
+    socket.send_to(&query.build_bytes_vec()?, MDNS_ADDR).await?;
+
+    let mut devices: Vec<Device> = Vec::new();
+
+    for answer in answers(&socket, options).await {
+        let Ok(packet) = Packet::parse(&answer) else {
+            continue;
+        };
+
+        for device in mdns_devices(&packet) {
+            if !devices.iter().any(|d| d.url_onvif == device.url_onvif) {
+                devices.push(device);
+            }
+        }
+    }
+
+    Ok(devices)
+}
+
+/// Devices answering an SSDP search. SSDP only gives the address of the
+/// UPnP description, so the device service is assumed at its usual path
+/// on port 80, with the description port as another candidate (see
+/// client::select_xaddr()). Any UPnP device answers, routers and TVs
+/// included, which is why the device type is Unknown.
+pub async fn discover_ssdp(options: &DiscoverOptions) -> Result<Vec<Device>> {
+    let socket = probe_socket(options).await?;
+    socket.send_to(SSDP_SEARCH.as_bytes(), SSDP_ADDR).await?;
+
+    let mut devices: Vec<Device> = Vec::new();
+
+    for answer in answers(&socket, options).await {
+        let Some(device) = ssdp_device(&String::from_utf8_lossy(&answer)) else {
+            continue;
+        };
+
+        if !devices.iter().any(|d| d.url_onvif == device.url_onvif) {
+            devices.push(device);
+        }
+    }
+
+    Ok(devices)
+}
+
+/// Every datagram received until the timeout of `options`
+async fn answers(socket: &UdpSocket, options: &DiscoverOptions) -> Vec<Vec<u8>> {
+    let deadline = Instant::now() + options.timeout;
+    let mut answers = Vec::new();
+
+    loop {
+        let mut buf = vec![0u8; 9000];
+        let remaining = deadline.saturating_duration_since(Instant::now());
+
+        match timeout(remaining, socket.recv(&mut buf)).await {
+            Ok(Ok(size)) => {
+                buf.truncate(size);
+                answers.push(buf);
+            }
+            Ok(Err(e)) => log::warn!("[OnvifClient][Discover] Error in response {e}"),
+            Err(_) => return answers,
+        }
+    }
+}
+
+/// The `_onvif._tcp` instances of an mDNS answer, their SRV, TXT and A
+/// records looked up in the same packet
+fn mdns_devices(packet: &Packet) -> Vec<Device> {
+    let records = || packet.answers.iter().chain(&packet.additional_records);
+
+    let mut addresses: HashMap<String, Ipv4Addr> = HashMap::new();
+    let mut services: HashMap<String, (String, u16)> = HashMap::new();
+    let mut paths: HashMap<String, String> = HashMap::new();
+    let mut instances: Vec<String> = Vec::new();
+
+    for record in records() {
+        let name = record.name.to_string();
+
+        match &record.rdata {
+            RData::PTR(ptr) if name == MDNS_SERVICE => instances.push(ptr.0.to_string()),
+            RData::SRV(srv) => {
+                services.insert(name, (srv.target.to_string(), srv.port));
+            }
+            RData::A(a) => {
+                addresses.insert(name, Ipv4Addr::from(a.address));
+            }
+            RData::TXT(txt) => {
+                if let Some(Some(path)) = txt.attributes().get("path") {
+                    paths.insert(name, path.clone());
+                }
+            }
+            _ => (),
+        }
+    }
+
+    instances
+        .into_iter()
+        .filter_map(|instance| {
+            let (target, port) = services.get(&instance)?;
+            let host = match addresses.get(target) {
+                Some(address) => address.to_string(),
+                None => target.clone(),
+            };
+            let path = paths.get(&instance).map_or(DEVICE_SERVICE, |p| p.as_str());
+            let url_onvif: url::Url = format!("http://{host}:{port}{path}").parse().ok()?;
+            let name = instance.strip_suffix(&format!(".{MDNS_SERVICE}"))?;
+
+            Some(Device {
+                xaddrs: vec![url_onvif.clone()],
+                scopes: vec![format!(
+                    "onvif://www.onvif.org/name/{}",
+                    url::form_urlencoded::byte_serialize(name.as_bytes())
+                        .collect::<String>()
+                        .replace('+', "%20")
+                )],
+                ..Device::new(url_onvif)
+            })
+        })
+        .collect()
+}
+
+/// The device behind the LOCATION header of an SSDP answer
+fn ssdp_device(answer: &str) -> Option<Device> {
+    let location = answer.lines().find_map(|line| {
+        let (header, value) = line.split_once(':')?;
+        header
+            .trim()
+            .eq_ignore_ascii_case("location")
+            .then(|| value.trim())
+    })?;
+
+    let location: url::Url = location.parse().ok()?;
+    let host = location.host_str()?;
+
+    let mut xaddrs: Vec<url::Url> = vec![format!("http://{host}{DEVICE_SERVICE}").parse().ok()?];
+
+    if let Some(port) = location.port().filter(|port| *port != 80) {
+        xaddrs.push(
+            format!("http://{host}:{port}{DEVICE_SERVICE}")
+                .parse()
+                .ok()?,
+        );
+    }
+
+    let url_onvif = xaddrs[0].clone();
+
+    Some(Device {
+        device_type: DeviceTypes::Unknown,
+        xaddrs,
+        ..Device::new(url_onvif)
+    })
+}
@@ -0,0 +1,267 @@
+use crate::utils::xml::XmlNode;
+
+use anyhow::{anyhow, Context, Result};
+use bytes::Bytes;
+use log::debug;
+use reqwest::Response;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Version of the fixture format written by this crate
+pub const FIXTURE_VERSION: u32 = 1;
+
+/// One request and the answer the device gave
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[rustfmt::skip]
+pub struct Exchange {
+    /// First element of the SOAP body, e.g. "GetProfiles"
+    pub operation:   String,
+    /// Path of the service URL, replay ignores the host. An empty path
+    /// answers requests to any service.
+    pub path:        String,
+    pub request:     String,
+    pub status:      u16,
+    pub response:    String,
+}
+
+/// SOAP exchanges captured from one or more devices. While a Recorder is
+/// alive every request sent through client::send() and send_timeout() is
+/// captured with the device answer. A Player answers the same requests
+/// from a fixture instead of the network, so quirky vendor responses can
+/// be replayed in tests without the camera. WS-Discovery and RTSP are
+/// not covered.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[rustfmt::skip]
+pub struct Fixture {
+    pub version:     u32,
+    pub exchanges:   Vec<Exchange>,
+}
+
+impl Default for Fixture {
+    fn default() -> Self {
+        Fixture {
+            version: FIXTURE_VERSION,
+            exchanges: Vec::new(),
+        }
+    }
+}
+
+impl Fixture {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("[Fixtures][load] Unable to read {}", path.display()))?;
+        let fixture: Fixture = serde_json::from_str(&contents)
+            .with_context(|| format!("[Fixtures][load] Malformed fixture {}", path.display()))?;
+
+        if fixture.version > FIXTURE_VERSION {
+            return Err(anyhow!(
+                "[Fixtures][load] Fixture version {} is newer than this crate",
+                fixture.version
+            ));
+        }
+
+        Ok(fixture)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("[Fixtures][save] Unable to write {}", path.display()))
+    }
+
+    /// Replaces every occurrence of `secret` (an address, serial number,
+    /// MAC...) in requests and answers before a fixture is shared
+    pub fn redact(&mut self, secret: &str, replacement: &str) {
+        if secret.is_empty() {
+            return;
+        }
+
+        for exchange in &mut self.exchanges {
+            for text in [
+                &mut exchange.path,
+                &mut exchange.request,
+                &mut exchange.response,
+            ] {
+                *text = text.replace(secret, replacement);
+            }
+        }
+    }
+}
+
+enum Mode {
+    Record(Vec<Exchange>),
+    Replay {
+        exchanges: Vec<Exchange>,
+        used: Vec<bool>,
+    },
+}
+
+/// The mode is process wide, so tests using fixtures should not run
+/// in parallel with tests talking to real devices
+static MODE: Mutex<Option<Mode>> = Mutex::new(None);
+
+/// Captures every SOAP exchange until it is finished or dropped
+pub struct Recorder {
+    _private: (),
+}
+
+/// Starts recording, replacing any recording or replay in progress
+pub fn record() -> Recorder {
+    *MODE.lock().unwrap() = Some(Mode::Record(Vec::new()));
+    Recorder { _private: () }
+}
+
+impl Recorder {
+    /// Stops recording and returns the captured exchanges
+    pub fn finish(self) -> Fixture {
+        match MODE.lock().unwrap().take() {
+            Some(Mode::Record(exchanges)) => Fixture {
+                exchanges,
+                ..Default::default()
+            },
+            _ => Fixture::default(),
+        }
+    }
+
+    /// Stops recording and writes the fixture file
+    pub fn save(self, path: impl AsRef<Path>) -> Result<Fixture> {
+        let fixture = self.finish();
+        fixture.save(path)?;
+        Ok(fixture)
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        let mut mode = MODE.lock().unwrap();
+
+        if matches!(*mode, Some(Mode::Record(_))) {
+            *mode = None;
+        }
+    }
+}
+
+/// Answers SOAP requests from a fixture until it is dropped
+pub struct Player {
+    _private: (),
+}
+
+/// Starts replaying `fixture`. Requests are matched on operation and
+/// service path, repeated requests get the recorded answers in order and
+/// then the last one again.
+pub fn replay(fixture: Fixture) -> Player {
+    let used = vec![false; fixture.exchanges.len()];
+
+    *MODE.lock().unwrap() = Some(Mode::Replay {
+        exchanges: fixture.exchanges,
+        used,
+    });
+
+    Player { _private: () }
+}
+
+impl Player {
+    /// Number of recorded exchanges not requested yet
+    pub fn remaining(&self) -> usize {
+        match &*MODE.lock().unwrap() {
+            Some(Mode::Replay { used, .. }) => used.iter().filter(|u| !**u).count(),
+            _ => 0,
+        }
+    }
+}
+
+impl Drop for Player {
+    fn drop(&mut self) {
+        let mut mode = MODE.lock().unwrap();
+
+        if matches!(*mode, Some(Mode::Replay { .. })) {
+            *mode = None;
+        }
+    }
+}
+
+/// First element of the SOAP body, e.g. "GetProfiles"
+pub(crate) fn operation(body: &str) -> String {
+    XmlNode::parse(body.as_bytes())
+        .ok()
+        .and_then(|root| {
+            root.find("Body")
+                .and_then(|b| b.children.first())
+                .map(|op| op.name.clone())
+        })
+        .unwrap_or_default()
+}
+
+fn build_response(status: u16, body: String) -> Result<Response> {
+    let response = http::Response::builder()
+        .status(status)
+        .header("Content-Type", "application/soap+xml; charset=utf-8")
+        .body(body)?;
+
+    Ok(Response::from(response))
+}
+
+/// The recorded answer when replaying, None when the request should go
+/// to the device
+pub(crate) fn replayed(onvif_url: &url::Url, body: &str) -> Result<Option<Response>> {
+    let mut mode = MODE.lock().unwrap();
+
+    let Some(Mode::Replay { exchanges, used }) = mode.as_mut() else {
+        return Ok(None);
+    };
+
+    let operation = operation(body);
+    let path = onvif_url.path();
+    let matching: Vec<usize> = exchanges
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| e.operation == operation && (e.path.is_empty() || e.path == path))
+        .map(|(i, _)| i)
+        .collect();
+
+    let index = matching
+        .iter()
+        .find(|i| !used[**i])
+        .or(matching.last())
+        .copied()
+        .ok_or_else(|| {
+            anyhow!("[Fixtures][replay] No recorded answer for {operation} at {path}")
+        })?;
+
+    used[index] = true;
+    let exchange = &exchanges[index];
+    debug!("Replaying {operation} at {path}");
+
+    build_response(exchange.status, exchange.response.clone()).map(Some)
+}
+
+/// Stores the exchange when recording and hands back an equivalent
+/// response, since reading the body consumes the original
+pub(crate) async fn recorded(
+    onvif_url: &url::Url,
+    body: &str,
+    response: Response,
+) -> Result<Response> {
+    if !matches!(*MODE.lock().unwrap(), Some(Mode::Record(_))) {
+        return Ok(response);
+    }
+
+    let status = response.status().as_u16();
+    let answer: Bytes = response.bytes().await?;
+    let answer = String::from_utf8_lossy(&answer).into_owned();
+
+    if let Some(Mode::Record(exchanges)) = MODE.lock().unwrap().as_mut() {
+        exchanges.push(Exchange {
+            operation: operation(body),
+            path: onvif_url.path().to_string(),
+            request: body.to_string(),
+            status,
+            response: answer.clone(),
+        });
+    }
+
+    build_response(status, answer)
+}
@@ -1,16 +1,147 @@
-use crate::device::{parse_device_type, Device};
-use crate::utils::parse_soap;
+#[cfg(feature = "http")]
+pub mod audit;
+#[cfg(feature = "http")]
+pub mod clock;
+#[cfg(feature = "http")]
+pub mod error;
+#[cfg(feature = "discovery-fallback")]
+pub mod fallback;
+#[cfg(feature = "http")]
+pub mod fixtures;
+#[cfg(feature = "http")]
+pub mod negotiate;
+#[cfg(feature = "http")]
+pub mod proxy;
+#[cfg(feature = "http")]
+pub mod scan;
+#[cfg(feature = "http")]
+pub mod stats;
+#[cfg(feature = "http")]
+pub mod tls;
+#[cfg(feature = "discovery")]
+pub mod watch;
 
+#[cfg(feature = "http")]
+pub use audit::{set_audit_sink, AuditDirection, AuditEntry, AuditLog, AuditSink};
+#[cfg(feature = "http")]
+pub use clock::{clock_offset, forget_clock_offset};
+#[cfg(feature = "http")]
+pub use error::Error;
+#[cfg(feature = "discovery-fallback")]
+pub use fallback::{discover_mdns, discover_ssdp, discover_with_fallback};
+#[cfg(feature = "http")]
+pub use negotiate::{auth_scheme, forget_auth_scheme, soap11};
+#[cfg(feature = "http")]
+pub use proxy::{proxy_probe, proxy_resolve};
+#[cfg(feature = "http")]
+pub use scan::{scan, scan_ports, SCAN_PORTS};
+#[cfg(feature = "http")]
+pub use stats::{reset_stats, stats, CallStats, ClientStats};
+#[cfg(feature = "http")]
+pub use tls::{set_tls_config, tls_config, ClientIdentity, TlsConfig};
+#[cfg(feature = "discovery")]
+pub use watch::{listen, watch, DiscoveryEvent};
+
+#[cfg(feature = "http")]
+use crate::auth::Credentials;
+#[cfg(any(feature = "discovery", feature = "http"))]
+use crate::device::Device;
+use crate::device::{
+    ConfigurationType, FocusMove, ImagingSettings, MetadataConfig, Osd, PtzConfig, PtzVector,
+    StreamProtocol, StreamTransport, VideoEncoderConfig, VideoSourceConfig,
+};
+use crate::provisioning::{FocusDirection, PanDirection, TiltDirection};
+use crate::recording::{
+    RecordedDataExport, RecordingConfiguration, RecordingJobConfiguration, RecordingJobMode,
+};
+#[cfg(feature = "discovery")]
+use crate::rtsp::multicast::MulticastInterface;
+use crate::rtsp::{Message, RtspConnection};
+use crate::search::MetadataSearch;
+#[cfg(feature = "discovery")]
+use crate::soap::{parse_probe_match, parse_relates_to};
+use crate::utils::{xml_escape, xsd_datetime, xsd_duration};
+#[cfg(feature = "http")]
+use error::check_authorized;
+
+#[cfg(feature = "discovery")]
+use anyhow::Context;
 use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Timelike, Utc};
 use log::trace;
+#[cfg(feature = "http")]
 use reqwest::{RequestBuilder, Response};
-use std::{net::SocketAddr, time::Duration};
-use tokio::{net::UdpSocket, time::timeout};
+#[cfg(feature = "discovery")]
+use socket2::SockRef;
+#[cfg(feature = "discovery")]
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+#[cfg(feature = "discovery")]
+use tokio::net::UdpSocket;
+use tokio::time::timeout;
 use url::Url;
 use uuid::Uuid;
 
-const DISCOVER_URI: &'static str = "239.255.255.250:3702";
-const CLIENT_LISTEN_IP: &'static str = "0.0.0.0:0"; // notice port is 0
+#[cfg(feature = "discovery")]
+const DISCOVER_URI: &str = "239.255.255.250:3702";
+/// Hosts client::sweep() probes at the same time
+#[cfg(feature = "discovery")]
+const SWEEP_CONCURRENCY: usize = 64;
+/// Time client::sweep() waits for each host to answer
+#[cfg(feature = "discovery")]
+const SWEEP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Tunes client::discover_with(). The defaults suit a wired LAN, slow
+/// WiFi cameras may need a longer `timeout` and more `probe_retries`.
+#[cfg(feature = "discovery")]
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct DiscoverOptions {
+    /// Time replies are collected after each probe
+    pub timeout:          Duration,
+    /// Probes sent after the first one, UDP multicast being lossy
+    pub probe_retries:    u32,
+    /// Stops as soon as this many devices answered
+    pub max_devices:      Option<usize>,
+    /// Local UDP port probes are sent from and replies received on, 0
+    /// picks a free one
+    pub listen_port:      u16,
+    /// Device types probed for, qualified names in the dn: (ONVIF network)
+    /// or tds: namespace, devices answer when they are of all of them.
+    /// Some devices only answer "tds:Device", an empty list asks every
+    /// device to answer.
+    pub types:            Vec<String>,
+    /// Interface the probes go out on. With several NICs, e.g. a docker
+    /// bridge next to the LAN, the OS may pick the wrong one.
+    pub interface:        MulticastInterface,
+    /// Discovery Proxy probed over HTTP instead of the multicast group
+    /// (WS-Discovery managed mode), see client::proxy_probe(). The
+    /// timeout, retries, port and interface do not apply then.
+    #[cfg(feature = "http")]
+    pub proxy:            Option<url::Url>,
+    /// Drops ProbeMatches whose RelatesTo header is not the MessageID of
+    /// the probe, i.e. stale answers to earlier probes and spoofed ones.
+    /// Some devices leave it out or get it wrong, false accepts them.
+    pub check_relates_to: bool,
+}
+
+#[cfg(feature = "discovery")]
+impl Default for DiscoverOptions {
+    fn default() -> Self {
+        DiscoverOptions {
+            timeout: Duration::from_secs(2),
+            probe_retries: 1,
+            max_devices: None,
+            listen_port: 0,
+            types: vec!["dn:NetworkVideoTransmitter".to_string()],
+            interface: MulticastInterface::Any,
+            #[cfg(feature = "http")]
+            proxy: None,
+            check_relates_to: true,
+        }
+    }
+}
 
 /// All of the ONVIF requests that this program plans to support
 #[derive(Debug)]
@@ -19,7 +150,10 @@ pub enum Messages {
     Capabilities,
     DeviceInfo,
     Profiles,
-    GetStreamURI,
+    /// GetStreamUri of the default profile of the device
+    GetStreamURI {
+        transport: StreamTransport,
+    },
     GetServices, // a summarized version of Capabilities
     GetServiceCapabilities,
     GetDNS,
@@ -38,7 +172,293 @@ pub enum Messages {
     GetEventProperties,
     GetProfiles,
     GetEventBrokers,
-    PullMessages,
+    PullMessages {
+        timeout: Duration,
+        limit: u32,
+    },
+    PanMove {
+        video_source: String,
+        direction: PanDirection,
+        timeout: Option<Duration>,
+    },
+    TiltMove {
+        video_source: String,
+        direction: TiltDirection,
+        timeout: Option<Duration>,
+    },
+    FocusMove {
+        video_source: String,
+        direction: FocusDirection,
+        timeout: Option<Duration>,
+    },
+    ProvisioningStop {
+        video_source: String,
+    },
+    GetUsage {
+        video_source: String,
+    },
+    GetRecordings,
+    CreateRecording {
+        configuration: RecordingConfiguration,
+    },
+    DeleteRecording {
+        recording_token: String,
+    },
+    GetRecordingConfiguration {
+        recording_token: String,
+    },
+    CreateRecordingJob {
+        configuration: RecordingJobConfiguration,
+    },
+    SetRecordingJobMode {
+        job_token: String,
+        mode: RecordingJobMode,
+    },
+    GetRecordingJobs,
+    ExportRecordedData {
+        export: RecordedDataExport,
+    },
+    GetExportRecordedDataStatus {
+        operation_token: String,
+    },
+    StopExportRecordedData {
+        operation_token: String,
+    },
+    GetTrackConfiguration {
+        recording_token: String,
+        track_token: String,
+    },
+    GetRecordingSummary,
+    GetMediaAttributes {
+        recording_tokens: Vec<String>,
+        time: DateTime<Utc>,
+    },
+    FindMetadata {
+        search: MetadataSearch,
+    },
+    GetMetadataSearchResults {
+        search_token: String,
+        max_results: Option<u32>,
+        wait_time: Option<Duration>,
+    },
+    EndSearch {
+        search_token: String,
+    },
+    GetSystemDateAndTime,
+    GetPresets {
+        profile_token: String,
+    },
+    GotoPreset {
+        profile_token: String,
+        preset_token: String,
+    },
+    /// A manual clock, keeping the daylight saving and time zone the
+    /// device already has
+    SetSystemDateAndTime {
+        time: DateTime<Utc>,
+        daylight_savings: bool,
+        time_zone: Option<String>,
+    },
+    Renew {
+        termination: Duration,
+    },
+    Unsubscribe,
+    GetProfileStreamUri {
+        profile_token: String,
+        transport: StreamTransport,
+    },
+    GetMedia2ServiceCapabilities,
+    GetMedia2StreamUri {
+        profile_token: String,
+        protocol: StreamProtocol,
+    },
+    GetSnapshotUri {
+        profile_token: String,
+    },
+    /// A new empty media profile, with the token when given or one the
+    /// device picks
+    CreateProfile {
+        name: String,
+        token: Option<String>,
+    },
+    DeleteProfile {
+        profile_token: String,
+    },
+    AddVideoSourceConfiguration {
+        profile_token: String,
+        configuration_token: String,
+    },
+    AddVideoEncoderConfiguration {
+        profile_token: String,
+        configuration_token: String,
+    },
+    /// Video encoder configurations that can be added to the profile
+    GetCompatibleVideoEncoderConfigurations {
+        profile_token: String,
+    },
+    GetMetadataConfigurations,
+    AddMetadataConfiguration {
+        profile_token: String,
+        configuration_token: String,
+    },
+    SetMetadataConfiguration {
+        configuration: MetadataConfig,
+    },
+    /// Attaches configurations to a Media2 profile, at most one of each type
+    AddConfiguration {
+        profile_token: String,
+        configurations: Vec<(ConfigurationType, String)>,
+    },
+    StartMulticastStreaming {
+        profile_token: String,
+    },
+    StopMulticastStreaming {
+        profile_token: String,
+    },
+    GetVideoSources,
+    GetVideoSourceConfigurations,
+    GetVideoSourceModes {
+        video_source_token: String,
+    },
+    SetVideoSourceMode {
+        video_source_token: String,
+        mode_token: String,
+    },
+    GetAudioSources,
+    GetAudioSourceConfigurations,
+    AddAudioSourceConfiguration {
+        profile_token: String,
+        configuration_token: String,
+    },
+    SetVideoSourceConfiguration {
+        configuration: VideoSourceConfig,
+    },
+    GetVideoEncoderConfiguration {
+        configuration_token: String,
+    },
+    /// Every video encoder configuration, whether in a profile or not
+    GetVideoEncoderConfigurations,
+    SetVideoEncoderConfiguration {
+        configuration: VideoEncoderConfig,
+    },
+    GetVideoEncoderConfigurationOptions {
+        configuration_token: String,
+    },
+    /// Every video encoder configuration of the Media2 service, or the
+    /// one with the token
+    GetMedia2VideoEncoderConfigurations {
+        configuration_token: Option<String>,
+    },
+    SetMedia2VideoEncoderConfiguration {
+        configuration: VideoEncoderConfig,
+    },
+    GetMedia2VideoEncoderConfigurationOptions {
+        configuration_token: String,
+    },
+    /// Every OSD, or those of the video source configuration with the token
+    GetOSDs {
+        configuration_token: Option<String>,
+    },
+    CreateOSD {
+        osd: Osd,
+    },
+    SetOSD {
+        osd: Osd,
+    },
+    DeleteOSD {
+        osd_token: String,
+    },
+    ContinuousMove {
+        profile_token: String,
+        pan: f32,
+        tilt: f32,
+        zoom: f32,
+    },
+    Stop {
+        profile_token: String,
+    },
+    GetPtzNodes,
+    GetPtzConfigurations,
+    GetPtzConfigurationOptions {
+        configuration_token: String,
+    },
+    SetPtzConfiguration {
+        configuration: PtzConfig,
+    },
+    /// Focus move of the imaging service
+    ImagingMove {
+        video_source_token: String,
+        focus: FocusMove,
+    },
+    ImagingStop {
+        video_source_token: String,
+    },
+    GetImagingMoveOptions {
+        video_source_token: String,
+    },
+    GetImagingOptions {
+        video_source_token: String,
+    },
+    GetImagingSettings {
+        video_source_token: String,
+    },
+    SetImagingSettings {
+        video_source_token: String,
+        settings: ImagingSettings,
+    },
+    AbsoluteMove {
+        profile_token: String,
+        position: PtzVector,
+        speed: Option<PtzVector>,
+    },
+    RelativeMove {
+        profile_token: String,
+        translation: PtzVector,
+        speed: Option<PtzVector>,
+    },
+    /// Any body element, sent as is inside the SOAP envelope. The
+    /// namespace prefixes of soap_msg() can be used.
+    Raw {
+        body: String,
+    },
+    GetAccessPointInfoList {
+        start_reference: Option<String>,
+    },
+    GetAccessPointState {
+        token: String,
+    },
+    EnableAccessPoint {
+        token: String,
+    },
+    DisableAccessPoint {
+        token: String,
+    },
+    GetDoorInfoList {
+        start_reference: Option<String>,
+    },
+    GetDoorState {
+        token: String,
+    },
+    AccessDoor {
+        token: String,
+        access_time: Option<Duration>,
+    },
+    LockDoor {
+        token: String,
+    },
+    UnlockDoor {
+        token: String,
+    },
+    /// A WS-Discovery Probe for devices of all these qualified types, in
+    /// the dn: or tds: namespace, or of any type when empty
+    Probe {
+        types: Vec<String>,
+    },
+    /// A WS-Discovery Resolve for the device with this endpoint reference,
+    /// e.g. "urn:uuid:..."
+    Resolve {
+        endpoint: String,
+    },
 }
 
 /// Sends a multicast request via raw udpsocket on LAN.
@@ -49,21 +469,80 @@ pub enum Messages {
 ///
 /// # Examples
 ///
-/// ```
+/// ```no_run
+/// # use onvif_cam_rs::client;
+/// # use onvif_cam_rs::device::camera::Camera;
+/// # async fn run() -> anyhow::Result<()> {
 /// // Find all IP Devices on local network using ONVIF
 /// let mut devices = client::discover().await?;
 /// let mut cameras: Vec<Camera> = Vec::new();
-///
+/// # Ok(())
+/// # }
 /// ```
+#[cfg(feature = "discovery")]
 pub async fn discover() -> Result<Vec<Device>> {
+    discover_with(DiscoverOptions::default()).await
+}
+
+/// Same as discover() with the timeout, retries and limits of `options`
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::client::{self, DiscoverOptions};
+/// # use std::time::Duration;
+/// # async fn run() -> anyhow::Result<()> {
+/// // Slow WiFi cameras
+/// let options = DiscoverOptions {
+///     timeout: Duration::from_secs(5),
+///     probe_retries: 3,
+///     ..Default::default()
+/// };
+/// let devices = client::discover_with(options).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "discovery")]
+pub async fn discover_with(options: DiscoverOptions) -> Result<Vec<Device>> {
+    discover_each(options, |_| ()).await
+}
+
+/// Same as discover_with(), also handing each device to `on_device` as
+/// soon as its first answer arrives, so a UI can list devices while
+/// discovery goes on. The returned list holds the latest answer of each
+/// device.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::client::{self, DiscoverOptions};
+/// # async fn run() -> anyhow::Result<()> {
+/// let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+///
+/// tokio::spawn(client::discover_each(DiscoverOptions::default(), move |device| {
+///     let _ = tx.send(device.clone());
+/// }));
+///
+/// while let Some(device) = rx.recv().await {
+///     println!("Found {}", device.url_onvif);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "discovery")]
+pub async fn discover_each(
+    options: DiscoverOptions,
+    mut on_device: impl FnMut(&Device),
+) -> Result<Vec<Device>> {
+    #[cfg(feature = "http")]
+    if let Some(proxy_url) = options.proxy.clone() {
+        return discover_proxy(proxy_url, options, on_device).await;
+    }
+
     // Discovery is based on ws-discovery
     // Which allows for TCP or UDP
     // We will use a raw UDP socket
-    let addr_listen: Result<SocketAddr, _> = CLIENT_LISTEN_IP.parse();
-    let addr_listen = match addr_listen {
-        Ok(addr) => addr,
-        Err(e) => panic!("[OnvifClient][Discover] Error creating listen address: {e}"),
-    };
+    let udp_client = probe_socket(&options).await?;
 
     let addr_send: Result<SocketAddr, _> = DISCOVER_URI.parse();
     let addr_send = match addr_send {
@@ -71,90 +550,395 @@ pub async fn discover() -> Result<Vec<Device>> {
         Err(e) => panic!("[OnvifClient][Discover] Error creating send address: {e}"),
     };
 
-    // Bind to "0.0.0.0" by default
-    // This is to receive incoming replies
-    let udp_client = UdpSocket::bind(addr_listen).await?;
-
     // Get the XML SOAP message to broadcast
     let uuid = Uuid::new_v4();
-    let msg_discover = soap_msg(&Messages::Discovery, uuid);
+    let msg_discover = soap_msg(
+        &Messages::Probe {
+            types: options.types.clone(),
+        },
+        uuid,
+    );
+
+    #[cfg(feature = "metrics")]
+    let started = Instant::now();
 
     // Get responses to broadcast message
     let mut devices_found: Vec<Device> = Vec::new();
-    let mut devices_check = String::new();
-    let mut try_send = 0;
-
-    while try_send < 2 {
-        let mut try_recv = 0;
-        try_send += 1;
+    let enough = |found: &Vec<Device>| options.max_devices.is_some_and(|max| found.len() >= max);
 
+    'probes: for _ in 0..=options.probe_retries {
         // Send the SOAP message over UDP
         // Use default IP and Port
-        let success = udp_client.send_to(msg_discover.as_ref(), addr_send).await?;
+        udp_client.send_to(msg_discover.as_ref(), addr_send).await?;
+        let deadline = Instant::now() + options.timeout;
+
+        loop {
+            if enough(&devices_found) {
+                break 'probes;
+            }
 
-        while try_recv < 5 {
-            try_recv += 1;
             let mut buf = Vec::with_capacity(4096);
+            let remaining = deadline.saturating_duration_since(Instant::now());
 
-            // Wait 1 sec for a response
-            if let Ok(recv) = timeout(
-                Duration::from_millis(2000),
-                udp_client.recv_buf_from(&mut buf),
-            )
-            .await
-            {
-                match recv {
+            // Collect replies until the deadline of this probe
+            match timeout(remaining, udp_client.recv_buf_from(&mut buf)).await {
+                Err(_) => break,
+                Ok(recv) => match recv {
                     Ok((size, addr)) => {
-                        println!("[OnvifClient][Discover] Received response from: {addr}");
-
-                        if !devices_check.contains(&addr.to_string()) {
-                            println!("[OnvifClient][Discover] Found a new device: {addr}");
-                            println!("[OnvifClient][Discover] Size of response: {size}");
-
-                            // Add to list of devices already found
-                            devices_check = format!("{devices_check}:{addr}");
-
-                            // The SOAP response should provide an XAddrs which will be the
-                            // ONVIF URL of the device that responded
-                            let xaddrs = parse_soap(&buf[..size], "XAddrs", None, true, false);
-                            let url_onvif: Url = xaddrs[0].parse()?;
-
-                            // Get device type
-                            let mut device_type =
-                                parse_soap(&buf[..size], "Types", None, true, false);
-                            let device_type = parse_device_type(device_type.remove(0));
-
-                            // Get scope list
-                            let scopes = parse_soap(&buf[..size], "Scopes", None, true, false);
-                            let scopes = scopes[0]
-                                .split(' ')
-                                .map(|s| s.to_string())
-                                .collect::<Vec<String>>();
-
-                            devices_found.push(Device {
-                                url_onvif,
-                                device_type,
-                                scopes,
-                            });
+                        log::debug!("[OnvifClient][Discover] Received response from: {addr}");
+                        log::debug!("[OnvifClient][Discover] Size of response: {size}");
+
+                        if options.check_relates_to && !relates_to(&buf[..size], uuid) {
+                            log::debug!("[OnvifClient][Discover] Unrelated response from {addr}");
+                            continue;
+                        }
+
+                        // A broken reply, e.g. truncated, must not hide
+                        // the other devices
+                        match parse_probe_match(&buf[..size]) {
+                            Ok(device) => {
+                                if merge_device(&mut devices_found, device) {
+                                    on_device(&devices_found[devices_found.len() - 1]);
+                                }
+                            }
+                            Err(e) => log::debug!(
+                                "[OnvifClient][Discover] Unusable response from {addr}: {e}"
+                            ),
                         }
                     }
                     Err(e) => eprintln!("[OnvifClient][Discover] Error in response {e}"),
+                },
+            }
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    crate::telemetry::discovery(started.elapsed(), devices_found.len());
+
+    if devices_found.is_empty() {
+        return Err(anyhow!(
+            "[OnvifClient][Discover] Unable to find any devices."
+        ));
+    }
+
+    Ok(devices_found)
+}
+
+/// True when `response` answers the message sent with this MessageID.
+/// Devices write it back as "uuid:..." or "urn:uuid:...".
+#[cfg(feature = "discovery")]
+fn relates_to(response: &[u8], message_id: Uuid) -> bool {
+    parse_relates_to(response).is_some_and(|id| {
+        let id = id.trim_start_matches("urn:").trim_start_matches("uuid:");
+        id.eq_ignore_ascii_case(&message_id.to_string())
+    })
+}
+
+/// What client::rediscover() changed in the list of known devices
+#[cfg(feature = "discovery")]
+#[derive(Debug, Clone)]
+pub enum Rediscovered {
+    /// A device that was not known, appended to the list
+    New(Device),
+    /// A known device now answering at another device service URL, e.g.
+    /// after DHCP gave it a new address
+    Moved { from: url::Url, device: Device },
+}
+
+/// Runs discovery again and merges the answers into devices found
+/// earlier. Known devices are matched by endpoint reference, or by URL
+/// when either side has none, and updated in place so their positions
+/// stay the same, new devices are appended. Devices that did not answer
+/// are left untouched. Returns the devices that are new or moved.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::client::{self, DiscoverOptions, Rediscovered};
+/// # async fn run() -> anyhow::Result<()> {
+/// let mut devices = client::discover().await?;
+///
+/// for change in client::rediscover(&mut devices, DiscoverOptions::default()).await? {
+///     if let Rediscovered::Moved { from, device } = change {
+///         println!("{from} moved to {}", device.url_onvif);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "discovery")]
+pub async fn rediscover(
+    known: &mut Vec<Device>,
+    options: DiscoverOptions,
+) -> Result<Vec<Rediscovered>> {
+    let mut changes = Vec::new();
+
+    for device in discover_with(options).await? {
+        let same = |k: &Device| match (&k.endpoint, &device.endpoint) {
+            (Some(known), Some(endpoint)) => known == endpoint,
+            _ => k.url_onvif == device.url_onvif,
+        };
+
+        match known.iter_mut().find(|k| same(k)) {
+            Some(k) => {
+                if k.url_onvif != device.url_onvif {
+                    changes.push(Rediscovered::Moved {
+                        from: k.url_onvif.clone(),
+                        device: device.clone(),
+                    });
                 }
+
+                // Credentials and overrides are never part of discovery answers
+                let credentials = k.credentials.take();
+                let address_override = k.address_override.take();
+                *k = Device {
+                    credentials,
+                    address_override,
+                    ..device
+                };
+            }
+            None => {
+                changes.push(Rediscovered::New(device.clone()));
+                known.push(device);
             }
         }
     }
 
+    Ok(changes)
+}
+
+/// discover_each() through a Discovery Proxy
+#[cfg(all(feature = "discovery", feature = "http"))]
+async fn discover_proxy(
+    proxy_url: url::Url,
+    options: DiscoverOptions,
+    mut on_device: impl FnMut(&Device),
+) -> Result<Vec<Device>> {
+    #[cfg(feature = "metrics")]
+    let started = Instant::now();
+
+    let mut devices_found: Vec<Device> = Vec::new();
+
+    for device in proxy_probe(proxy_url.clone(), options.types).await? {
+        if options
+            .max_devices
+            .is_some_and(|max| devices_found.len() >= max)
+        {
+            break;
+        }
+
+        if merge_device(&mut devices_found, device) {
+            on_device(&devices_found[devices_found.len() - 1]);
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    crate::telemetry::discovery(started.elapsed(), devices_found.len());
+
+    if devices_found.is_empty() {
+        return Err(anyhow!(
+            "[OnvifClient][Discover] {proxy_url} knows no devices."
+        ));
+    }
+
+    Ok(devices_found)
+}
+
+/// Discovers devices by sending a unicast probe to every host of an IPv4
+/// range, e.g. "192.168.1.0/24", for networks whose switches filter
+/// multicast. Up to 64 hosts are probed at a time, each given a second to
+/// answer, so a /24 takes about 4 seconds. Ranges larger than a /16 are
+/// refused.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::client;
+/// # async fn run() -> anyhow::Result<()> {
+/// let devices = client::sweep("192.168.1.0/24").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "discovery")]
+pub async fn sweep(cidr: &str) -> Result<Vec<Device>> {
+    use futures::StreamExt;
+
+    let hosts = cidr_hosts(cidr)?;
+    let probe = soap_msg(
+        &Messages::Probe {
+            types: DiscoverOptions::default().types,
+        },
+        Uuid::new_v4(),
+    );
+
+    let answers: Vec<Option<Device>> = futures::stream::iter(hosts)
+        .map(|host| sweep_host(host, &probe))
+        .buffer_unordered(SWEEP_CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut devices_found: Vec<Device> = Vec::new();
+
+    for device in answers.into_iter().flatten() {
+        merge_device(&mut devices_found, device);
+    }
+
     if devices_found.is_empty() {
-        panic!("[OnvifClient][Discover] Unable to find any devices.");
+        return Err(anyhow!(
+            "[OnvifClient][sweep] Unable to find any devices in {cidr}."
+        ));
     }
 
     Ok(devices_found)
 }
 
+/// The ProbeMatch of one host, None when it does not answer in time
+#[cfg(feature = "discovery")]
+async fn sweep_host(host: Ipv4Addr, probe: &str) -> Option<Device> {
+    let udp_client = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await.ok()?;
+    udp_client
+        .send_to(probe.as_bytes(), (host, 3702))
+        .await
+        .ok()?;
+
+    let deadline = Instant::now() + SWEEP_TIMEOUT;
+
+    loop {
+        let mut buf = Vec::with_capacity(4096);
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let (size, addr) = timeout(remaining, udp_client.recv_buf_from(&mut buf))
+            .await
+            .ok()?
+            .ok()?;
+
+        if addr.ip() != host {
+            continue;
+        }
+
+        match parse_probe_match(&buf[..size]) {
+            Ok(device) => return Some(device),
+            Err(e) => {
+                log::debug!("[OnvifClient][sweep] Unusable response from {addr}: {e}");
+                return None;
+            }
+        }
+    }
+}
+
+/// The host addresses of an IPv4 range, without the network and broadcast
+/// addresses when the prefix leaves room for them. A bare address is a /32.
+#[cfg(feature = "discovery")]
+fn cidr_hosts(cidr: &str) -> Result<Vec<Ipv4Addr>> {
+    let invalid = || anyhow!("[OnvifClient][sweep] Invalid IPv4 range {cidr}");
+
+    let (address, prefix) = match cidr.split_once('/') {
+        Some((address, prefix)) => (address, prefix.parse::<u32>().map_err(|_| invalid())?),
+        None => (cidr, 32),
+    };
+    let address: Ipv4Addr = address.trim().parse().map_err(|_| invalid())?;
+
+    if prefix > 32 {
+        return Err(invalid());
+    }
+
+    if prefix < 16 {
+        return Err(anyhow!(
+            "[OnvifClient][sweep] Range {cidr} is too large, at most a /16 is swept"
+        ));
+    }
+
+    let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+    let network = u32::from(address) & mask;
+    let broadcast = network | !mask;
+
+    let hosts = match prefix {
+        31 | 32 => network..=broadcast,
+        _ => network + 1..=broadcast - 1,
+    };
+
+    Ok(hosts.map(Ipv4Addr::from).collect())
+}
+
+/// Adds a ProbeMatch to the devices found. Devices with several NICs answer
+/// once per address and every device answers each probe, so devices are
+/// told apart by endpoint reference, or by URL when they have none, and
+/// the answer with the highest metadata version is kept. The XAddrs of
+/// all answers are kept as candidates. True when the device is new.
+#[cfg(feature = "discovery")]
+fn merge_device(found: &mut Vec<Device>, mut device: Device) -> bool {
+    let same = |known: &Device| match (&known.endpoint, &device.endpoint) {
+        (Some(known), Some(endpoint)) => known == endpoint,
+        _ => known.url_onvif == device.url_onvif,
+    };
+
+    match found.iter_mut().find(|known| same(known)) {
+        Some(known) => {
+            let newer = device.metadata_version > known.metadata_version;
+            let (kept, other) = match newer {
+                true => (&mut device, &known.xaddrs),
+                false => (&mut *known, &device.xaddrs),
+            };
+
+            for xaddr in other {
+                if !kept.xaddrs.contains(xaddr) {
+                    kept.xaddrs.push(xaddr.clone());
+                }
+            }
+
+            if newer {
+                *known = device;
+            }
+
+            false
+        }
+        None => {
+            log::info!(
+                "[OnvifClient][Discover] Found a new device: {}",
+                device.url_onvif
+            );
+            found.push(device);
+            true
+        }
+    }
+}
+
+/// The socket probes are sent from and ProbeMatches received on, bound to
+/// the interface and port of `options`
+#[cfg(feature = "discovery")]
+async fn probe_socket(options: &DiscoverOptions) -> Result<UdpSocket> {
+    let local = match options.interface {
+        MulticastInterface::Any => Ipv4Addr::UNSPECIFIED,
+        MulticastInterface::Address(local) => local,
+        MulticastInterface::Index(_) => {
+            return Err(anyhow!(
+                "[OnvifClient][Discover] Probes are IPv4, pick the interface by address"
+            ))
+        }
+    };
+    let addr_listen = SocketAddr::from((local, options.listen_port));
+
+    // Bind to "0.0.0.0" by default
+    // This is to receive incoming replies
+    let udp_client = UdpSocket::bind(addr_listen)
+        .await
+        .with_context(|| format!("[OnvifClient][Discover] Unable to bind {addr_listen}"))?;
+
+    // Binding alone does not choose the interface multicast leaves from
+    if let MulticastInterface::Address(local) = options.interface {
+        SockRef::from(&udp_client).set_multicast_if_v4(&local)?;
+    }
+
+    Ok(udp_client)
+}
+
 /// Returns the response received when sending an ONVIF request to a
 /// device found via device discovery
 /// The response is SOAP formatted as byte array
 ///
+/// A device refusing the request for want of credentials, with a 401 or a
+/// NotAuthorized fault, fails it at once with client::Error::AuthRequired,
+/// or AuthFailed when the credentials were wrong.
+///
 /// # Arguments
 ///
 /// * `onvif_url` - The main ONVIF service URL to the device
@@ -162,23 +946,39 @@ pub async fn discover() -> Result<Vec<Device>> {
 ///
 /// # Examples
 ///
-/// ```
+/// ```no_run
+/// # use onvif_cam_rs::client::{self, Messages};
+/// # use onvif_cam_rs::device::StreamTransport;
+/// # async fn run() -> anyhow::Result<()> {
 /// let mut devices = client::discover().await?;
-/// let onvif_url = devices[0].base.url;
+/// let onvif_url = devices.remove(0).url_onvif;
 ///
-/// let response = client::send(onvif_url, Messages::GetStreamURI).await?;
-/// let stream_url = response.remove(0);
+/// let msg = Messages::GetStreamURI {
+///     transport: StreamTransport::RtspOverTcp,
+/// };
+/// let response = client::send(onvif_url, msg).await?;
+/// let stream_url = response.text().await?;
 ///
 /// println!("RTP port for streaming video: {stream_url}");
+/// # Ok(())
+/// # }
 /// ```
+#[cfg(feature = "http")]
 pub async fn send(onvif_url: url::Url, msg: Messages) -> Result<Response> {
     let uuid = Uuid::new_v4();
     let mut try_times = 0;
+    let started = Instant::now();
+    let (onvif_url, credentials) = split_credentials(onvif_url);
 
     // Try to send the reqwest try_times (5)
     // with a 1sec timemout for each reqwest
+    let client = tls::http_client()?;
     let soap_msg = soap_msg(&msg, uuid);
-    let client = reqwest::Client::new();
+    let operation = fixtures::operation(&soap_msg);
+
+    if credentials.is_some() {
+        negotiate::prepare(&client, &onvif_url).await;
+    }
 
     'read: loop {
         try_times += 1;
@@ -187,36 +987,381 @@ pub async fn send(onvif_url: url::Url, msg: Messages) -> Result<Response> {
             break 'read;
         }
 
-        // Create HTTP request using onvif_url
-        let request: RequestBuilder = client
-            .post(onvif_url.clone())
-            .header("Content-Type", "application/soap+xml; charset=utf-8")
-            .body(soap_msg.clone());
-
         // Send the HTTP request and receive the response
-        match timeout(Duration::from_secs(1), request.send()).await {
+        match timeout(
+            Duration::from_secs(1),
+            post(&client, &onvif_url, &soap_msg, credentials.as_ref()),
+        )
+        .await
+        {
             Ok(resp) => {
                 trace!("SOAP reply for {msg:?}: {resp:?}");
+                let retries = try_times - 1;
+                stats::record(
+                    &onvif_url,
+                    operation,
+                    started,
+                    retries,
+                    soap_msg.len(),
+                    resp.as_ref().ok(),
+                );
                 let response = resp?;
-                return Ok(response);
+                return check_authorized(&onvif_url, response, credentials.is_some()).await;
             }
             Err(_) => println!("[Discover][send] Error waiting for response, trying again..."),
         };
     }
 
+    stats::record(
+        &onvif_url,
+        operation,
+        started,
+        try_times - 2,
+        soap_msg.len(),
+        None,
+    );
     Err(anyhow!("[Client] Error getting response from message"))
 }
 
+/// Same as send() with these credentials for this one request, in place
+/// of those `onvif_url` carries, if any
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::auth::Credentials;
+/// # use onvif_cam_rs::client::{self, Messages};
+/// # async fn run(onvif_url: url::Url) -> anyhow::Result<()> {
+/// // Try admin, fall back to viewer
+/// let mut response = client::send_as(onvif_url.clone(), Messages::GetProfiles, &Credentials::new("admin", "secret")).await?;
+///
+/// if !response.status().is_success() {
+///     response = client::send_as(onvif_url, Messages::GetProfiles, &Credentials::new("viewer", "view")).await?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "http")]
+pub async fn send_as(
+    onvif_url: url::Url,
+    msg: Messages,
+    credentials: &Credentials,
+) -> Result<Response> {
+    send(credentials.add_to_url(&onvif_url), msg).await
+}
+
+/// Sends a single request and waits up to `wait` for the response.
+/// Unlike send(), there are no retries, which suits requests the device
+/// may hold open for a while such as PullMessages.
+#[cfg(feature = "http")]
+pub async fn send_timeout(onvif_url: url::Url, msg: Messages, wait: Duration) -> Result<Response> {
+    let (onvif_url, credentials) = split_credentials(onvif_url);
+    let client = tls::http_client()?;
+    let soap_msg = soap_msg(&msg, Uuid::new_v4());
+    let operation = fixtures::operation(&soap_msg);
+
+    if credentials.is_some() {
+        negotiate::prepare(&client, &onvif_url).await;
+    }
+    let started = Instant::now();
+
+    let resp = timeout(
+        wait,
+        post(&client, &onvif_url, &soap_msg, credentials.as_ref()),
+    )
+    .await;
+    let response = resp.as_ref().ok().and_then(|r| r.as_ref().ok());
+    stats::record(&onvif_url, operation, started, 0, soap_msg.len(), response);
+
+    match resp {
+        Ok(resp) => {
+            trace!("SOAP reply for {msg:?}: {resp:?}");
+            check_authorized(&onvif_url, resp?, credentials.is_some()).await
+        }
+        Err(_) => Err(anyhow!(
+            "[Client][send_timeout] No response from {onvif_url}"
+        )),
+    }
+}
+
+/// The URL without its user info and the credentials it carried, see
+/// Device::onvif_url(). Credentials never go out in the request line.
+#[cfg(feature = "http")]
+fn split_credentials(mut onvif_url: Url) -> (Url, Option<Credentials>) {
+    let credentials = Credentials::from_url(&onvif_url);
+
+    if credentials.is_some() {
+        let _ = onvif_url.set_username("");
+        let _ = onvif_url.set_password(None);
+    }
+
+    (onvif_url, credentials)
+}
+
+/// Posts a SOAP message, or answers it from the fixture being replayed.
+/// Exchanges are captured while a fixture is being recorded. Credentials
+/// go in the scheme the device asks for, see negotiate::post_authenticated().
+#[cfg(feature = "http")]
+async fn post(
+    client: &reqwest::Client,
+    onvif_url: &Url,
+    soap_msg: &str,
+    credentials: Option<&Credentials>,
+) -> Result<Response> {
+    if let Some(response) = fixtures::replayed(onvif_url, soap_msg)? {
+        return Ok(response);
+    }
+
+    let post_as = || async {
+        match credentials {
+            Some(credentials) => {
+                negotiate::post_authenticated(client, onvif_url, soap_msg, credentials).await
+            }
+            None => post_once(client, onvif_url, soap_msg, None).await,
+        }
+    };
+
+    // Devices only speaking SOAP 1.1 fault the first request they get
+    let (mut response, mismatch) = negotiate::version_mismatch(onvif_url, post_as().await?).await?;
+    if mismatch {
+        response = post_as().await?;
+    }
+
+    fixtures::recorded(onvif_url, soap_msg, response).await
+}
+
+/// A single HTTP POST of a SOAP message
+#[cfg(feature = "http")]
+async fn post_once(
+    client: &reqwest::Client,
+    onvif_url: &Url,
+    soap_msg: &str,
+    authorization: Option<&str>,
+) -> Result<Response> {
+    let (soap_msg, content_type) = negotiate::soap_version(onvif_url, soap_msg);
+    let mut request: RequestBuilder = client
+        .post(onvif_url.clone())
+        .header("Content-Type", content_type)
+        .body(soap_msg.to_string());
+
+    if let Some(authorization) = authorization {
+        request = request.header(reqwest::header::AUTHORIZATION, authorization);
+    }
+
+    if !audit::enabled() {
+        return Ok(request.send().await?);
+    }
+
+    // Reading the body for the audit sink consumes the response
+    let action = fixtures::operation(&soap_msg);
+    audit::record(AuditDirection::Request, onvif_url, &action, None, &soap_msg);
+
+    let response = request.send().await?;
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = response.bytes().await?;
+
+    let text = String::from_utf8_lossy(&body);
+    audit::record(
+        AuditDirection::Response,
+        onvif_url,
+        &action,
+        Some(status.as_u16()),
+        &text,
+    );
+
+    rebuilt_response(status, &headers, body)
+}
+
+/// A response with the status, headers and body of one already read
+#[cfg(feature = "http")]
+fn rebuilt_response(
+    status: reqwest::StatusCode,
+    headers: &reqwest::header::HeaderMap,
+    body: impl Into<reqwest::Body>,
+) -> Result<Response> {
+    let mut response = http::Response::builder().status(status);
+    for (name, value) in headers {
+        response = response.header(name, value);
+    }
+
+    Ok(Response::from(response.body(body.into())?))
+}
+
+/// Checks that a device answers at `onvif_url` by sending a single
+/// GetSystemDateAndTime request (which needs no authentication) and
+/// returns the round trip time. Unlike send(), there are no retries.
+#[cfg(feature = "http")]
+pub async fn ping(onvif_url: url::Url, wait: Duration) -> Result<Duration> {
+    let start = Instant::now();
+
+    send_timeout(onvif_url, Messages::GetSystemDateAndTime, wait)
+        .await?
+        .error_for_status()?;
+
+    Ok(start.elapsed())
+}
+
+/// Reachability of one device, see client::health_check_all()
+#[cfg(feature = "http")]
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct HealthCheck {
+    pub url_onvif:   url::Url,
+    /// Round trip time of GetSystemDateAndTime, None when offline
+    pub latency:     Option<Duration>,
+    /// Why the device is offline
+    pub error:       Option<String>,
+}
+
+#[cfg(feature = "http")]
+impl HealthCheck {
+    pub fn online(&self) -> bool {
+        self.latency.is_some()
+    }
+}
+
+/// Pings every device at once, see ping(), and reports which are online
+/// and how fast they answered, in the order of `devices`. Cheap enough to
+/// run every few seconds, unlike discovery.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::client;
+/// # use std::time::Duration;
+/// # async fn run(devices: Vec<onvif_cam_rs::device::Device>) {
+/// for check in client::health_check_all(&devices, Duration::from_secs(2)).await {
+///     match check.latency {
+///         Some(latency) => println!("{} online, {latency:?}", check.url_onvif),
+///         None => println!("{} offline: {:?}", check.url_onvif, check.error),
+///     }
+/// }
+/// # }
+/// ```
+#[cfg(feature = "http")]
+pub async fn health_check_all(devices: &[Device], wait: Duration) -> Vec<HealthCheck> {
+    let pings = devices.iter().map(|d| ping(d.url_onvif.clone(), wait));
+    let answers = futures::future::join_all(pings).await;
+
+    devices
+        .iter()
+        .zip(answers)
+        .map(|(device, answer)| HealthCheck {
+            url_onvif: device.url_onvif.clone(),
+            latency: answer.as_ref().ok().copied(),
+            error: answer.err().map(|e| e.to_string()),
+        })
+        .collect()
+}
+
+/// Pings every device service URL the device announced at once and moves
+/// `url_onvif` to the first one, in announced order, that answers. Useful
+/// when a device lists IPv6 or host name XAddrs unreachable from here.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::client;
+/// # use std::time::Duration;
+/// # async fn run() -> anyhow::Result<()> {
+/// for mut device in client::discover().await? {
+///     client::select_xaddr(&mut device, Duration::from_secs(2)).await?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "http")]
+pub async fn select_xaddr(device: &mut Device, wait: Duration) -> Result<url::Url> {
+    let candidates = match device.xaddrs.is_empty() {
+        true => vec![device.url_onvif.clone()],
+        false => device.xaddrs.clone(),
+    };
+
+    let pings = candidates.iter().map(|url| ping(url.clone(), wait));
+    let answers = futures::future::join_all(pings).await;
+
+    let reachable = candidates
+        .into_iter()
+        .zip(answers)
+        .find_map(|(url, answer)| answer.is_ok().then_some(url))
+        .ok_or_else(|| {
+            anyhow!(
+                "[OnvifClient][select_xaddr] No XAddr of {} answers",
+                device.url_onvif
+            )
+        })?;
+
+    device.url_onvif = reachable.clone();
+    Ok(reachable)
+}
+
+/// Checks that something accepts TCP connections at the host and port
+/// of `url` and returns the time taken to connect
+pub async fn tcp_ping(url: url::Url, wait: Duration) -> Result<Duration> {
+    let host = url
+        .host_str()
+        .ok_or_else(|| anyhow!("[Client][tcp_ping] No host in {url}"))?;
+    let port = url
+        .port_or_known_default()
+        .or_else(|| (url.scheme() == "rtsp").then_some(554))
+        .ok_or_else(|| anyhow!("[Client][tcp_ping] No port in {url}"))?;
+
+    let start = Instant::now();
+
+    match timeout(wait, TcpStream::connect((host, port))).await {
+        Ok(stream) => {
+            stream?;
+            Ok(start.elapsed())
+        }
+        Err(_) => Err(anyhow!("[Client][tcp_ping] No connection to {url}")),
+    }
+}
+
+/// Sends an RTSP OPTIONS request for `stream_uri` and returns the round
+/// trip time once the server answers. This checks the media server itself
+/// rather than the ONVIF web service.
+pub async fn rtsp_options(stream_uri: url::Url, wait: Duration) -> Result<Duration> {
+    let start = Instant::now();
+
+    let mut conn = RtspConnection::connect(&stream_uri, wait).await?;
+    let uri = conn.uri().clone();
+    conn.send("OPTIONS", &uri, &[], false).await?;
+
+    // Any status, even 401 Unauthorized, shows the media server is up
+    match timeout(wait, conn.read_message()).await {
+        Ok(Ok(Some(Message::Response(_)))) => Ok(start.elapsed()),
+        Ok(Err(e)) => Err(e),
+        _ => Err(anyhow!(
+            "[Client][rtsp_options] No response from {stream_uri}"
+        )),
+    }
+}
+
 pub fn soap_msg(msg_type: &Messages, uuid: Uuid) -> String {
     let prefix = r#"<Envelope xmlns="http://www.w3.org/2003/05/soap-envelope"
-                         xmlns:tds="http://www.onvif.org/ver10/device/wsdl">
+                         xmlns:tac="http://www.onvif.org/ver10/accesscontrol/wsdl"
+                         xmlns:tdc="http://www.onvif.org/ver10/doorcontrol/wsdl"
+                         xmlns:tds="http://www.onvif.org/ver10/device/wsdl"
+                         xmlns:tev="http://www.onvif.org/ver10/events/wsdl"
+                         xmlns:timg="http://www.onvif.org/ver20/imaging/wsdl"
+                         xmlns:tt="http://www.onvif.org/ver10/schema"
+                         xmlns:tpv="http://www.onvif.org/ver10/provisioning/wsdl"
+                         xmlns:tptz="http://www.onvif.org/ver20/ptz/wsdl"
+                         xmlns:tr2="http://www.onvif.org/ver20/media/wsdl"
+                         xmlns:trc="http://www.onvif.org/ver10/recording/wsdl"
+                         xmlns:trt="http://www.onvif.org/ver10/media/wsdl"
+                         xmlns:tse="http://www.onvif.org/ver10/search/wsdl"
+                         xmlns:wsnt="http://docs.oasis-open.org/wsn/b-2"
+                         xmlns:tns1="http://www.onvif.org/ver10/topics">
                  <Body>"#;
 
     let prefix_discovery = r#"<?xml version="1.0" encoding="UTF-8"?>
                         <e:Envelope xmlns:e="http://www.w3.org/2003/05/soap-envelope"
                         xmlns:w="http://schemas.xmlsoap.org/ws/2004/08/addressing"
                         xmlns:d="http://schemas.xmlsoap.org/ws/2005/04/discovery"
-                        xmlns:dn="http://www.onvif.org/ver10/network/wsdl">"#;
+                        xmlns:dn="http://www.onvif.org/ver10/network/wsdl"
+                        xmlns:tds="http://www.onvif.org/ver10/device/wsdl">"#;
 
     // Insert UUID in the MessageID here
     let header_pt1 = format!("<e:Header><w:MessageID>uuid:{uuid}</w:MessageID>");
@@ -225,21 +1370,29 @@ pub fn soap_msg(msg_type: &Messages, uuid: Uuid) -> String {
                      </e:Header>"#;
 
     let suffix = "</Body></Envelope><Header/>";
-    let suffix_discovery = r#"<e:Body>
-                                   <d:Probe>
-                                       <d:Types>dn:NetworkVideoTransmitter</d:Types>
-                                   </d:Probe>
-                               </e:Body>
-                           </e:Envelope>"#;
-
-    let stream = r#"<trt:GetStreamUri>
-           <trt:StreamSetup>
-               <tt:Stream>RTP-multicast</tt:Stream>
-               <tt:Transport>
-                   <tt:Protocol>RTSP</tt:Protocol>
-               </tt:Transport>
-           </trt:StreamSetup>
-       </trt:GetStreamUri>"#;
+    // Without Types every device answers
+    let probe_body = |types: &Vec<String>| {
+        let types = match types.is_empty() {
+            true => String::new(),
+            false => format!("<d:Types>{}</d:Types>", xml_escape(&types.join(" "))),
+        };
+
+        format!(
+            "<e:Body>
+                <d:Probe>
+                    {types}
+                </d:Probe>
+            </e:Body>
+        </e:Envelope>"
+        )
+    };
+    let suffix_discovery = probe_body(&vec!["dn:NetworkVideoTransmitter".to_string()]);
+
+    // Optional xs:duration element shared by the provisioning moves
+    let provisioning_timeout = |timeout: &Option<Duration>| match timeout {
+        Some(t) => format!("<tpv:Timeout>{}</tpv:Timeout>", xsd_duration(*t)),
+        None => String::new(),
+    };
 
     match msg_type {
         Messages::Discovery => format!(
@@ -273,12 +1426,21 @@ pub fn soap_msg(msg_type: &Messages, uuid: Uuid) -> String {
                 {suffix}
             "
         ),
-        Messages::GetStreamURI => format!(
+        Messages::GetStreamURI { transport } => format!(
             "
                 {prefix}
-                {stream}
+                <trt:GetStreamUri>
+                    <trt:StreamSetup>
+                        <tt:Stream>{}</tt:Stream>
+                        <tt:Transport>
+                            <tt:Protocol>{}</tt:Protocol>
+                        </tt:Transport>
+                    </trt:StreamSetup>
+                </trt:GetStreamUri>
                 {suffix}
-            "
+            ",
+            transport.stream(),
+            transport.as_str()
         ),
         Messages::GetServices => format!(
             "
@@ -425,15 +1587,1088 @@ pub fn soap_msg(msg_type: &Messages, uuid: Uuid) -> String {
                 {suffix}
             "
         ),
-        Messages::PullMessages => format!(
+        Messages::PullMessages { timeout, limit } => format!(
             "
                 {prefix}
-                <wsnt:PullMessages>
-                    <wsnt:Timeout>PT5S</wsnt:Timeout>
-                    <wsnt:MessageLimit>10</wsnt:MessageLimit>
-                </wsnt:PullMessages>
+                <tev:PullMessages>
+                    <tev:Timeout>{}</tev:Timeout>
+                    <tev:MessageLimit>{limit}</tev:MessageLimit>
+                </tev:PullMessages>
                 {suffix}
+            ",
+            xsd_duration(*timeout)
+        ),
+        Messages::PanMove {
+            video_source,
+            direction,
+            timeout,
+        } => format!(
             "
+                {prefix}
+                <tpv:PanMove>
+                    <tpv:VideoSource>{}</tpv:VideoSource>
+                    <tpv:Direction>{}</tpv:Direction>
+                    {}
+                </tpv:PanMove>
+                {suffix}
+            ",
+            xml_escape(video_source),
+            direction.as_str(),
+            provisioning_timeout(timeout)
         ),
-    }
+        Messages::TiltMove {
+            video_source,
+            direction,
+            timeout,
+        } => format!(
+            "
+                {prefix}
+                <tpv:TiltMove>
+                    <tpv:VideoSource>{}</tpv:VideoSource>
+                    <tpv:Direction>{}</tpv:Direction>
+                    {}
+                </tpv:TiltMove>
+                {suffix}
+            ",
+            xml_escape(video_source),
+            direction.as_str(),
+            provisioning_timeout(timeout)
+        ),
+        Messages::FocusMove {
+            video_source,
+            direction,
+            timeout,
+        } => format!(
+            "
+                {prefix}
+                <tpv:FocusMove>
+                    <tpv:VideoSource>{}</tpv:VideoSource>
+                    <tpv:Direction>{}</tpv:Direction>
+                    {}
+                </tpv:FocusMove>
+                {suffix}
+            ",
+            xml_escape(video_source),
+            direction.as_str(),
+            provisioning_timeout(timeout)
+        ),
+        Messages::ProvisioningStop { video_source } => format!(
+            "
+                {prefix}
+                <tpv:Stop>
+                    <tpv:VideoSource>{}</tpv:VideoSource>
+                </tpv:Stop>
+                {suffix}
+            ",
+            xml_escape(video_source)
+        ),
+        Messages::GetUsage { video_source } => format!(
+            "
+                {prefix}
+                <tpv:GetUsage>
+                    <tpv:VideoSource>{}</tpv:VideoSource>
+                </tpv:GetUsage>
+                {suffix}
+            ",
+            xml_escape(video_source)
+        ),
+        Messages::GetRecordings => format!(
+            "
+                {prefix}
+                <trc:GetRecordings/>
+                {suffix}
+            "
+        ),
+        Messages::CreateRecording { configuration } => format!(
+            "
+                {prefix}
+                <trc:CreateRecording>
+                    <trc:RecordingConfiguration>
+                        {}
+                    </trc:RecordingConfiguration>
+                </trc:CreateRecording>
+                {suffix}
+            ",
+            configuration.to_soap()
+        ),
+        Messages::DeleteRecording { recording_token } => format!(
+            "
+                {prefix}
+                <trc:DeleteRecording>
+                    <trc:RecordingToken>{}</trc:RecordingToken>
+                </trc:DeleteRecording>
+                {suffix}
+            ",
+            xml_escape(recording_token)
+        ),
+        Messages::GetRecordingConfiguration { recording_token } => format!(
+            "
+                {prefix}
+                <trc:GetRecordingConfiguration>
+                    <trc:RecordingToken>{}</trc:RecordingToken>
+                </trc:GetRecordingConfiguration>
+                {suffix}
+            ",
+            xml_escape(recording_token)
+        ),
+        Messages::CreateRecordingJob { configuration } => format!(
+            "
+                {prefix}
+                <trc:CreateRecordingJob>
+                    <trc:JobConfiguration>
+                        {}
+                    </trc:JobConfiguration>
+                </trc:CreateRecordingJob>
+                {suffix}
+            ",
+            configuration.to_soap()
+        ),
+        Messages::SetRecordingJobMode { job_token, mode } => format!(
+            "
+                {prefix}
+                <trc:SetRecordingJobMode>
+                    <trc:JobToken>{}</trc:JobToken>
+                    <trc:Mode>{}</trc:Mode>
+                </trc:SetRecordingJobMode>
+                {suffix}
+            ",
+            xml_escape(job_token),
+            mode.as_str()
+        ),
+        Messages::GetRecordingJobs => format!(
+            "
+                {prefix}
+                <trc:GetRecordingJobs/>
+                {suffix}
+            "
+        ),
+        Messages::ExportRecordedData { export } => format!(
+            "
+                {prefix}
+                <trc:ExportRecordedData>
+                    {}
+                </trc:ExportRecordedData>
+                {suffix}
+            ",
+            export.to_soap()
+        ),
+        Messages::GetExportRecordedDataStatus { operation_token } => format!(
+            "
+                {prefix}
+                <trc:GetExportRecordedDataStatus>
+                    <trc:OperationToken>{}</trc:OperationToken>
+                </trc:GetExportRecordedDataStatus>
+                {suffix}
+            ",
+            xml_escape(operation_token)
+        ),
+        Messages::StopExportRecordedData { operation_token } => format!(
+            "
+                {prefix}
+                <trc:StopExportRecordedData>
+                    <trc:OperationToken>{}</trc:OperationToken>
+                </trc:StopExportRecordedData>
+                {suffix}
+            ",
+            xml_escape(operation_token)
+        ),
+        Messages::GetTrackConfiguration {
+            recording_token,
+            track_token,
+        } => format!(
+            "
+                {prefix}
+                <trc:GetTrackConfiguration>
+                    <trc:RecordingToken>{}</trc:RecordingToken>
+                    <trc:TrackToken>{}</trc:TrackToken>
+                </trc:GetTrackConfiguration>
+                {suffix}
+            ",
+            xml_escape(recording_token),
+            xml_escape(track_token)
+        ),
+        Messages::GetRecordingSummary => format!(
+            "
+                {prefix}
+                <tse:GetRecordingSummary/>
+                {suffix}
+            "
+        ),
+        Messages::GetMediaAttributes {
+            recording_tokens,
+            time,
+        } => format!(
+            "
+                {prefix}
+                <tse:GetMediaAttributes>
+                    {}
+                    <tse:Time>{}</tse:Time>
+                </tse:GetMediaAttributes>
+                {suffix}
+            ",
+            recording_tokens
+                .iter()
+                .map(|t| format!(
+                    "<tse:RecordingTokens>{}</tse:RecordingTokens>",
+                    xml_escape(t)
+                ))
+                .collect::<String>(),
+            xsd_datetime(time)
+        ),
+        Messages::FindMetadata { search } => format!(
+            "
+                {prefix}
+                <tse:FindMetadata>
+                    {}
+                </tse:FindMetadata>
+                {suffix}
+            ",
+            search.to_soap()
+        ),
+        Messages::GetMetadataSearchResults {
+            search_token,
+            max_results,
+            wait_time,
+        } => format!(
+            "
+                {prefix}
+                <tse:GetMetadataSearchResults>
+                    <tse:SearchToken>{}</tse:SearchToken>
+                    {}
+                    {}
+                </tse:GetMetadataSearchResults>
+                {suffix}
+            ",
+            xml_escape(search_token),
+            max_results
+                .map(|m| format!("<tse:MaxResults>{m}</tse:MaxResults>"))
+                .unwrap_or_default(),
+            wait_time
+                .map(|w| format!("<tse:WaitTime>{}</tse:WaitTime>", xsd_duration(w)))
+                .unwrap_or_default()
+        ),
+        Messages::EndSearch { search_token } => format!(
+            "
+                {prefix}
+                <tse:EndSearch>
+                    <tse:SearchToken>{}</tse:SearchToken>
+                </tse:EndSearch>
+                {suffix}
+            ",
+            xml_escape(search_token)
+        ),
+        Messages::GetSystemDateAndTime => format!(
+            "
+                {prefix}
+                <tds:GetSystemDateAndTime/>
+                {suffix}
+            "
+        ),
+        Messages::GetPresets { profile_token } => format!(
+            "
+                {prefix}
+                <tptz:GetPresets>
+                    <tptz:ProfileToken>{}</tptz:ProfileToken>
+                </tptz:GetPresets>
+                {suffix}
+            ",
+            xml_escape(profile_token)
+        ),
+        Messages::GotoPreset {
+            profile_token,
+            preset_token,
+        } => format!(
+            "
+                {prefix}
+                <tptz:GotoPreset>
+                    <tptz:ProfileToken>{}</tptz:ProfileToken>
+                    <tptz:PresetToken>{}</tptz:PresetToken>
+                </tptz:GotoPreset>
+                {suffix}
+            ",
+            xml_escape(profile_token),
+            xml_escape(preset_token)
+        ),
+        Messages::SetSystemDateAndTime {
+            time,
+            daylight_savings,
+            time_zone,
+        } => format!(
+            "
+                {prefix}
+                <tds:SetSystemDateAndTime>
+                    <tds:DateTimeType>Manual</tds:DateTimeType>
+                    <tds:DaylightSavings>{daylight_savings}</tds:DaylightSavings>
+                    {}
+                    <tds:UTCDateTime>
+                        <tt:Date>
+                            <tt:Year>{}</tt:Year>
+                            <tt:Month>{}</tt:Month>
+                            <tt:Day>{}</tt:Day>
+                        </tt:Date>
+                        <tt:Time>
+                            <tt:Hour>{}</tt:Hour>
+                            <tt:Minute>{}</tt:Minute>
+                            <tt:Second>{}</tt:Second>
+                        </tt:Time>
+                    </tds:UTCDateTime>
+                </tds:SetSystemDateAndTime>
+                {suffix}
+            ",
+            time_zone
+                .as_deref()
+                .map(|tz| format!("<tds:TimeZone><tt:TZ>{}</tt:TZ></tds:TimeZone>", xml_escape(tz)))
+                .unwrap_or_default(),
+            time.year(),
+            time.month(),
+            time.day(),
+            time.hour(),
+            time.minute(),
+            time.second()
+        ),
+        Messages::Renew { termination } => format!(
+            "
+                {prefix}
+                <wsnt:Renew>
+                    <wsnt:TerminationTime>{}</wsnt:TerminationTime>
+                </wsnt:Renew>
+                {suffix}
+            ",
+            xsd_duration(*termination)
+        ),
+        Messages::Unsubscribe => format!(
+            "
+                {prefix}
+                <wsnt:Unsubscribe/>
+                {suffix}
+            "
+        ),
+        Messages::GetProfileStreamUri {
+            profile_token,
+            transport,
+        } => format!(
+            "
+                {prefix}
+                <trt:GetStreamUri>
+                    <trt:StreamSetup>
+                        <tt:Stream>{}</tt:Stream>
+                        <tt:Transport>
+                            <tt:Protocol>{}</tt:Protocol>
+                        </tt:Transport>
+                    </trt:StreamSetup>
+                    <trt:ProfileToken>{}</trt:ProfileToken>
+                </trt:GetStreamUri>
+                {suffix}
+            ",
+            transport.stream(),
+            transport.as_str(),
+            xml_escape(profile_token)
+        ),
+        Messages::GetMedia2ServiceCapabilities => format!(
+            "
+                {prefix}
+                <tr2:GetServiceCapabilities/>
+                {suffix}
+            "
+        ),
+        Messages::GetMedia2StreamUri {
+            profile_token,
+            protocol,
+        } => format!(
+            "
+                {prefix}
+                <tr2:GetStreamUri>
+                    <tr2:Protocol>{}</tr2:Protocol>
+                    <tr2:ProfileToken>{}</tr2:ProfileToken>
+                </tr2:GetStreamUri>
+                {suffix}
+            ",
+            protocol.as_str(),
+            xml_escape(profile_token)
+        ),
+        Messages::GetSnapshotUri { profile_token } => format!(
+            "
+                {prefix}
+                <trt:GetSnapshotUri>
+                    <trt:ProfileToken>{}</trt:ProfileToken>
+                </trt:GetSnapshotUri>
+                {suffix}
+            ",
+            xml_escape(profile_token)
+        ),
+        Messages::CreateProfile { name, token } => format!(
+            "
+                {prefix}
+                <trt:CreateProfile>
+                    <trt:Name>{}</trt:Name>
+                    {}
+                </trt:CreateProfile>
+                {suffix}
+            ",
+            xml_escape(name),
+            token
+                .as_deref()
+                .map(|t| format!("<trt:Token>{}</trt:Token>", xml_escape(t)))
+                .unwrap_or_default()
+        ),
+        Messages::DeleteProfile { profile_token } => format!(
+            "
+                {prefix}
+                <trt:DeleteProfile>
+                    <trt:ProfileToken>{}</trt:ProfileToken>
+                </trt:DeleteProfile>
+                {suffix}
+            ",
+            xml_escape(profile_token)
+        ),
+        Messages::AddVideoSourceConfiguration {
+            profile_token,
+            configuration_token,
+        } => format!(
+            "
+                {prefix}
+                <trt:AddVideoSourceConfiguration>
+                    <trt:ProfileToken>{}</trt:ProfileToken>
+                    <trt:ConfigurationToken>{}</trt:ConfigurationToken>
+                </trt:AddVideoSourceConfiguration>
+                {suffix}
+            ",
+            xml_escape(profile_token),
+            xml_escape(configuration_token)
+        ),
+        Messages::AddVideoEncoderConfiguration {
+            profile_token,
+            configuration_token,
+        } => format!(
+            "
+                {prefix}
+                <trt:AddVideoEncoderConfiguration>
+                    <trt:ProfileToken>{}</trt:ProfileToken>
+                    <trt:ConfigurationToken>{}</trt:ConfigurationToken>
+                </trt:AddVideoEncoderConfiguration>
+                {suffix}
+            ",
+            xml_escape(profile_token),
+            xml_escape(configuration_token)
+        ),
+        Messages::GetCompatibleVideoEncoderConfigurations { profile_token } => format!(
+            "
+                {prefix}
+                <trt:GetCompatibleVideoEncoderConfigurations>
+                    <trt:ProfileToken>{}</trt:ProfileToken>
+                </trt:GetCompatibleVideoEncoderConfigurations>
+                {suffix}
+            ",
+            xml_escape(profile_token)
+        ),
+        Messages::GetMetadataConfigurations => format!(
+            "
+                {prefix}
+                <trt:GetMetadataConfigurations/>
+                {suffix}
+            "
+        ),
+        Messages::AddMetadataConfiguration {
+            profile_token,
+            configuration_token,
+        } => format!(
+            "
+                {prefix}
+                <trt:AddMetadataConfiguration>
+                    <trt:ProfileToken>{}</trt:ProfileToken>
+                    <trt:ConfigurationToken>{}</trt:ConfigurationToken>
+                </trt:AddMetadataConfiguration>
+                {suffix}
+            ",
+            xml_escape(profile_token),
+            xml_escape(configuration_token)
+        ),
+        Messages::SetMetadataConfiguration { configuration } => format!(
+            "
+                {prefix}
+                <trt:SetMetadataConfiguration>
+                    {}
+                    {}
+                </trt:SetMetadataConfiguration>
+                {suffix}
+            ",
+            configuration.to_soap(),
+            force_persistence("trt")
+        ),
+        Messages::AddConfiguration {
+            profile_token,
+            configurations,
+        } => format!(
+            "
+                {prefix}
+                <tr2:AddConfiguration>
+                    <tr2:ProfileToken>{}</tr2:ProfileToken>
+                    {}
+                </tr2:AddConfiguration>
+                {suffix}
+            ",
+            xml_escape(profile_token),
+            configurations
+                .iter()
+                .map(|(kind, token)| format!(
+                    "<tr2:Configuration><tr2:Type>{}</tr2:Type><tr2:Token>{}</tr2:Token></tr2:Configuration>",
+                    kind.as_str(),
+                    xml_escape(token)
+                ))
+                .collect::<String>()
+        ),
+        Messages::StartMulticastStreaming { profile_token } => format!(
+            "
+                {prefix}
+                <trt:StartMulticastStreaming>
+                    <trt:ProfileToken>{}</trt:ProfileToken>
+                </trt:StartMulticastStreaming>
+                {suffix}
+            ",
+            xml_escape(profile_token)
+        ),
+        Messages::StopMulticastStreaming { profile_token } => format!(
+            "
+                {prefix}
+                <trt:StopMulticastStreaming>
+                    <trt:ProfileToken>{}</trt:ProfileToken>
+                </trt:StopMulticastStreaming>
+                {suffix}
+            ",
+            xml_escape(profile_token)
+        ),
+        Messages::GetVideoSources => format!(
+            "
+                {prefix}
+                <trt:GetVideoSources/>
+                {suffix}
+            "
+        ),
+        Messages::GetVideoSourceConfigurations => format!(
+            "
+                {prefix}
+                <trt:GetVideoSourceConfigurations/>
+                {suffix}
+            "
+        ),
+        Messages::GetVideoSourceModes { video_source_token } => format!(
+            "
+                {prefix}
+                <tr2:GetVideoSourceModes>
+                    <tr2:VideoSourceToken>{}</tr2:VideoSourceToken>
+                </tr2:GetVideoSourceModes>
+                {suffix}
+            ",
+            xml_escape(video_source_token)
+        ),
+        Messages::SetVideoSourceMode {
+            video_source_token,
+            mode_token,
+        } => format!(
+            "
+                {prefix}
+                <tr2:SetVideoSourceMode>
+                    <tr2:VideoSourceToken>{}</tr2:VideoSourceToken>
+                    <tr2:VideoSourceModeToken>{}</tr2:VideoSourceModeToken>
+                </tr2:SetVideoSourceMode>
+                {suffix}
+            ",
+            xml_escape(video_source_token),
+            xml_escape(mode_token)
+        ),
+        Messages::GetAudioSources => format!(
+            "
+                {prefix}
+                <trt:GetAudioSources/>
+                {suffix}
+            "
+        ),
+        Messages::GetAudioSourceConfigurations => format!(
+            "
+                {prefix}
+                <trt:GetAudioSourceConfigurations/>
+                {suffix}
+            "
+        ),
+        Messages::AddAudioSourceConfiguration {
+            profile_token,
+            configuration_token,
+        } => format!(
+            "
+                {prefix}
+                <trt:AddAudioSourceConfiguration>
+                    <trt:ProfileToken>{}</trt:ProfileToken>
+                    <trt:ConfigurationToken>{}</trt:ConfigurationToken>
+                </trt:AddAudioSourceConfiguration>
+                {suffix}
+            ",
+            xml_escape(profile_token),
+            xml_escape(configuration_token)
+        ),
+        Messages::SetVideoSourceConfiguration { configuration } => format!(
+            "
+                {prefix}
+                <trt:SetVideoSourceConfiguration>
+                    {}
+                    {}
+                </trt:SetVideoSourceConfiguration>
+                {suffix}
+            ",
+            configuration.to_soap(),
+            force_persistence("trt")
+        ),
+        Messages::GetVideoEncoderConfiguration {
+            configuration_token,
+        } => format!(
+            "
+                {prefix}
+                <trt:GetVideoEncoderConfiguration>
+                    <trt:ConfigurationToken>{}</trt:ConfigurationToken>
+                </trt:GetVideoEncoderConfiguration>
+                {suffix}
+            ",
+            xml_escape(configuration_token)
+        ),
+        Messages::GetVideoEncoderConfigurations => format!(
+            "
+                {prefix}
+                <trt:GetVideoEncoderConfigurations/>
+                {suffix}
+            "
+        ),
+        Messages::SetVideoEncoderConfiguration { configuration } => format!(
+            "
+                {prefix}
+                <trt:SetVideoEncoderConfiguration>
+                    {}
+                    {}
+                </trt:SetVideoEncoderConfiguration>
+                {suffix}
+            ",
+            configuration.to_soap(),
+            force_persistence("trt")
+        ),
+        Messages::GetVideoEncoderConfigurationOptions {
+            configuration_token,
+        } => format!(
+            "
+                {prefix}
+                <trt:GetVideoEncoderConfigurationOptions>
+                    <trt:ConfigurationToken>{}</trt:ConfigurationToken>
+                </trt:GetVideoEncoderConfigurationOptions>
+                {suffix}
+            ",
+            xml_escape(configuration_token)
+        ),
+        Messages::GetMedia2VideoEncoderConfigurations {
+            configuration_token,
+        } => format!(
+            "
+                {prefix}
+                <tr2:GetVideoEncoderConfigurations>
+                    {}
+                </tr2:GetVideoEncoderConfigurations>
+                {suffix}
+            ",
+            configuration_token
+                .as_deref()
+                .map(|t| format!(
+                    "<tr2:ConfigurationToken>{}</tr2:ConfigurationToken>",
+                    xml_escape(t)
+                ))
+                .unwrap_or_default()
+        ),
+        Messages::GetMedia2VideoEncoderConfigurationOptions {
+            configuration_token,
+        } => format!(
+            "
+                {prefix}
+                <tr2:GetVideoEncoderConfigurationOptions>
+                    <tr2:ConfigurationToken>{}</tr2:ConfigurationToken>
+                </tr2:GetVideoEncoderConfigurationOptions>
+                {suffix}
+            ",
+            xml_escape(configuration_token)
+        ),
+        Messages::SetMedia2VideoEncoderConfiguration { configuration } => format!(
+            "
+                {prefix}
+                <tr2:SetVideoEncoderConfiguration>
+                    {}
+                </tr2:SetVideoEncoderConfiguration>
+                {suffix}
+            ",
+            configuration.to_soap2()
+        ),
+        Messages::GetOSDs {
+            configuration_token,
+        } => format!(
+            "
+                {prefix}
+                <tr2:GetOSDs>
+                    {}
+                </tr2:GetOSDs>
+                {suffix}
+            ",
+            configuration_token
+                .as_deref()
+                .map(|t| format!(
+                    "<tr2:ConfigurationToken>{}</tr2:ConfigurationToken>",
+                    xml_escape(t)
+                ))
+                .unwrap_or_default()
+        ),
+        Messages::CreateOSD { osd } => format!(
+            "
+                {prefix}
+                <tr2:CreateOSD>
+                    {}
+                </tr2:CreateOSD>
+                {suffix}
+            ",
+            osd.to_soap()
+        ),
+        Messages::SetOSD { osd } => format!(
+            "
+                {prefix}
+                <tr2:SetOSD>
+                    {}
+                </tr2:SetOSD>
+                {suffix}
+            ",
+            osd.to_soap()
+        ),
+        Messages::DeleteOSD { osd_token } => format!(
+            "
+                {prefix}
+                <tr2:DeleteOSD>
+                    <tr2:OSDToken>{}</tr2:OSDToken>
+                </tr2:DeleteOSD>
+                {suffix}
+            ",
+            xml_escape(osd_token)
+        ),
+        Messages::ContinuousMove {
+            profile_token,
+            pan,
+            tilt,
+            zoom,
+        } => format!(
+            r#"
+                {prefix}
+                <tptz:ContinuousMove>
+                    <tptz:ProfileToken>{}</tptz:ProfileToken>
+                    <tptz:Velocity>
+                        <tt:PanTilt x="{pan}" y="{tilt}"/>
+                        <tt:Zoom x="{zoom}"/>
+                    </tptz:Velocity>
+                </tptz:ContinuousMove>
+                {suffix}
+            "#,
+            xml_escape(profile_token)
+        ),
+        Messages::Stop { profile_token } => format!(
+            "
+                {prefix}
+                <tptz:Stop>
+                    <tptz:ProfileToken>{}</tptz:ProfileToken>
+                    <tptz:PanTilt>true</tptz:PanTilt>
+                    <tptz:Zoom>true</tptz:Zoom>
+                </tptz:Stop>
+                {suffix}
+            ",
+            xml_escape(profile_token)
+        ),
+        Messages::ImagingMove {
+            video_source_token,
+            focus,
+        } => format!(
+            "
+                {prefix}
+                <timg:Move>
+                    <timg:VideoSourceToken>{}</timg:VideoSourceToken>
+                    <timg:Focus>{}</timg:Focus>
+                </timg:Move>
+                {suffix}
+            ",
+            xml_escape(video_source_token),
+            focus.to_soap()
+        ),
+        Messages::ImagingStop { video_source_token } => format!(
+            "
+                {prefix}
+                <timg:Stop>
+                    <timg:VideoSourceToken>{}</timg:VideoSourceToken>
+                </timg:Stop>
+                {suffix}
+            ",
+            xml_escape(video_source_token)
+        ),
+        Messages::GetImagingMoveOptions { video_source_token } => format!(
+            "
+                {prefix}
+                <timg:GetMoveOptions>
+                    <timg:VideoSourceToken>{}</timg:VideoSourceToken>
+                </timg:GetMoveOptions>
+                {suffix}
+            ",
+            xml_escape(video_source_token)
+        ),
+        Messages::GetImagingOptions { video_source_token } => format!(
+            "
+                {prefix}
+                <timg:GetOptions>
+                    <timg:VideoSourceToken>{}</timg:VideoSourceToken>
+                </timg:GetOptions>
+                {suffix}
+            ",
+            xml_escape(video_source_token)
+        ),
+        Messages::GetImagingSettings { video_source_token } => format!(
+            "
+                {prefix}
+                <timg:GetImagingSettings>
+                    <timg:VideoSourceToken>{}</timg:VideoSourceToken>
+                </timg:GetImagingSettings>
+                {suffix}
+            ",
+            xml_escape(video_source_token)
+        ),
+        Messages::SetImagingSettings {
+            video_source_token,
+            settings,
+        } => format!(
+            "
+                {prefix}
+                <timg:SetImagingSettings>
+                    <timg:VideoSourceToken>{}</timg:VideoSourceToken>
+                    {}
+                    {}
+                </timg:SetImagingSettings>
+                {suffix}
+            ",
+            xml_escape(video_source_token),
+            settings.to_soap(),
+            force_persistence("timg")
+        ),
+        Messages::GetPtzNodes => format!(
+            "
+                {prefix}
+                <tptz:GetNodes/>
+                {suffix}
+            "
+        ),
+        Messages::GetPtzConfigurations => format!(
+            "
+                {prefix}
+                <tptz:GetConfigurations/>
+                {suffix}
+            "
+        ),
+        Messages::GetPtzConfigurationOptions {
+            configuration_token,
+        } => format!(
+            "
+                {prefix}
+                <tptz:GetConfigurationOptions>
+                    <tptz:ConfigurationToken>{}</tptz:ConfigurationToken>
+                </tptz:GetConfigurationOptions>
+                {suffix}
+            ",
+            xml_escape(configuration_token)
+        ),
+        Messages::SetPtzConfiguration { configuration } => format!(
+            "
+                {prefix}
+                <tptz:SetConfiguration>
+                    {}
+                    {}
+                </tptz:SetConfiguration>
+                {suffix}
+            ",
+            configuration.to_soap(),
+            force_persistence("tptz")
+        ),
+        Messages::AbsoluteMove {
+            profile_token,
+            position,
+            speed,
+        } => format!(
+            "
+                {prefix}
+                <tptz:AbsoluteMove>
+                    <tptz:ProfileToken>{}</tptz:ProfileToken>
+                    <tptz:Position>{}</tptz:Position>
+                    {}
+                </tptz:AbsoluteMove>
+                {suffix}
+            ",
+            xml_escape(profile_token),
+            position.to_soap(),
+            speed
+                .map(|s| format!("<tptz:Speed>{}</tptz:Speed>", s.to_soap()))
+                .unwrap_or_default()
+        ),
+        Messages::RelativeMove {
+            profile_token,
+            translation,
+            speed,
+        } => format!(
+            "
+                {prefix}
+                <tptz:RelativeMove>
+                    <tptz:ProfileToken>{}</tptz:ProfileToken>
+                    <tptz:Translation>{}</tptz:Translation>
+                    {}
+                </tptz:RelativeMove>
+                {suffix}
+            ",
+            xml_escape(profile_token),
+            translation.to_soap(),
+            speed
+                .map(|s| format!("<tptz:Speed>{}</tptz:Speed>", s.to_soap()))
+                .unwrap_or_default()
+        ),
+        Messages::Raw { body } => format!(
+            "
+                {prefix}
+                {body}
+                {suffix}
+            "
+        ),
+        Messages::GetAccessPointInfoList { start_reference } => format!(
+            "
+                {prefix}
+                <tac:GetAccessPointInfoList>
+                    {}
+                </tac:GetAccessPointInfoList>
+                {suffix}
+            ",
+            start_reference
+                .as_deref()
+                .map(|r| format!("<tac:StartReference>{}</tac:StartReference>", xml_escape(r)))
+                .unwrap_or_default()
+        ),
+        Messages::GetAccessPointState { token } => format!(
+            "
+                {prefix}
+                <tac:GetAccessPointState>
+                    <tac:Token>{}</tac:Token>
+                </tac:GetAccessPointState>
+                {suffix}
+            ",
+            xml_escape(token)
+        ),
+        Messages::EnableAccessPoint { token } => format!(
+            "
+                {prefix}
+                <tac:EnableAccessPoint>
+                    <tac:Token>{}</tac:Token>
+                </tac:EnableAccessPoint>
+                {suffix}
+            ",
+            xml_escape(token)
+        ),
+        Messages::DisableAccessPoint { token } => format!(
+            "
+                {prefix}
+                <tac:DisableAccessPoint>
+                    <tac:Token>{}</tac:Token>
+                </tac:DisableAccessPoint>
+                {suffix}
+            ",
+            xml_escape(token)
+        ),
+        Messages::GetDoorInfoList { start_reference } => format!(
+            "
+                {prefix}
+                <tdc:GetDoorInfoList>
+                    {}
+                </tdc:GetDoorInfoList>
+                {suffix}
+            ",
+            start_reference
+                .as_deref()
+                .map(|r| format!("<tdc:StartReference>{}</tdc:StartReference>", xml_escape(r)))
+                .unwrap_or_default()
+        ),
+        Messages::GetDoorState { token } => format!(
+            "
+                {prefix}
+                <tdc:GetDoorState>
+                    <tdc:Token>{}</tdc:Token>
+                </tdc:GetDoorState>
+                {suffix}
+            ",
+            xml_escape(token)
+        ),
+        Messages::AccessDoor { token, access_time } => format!(
+            "
+                {prefix}
+                <tdc:AccessDoor>
+                    <tdc:Token>{}</tdc:Token>
+                    {}
+                </tdc:AccessDoor>
+                {suffix}
+            ",
+            xml_escape(token),
+            access_time
+                .map(|t| format!("<tdc:AccessTime>{}</tdc:AccessTime>", xsd_duration(t)))
+                .unwrap_or_default()
+        ),
+        Messages::LockDoor { token } => format!(
+            "
+                {prefix}
+                <tdc:LockDoor>
+                    <tdc:Token>{}</tdc:Token>
+                </tdc:LockDoor>
+                {suffix}
+            ",
+            xml_escape(token)
+        ),
+        Messages::UnlockDoor { token } => format!(
+            "
+                {prefix}
+                <tdc:UnlockDoor>
+                    <tdc:Token>{}</tdc:Token>
+                </tdc:UnlockDoor>
+                {suffix}
+            ",
+            xml_escape(token)
+        ),
+        Messages::Probe { types } => format!(
+            "
+                {prefix_discovery}
+                {header_pt1}
+                {header_pt2}
+                {}
+            ",
+            probe_body(types)
+        ),
+        Messages::Resolve { endpoint } => format!(
+            "
+                {prefix_discovery}
+                {header_pt1}
+                <w:To>urn:schemas-xmlsoap-org:ws:2005:04:discovery</w:To>
+                <w:Action>http://schemas.xmlsoap.org/ws/2005/04/discovery/Resolve</w:Action>
+                </e:Header>
+                <e:Body>
+                    <d:Resolve>
+                        <w:EndpointReference>
+                            <w:Address>{}</w:Address>
+                        </w:EndpointReference>
+                    </d:Resolve>
+                </e:Body>
+                </e:Envelope>
+            ",
+            xml_escape(endpoint)
+        ),
+    }
+}
+
+/// The ForcePersistence element of a Set* request in the namespace of its
+/// service, e.g. "trt". It is deprecated and devices keep the changes
+/// anyway, but the schemas still require it.
+fn force_persistence(ns: &str) -> String {
+    format!("<{ns}:ForcePersistence>true</{ns}:ForcePersistence>")
 }
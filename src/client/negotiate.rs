@@ -0,0 +1,254 @@
+use super::{clock, post_once, rebuilt_response, stats};
+use crate::auth::{self, AuthScheme, Credentials, DigestChallenge};
+use crate::soap::fault_code;
+
+use anyhow::Result;
+use log::debug;
+use reqwest::{header, Response, StatusCode};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Mutex;
+use url::Url;
+use uuid::Uuid;
+
+const SOAP_NAMESPACE: &str = "http://www.w3.org/2003/05/soap-envelope";
+const SOAP11_NAMESPACE: &str = "http://schemas.xmlsoap.org/soap/envelope/";
+
+const SOAP_CONTENT_TYPE: &str = "application/soap+xml; charset=utf-8";
+const SOAP11_CONTENT_TYPE: &str = "text/xml; charset=utf-8";
+
+/// The scheme each device last accepted credentials in, per host and port
+static SCHEMES: Mutex<BTreeMap<String, AuthScheme>> = Mutex::new(BTreeMap::new());
+
+/// Devices that faulted a SOAP 1.2 request with VersionMismatch, per host
+/// and port
+static SOAP11_DEVICES: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// The scheme the device at `onvif_url` accepted credentials in, None
+/// before its first successful authenticated request
+pub fn auth_scheme(onvif_url: &Url) -> Option<AuthScheme> {
+    SCHEMES
+        .lock()
+        .unwrap()
+        .get(&stats::host(onvif_url))
+        .copied()
+}
+
+/// Drops the scheme kept for a device so the next authenticated request
+/// negotiates it again, e.g. after its security settings changed
+pub fn forget_auth_scheme(onvif_url: &Url) {
+    SCHEMES.lock().unwrap().remove(&stats::host(onvif_url));
+}
+
+/// True when the device at `onvif_url` only speaks SOAP 1.1, which is
+/// known once it faulted a SOAP 1.2 request with VersionMismatch
+pub fn soap11(onvif_url: &Url) -> bool {
+    SOAP11_DEVICES
+        .lock()
+        .unwrap()
+        .contains(&stats::host(onvif_url))
+}
+
+/// `soap_msg` in the SOAP version of the device at `onvif_url` and the
+/// Content-Type to post it with
+pub(crate) fn soap_version<'a>(onvif_url: &Url, soap_msg: &'a str) -> (Cow<'a, str>, &'static str) {
+    match soap11(onvif_url) {
+        true => (
+            Cow::Owned(soap_msg.replace(SOAP_NAMESPACE, SOAP11_NAMESPACE)),
+            SOAP11_CONTENT_TYPE,
+        ),
+        false => (Cow::Borrowed(soap_msg), SOAP_CONTENT_TYPE),
+    }
+}
+
+/// True when `response` is a VersionMismatch fault to a SOAP 1.2 request,
+/// the device then being kept as SOAP 1.1 only so the request can be
+/// posted again. The response handed back carries the same status,
+/// headers and body.
+pub(crate) async fn version_mismatch(
+    onvif_url: &Url,
+    response: Response,
+) -> Result<(Response, bool)> {
+    if response.status().is_success() || soap11(onvif_url) {
+        return Ok((response, false));
+    }
+
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = response.text().await?;
+
+    let mismatch =
+        fault_code(body.as_bytes()).is_some_and(|code| code.ends_with("VersionMismatch"));
+    if mismatch {
+        debug!("[Client][version_mismatch] {onvif_url} only speaks SOAP 1.1");
+        SOAP11_DEVICES
+            .lock()
+            .unwrap()
+            .insert(stats::host(onvif_url));
+    }
+
+    Ok((rebuilt_response(status, &headers, body)?, mismatch))
+}
+
+/// Gets what the first attempt needs ready before the request is timed:
+/// the device clock when the device may want a UsernameToken
+pub(crate) async fn prepare(client: &reqwest::Client, onvif_url: &Url) {
+    if matches!(
+        auth_scheme(onvif_url),
+        None | Some(AuthScheme::UsernameToken)
+    ) {
+        clock::measure_clock(client, onvif_url).await;
+    }
+}
+
+/// Posts `soap_msg` with credentials in the scheme the device accepted
+/// last time, a UsernameToken for devices not seen before. A refusal
+/// tells which scheme the device wants instead: a 401 challenge for HTTP
+/// Digest or Basic, a NotAuthorized fault for a UsernameToken. The message
+/// is posted again in that scheme, each scheme being tried once, and the
+/// scheme that succeeds is kept for the device.
+pub(crate) async fn post_authenticated(
+    client: &reqwest::Client,
+    onvif_url: &Url,
+    soap_msg: &str,
+    credentials: &Credentials,
+) -> Result<Response> {
+    let mut scheme = auth_scheme(onvif_url).unwrap_or(AuthScheme::UsernameToken);
+    let mut challenge = None;
+    let mut tried = Vec::new();
+
+    loop {
+        tried.push(scheme);
+        let response = attempt(client, onvif_url, soap_msg, credentials, scheme, challenge).await?;
+
+        if response.status().is_success() {
+            SCHEMES
+                .lock()
+                .unwrap()
+                .insert(stats::host(onvif_url), scheme);
+            return Ok(response);
+        }
+
+        let (response, next) = wanted_scheme(response, &tried).await?;
+        match next {
+            Some((next, next_challenge)) => {
+                debug!(
+                    "[Client][post_authenticated] {onvif_url} refused {scheme:?}, trying {next:?}"
+                );
+                scheme = next;
+                challenge = next_challenge;
+            }
+            None => return Ok(response),
+        }
+    }
+}
+
+/// Posts `soap_msg` once with credentials in `scheme`. Without a Digest
+/// challenge at hand, one is first asked for by posting without them.
+async fn attempt(
+    client: &reqwest::Client,
+    onvif_url: &Url,
+    soap_msg: &str,
+    credentials: &Credentials,
+    scheme: AuthScheme,
+    challenge: Option<DigestChallenge>,
+) -> Result<Response> {
+    match scheme {
+        AuthScheme::UsernameToken => {
+            let signed = auth::sign(soap_msg, credentials, clock::device_now(onvif_url));
+            post_once(client, onvif_url, &signed, None).await
+        }
+        AuthScheme::Basic => {
+            let authorization = credentials.basic_authorization();
+            post_once(client, onvif_url, soap_msg, Some(&authorization)).await
+        }
+        AuthScheme::Digest => {
+            let challenge = match challenge {
+                Some(challenge) => challenge,
+                None => {
+                    let response = post_once(client, onvif_url, soap_msg, None).await?;
+                    match digest_challenge(&response) {
+                        Some(challenge) => challenge,
+                        None => return Ok(response),
+                    }
+                }
+            };
+
+            let authorization = digest_authorization(credentials, &challenge, "POST", onvif_url);
+            post_once(client, onvif_url, soap_msg, Some(&authorization)).await
+        }
+    }
+}
+
+/// The scheme a refused request asks for, if not tried yet, along with
+/// the refusal itself. Faults are read to find NotAuthorized, the response
+/// handed back then carrying the same status, headers and body.
+async fn wanted_scheme(
+    response: Response,
+    tried: &[AuthScheme],
+) -> Result<(Response, Option<(AuthScheme, Option<DigestChallenge>)>)> {
+    let untried = |scheme: &AuthScheme| !tried.contains(scheme);
+
+    if response.status() == StatusCode::UNAUTHORIZED {
+        if let Some(challenge) =
+            digest_challenge(&response).filter(|_| untried(&AuthScheme::Digest))
+        {
+            return Ok((response, Some((AuthScheme::Digest, Some(challenge)))));
+        }
+
+        let basic = response
+            .headers()
+            .get_all(header::WWW_AUTHENTICATE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .any(|value| value.trim().to_ascii_lowercase().starts_with("basic"));
+        if basic && untried(&AuthScheme::Basic) {
+            return Ok((response, Some((AuthScheme::Basic, None))));
+        }
+    }
+
+    if !untried(&AuthScheme::UsernameToken) {
+        return Ok((response, None));
+    }
+
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = response.text().await?;
+
+    let next = fault_code(body.as_bytes())
+        .is_some_and(|code| code.ends_with("NotAuthorized"))
+        .then_some((AuthScheme::UsernameToken, None));
+
+    Ok((rebuilt_response(status, &headers, body)?, next))
+}
+
+/// The Digest challenge of a 401 response
+pub(crate) fn digest_challenge(response: &Response) -> Option<DigestChallenge> {
+    if response.status() != StatusCode::UNAUTHORIZED {
+        return None;
+    }
+
+    response
+        .headers()
+        .get_all(header::WWW_AUTHENTICATE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .find_map(DigestChallenge::parse)
+}
+
+/// The Authorization header answering `challenge` for a request of
+/// `method` to `url`
+pub(crate) fn digest_authorization(
+    credentials: &Credentials,
+    challenge: &DigestChallenge,
+    method: &str,
+    url: &Url,
+) -> String {
+    let uri = match url.query() {
+        Some(query) => format!("{}?{query}", url.path()),
+        None => url.path().to_string(),
+    };
+    let cnonce = Uuid::new_v4().simple().to_string();
+
+    credentials.digest_authorization(challenge, method, &uri, &cnonce)
+}
@@ -0,0 +1,53 @@
+use super::{send, Messages};
+use crate::device::Device;
+use crate::soap::parse_probe_matches;
+
+use anyhow::{anyhow, Result};
+
+/// Sends a Probe to a WS-Discovery Discovery Proxy (managed mode) instead
+/// of the multicast group and returns every device it knows of the given
+/// types, see DiscoverOptions::types. For networks where multicast does
+/// not cross subnets, the proxy being the one device listening on each.
+///
+/// # Arguments
+///
+/// * `proxy_url` - The SOAP over HTTP address of the Discovery Proxy
+/// * `types` - Qualified device types, any type when empty
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::client;
+/// # async fn run() -> anyhow::Result<()> {
+/// let proxy_url = "http://10.0.0.5:5357/discovery".parse()?;
+/// let devices = client::proxy_probe(proxy_url, vec!["dn:NetworkVideoTransmitter".into()]).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn proxy_probe(proxy_url: url::Url, types: Vec<String>) -> Result<Vec<Device>> {
+    let response = send(proxy_url, Messages::Probe { types })
+        .await?
+        .bytes()
+        .await?;
+
+    parse_probe_matches(&response)
+}
+
+/// Asks a Discovery Proxy for the current addresses of the device with
+/// this endpoint reference, e.g. after it was renumbered
+///
+/// # Arguments
+///
+/// * `proxy_url` - The SOAP over HTTP address of the Discovery Proxy
+/// * `endpoint` - The endpoint reference of the device, see Device::endpoint
+pub async fn proxy_resolve(proxy_url: url::Url, endpoint: &str) -> Result<Device> {
+    let msg = Messages::Resolve {
+        endpoint: endpoint.to_string(),
+    };
+    let response = send(proxy_url, msg).await?.bytes().await?;
+
+    parse_probe_matches(&response)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("[OnvifClient][proxy_resolve] {endpoint} is unknown to the proxy"))
+}
@@ -0,0 +1,105 @@
+use super::{send_timeout, Error, Messages};
+use crate::auth::AuthScheme;
+use crate::device::{Device, DeviceTypes};
+use crate::utils::xml::XmlNode;
+
+use anyhow::{anyhow, Result};
+use futures::StreamExt;
+use std::net::IpAddr;
+use std::time::Duration;
+
+/// Ports ONVIF devices commonly serve the device service on
+pub const SCAN_PORTS: [u16; 4] = [80, 8080, 2020, 8899];
+
+/// Hosts client::scan() probes at the same time
+const SCAN_CONCURRENCY: usize = 16;
+
+/// Finds ONVIF devices at known hosts without WS-Discovery, which
+/// installers often disable. Each of SCAN_PORTS is sent a
+/// GetSystemDateAndTime to /onvif/device_service and a host becomes a
+/// device at the first port, in that order, answering it with SOAP. A
+/// fault counts as an answer since some devices want credentials even
+/// for this request.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::client;
+/// # use std::time::Duration;
+/// # async fn run() -> anyhow::Result<()> {
+/// let hosts = ["192.168.1.64".parse()?, "192.168.1.65".parse()?];
+/// let devices = client::scan(&hosts, Duration::from_secs(2)).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn scan(hosts: &[IpAddr], wait: Duration) -> Result<Vec<Device>> {
+    scan_ports(hosts, &SCAN_PORTS, wait).await
+}
+
+/// Same as scan() on ports of your choosing
+pub async fn scan_ports(hosts: &[IpAddr], ports: &[u16], wait: Duration) -> Result<Vec<Device>> {
+    let devices: Vec<Device> = futures::stream::iter(hosts)
+        .map(|host| scan_host(*host, ports, wait))
+        .buffered(SCAN_CONCURRENCY)
+        .filter_map(|device| async move { device })
+        .collect()
+        .await;
+
+    if devices.is_empty() {
+        return Err(anyhow!("[OnvifClient][scan] Unable to find any devices."));
+    }
+
+    Ok(devices)
+}
+
+/// The device at the first port of `host` answering with SOAP
+async fn scan_host(host: IpAddr, ports: &[u16], wait: Duration) -> Option<Device> {
+    let urls: Vec<url::Url> = ports
+        .iter()
+        .filter_map(|port| {
+            let host = match host {
+                IpAddr::V4(host) => host.to_string(),
+                IpAddr::V6(host) => format!("[{host}]"),
+            };
+            format!("http://{host}:{port}/onvif/device_service")
+                .parse()
+                .ok()
+        })
+        .collect();
+
+    let answers = futures::future::join_all(urls.iter().map(|url| speaks_onvif(url, wait))).await;
+    let url_onvif = urls
+        .into_iter()
+        .zip(answers)
+        .find_map(|(url, answer)| answer.then_some(url))?;
+
+    log::debug!("[OnvifClient][scan] Found a device: {url_onvif}");
+
+    Some(Device {
+        device_type: DeviceTypes::Unknown,
+        xaddrs: vec![url_onvif.clone()],
+        ..Device::new(url_onvif)
+    })
+}
+
+/// True when `url` answers GetSystemDateAndTime with a SOAP response or
+/// fault. Faults come with error statuses, so the status is not checked.
+/// A NotAuthorized fault is an answer too, unlike a bare 401.
+async fn speaks_onvif(url: &url::Url, wait: Duration) -> bool {
+    let response = match send_timeout(url.clone(), Messages::GetSystemDateAndTime, wait).await {
+        Ok(response) => response,
+        Err(e) => {
+            return matches!(
+                e.downcast_ref(),
+                Some(Error::AuthRequired { schemes, .. }) if schemes.contains(&AuthScheme::UsernameToken)
+            )
+        }
+    };
+    let Ok(body) = response.bytes().await else {
+        return false;
+    };
+
+    XmlNode::parse(&body).is_ok_and(|root| {
+        root.find("GetSystemDateAndTimeResponse").is_some() || root.find("Fault").is_some()
+    })
+}
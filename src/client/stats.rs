@@ -0,0 +1,147 @@
+use reqwest::Response;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Counters of one SOAP operation on one device. Durations include the
+/// retries of client::send().
+#[derive(Debug, Clone, Default)]
+#[rustfmt::skip]
+pub struct CallStats {
+    /// Host and port of the service URL
+    pub host:             String,
+    /// First element of the SOAP body, e.g. "GetProfiles"
+    pub operation:        String,
+    pub requests:         u64,
+    /// Requests that got no answer or an HTTP error status
+    pub errors:           u64,
+    /// Attempts beyond the first made by client::send()
+    pub retries:          u64,
+    pub total_time:       Duration,
+    pub min_time:         Option<Duration>,
+    pub max_time:         Option<Duration>,
+    pub bytes_sent:       u64,
+    /// As announced by the Content-Length of the answers
+    pub bytes_received:   u64,
+}
+
+impl CallStats {
+    pub fn mean_time(&self) -> Option<Duration> {
+        match self.requests {
+            0 => None,
+            n => Some(self.total_time / n as u32),
+        }
+    }
+}
+
+/// Snapshot of the counters of every request sent since the process
+/// started or reset_stats() was called
+#[derive(Debug, Clone, Default)]
+pub struct ClientStats {
+    pub calls: Vec<CallStats>,
+}
+
+impl ClientStats {
+    /// The `count` operations that took the most time overall
+    pub fn slowest(&self, count: usize) -> Vec<&CallStats> {
+        let mut calls: Vec<&CallStats> = self.calls.iter().collect();
+        calls.sort_by_key(|call| std::cmp::Reverse(call.total_time));
+        calls.truncate(count);
+        calls
+    }
+
+    /// Time spent waiting on each device, slowest first
+    pub fn time_per_host(&self) -> Vec<(String, Duration)> {
+        let mut hosts: BTreeMap<&str, Duration> = BTreeMap::new();
+
+        for call in &self.calls {
+            *hosts.entry(&call.host).or_default() += call.total_time;
+        }
+
+        let mut hosts: Vec<(String, Duration)> = hosts
+            .into_iter()
+            .map(|(host, time)| (host.to_string(), time))
+            .collect();
+        hosts.sort_by_key(|(_, time)| std::cmp::Reverse(*time));
+        hosts
+    }
+
+    pub fn total_requests(&self) -> u64 {
+        self.calls.iter().map(|call| call.requests).sum()
+    }
+}
+
+static STATS: Mutex<BTreeMap<(String, String), CallStats>> = Mutex::new(BTreeMap::new());
+
+/// Counters of every SOAP request sent through client::send() and
+/// send_timeout() so far
+pub fn stats() -> ClientStats {
+    ClientStats {
+        calls: STATS.lock().unwrap().values().cloned().collect(),
+    }
+}
+
+pub fn reset_stats() {
+    STATS.lock().unwrap().clear();
+}
+
+/// Counts one request, `retries` being the failed attempts before the
+/// last one
+pub(crate) fn record(
+    onvif_url: &url::Url,
+    operation: String,
+    started: Instant,
+    retries: u64,
+    bytes_sent: usize,
+    response: Option<&Response>,
+) {
+    let elapsed = started.elapsed();
+
+    #[cfg(feature = "metrics")]
+    crate::telemetry::request(
+        &operation,
+        elapsed,
+        match response {
+            Some(response) if response.status().is_success() => None,
+            Some(response) => Some(response.status().as_str().to_string()),
+            None => Some("no_response".to_string()),
+        },
+    );
+
+    let host = host(onvif_url);
+
+    let mut stats = STATS.lock().unwrap();
+    let call = stats
+        .entry((host.clone(), operation.clone()))
+        .or_insert_with(|| CallStats {
+            host,
+            operation,
+            ..Default::default()
+        });
+
+    call.requests += 1;
+    call.retries += retries;
+    call.total_time += elapsed;
+    call.min_time = Some(call.min_time.map_or(elapsed, |min| min.min(elapsed)));
+    call.max_time = Some(call.max_time.map_or(elapsed, |max| max.max(elapsed)));
+    call.bytes_sent += bytes_sent as u64;
+
+    match response {
+        Some(response) if response.status().is_success() => {
+            call.bytes_received += response.content_length().unwrap_or_default();
+        }
+        Some(response) => {
+            call.errors += 1;
+            call.bytes_received += response.content_length().unwrap_or_default();
+        }
+        None => call.errors += 1,
+    }
+}
+
+/// The host and port of a URL, which identify a device
+pub(crate) fn host(onvif_url: &url::Url) -> String {
+    match onvif_url.port_or_known_default() {
+        Some(port) => format!("{}:{port}", onvif_url.host_str().unwrap_or_default()),
+        None => onvif_url.host_str().unwrap_or_default().to_string(),
+    }
+}
@@ -0,0 +1,102 @@
+use anyhow::{anyhow, Result};
+use reqwest::{Certificate, Identity};
+use std::sync::Mutex;
+
+/// TLS settings for devices serving ONVIF over `https://`, which usually
+/// present self-signed certificates issued to no hostname in particular.
+/// The default trusts the system roots only, like a browser would.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::client::{self, TlsConfig};
+/// # fn run() -> anyhow::Result<()> {
+/// client::set_tls_config(TlsConfig {
+///     root_certificates: vec![std::fs::read("site-ca.pem")?],
+///     accept_invalid_hostnames: true,
+///     ..Default::default()
+/// })?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+#[rustfmt::skip]
+pub struct TlsConfig {
+    /// Accept any certificate, expired and self-signed ones included. Only
+    /// for networks where nobody can impersonate the cameras.
+    pub accept_invalid_certs:       bool,
+    /// Accept certificates issued to another name than the host, e.g. to
+    /// the camera model while it is reached by IP address
+    pub accept_invalid_hostnames:   bool,
+    /// PEM certificates trusted besides the system roots, such as the CA
+    /// a site signs its camera certificates with
+    pub root_certificates:          Vec<Vec<u8>>,
+    /// Certificate presented to devices requiring mutual TLS
+    pub client_identity:            Option<ClientIdentity>,
+}
+
+/// A PEM certificate chain and its PKCS #8 PEM private key
+#[derive(Clone)]
+#[rustfmt::skip]
+pub struct ClientIdentity {
+    pub certificate:   Vec<u8>,
+    pub key:           Vec<u8>,
+}
+
+impl std::fmt::Debug for ClientIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientIdentity")
+            .field("certificate", &self.certificate.len())
+            .field("key", &"<redacted>")
+            .finish()
+    }
+}
+
+impl TlsConfig {
+    /// An HTTP client with these settings
+    pub(crate) fn client(&self) -> Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder()
+            .danger_accept_invalid_certs(self.accept_invalid_certs)
+            .danger_accept_invalid_hostnames(self.accept_invalid_hostnames);
+
+        for pem in &self.root_certificates {
+            let certificate = Certificate::from_pem(pem)
+                .map_err(|e| anyhow!("[Client][TlsConfig] Invalid root certificate: {e}"))?;
+            builder = builder.add_root_certificate(certificate);
+        }
+
+        if let Some(identity) = &self.client_identity {
+            let identity = Identity::from_pkcs8_pem(&identity.certificate, &identity.key)
+                .map_err(|e| anyhow!("[Client][TlsConfig] Invalid client identity: {e}"))?;
+            builder = builder.identity(identity);
+        }
+
+        Ok(builder.build()?)
+    }
+}
+
+static TLS: Mutex<Option<TlsConfig>> = Mutex::new(None);
+
+/// Uses these TLS settings for every request sent from now on, through
+/// client::send() and the services built on it as well as for snapshots.
+/// Fails, keeping the previous settings, when a certificate or key does
+/// not parse.
+pub fn set_tls_config(config: TlsConfig) -> Result<()> {
+    config.client()?;
+    *TLS.lock().unwrap() = Some(config);
+
+    Ok(())
+}
+
+/// The TLS settings set with set_tls_config(), the default ones otherwise
+pub fn tls_config() -> TlsConfig {
+    TLS.lock().unwrap().clone().unwrap_or_default()
+}
+
+/// An HTTP client with the current TLS settings
+pub(crate) fn http_client() -> Result<reqwest::Client> {
+    match TLS.lock().unwrap().as_ref() {
+        Some(config) => config.client(),
+        None => Ok(reqwest::Client::new()),
+    }
+}
@@ -0,0 +1,224 @@
+use super::{probe_socket, soap_msg, DiscoverOptions, Messages};
+use crate::device::Device;
+use crate::discovery::{DISCOVERY_GROUP, DISCOVERY_PORT};
+use crate::rtsp::multicast::{MulticastGroup, MulticastInterface};
+use crate::soap::{parse_discovery_message, DiscoveryMessage};
+
+use anyhow::Result;
+use futures::Stream;
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::time::{interval, Interval, MissedTickBehavior};
+use uuid::Uuid;
+
+/// A change client::watch() noticed on the network
+#[derive(Debug, Clone)]
+pub enum DiscoveryEvent {
+    /// A device seen for the first time, through a ProbeMatch or a Hello
+    Joined(Device),
+    /// A known device answered or said Hello from another address
+    Moved(Device),
+    /// A device said Bye, `device` is None when it was never seen
+    Left {
+        endpoint: String,
+        device: Option<Device>,
+    },
+}
+
+struct Watcher {
+    /// None when listening passively
+    probe_socket: Option<UdpSocket>,
+    /// Joined to the discovery group, receives Hello and Bye
+    group_socket: UdpSocket,
+    reprobe: Option<Interval>,
+    /// Device types probed for
+    types: Vec<String>,
+    /// Devices seen so far by endpoint reference, or URL when they have
+    /// none
+    known: HashMap<String, Device>,
+}
+
+/// Keeps discovering devices for as long as the stream is polled: probes
+/// every `reprobe` and listens for the Hello and Bye messages devices send
+/// when they join and leave the network. The interface, listen port and
+/// types of `options` apply, the timeout, retries and limit do not.
+///
+/// Devices announcing a Hello without their XAddrs are only reported once
+/// they answer a probe.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::client::{self, DiscoverOptions, DiscoveryEvent};
+/// # use futures::StreamExt;
+/// # use std::time::Duration;
+/// # async fn run() -> anyhow::Result<()> {
+/// let events = client::watch(DiscoverOptions::default(), Duration::from_secs(60)).await?;
+/// let mut events = std::pin::pin!(events);
+///
+/// while let Some(event) = events.next().await {
+///     match event {
+///         DiscoveryEvent::Joined(device) => println!("New device {}", device.url_onvif),
+///         DiscoveryEvent::Moved(device) => println!("Device now at {}", device.url_onvif),
+///         DiscoveryEvent::Left { endpoint, .. } => println!("{endpoint} left"),
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn watch(
+    options: DiscoverOptions,
+    reprobe: Duration,
+) -> Result<impl Stream<Item = DiscoveryEvent>> {
+    let group = MulticastGroup::new(DISCOVERY_GROUP.into(), DISCOVERY_PORT);
+    let mut reprobe = interval(reprobe);
+    reprobe.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    let watcher = Watcher {
+        probe_socket: Some(probe_socket(&options).await?),
+        group_socket: group.join(options.interface).await?,
+        reprobe: Some(reprobe),
+        types: options.types,
+        known: HashMap::new(),
+    };
+
+    Ok(watcher.into_stream())
+}
+
+/// Discovers devices without ever sending: joins the discovery group on
+/// port 3702 and reports the Hello and Bye messages devices multicast,
+/// along with the ProbeMatches of other clients when devices multicast
+/// them. For networks where active probing is not allowed, devices that
+/// were already running are only seen once they announce themselves
+/// again.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::client::{self, DiscoveryEvent};
+/// # use onvif_cam_rs::rtsp::multicast::MulticastInterface;
+/// # use futures::StreamExt;
+/// # async fn run() -> anyhow::Result<()> {
+/// let events = client::listen(MulticastInterface::Any).await?;
+/// let mut events = std::pin::pin!(events);
+///
+/// while let Some(DiscoveryEvent::Joined(device)) = events.next().await {
+///     println!("New device {}", device.url_onvif);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn listen(interface: MulticastInterface) -> Result<impl Stream<Item = DiscoveryEvent>> {
+    let group = MulticastGroup::new(DISCOVERY_GROUP.into(), DISCOVERY_PORT);
+
+    let watcher = Watcher {
+        probe_socket: None,
+        group_socket: group.join(interface).await?,
+        reprobe: None,
+        types: Vec::new(),
+        known: HashMap::new(),
+    };
+
+    Ok(watcher.into_stream())
+}
+
+impl Watcher {
+    fn into_stream(self) -> impl Stream<Item = DiscoveryEvent> {
+        futures::stream::unfold(self, |mut watcher| async move {
+            let event = watcher.next_event().await;
+            Some((event, watcher))
+        })
+    }
+
+    async fn next_event(&mut self) -> DiscoveryEvent {
+        let mut probe_buf = vec![0u8; 65536];
+        let mut group_buf = vec![0u8; 65536];
+
+        loop {
+            let message = tokio::select! {
+                _ = tick(self.reprobe.as_mut()) => {
+                    self.probe().await;
+                    continue;
+                }
+                Ok((size, _)) = recv_from(self.probe_socket.as_ref(), &mut probe_buf) => {
+                    parse_discovery_message(&probe_buf[..size])
+                }
+                Ok((size, _)) = self.group_socket.recv_from(&mut group_buf) => {
+                    parse_discovery_message(&group_buf[..size])
+                }
+            };
+
+            // Probes of other clients, this one included, land here too
+            match message {
+                Ok(message) => {
+                    if let Some(event) = self.update(message) {
+                        return event;
+                    }
+                }
+                Err(e) => debug!("[OnvifClient][watch] Ignoring message: {e}"),
+            }
+        }
+    }
+
+    async fn probe(&self) {
+        let Some(probe_socket) = &self.probe_socket else {
+            return;
+        };
+
+        let probe = soap_msg(
+            &Messages::Probe {
+                types: self.types.clone(),
+            },
+            Uuid::new_v4(),
+        );
+        let group = SocketAddr::from((DISCOVERY_GROUP, DISCOVERY_PORT));
+
+        if let Err(e) = probe_socket.send_to(probe.as_bytes(), group).await {
+            warn!("[OnvifClient][watch] Unable to send probe: {e}");
+        }
+    }
+
+    fn update(&mut self, message: DiscoveryMessage) -> Option<DiscoveryEvent> {
+        match message {
+            DiscoveryMessage::Hello(device) | DiscoveryMessage::ProbeMatch(device) => {
+                let key = device
+                    .endpoint
+                    .clone()
+                    .unwrap_or_else(|| device.url_onvif.to_string());
+
+                match self.known.insert(key, device.clone()) {
+                    None => Some(DiscoveryEvent::Joined(device)),
+                    Some(known) if known.url_onvif != device.url_onvif => {
+                        Some(DiscoveryEvent::Moved(device))
+                    }
+                    Some(_) => None,
+                }
+            }
+            DiscoveryMessage::Bye(endpoint) => {
+                let device = self.known.remove(&endpoint);
+                Some(DiscoveryEvent::Left { endpoint, device })
+            }
+        }
+    }
+}
+
+/// Never completes when listening passively
+async fn tick(reprobe: Option<&mut Interval>) {
+    match reprobe {
+        Some(reprobe) => {
+            reprobe.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+async fn recv_from(socket: Option<&UdpSocket>, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+    match socket {
+        Some(socket) => socket.recv_from(buf).await,
+        None => std::future::pending().await,
+    }
+}
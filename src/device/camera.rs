@@ -1,31 +1,67 @@
+use crate::auth::Credentials;
+#[cfg(feature = "http")]
 use crate::builder::camera::CameraBuilder;
 use crate::device::*;
+#[cfg(feature = "http")]
+use crate::media::StreamUriRefresher;
 
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
+#[cfg(feature = "http")]
 use async_trait::async_trait;
+#[cfg(feature = "http")]
+use bytes::Bytes;
+#[cfg(feature = "http")]
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+#[cfg(feature = "http")]
+use std::time::Duration;
+#[cfg(feature = "http")]
+use tokio::sync::watch;
+#[cfg(feature = "http")]
+use tokio::task::JoinHandle;
 
+/// Version of the snapshot format written by Camera::to_snapshot_json()
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Longest wait of snapshot_stream() between attempts while the camera
+/// fails, unless the interval is longer
+#[cfg(feature = "http")]
+const SNAPSHOT_MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[rustfmt::skip]
 pub struct Camera {
-    base:                 Device,
-    capabilities:         Capabilities,
-    profiles:             Profiles,
-    device_info:          DeviceInfo,
-    pub stream:           StreamUri,
-    services:             Services,
-    event_props:          EventCapabilities,
-    analytics_props:      AnalyticsCapabilities,
-    analytics_configs:    AnalyticsConfigList,
+    pub(crate) base:                 Device,
+    pub(crate) capabilities:         Capabilities,
+    #[serde(default, deserialize_with = "deserialize_profiles")]
+    pub(crate) profiles:             Vec<MediaProfile>,
+    pub(crate) device_info:          DeviceInfo,
+    pub stream:                      StreamUri,
+    pub(crate) services:             Services,
+    pub(crate) event_props:          EventCapabilities,
+    pub(crate) analytics_props:      AnalyticsCapabilities,
+    pub(crate) analytics_configs:    AnalyticsConfigList,
+    #[serde(default)]
+    pub(crate) labels:               BTreeSet<String>,
+    /// Transport build_all() asks the stream URI for
+    #[serde(default)]
+    pub(crate) transport:            StreamTransport,
 }
 
+#[cfg(feature = "http")]
 #[async_trait]
 impl CameraBuilder for Camera {
     #[rustfmt::skip]
     async fn build_all(&mut self) -> Result<()> {
-        self.capabilities     = Camera::set_capabilities(    self.base.url_onvif.clone()).await?;
-        self.device_info      = Camera::set_device_info(     self.base.url_onvif.clone()).await?;
-        self.profiles         = Camera::set_profiles(        self.base.url_onvif.clone()).await?;
-        self.stream           = Camera::set_stream_uri(      self.base.url_onvif.clone()).await?;
-        self.services         = Camera::set_services(        self.base.url_onvif.clone()).await?;
+        self.capabilities     = Camera::set_capabilities(    self.base.onvif_url()).await?;
+        self.device_info      = Camera::set_device_info(     self.base.onvif_url()).await?;
+        self.profiles         = Camera::set_profiles(        self.base.onvif_url()).await?;
+        self.stream           = Camera::set_stream_uri(      self.base.onvif_url(), self.transport).await?;
+        self.stream           = self.reachable_stream(self.stream.clone());
+        self.services         = Camera::set_services(        self.base.onvif_url()).await?;
         // _ =           Camera::set_dot11_status(      self.base.url_onvif.clone()).await?;
         // _ =           Camera::set_geo_location(      self.base.url_onvif.clone()).await?;
         
@@ -65,9 +101,10 @@ impl CameraBuilder for Camera {
         // _                     = Camera::set_event_brokers(event_url).await?;
         
         // Get EVENT SERVICE Url to send request to PULL EVENT MESSAGES
-        let url                     = self.services.event.as_ref().unwrap();
-        let event_url               = url::Url::parse(&url)?;
-        _      = Camera::pull_messages(event_url).await?;
+        if let Some(url) = self.services.event.as_ref() {
+            let event_url           = self.base.authorize(&url::Url::parse(url)?);
+            _      = Camera::pull_messages(event_url).await?;
+        }
 
         Ok(())
     }
@@ -79,17 +116,369 @@ impl Camera {
         Camera {
             base,
             capabilities:         Capabilities::default(),
-            profiles:             Profiles::default(),
+            profiles:             Vec::new(),
             device_info:          DeviceInfo::default(),
             stream:               StreamUri::default(),
             services:             Services::default(),
             event_props:          EventCapabilities::default(),
             analytics_props:      AnalyticsCapabilities::default(),
             analytics_configs:    AnalyticsConfigList::default(),
+            labels:               BTreeSet::new(),
+            transport:            StreamTransport::default(),
+        }
+    }
+
+    pub fn device(&self) -> &Device                                 { &self.base }
+    pub fn capabilities(&self) -> &Capabilities                     { &self.capabilities }
+    pub fn profiles(&self) -> &[MediaProfile]                       { &self.profiles }
+    /// The first media profile, the one build_all() asks the stream URI of
+    pub fn profile(&self) -> Option<&MediaProfile>                  { self.profiles.first() }
+    pub fn device_info(&self) -> &DeviceInfo                        { &self.device_info }
+    pub fn services(&self) -> &Services                             { &self.services }
+    pub fn event_capabilities(&self) -> &EventCapabilities          { &self.event_props }
+    pub fn analytics_capabilities(&self) -> &AnalyticsCapabilities  { &self.analytics_props }
+    pub fn analytics_configs(&self) -> &AnalyticsConfigList         { &self.analytics_configs }
+    pub fn labels(&self) -> &BTreeSet<String>                       { &self.labels }
+    pub fn stream_transport(&self) -> StreamTransport               { self.transport }
+}
+
+#[derive(Serialize)]
+struct SnapshotRef<'a> {
+    version: u32,
+    camera: &'a Camera,
+}
+
+#[derive(Deserialize)]
+struct Snapshot {
+    version: u32,
+    camera: Camera,
+}
+
+impl Camera {
+    /// Serializes the complete state of a built camera (capabilities,
+    /// profiles, services, stream URI...) as JSON
+    pub fn to_snapshot_json(&self) -> Result<String> {
+        let snapshot = SnapshotRef {
+            version: SNAPSHOT_VERSION,
+            camera: self,
+        };
+
+        Ok(serde_json::to_string_pretty(&snapshot)?)
+    }
+
+    /// Rehydrates a camera from to_snapshot_json() output without any network
+    /// calls, so short lived programs can skip build_all()
+    pub fn from_snapshot_json(snapshot: &str) -> Result<Self> {
+        let snapshot: Snapshot = serde_json::from_str(snapshot)?;
+
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(anyhow!(
+                "[Device][Camera] Unsupported snapshot version {}",
+                snapshot.version
+            ));
+        }
+
+        Ok(snapshot.camera)
+    }
+
+    pub fn save_snapshot(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_snapshot_json()?)
+            .with_context(|| format!("[Device][Camera] Unable to write {}", path.display()))
+    }
+
+    pub fn load_snapshot(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("[Device][Camera] Unable to read {}", path.display()))?;
+
+        Self::from_snapshot_json(&contents)
+    }
+}
+
+/// Ways to pick out a camera other than its position in a list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CameraQuery {
+    /// A label attached with Camera::add_label()
+    Label(String),
+    /// Serial number reported by GetDeviceInformation
+    Serial(String),
+    /// Model reported by GetDeviceInformation, ignoring case
+    Model(String),
+    /// Name from the `onvif://www.onvif.org/name/` discovery scope, ignoring case
+    Name(String),
+}
+
+impl Camera {
+    pub fn add_label(&mut self, label: &str) {
+        self.labels.insert(label.to_string());
+    }
+
+    pub fn remove_label(&mut self, label: &str) -> bool {
+        self.labels.remove(label)
+    }
+
+    /// Transport build_all() asks the stream URI for, RTP unicast over
+    /// RTSP by default
+    pub fn set_stream_transport(&mut self, transport: StreamTransport) {
+        self.transport = transport;
+    }
+
+    /// Credentials sent with every request to the camera from now on,
+    /// including those of build_all()
+    pub fn set_credentials(&mut self, credentials: Credentials) {
+        self.base.credentials = Some(credentials);
+    }
+
+    pub fn credentials(&self) -> Option<&Credentials> {
+        self.base.credentials.as_ref()
+    }
+
+    /// Public address of a camera behind port forwarding. The service,
+    /// stream and snapshot URLs the camera reports are moved to `host`,
+    /// their ports mapped from internal to public port through `port_map`,
+    /// e.g. `&[(80, 8080), (554, 8554)]`.
+    pub fn set_address_override(&mut self, host: &str, port_map: &[(u16, u16)]) {
+        let address = AddressOverride::new(host, port_map);
+
+        self.stream = self.stream.clone().with_address_override(&address);
+        self.base.address_override = Some(address);
+    }
+
+    pub fn address_override(&self) -> Option<&AddressOverride> {
+        self.base.address_override.as_ref()
+    }
+
+    /// A stream URI reported by the camera, on its public address when it
+    /// has an override
+    pub(crate) fn reachable_stream(&self, uri: StreamUri) -> StreamUri {
+        match &self.base.address_override {
+            Some(address) => uri.with_address_override(address),
+            None => uri,
+        }
+    }
+
+    /// Name the device advertises in its discovery scopes, if any
+    pub fn scope_name(&self) -> Option<String> {
+        self.base.parsed_scopes().name
+    }
+
+    pub fn matches(&self, query: &CameraQuery) -> bool {
+        match query {
+            CameraQuery::Label(label) => self.labels.contains(label),
+            CameraQuery::Serial(serial) => self.device_info.serial_num.as_ref() == Some(serial),
+            CameraQuery::Model(model) => self
+                .device_info
+                .model
+                .as_ref()
+                .is_some_and(|m| m.eq_ignore_ascii_case(model)),
+            CameraQuery::Name(name) => self
+                .scope_name()
+                .is_some_and(|n| n.eq_ignore_ascii_case(name)),
         }
     }
 }
 
+#[cfg(feature = "http")]
+impl Camera {
+    /// Checks that the camera answers with a single GetSystemDateAndTime,
+    /// see client::ping(). Returns the round trip time.
+    pub async fn ping(&self, wait: std::time::Duration) -> Result<std::time::Duration> {
+        crate::client::ping(self.base.onvif_url(), wait).await
+    }
+
+    /// Grabs a JPEG still of the first media profile without opening an
+    /// RTSP session: asks the media service for the snapshot URI and
+    /// downloads it with the camera credentials. Needs build_all() first.
+    /// Not to be confused with to_snapshot_json(), which serializes the
+    /// camera.
+    pub async fn snapshot(&self) -> Result<Bytes> {
+        crate::media::fetch_snapshot(&self.snapshot_uri().await?).await
+    }
+
+    /// Fetches a JPEG still of the first media profile every `interval`, a
+    /// lightweight alternative to RTSP for dashboards. The snapshot URI is
+    /// asked for once and again after each failure. Failures are yielded
+    /// and the stream goes on, waiting twice as long after each one up to
+    /// a minute, or `interval` when longer.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use onvif_cam_rs::device::camera::Camera;
+    /// # use futures::StreamExt;
+    /// # use std::time::Duration;
+    /// # async fn run(camera: Camera) {
+    /// let snapshots = camera.snapshot_stream(Duration::from_secs(2));
+    /// let mut snapshots = std::pin::pin!(snapshots);
+    ///
+    /// while let Some(jpeg) = snapshots.next().await {
+    ///     match jpeg {
+    ///         Ok(jpeg) => println!("{} bytes", jpeg.len()),
+    ///         Err(e) => println!("No snapshot: {e}"),
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn snapshot_stream(&self, interval: Duration) -> impl Stream<Item = Result<Bytes>> {
+        let poller = SnapshotPoller {
+            camera: self.clone(),
+            uri: None,
+            interval,
+            failures: 0,
+            next: tokio::time::Instant::now(),
+        };
+
+        futures::stream::unfold(poller, |mut poller| async move {
+            let snapshot = poller.next_snapshot().await;
+            Some((snapshot, poller))
+        })
+    }
+
+    /// The stream URI found by build_all(), first requested again when it
+    /// expires within `margin`, so cameras that limit the lifetime of
+    /// their URIs can be streamed from without tracking it. See
+    /// media::DEFAULT_REFRESH_MARGIN.
+    pub async fn refresh_stream(&mut self, margin: Duration) -> Result<&StreamUri> {
+        if self.stream.uri.is_none() || self.stream.needs_refresh(margin) {
+            let uri = Camera::set_stream_uri(self.base.onvif_url(), self.transport).await?;
+            self.stream = self.reachable_stream(uri);
+        }
+
+        Ok(&self.stream)
+    }
+
+    /// Keeps the stream URI of the first media profile valid in a
+    /// background task, which requests it again before every expiry and
+    /// sends it to the receiver. See StreamUriRefresher::spawn().
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use onvif_cam_rs::device::camera::Camera;
+    /// # async fn run(camera: Camera) -> anyhow::Result<()> {
+    /// let (mut uris, _task) = camera.watch_stream().await?;
+    ///
+    /// while uris.changed().await.is_ok() {
+    ///     println!("Reconnect to {:?}", uris.borrow().uri);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn watch_stream(&self) -> Result<(watch::Receiver<StreamUri>, JoinHandle<()>)> {
+        StreamUriRefresher::for_camera(self, self.profile_token()?, self.transport)?
+            .spawn()
+            .await
+    }
+
+    /// Pan, tilt and zoom control of the camera, through its first media
+    /// profile with a PTZ configuration. Fails when the camera has no PTZ
+    /// service.
+    pub fn ptz(&self) -> Result<crate::ptz::Ptz> {
+        crate::ptz::Ptz::new(self)
+    }
+
+    /// Starts the multicast stream of the first media profile, for cameras
+    /// that do not stream to their multicast group until asked to. See
+    /// set_stream_transport() for the multicast stream URI.
+    pub async fn start_multicast(&self) -> Result<()> {
+        let token = self.profile_token()?;
+
+        crate::media::start_multicast_streaming(crate::media::media_url(self)?, token).await
+    }
+
+    /// Stops the multicast stream of the first media profile
+    pub async fn stop_multicast(&self) -> Result<()> {
+        let token = self.profile_token()?;
+
+        crate::media::stop_multicast_streaming(crate::media::media_url(self)?, token).await
+    }
+
+    pub(crate) fn profile_token(&self) -> Result<&str> {
+        self.profile()
+            .map(|profile| profile.token.as_str())
+            .ok_or_else(|| anyhow!("[Device][Camera] No media profile, call build_all() first"))
+    }
+
+    /// Snapshot URI of the first media profile, with the camera credentials
+    async fn snapshot_uri(&self) -> Result<String> {
+        let token = self.profile_token()?;
+
+        let uri = crate::media::get_snapshot_uri(crate::media::media_url(self)?, token).await?;
+        let uri = self.reachable_stream(uri);
+        let uri = match self.credentials() {
+            Some(credentials) => uri.with_credentials(credentials),
+            None => uri,
+        };
+
+        uri.uri
+            .ok_or_else(|| anyhow!("[Device][Camera] Camera returned no snapshot uri"))
+    }
+}
+
+#[cfg(feature = "http")]
+struct SnapshotPoller {
+    camera: Camera,
+    uri: Option<String>,
+    interval: Duration,
+    failures: u32,
+    next: tokio::time::Instant,
+}
+
+#[cfg(feature = "http")]
+impl SnapshotPoller {
+    async fn next_snapshot(&mut self) -> Result<Bytes> {
+        tokio::time::sleep_until(self.next).await;
+        let started = tokio::time::Instant::now();
+
+        let snapshot = self.fetch().await;
+        let wait = match snapshot {
+            Ok(_) => {
+                self.failures = 0;
+                self.interval
+            }
+            Err(_) => {
+                // The URI may have expired or moved
+                self.uri = None;
+                self.failures += 1;
+                let backoff = self.interval.saturating_mul(2u32.pow(self.failures.min(6)));
+                backoff.min(SNAPSHOT_MAX_BACKOFF.max(self.interval))
+            }
+        };
+        self.next = started + wait;
+
+        snapshot
+    }
+
+    async fn fetch(&mut self) -> Result<Bytes> {
+        let uri = match &self.uri {
+            Some(uri) => uri.clone(),
+            None => self.uri.insert(self.camera.snapshot_uri().await?).clone(),
+        };
+
+        crate::media::fetch_snapshot(&uri).await
+    }
+}
+
+/// Returns the first camera matching `query`
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::device::camera::{self, Camera, CameraQuery};
+/// # fn run(cameras: Vec<Camera>) {
+/// let door = camera::find(&cameras, &CameraQuery::Name("Front Door".into()));
+/// # }
+/// ```
+pub fn find<'a>(cameras: &'a [Camera], query: &CameraQuery) -> Option<&'a Camera> {
+    cameras.iter().find(|c| c.matches(query))
+}
+
+/// Returns every camera matching `query`, e.g. all cameras of one model
+pub fn find_all<'a>(cameras: &'a [Camera], query: &CameraQuery) -> Vec<&'a Camera> {
+    cameras.iter().filter(|c| c.matches(query)).collect()
+}
+
 #[rustfmt::skip]
 impl From<&str> for Camera {
     fn from(input: &str) -> Self {
@@ -98,22 +487,20 @@ impl From<&str> for Camera {
             Err(e) => panic!("[Device][Camera] Error parsing str: {e}"),
         };
 
-        let base = Device {
-            url_onvif,
-            device_type:    DeviceTypes::Camera,
-            scopes:         Vec::new(),
-        };    
+        let base = Device::new(url_onvif);
 
         Camera {
             base,
             capabilities:         Capabilities::default(),
-            profiles:             Profiles::default(),
+            profiles:             Vec::new(),
             device_info:          DeviceInfo::default(),
             stream:               StreamUri::default(),
             services:             Services::default(),
             event_props:          EventCapabilities::default(),
             analytics_props:      AnalyticsCapabilities::default(),
             analytics_configs:    AnalyticsConfigList::default(),
+            labels:               BTreeSet::new(),
+            transport:            StreamTransport::default(),
         }
     }
 }
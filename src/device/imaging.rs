@@ -0,0 +1,229 @@
+use super::{optional_element, range};
+use crate::utils::xml::XmlNode;
+
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+
+/// A focus move of the imaging service. Positions, distances and speeds
+/// are in the units of the device, see FocusMoveOptions for their ranges.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FocusMove {
+    /// To a lens position, at the default speed when None
+    Absolute { position: f32, speed: Option<f32> },
+    /// By a distance from the current position, negative towards near
+    Relative { distance: f32, speed: Option<f32> },
+    /// At a speed until stopped, negative towards near
+    Continuous { speed: f32 },
+}
+
+impl FocusMove {
+    pub(crate) fn to_soap(self) -> String {
+        let speed = |speed: Option<f32>| match speed {
+            Some(speed) => format!("<tt:Speed>{speed}</tt:Speed>"),
+            None => String::new(),
+        };
+
+        match self {
+            FocusMove::Absolute { position, speed: s } => format!(
+                "<tt:Absolute><tt:Position>{position}</tt:Position>{}</tt:Absolute>",
+                speed(s)
+            ),
+            FocusMove::Relative { distance, speed: s } => format!(
+                "<tt:Relative><tt:Distance>{distance}</tt:Distance>{}</tt:Relative>",
+                speed(s)
+            ),
+            FocusMove::Continuous { speed } => {
+                format!("<tt:Continuous><tt:Speed>{speed}</tt:Speed></tt:Continuous>")
+            }
+        }
+    }
+}
+
+/// The focus moves a video source supports and their ranges, as answered
+/// by GetMoveOptions. A move the lens cannot do has no ranges.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[rustfmt::skip]
+pub struct FocusMoveOptions {
+    pub absolute_position:   Option<RangeInclusive<f32>>,
+    pub absolute_speed:      Option<RangeInclusive<f32>>,
+    pub relative_distance:   Option<RangeInclusive<f32>>,
+    pub relative_speed:      Option<RangeInclusive<f32>>,
+    pub continuous_speed:    Option<RangeInclusive<f32>>,
+}
+
+impl FocusMoveOptions {
+    pub(crate) fn from_xml(node: &XmlNode) -> Self {
+        let options =
+            |kind: &str, name: &str| node.child(kind).and_then(|k| k.child(name)).and_then(range);
+
+        FocusMoveOptions {
+            absolute_position: options("Absolute", "Position"),
+            absolute_speed: options("Absolute", "Speed"),
+            relative_distance: options("Relative", "Distance"),
+            relative_speed: options("Relative", "Speed"),
+            continuous_speed: options("Continuous", "Speed"),
+        }
+    }
+
+    /// Whether the move can be sent to the video source
+    pub fn supports(&self, focus: &FocusMove) -> bool {
+        match focus {
+            FocusMove::Absolute { .. } => self.absolute_position.is_some(),
+            FocusMove::Relative { .. } => self.relative_distance.is_some(),
+            FocusMove::Continuous { .. } => self.continuous_speed.is_some(),
+        }
+    }
+}
+
+/// Ranges of the imaging settings of a video source, as answered by
+/// GetOptions, e.g. for the bounds of sliders. None ranges and empty mode
+/// lists are settings the video source does not have, or does not report.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[rustfmt::skip]
+pub struct ImagingOptions {
+    pub brightness:                     Option<RangeInclusive<f32>>,
+    pub color_saturation:               Option<RangeInclusive<f32>>,
+    pub contrast:                       Option<RangeInclusive<f32>>,
+    pub sharpness:                      Option<RangeInclusive<f32>>,
+    /// The IR cut filter modes the video source can be forced to
+    pub ir_cut_filter_modes:            Vec<IrCutFilterMode>,
+    /// e.g. "OFF" and "ON"
+    pub backlight_compensation_modes:   Vec<String>,
+    pub backlight_compensation_level:   Option<RangeInclusive<f32>>,
+    /// e.g. "OFF" and "ON"
+    pub wide_dynamic_range_modes:       Vec<String>,
+    pub wide_dynamic_range_level:       Option<RangeInclusive<f32>>,
+    /// e.g. "AUTO" and "MANUAL"
+    pub exposure_modes:                 Vec<String>,
+    /// Microseconds
+    pub exposure_time:                  Option<RangeInclusive<f32>>,
+    /// Decibels
+    pub gain:                           Option<RangeInclusive<f32>>,
+    pub iris:                           Option<RangeInclusive<f32>>,
+    /// e.g. "AUTO" and "MANUAL"
+    pub focus_modes:                    Vec<String>,
+    pub focus_default_speed:            Option<RangeInclusive<f32>>,
+    /// Meters
+    pub focus_near_limit:               Option<RangeInclusive<f32>>,
+    /// Meters
+    pub focus_far_limit:                Option<RangeInclusive<f32>>,
+    /// e.g. "AUTO" and "MANUAL"
+    pub white_balance_modes:            Vec<String>,
+    pub white_balance_yr_gain:          Option<RangeInclusive<f32>>,
+    pub white_balance_yb_gain:          Option<RangeInclusive<f32>>,
+}
+
+impl ImagingOptions {
+    pub(crate) fn from_xml(node: &XmlNode) -> Self {
+        let options =
+            |kind: &str, name: &str| node.child(kind).and_then(|k| k.child(name)).and_then(range);
+        let modes = |kind: &str, name: &str| -> Vec<String> {
+            node.child(kind)
+                .map(|k| {
+                    k.children_named(name)
+                        .map(|m| m.text.trim().to_string())
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        ImagingOptions {
+            brightness: node.child("Brightness").and_then(range),
+            color_saturation: node.child("ColorSaturation").and_then(range),
+            contrast: node.child("Contrast").and_then(range),
+            sharpness: node.child("Sharpness").and_then(range),
+            ir_cut_filter_modes: node
+                .children_named("IrCutFilterModes")
+                .filter_map(|mode| IrCutFilterMode::parse(&mode.text))
+                .collect(),
+            backlight_compensation_modes: modes("BacklightCompensation", "Mode"),
+            backlight_compensation_level: options("BacklightCompensation", "Level"),
+            wide_dynamic_range_modes: modes("WideDynamicRange", "Mode"),
+            wide_dynamic_range_level: options("WideDynamicRange", "Level"),
+            exposure_modes: modes("Exposure", "Mode"),
+            exposure_time: options("Exposure", "ExposureTime"),
+            gain: options("Exposure", "Gain"),
+            iris: options("Exposure", "Iris"),
+            focus_modes: modes("Focus", "AutoFocusModes"),
+            focus_default_speed: options("Focus", "DefaultSpeed"),
+            focus_near_limit: options("Focus", "NearLimit"),
+            focus_far_limit: options("Focus", "FarLimit"),
+            white_balance_modes: modes("WhiteBalance", "Mode"),
+            white_balance_yr_gain: options("WhiteBalance", "YrGain"),
+            white_balance_yb_gain: options("WhiteBalance", "YbGain"),
+        }
+    }
+}
+
+/// Day and night switching of a camera with an IR cut filter. The filter
+/// blocks infrared in daylight and is removed for night vision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IrCutFilterMode {
+    /// Filter in, day mode in colour
+    On,
+    /// Filter out, night mode in black and white
+    Off,
+    /// The camera switches on its own from the light level
+    Auto,
+}
+
+impl IrCutFilterMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IrCutFilterMode::On => "ON",
+            IrCutFilterMode::Off => "OFF",
+            IrCutFilterMode::Auto => "AUTO",
+        }
+    }
+
+    pub(crate) fn parse(text: &str) -> Option<Self> {
+        match text.trim() {
+            "ON" => Some(IrCutFilterMode::On),
+            "OFF" => Some(IrCutFilterMode::Off),
+            "AUTO" => Some(IrCutFilterMode::Auto),
+            _ => None,
+        }
+    }
+}
+
+/// Imaging settings of a video source, as answered by GetImagingSettings.
+/// Settings that are None are left as they are by SetImagingSettings, so
+/// a single one can be changed without reading the others first.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[rustfmt::skip]
+pub struct ImagingSettings {
+    pub brightness:         Option<f32>,
+    pub color_saturation:   Option<f32>,
+    pub contrast:           Option<f32>,
+    pub ir_cut_filter:      Option<IrCutFilterMode>,
+    pub sharpness:          Option<f32>,
+}
+
+impl ImagingSettings {
+    pub(crate) fn from_xml(node: &XmlNode) -> Self {
+        let number = |name: &str| node.child_text(name).and_then(|n| n.parse().ok());
+
+        ImagingSettings {
+            brightness: number("Brightness"),
+            color_saturation: number("ColorSaturation"),
+            contrast: number("Contrast"),
+            ir_cut_filter: node
+                .child_text("IrCutFilter")
+                .and_then(|mode| IrCutFilterMode::parse(&mode)),
+            sharpness: number("Sharpness"),
+        }
+    }
+
+    /// `<timg:ImagingSettings>` with the settings that are set, in the
+    /// order of the schema
+    pub(crate) fn to_soap(&self) -> String {
+        format!(
+            "<timg:ImagingSettings>{}{}{}{}{}</timg:ImagingSettings>",
+            optional_element("Brightness", self.brightness),
+            optional_element("ColorSaturation", self.color_saturation),
+            optional_element("Contrast", self.contrast),
+            optional_element("IrCutFilter", self.ir_cut_filter.map(|mode| mode.as_str())),
+            optional_element("Sharpness", self.sharpness),
+        )
+    }
+}
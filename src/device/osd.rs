@@ -0,0 +1,216 @@
+use crate::utils::xml::XmlNode;
+use crate::utils::xml_escape;
+
+use serde::{Deserialize, Serialize};
+
+/// An on screen display: a text or image overlaid on the video of a video
+/// source configuration
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[rustfmt::skip]
+pub struct Osd {
+    /// Empty for an OSD to create, the device picks the token
+    pub token:                       String,
+    /// See MediaProfile::video_source_config_token
+    pub video_source_config_token:   String,
+    pub position:                    OsdPosition,
+    pub content:                     OsdContent,
+    /// Font size of text in percent of the device default, None for the
+    /// default
+    pub font_size:                   Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum OsdPosition {
+    #[default]
+    UpperLeft,
+    UpperRight,
+    LowerLeft,
+    LowerRight,
+    /// Normalized coordinates, -1.0 to 1.0 from the lower left to the
+    /// upper right corner
+    Custom {
+        x: f32,
+        y: f32,
+    },
+}
+
+/// What an OSD shows. Date and time formats are those of the device, e.g.
+/// "yyyy-MM-dd" and "HH:mm:ss".
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum OsdContent {
+    PlainText(String),
+    Date {
+        format: Option<String>,
+    },
+    Time {
+        format: Option<String>,
+    },
+    DateAndTime {
+        date_format: Option<String>,
+        time_format: Option<String>,
+    },
+    /// Path of an image on the device
+    Image(String),
+}
+
+impl Default for OsdContent {
+    fn default() -> Self {
+        OsdContent::PlainText(String::new())
+    }
+}
+
+impl Osd {
+    /// A plain text OSD on the video source configuration with
+    /// `video_source_config_token`
+    pub fn text(video_source_config_token: &str, text: &str, position: OsdPosition) -> Self {
+        Osd {
+            video_source_config_token: video_source_config_token.to_string(),
+            position,
+            content: OsdContent::PlainText(text.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// Parses an OSDs element of GetOSDs. None for OSD types other than
+    /// text and image.
+    pub(crate) fn from_xml(node: &XmlNode) -> Option<Self> {
+        let position = node.child("Position");
+        let position = match position.and_then(|p| p.child_text("Type")).as_deref() {
+            Some("UpperRight") => OsdPosition::UpperRight,
+            Some("LowerLeft") => OsdPosition::LowerLeft,
+            Some("LowerRight") => OsdPosition::LowerRight,
+            Some("Custom") => {
+                let pos = position.and_then(|p| p.child("Pos"));
+                let coordinate = |name: &str| {
+                    pos.and_then(|p| p.attr(name))
+                        .and_then(|c| c.trim().parse().ok())
+                        .unwrap_or_default()
+                };
+                OsdPosition::Custom {
+                    x: coordinate("x"),
+                    y: coordinate("y"),
+                }
+            }
+            _ => OsdPosition::UpperLeft,
+        };
+
+        let text = node.child("TextString");
+        let content = match node.child_text("Type").as_deref() {
+            Some("Text") => {
+                let text = text?;
+                let date_format = text.child_text("DateFormat");
+                let time_format = text.child_text("TimeFormat");
+                match text.child_text("Type").as_deref() {
+                    Some("Date") => OsdContent::Date {
+                        format: date_format,
+                    },
+                    Some("Time") => OsdContent::Time {
+                        format: time_format,
+                    },
+                    Some("DateAndTime") => OsdContent::DateAndTime {
+                        date_format,
+                        time_format,
+                    },
+                    _ => OsdContent::PlainText(text.child_text("PlainText").unwrap_or_default()),
+                }
+            }
+            Some("Image") => OsdContent::Image(
+                node.child("Image")?
+                    .child_text("ImgPath")
+                    .unwrap_or_default(),
+            ),
+            _ => return None,
+        };
+
+        Some(Osd {
+            token: node.attr("token").unwrap_or_default().to_string(),
+            video_source_config_token: node
+                .child_text("VideoSourceConfigurationToken")
+                .unwrap_or_default(),
+            position,
+            content,
+            font_size: text
+                .and_then(|t| t.child_text("FontSize"))
+                .and_then(|s| s.parse().ok()),
+        })
+    }
+
+    /// The OSD of a CreateOSD or SetOSD
+    pub(crate) fn to_soap(&self) -> String {
+        let position = match &self.position {
+            OsdPosition::UpperLeft => "<tt:Type>UpperLeft</tt:Type>".to_string(),
+            OsdPosition::UpperRight => "<tt:Type>UpperRight</tt:Type>".to_string(),
+            OsdPosition::LowerLeft => "<tt:Type>LowerLeft</tt:Type>".to_string(),
+            OsdPosition::LowerRight => "<tt:Type>LowerRight</tt:Type>".to_string(),
+            OsdPosition::Custom { x, y } => {
+                format!("<tt:Type>Custom</tt:Type><tt:Pos x=\"{x}\" y=\"{y}\"/>")
+            }
+        };
+
+        let element = |name: &str, value: &Option<String>| match value {
+            Some(value) => format!("<tt:{name}>{}</tt:{name}>", xml_escape(value)),
+            None => String::new(),
+        };
+        let font_size = self
+            .font_size
+            .map(|size| format!("<tt:FontSize>{size}</tt:FontSize>"))
+            .unwrap_or_default();
+
+        let (kind, content) = match &self.content {
+            OsdContent::Image(path) => (
+                "Image",
+                format!(
+                    "<tt:Image><tt:ImgPath>{}</tt:ImgPath></tt:Image>",
+                    xml_escape(path)
+                ),
+            ),
+            OsdContent::PlainText(text) => (
+                "Text",
+                format!(
+                    "<tt:TextString>
+                        <tt:Type>Plain</tt:Type>
+                        {font_size}
+                        <tt:PlainText>{}</tt:PlainText>
+                    </tt:TextString>",
+                    xml_escape(text)
+                ),
+            ),
+            OsdContent::Date { format: date } => (
+                "Text",
+                format!(
+                    "<tt:TextString><tt:Type>Date</tt:Type>{}{font_size}</tt:TextString>",
+                    element("DateFormat", date)
+                ),
+            ),
+            OsdContent::Time { format: time } => (
+                "Text",
+                format!(
+                    "<tt:TextString><tt:Type>Time</tt:Type>{}{font_size}</tt:TextString>",
+                    element("TimeFormat", time)
+                ),
+            ),
+            OsdContent::DateAndTime {
+                date_format,
+                time_format,
+            } => (
+                "Text",
+                format!(
+                    "<tt:TextString><tt:Type>DateAndTime</tt:Type>{}{}{font_size}</tt:TextString>",
+                    element("DateFormat", date_format),
+                    element("TimeFormat", time_format)
+                ),
+            ),
+        };
+
+        format!(
+            "<tr2:OSD token=\"{}\">
+                <tt:VideoSourceConfigurationToken>{}</tt:VideoSourceConfigurationToken>
+                <tt:Type>{kind}</tt:Type>
+                <tt:Position>{position}</tt:Position>
+                {content}
+            </tr2:OSD>",
+            xml_escape(&self.token),
+            xml_escape(&self.video_source_config_token),
+        )
+    }
+}
@@ -0,0 +1,429 @@
+use super::{optional_element, range};
+use crate::utils::xml::XmlNode;
+use crate::utils::{parse_xsd_duration, xml_escape, xsd_duration};
+
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+/// A PTZ position, translation or speed. In the generic spaces pan and
+/// tilt go from -1.0 to 1.0 and zoom from 0.0 to 1.0, speeds from 0.0 to
+/// 1.0.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[rustfmt::skip]
+pub struct PtzVector {
+    pub pan:    f32,
+    pub tilt:   f32,
+    pub zoom:   f32,
+}
+
+impl PtzVector {
+    pub fn new(pan: f32, tilt: f32, zoom: f32) -> Self {
+        PtzVector { pan, tilt, zoom }
+    }
+
+    pub(crate) fn to_soap(self) -> String {
+        format!(
+            r#"<tt:PanTilt x="{}" y="{}"/><tt:Zoom x="{}"/>"#,
+            self.pan, self.tilt, self.zoom
+        )
+    }
+}
+
+/// A coordinate space of PTZ moves and the range of its coordinates, e.g.
+/// `http://www.onvif.org/ver10/tptz/PanTiltSpaces/PositionGenericSpace`
+/// from -1.0 to 1.0. Zoom spaces have no `y`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[rustfmt::skip]
+pub struct PtzSpace {
+    pub uri:   String,
+    pub x:     RangeInclusive<f32>,
+    pub y:     Option<RangeInclusive<f32>>,
+}
+
+impl PtzSpace {
+    pub(crate) fn from_xml(node: &XmlNode) -> Option<Self> {
+        Some(PtzSpace {
+            uri: node.child_text("URI")?,
+            x: node.child("XRange").and_then(range)?,
+            y: node.child("YRange").and_then(range),
+        })
+    }
+
+    /// The URI and ranges, as in a Range element of PTZ limits
+    pub(crate) fn to_soap(&self) -> String {
+        let y = match &self.y {
+            Some(y) => format!("<tt:YRange>{}</tt:YRange>", range_to_soap(y)),
+            None => String::new(),
+        };
+
+        format!(
+            "<tt:URI>{}</tt:URI><tt:XRange>{}</tt:XRange>{y}",
+            xml_escape(&self.uri),
+            range_to_soap(&self.x)
+        )
+    }
+
+    /// Whether the space is one of the generic spaces every PTZ device
+    /// supports, with coordinates from -1.0 or 0.0 to 1.0
+    pub fn is_generic(&self) -> bool {
+        self.uri.ends_with("GenericSpace")
+    }
+
+    /// Whether coordinates of the space are in degrees, e.g.
+    /// `.../PanTiltSpaces/SphericalPositionSpaceDegrees`
+    pub fn is_degrees(&self) -> bool {
+        self.uri.ends_with("Degrees")
+    }
+
+    /// The coordinates moved into the ranges of the space
+    pub fn clamp(&self, x: f32, y: f32) -> (f32, f32) {
+        let y = match &self.y {
+            Some(range) => clamp(y, range),
+            None => y,
+        };
+
+        (clamp(x, &self.x), y)
+    }
+
+    /// The coordinates in another space of the same kind, e.g. a generic
+    /// position in degrees, assuming both spaces cover the same movement.
+    /// Coordinates out of range are clamped first.
+    pub fn convert(&self, x: f32, y: f32, to: &PtzSpace) -> (f32, f32) {
+        let (x, y) = self.clamp(x, y);
+        let y = match (&self.y, &to.y) {
+            (Some(from), Some(to)) => rescale(y, from, to),
+            _ => y,
+        };
+
+        (rescale(x, &self.x, &to.x), y)
+    }
+}
+
+/// The coordinate spaces a PTZ node supports for each kind of move. A
+/// kind of move the node cannot do has no space.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[rustfmt::skip]
+pub struct PtzSpaces {
+    pub absolute_pan_tilt:     Vec<PtzSpace>,
+    pub absolute_zoom:         Vec<PtzSpace>,
+    pub relative_pan_tilt:     Vec<PtzSpace>,
+    pub relative_zoom:         Vec<PtzSpace>,
+    pub continuous_pan_tilt:   Vec<PtzSpace>,
+    pub continuous_zoom:       Vec<PtzSpace>,
+    pub pan_tilt_speed:        Vec<PtzSpace>,
+    pub zoom_speed:            Vec<PtzSpace>,
+}
+
+impl PtzSpaces {
+    /// Parses the SupportedPTZSpaces of a node or the Spaces of
+    /// configuration options
+    pub(crate) fn from_xml(node: &XmlNode) -> Self {
+        let spaces = |name: &str| -> Vec<PtzSpace> {
+            node.children_named(name)
+                .filter_map(PtzSpace::from_xml)
+                .collect()
+        };
+
+        PtzSpaces {
+            absolute_pan_tilt: spaces("AbsolutePanTiltPositionSpace"),
+            absolute_zoom: spaces("AbsoluteZoomPositionSpace"),
+            relative_pan_tilt: spaces("RelativePanTiltTranslationSpace"),
+            relative_zoom: spaces("RelativeZoomTranslationSpace"),
+            continuous_pan_tilt: spaces("ContinuousPanTiltVelocitySpace"),
+            continuous_zoom: spaces("ContinuousZoomVelocitySpace"),
+            pan_tilt_speed: spaces("PanTiltSpeedSpace"),
+            zoom_speed: spaces("ZoomSpeedSpace"),
+        }
+    }
+
+    /// Whether absolute_move() can pan and tilt, or at least zoom
+    pub fn supports_absolute(&self) -> bool {
+        !self.absolute_pan_tilt.is_empty() || !self.absolute_zoom.is_empty()
+    }
+
+    /// Whether relative_move() can pan and tilt, or at least zoom
+    pub fn supports_relative(&self) -> bool {
+        !self.relative_pan_tilt.is_empty() || !self.relative_zoom.is_empty()
+    }
+
+    /// Whether continuous_move() can pan and tilt, or at least zoom
+    pub fn supports_continuous(&self) -> bool {
+        !self.continuous_pan_tilt.is_empty() || !self.continuous_zoom.is_empty()
+    }
+
+    /// An absolute position in the generic spaces with pan and tilt in
+    /// degrees and zoom in percent of its range. None when the device
+    /// reports no pan and tilt space in degrees, see rescale() to use the
+    /// ranges of the data sheet instead.
+    pub fn to_degrees(&self, position: PtzVector) -> Option<PtzVector> {
+        let generic = generic_space(&self.absolute_pan_tilt)?;
+        let degrees = self.absolute_pan_tilt.iter().find(|s| s.is_degrees())?;
+        let (pan, tilt) = generic.convert(position.pan, position.tilt, degrees);
+
+        let zoom = match generic_space(&self.absolute_zoom) {
+            Some(zoom) => rescale(clamp(position.zoom, &zoom.x), &zoom.x, &PERCENT),
+            None => position.zoom,
+        };
+
+        Some(PtzVector { pan, tilt, zoom })
+    }
+
+    /// A position with pan and tilt in degrees and zoom in percent in the
+    /// generic spaces, for absolute_move(). The reverse of to_degrees().
+    pub fn from_degrees(&self, degrees: PtzVector) -> Option<PtzVector> {
+        let generic = generic_space(&self.absolute_pan_tilt)?;
+        let space = self.absolute_pan_tilt.iter().find(|s| s.is_degrees())?;
+        let (pan, tilt) = space.convert(degrees.pan, degrees.tilt, generic);
+
+        let zoom = match generic_space(&self.absolute_zoom) {
+            Some(zoom) => rescale(clamp(degrees.zoom, &PERCENT), &PERCENT, &zoom.x),
+            None => degrees.zoom,
+        };
+
+        Some(PtzVector { pan, tilt, zoom })
+    }
+
+    /// The position moved into the generic absolute spaces, which devices
+    /// would otherwise answer with a fault
+    pub fn clamp_position(&self, position: PtzVector) -> PtzVector {
+        clamp_vector(
+            position,
+            generic_space(&self.absolute_pan_tilt),
+            generic_space(&self.absolute_zoom),
+        )
+    }
+
+    /// The translation moved into the generic relative spaces
+    pub fn clamp_translation(&self, translation: PtzVector) -> PtzVector {
+        clamp_vector(
+            translation,
+            generic_space(&self.relative_pan_tilt),
+            generic_space(&self.relative_zoom),
+        )
+    }
+
+    /// The speed moved into the generic speed spaces, 0.0 to 1.0 when the
+    /// device reports none. Pan and tilt share one speed range.
+    pub fn clamp_speed(&self, speed: PtzVector) -> PtzVector {
+        let pan_tilt = generic_space(&self.pan_tilt_speed).map_or(SPEED, |s| s.x.clone());
+        let zoom = generic_space(&self.zoom_speed).map_or(SPEED, |s| s.x.clone());
+
+        PtzVector {
+            pan: clamp(speed.pan, &pan_tilt),
+            tilt: clamp(speed.tilt, &pan_tilt),
+            zoom: clamp(speed.zoom, &zoom),
+        }
+    }
+}
+
+const PERCENT: RangeInclusive<f32> = 0.0..=100.0;
+const SPEED: RangeInclusive<f32> = 0.0..=1.0;
+
+/// A value of one range mapped linearly onto another, e.g. a generic pan
+/// from -1.0..=1.0 onto the -170.0..=170.0 degrees of a data sheet
+pub fn rescale(value: f32, from: &RangeInclusive<f32>, to: &RangeInclusive<f32>) -> f32 {
+    let span = from.end() - from.start();
+
+    if span == 0.0 {
+        return *to.start();
+    }
+
+    to.start() + (value - from.start()) / span * (to.end() - to.start())
+}
+
+fn clamp(value: f32, range: &RangeInclusive<f32>) -> f32 {
+    value.clamp(
+        range.start().min(*range.end()),
+        range.end().max(*range.start()),
+    )
+}
+
+fn generic_space(spaces: &[PtzSpace]) -> Option<&PtzSpace> {
+    spaces.iter().find(|s| s.is_generic())
+}
+
+fn clamp_vector(
+    vector: PtzVector,
+    pan_tilt: Option<&PtzSpace>,
+    zoom: Option<&PtzSpace>,
+) -> PtzVector {
+    let (pan, tilt) = match pan_tilt {
+        Some(space) => space.clamp(vector.pan, vector.tilt),
+        None => (vector.pan, vector.tilt),
+    };
+    let zoom = match zoom {
+        Some(space) => clamp(vector.zoom, &space.x),
+        None => vector.zoom,
+    };
+
+    PtzVector { pan, tilt, zoom }
+}
+
+/// A physical PTZ mechanism of the device, with what it can do
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[rustfmt::skip]
+pub struct PtzNode {
+    pub token:                 String,
+    pub name:                  Option<String>,
+    pub spaces:                PtzSpaces,
+    pub max_presets:           Option<u32>,
+    pub home_supported:        bool,
+    /// The home position cannot be changed with SetHomePosition
+    pub fixed_home_position:   Option<bool>,
+}
+
+impl PtzNode {
+    pub(crate) fn from_xml(node: &XmlNode) -> Option<Self> {
+        Some(PtzNode {
+            token: node.attr("token")?.to_string(),
+            name: node.child_text("Name"),
+            spaces: node
+                .child("SupportedPTZSpaces")
+                .map(PtzSpaces::from_xml)
+                .unwrap_or_default(),
+            max_presets: node
+                .child_text("MaximumNumberOfPresets")
+                .and_then(|m| m.parse().ok()),
+            home_supported: node
+                .child_text("HomeSupported")
+                .is_some_and(|h| h == "true"),
+            fixed_home_position: node.attr("FixedHomePosition").map(|f| f == "true"),
+        })
+    }
+}
+
+/// A PTZ configuration, the part of a media profile that ties it to a
+/// PTZ node with its default speed and timeout and its limits
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[rustfmt::skip]
+pub struct PtzConfig {
+    pub token:                    String,
+    pub name:                     Option<String>,
+    /// Number of profiles using the configuration
+    pub use_count:                Option<u32>,
+    pub node_token:               String,
+    /// Speed of absolute and relative moves sent without one
+    pub default_pan_tilt_speed:   Option<(f32, f32)>,
+    pub default_zoom_speed:       Option<f32>,
+    /// How long continuous moves last without a stop
+    pub default_timeout:          Option<Duration>,
+    /// Window pan and tilt are kept in, beyond the limits of the node
+    pub pan_tilt_limits:          Option<PtzSpace>,
+    pub zoom_limits:              Option<PtzSpace>,
+}
+
+impl PtzConfig {
+    pub(crate) fn from_xml(node: &XmlNode) -> Option<Self> {
+        let speed = node.child("DefaultPTZSpeed");
+        let pan_tilt_speed = speed
+            .and_then(|s| s.child("PanTilt"))
+            .and_then(|p| Some((p.attr("x")?.parse().ok()?, p.attr("y")?.parse().ok()?)));
+        let zoom_speed = speed
+            .and_then(|s| s.child("Zoom"))
+            .and_then(|z| z.attr("x")?.parse().ok());
+
+        Some(PtzConfig {
+            token: node.attr("token")?.to_string(),
+            name: node.child_text("Name"),
+            use_count: node.child_text("UseCount").and_then(|u| u.parse().ok()),
+            node_token: node.child_text("NodeToken")?,
+            default_pan_tilt_speed: pan_tilt_speed,
+            default_zoom_speed: zoom_speed,
+            default_timeout: node
+                .child_text("DefaultPTZTimeout")
+                .and_then(|t| parse_xsd_duration(&t)),
+            pan_tilt_limits: node
+                .child("PanTiltLimits")
+                .and_then(|l| l.child("Range"))
+                .and_then(PtzSpace::from_xml),
+            zoom_limits: node
+                .child("ZoomLimits")
+                .and_then(|l| l.child("Range"))
+                .and_then(PtzSpace::from_xml),
+        })
+    }
+
+    pub(crate) fn to_soap(&self) -> String {
+        let pan_tilt_speed = self
+            .default_pan_tilt_speed
+            .map(|(x, y)| format!(r#"<tt:PanTilt x="{x}" y="{y}"/>"#));
+        let zoom_speed = self
+            .default_zoom_speed
+            .map(|x| format!(r#"<tt:Zoom x="{x}"/>"#));
+        let speed = match (pan_tilt_speed, zoom_speed) {
+            (None, None) => None,
+            (pan_tilt, zoom) => Some(format!(
+                "{}{}",
+                pan_tilt.unwrap_or_default(),
+                zoom.unwrap_or_default()
+            )),
+        };
+
+        format!(
+            "<tptz:PTZConfiguration token=\"{}\">
+                <tt:Name>{}</tt:Name>
+                <tt:UseCount>{}</tt:UseCount>
+                <tt:NodeToken>{}</tt:NodeToken>
+                {}
+                {}
+                {}
+                {}
+            </tptz:PTZConfiguration>",
+            xml_escape(&self.token),
+            xml_escape(self.name.as_deref().unwrap_or_default()),
+            self.use_count.unwrap_or_default(),
+            xml_escape(&self.node_token),
+            optional_element("DefaultPTZSpeed", speed),
+            optional_element("DefaultPTZTimeout", self.default_timeout.map(xsd_duration)),
+            optional_element(
+                "PanTiltLimits",
+                self.pan_tilt_limits
+                    .as_ref()
+                    .map(|l| format!("<tt:Range>{}</tt:Range>", l.to_soap()))
+            ),
+            optional_element(
+                "ZoomLimits",
+                self.zoom_limits
+                    .as_ref()
+                    .map(|l| format!("<tt:Range>{}</tt:Range>", l.to_soap()))
+            ),
+        )
+    }
+}
+
+/// What a PTZ configuration accepts, as answered by
+/// GetConfigurationOptions
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[rustfmt::skip]
+pub struct PtzConfigOptions {
+    pub spaces:    PtzSpaces,
+    /// Range of PtzConfig::default_timeout
+    pub timeout:   Option<RangeInclusive<Duration>>,
+}
+
+impl PtzConfigOptions {
+    pub(crate) fn from_xml(node: &XmlNode) -> Self {
+        let timeout = node.child("PTZTimeout").and_then(|t| {
+            let min = parse_xsd_duration(&t.child_text("Min")?)?;
+            let max = parse_xsd_duration(&t.child_text("Max")?)?;
+            Some(min..=max)
+        });
+
+        PtzConfigOptions {
+            spaces: node
+                .child("Spaces")
+                .map(PtzSpaces::from_xml)
+                .unwrap_or_default(),
+            timeout,
+        }
+    }
+}
+
+fn range_to_soap(range: &RangeInclusive<f32>) -> String {
+    format!(
+        "<tt:Min>{}</tt:Min><tt:Max>{}</tt:Max>",
+        range.start(),
+        range.end()
+    )
+}
@@ -0,0 +1,486 @@
+pub use crate::auth::Credentials;
+
+use crate::auth::AuthScheme;
+use crate::builder::camera::CameraBuilder;
+use crate::client::{self, Messages};
+use crate::device::camera::Camera;
+use crate::device::{Device, DeviceInfo};
+use crate::utils::xml::XmlNode;
+use crate::{events, media, system};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+/// How long each check waits for the device
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Clock skew above which the report warns. WS-Security tokens are
+/// usually rejected once the skew reaches a few minutes.
+const SKEW_WARN: Duration = Duration::from_secs(5);
+const SKEW_FAIL: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    /// Works, but not the way the specification or this crate expects
+    Warn,
+    Fail,
+    /// Not run because an earlier check failed
+    Skip,
+}
+
+impl CheckStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "PASS",
+            CheckStatus::Warn => "WARN",
+            CheckStatus::Fail => "FAIL",
+            CheckStatus::Skip => "SKIP",
+        }
+    }
+}
+
+/// Outcome of a single check
+#[derive(Debug, Clone, Serialize)]
+#[rustfmt::skip]
+pub struct Check {
+    pub name:        &'static str,
+    pub status:      CheckStatus,
+    pub detail:      String,
+    pub elapsed_ms:  Option<u128>,
+}
+
+impl Check {
+    fn new(name: &'static str, status: CheckStatus, detail: String, start: Instant) -> Self {
+        Check {
+            name,
+            status,
+            detail,
+            elapsed_ms: Some(start.elapsed().as_millis()),
+        }
+    }
+
+    fn skip(name: &'static str, detail: &str) -> Self {
+        Check {
+            name,
+            status: CheckStatus::Skip,
+            detail: detail.to_string(),
+            elapsed_ms: None,
+        }
+    }
+}
+
+/// Compatibility report of one device, see diagnose()
+#[derive(Debug, Clone, Serialize)]
+#[rustfmt::skip]
+pub struct DiagnosticReport {
+    pub onvif_url:       String,
+    pub generated:       DateTime<Utc>,
+    pub crate_version:   &'static str,
+    pub device_info:     Option<DeviceInfo>,
+    /// Device clock minus local clock, in seconds
+    pub clock_skew:      Option<i64>,
+    pub checks:          Vec<Check>,
+}
+
+impl DiagnosticReport {
+    pub fn failures(&self) -> impl Iterator<Item = &Check> {
+        self.checks
+            .iter()
+            .filter(|check| check.status == CheckStatus::Fail)
+    }
+
+    /// True when no check failed, warnings are allowed
+    pub fn is_compatible(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Plain text version for terminals and bug reports
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "ONVIF diagnostic report for {}", self.onvif_url);
+        let _ = writeln!(
+            text,
+            "Generated {} by onvif-cam-rs {}",
+            self.generated.to_rfc3339(),
+            self.crate_version
+        );
+
+        if let Some(info) = &self.device_info {
+            let _ = writeln!(
+                text,
+                "Device: {} {}, firmware {}",
+                info.manufacturer.as_deref().unwrap_or("?"),
+                info.model.as_deref().unwrap_or("?"),
+                info.firmware_version.as_deref().unwrap_or("?")
+            );
+        }
+
+        text.push('\n');
+
+        for check in &self.checks {
+            let elapsed = check
+                .elapsed_ms
+                .map(|ms| format!(" ({ms} ms)"))
+                .unwrap_or_default();
+            let _ = writeln!(
+                text,
+                "{}  {:<12} {}{elapsed}",
+                check.status.as_str(),
+                check.name,
+                check.detail
+            );
+        }
+
+        text
+    }
+}
+
+/// Runs a battery of ONVIF calls against the device at `onvif_url` and
+/// reports how it behaves: reachability, clock skew, SOAP fault handling,
+/// authentication, the requests build_all() makes, stream reachability,
+/// snapshots and events. Device problems end up in the report rather
+/// than as errors, so a report is produced even for a dead device.
+///
+/// SOAP requests are sent without credentials, the crate has no
+/// WS-Security support yet. `credentials` are used for the RTSP checks.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::diagnose::{self, Credentials};
+/// # async fn run() -> anyhow::Result<()> {
+/// let url = "http://192.168.1.64/onvif/device_service".parse()?;
+/// let report = diagnose::diagnose(url, Some(&Credentials::new("admin", "secret"))).await?;
+///
+/// println!("{}", report.to_text());
+/// # Ok(())
+/// # }
+/// ```
+pub async fn diagnose(
+    onvif_url: url::Url,
+    credentials: Option<&Credentials>,
+) -> Result<DiagnosticReport> {
+    let mut report = DiagnosticReport {
+        onvif_url: onvif_url.to_string(),
+        generated: Utc::now(),
+        crate_version: env!("CARGO_PKG_VERSION"),
+        device_info: None,
+        clock_skew: None,
+        checks: Vec::new(),
+    };
+
+    let start = Instant::now();
+    match client::ping(onvif_url.clone(), CHECK_TIMEOUT).await {
+        Ok(_) => report.checks.push(Check::new(
+            "reachable",
+            CheckStatus::Pass,
+            "Answered GetSystemDateAndTime".to_string(),
+            start,
+        )),
+        Err(e) => {
+            report.checks.push(Check::new(
+                "reachable",
+                CheckStatus::Fail,
+                format!("No answer to GetSystemDateAndTime: {e}"),
+                start,
+            ));
+
+            for name in [
+                "clock",
+                "soap_fault",
+                "auth",
+                "build",
+                "stream",
+                "snapshot",
+                "events",
+            ] {
+                report
+                    .checks
+                    .push(Check::skip(name, "Device not reachable"));
+            }
+
+            return Ok(report);
+        }
+    }
+
+    let (check, skew) = check_clock(&onvif_url).await;
+    report.checks.push(check);
+    report.clock_skew = skew;
+    report.checks.push(check_soap_fault(&onvif_url).await);
+    report
+        .checks
+        .push(check_auth(&onvif_url, credentials).await);
+
+    let mut camera = Camera::new(Device::new(onvif_url.clone()));
+
+    let start = Instant::now();
+    match camera.build_all().await {
+        Ok(()) => {
+            let profile = camera.profile().cloned().unwrap_or_default();
+            report.device_info = Some(camera.device_info().clone());
+            report.checks.push(Check::new(
+                "build",
+                CheckStatus::Pass,
+                format!(
+                    "{} profiles, {} with {} {:?}",
+                    camera.profiles().len(),
+                    profile.token,
+                    profile.video_codec().unwrap_or("unknown codec"),
+                    profile.video_dim().unwrap_or_default()
+                ),
+                start,
+            ));
+        }
+        Err(e) => {
+            report.checks.push(Check::new(
+                "build",
+                CheckStatus::Fail,
+                format!("build_all() failed: {e}"),
+                start,
+            ));
+
+            for name in ["stream", "snapshot", "events"] {
+                report
+                    .checks
+                    .push(Check::skip(name, "Camera could not be built"));
+            }
+
+            return Ok(report);
+        }
+    }
+
+    report.checks.push(check_stream(&camera, credentials).await);
+    report.checks.push(check_snapshot(&camera).await);
+    report.checks.push(check_events(&camera).await);
+
+    Ok(report)
+}
+
+async fn check_clock(onvif_url: &url::Url) -> (Check, Option<i64>) {
+    let start = Instant::now();
+
+    let device_time = match system::get_system_date_and_time(onvif_url.clone()).await {
+        Ok(time) => time,
+        Err(e) => {
+            let detail = format!("Unable to read the device clock: {e}");
+            return (Check::new("clock", CheckStatus::Fail, detail, start), None);
+        }
+    };
+
+    // The device read its clock about halfway through the round trip
+    let local_time =
+        Utc::now() - chrono::Duration::from_std(start.elapsed() / 2).unwrap_or_default();
+    let skew = (device_time - local_time).num_seconds();
+    let abs = Duration::from_secs(skew.unsigned_abs());
+
+    let status = match abs {
+        abs if abs >= SKEW_FAIL => CheckStatus::Fail,
+        abs if abs >= SKEW_WARN => CheckStatus::Warn,
+        _ => CheckStatus::Pass,
+    };
+    let detail = match status {
+        CheckStatus::Pass => format!("Device clock is {skew}s off local time"),
+        _ => format!("Device clock is {skew}s off local time, fix it with system::set_system_date_and_time()"),
+    };
+
+    (Check::new("clock", status, detail, start), Some(skew))
+}
+
+/// Sends an operation no device implements and expects a SOAP fault
+async fn check_soap_fault(onvif_url: &url::Url) -> Check {
+    let start = Instant::now();
+    let msg = Messages::Raw {
+        body: "<tds:OnvifCamRsUnknownOperation/>".to_string(),
+    };
+
+    let response = match client::send_timeout(onvif_url.clone(), msg, CHECK_TIMEOUT).await {
+        Ok(response) => response,
+        Err(e) if e.downcast_ref::<client::Error>().is_some() => {
+            return Check::skip("soap_fault", "Device wants credentials first");
+        }
+        Err(e) => {
+            let detail = format!("No answer to an unknown operation: {e}");
+            return Check::new("soap_fault", CheckStatus::Fail, detail, start);
+        }
+    };
+
+    let status = response.status();
+    let body = response.bytes().await.unwrap_or_default();
+    let fault = XmlNode::parse(&body).ok().and_then(|root| {
+        let fault = root.find("Fault")?;
+        let code = fault.find("Value").map(|v| v.text.trim().to_string());
+        Some(code.unwrap_or_default())
+    });
+
+    match (status.is_success(), fault) {
+        (false, Some(code)) => Check::new(
+            "soap_fault",
+            CheckStatus::Pass,
+            format!(
+                "Unknown operation answered with HTTP {} and fault {code}",
+                status.as_u16()
+            ),
+            start,
+        ),
+        (true, Some(code)) => Check::new(
+            "soap_fault",
+            CheckStatus::Warn,
+            format!("Fault {code} sent with HTTP 200, errors may be mistaken for answers"),
+            start,
+        ),
+        (_, None) => Check::new(
+            "soap_fault",
+            CheckStatus::Fail,
+            format!(
+                "Unknown operation answered with HTTP {} and no SOAP fault",
+                status.as_u16()
+            ),
+            start,
+        ),
+    }
+}
+
+/// Sends GetDeviceInformation without credentials to see which kind of
+/// authentication the device asks for
+async fn check_auth(onvif_url: &url::Url, credentials: Option<&Credentials>) -> Check {
+    let start = Instant::now();
+
+    let schemes =
+        match client::send_timeout(onvif_url.clone(), Messages::DeviceInfo, CHECK_TIMEOUT).await {
+            Ok(response) if response.status().is_success() => {
+                return Check::new(
+                    "auth",
+                    CheckStatus::Pass,
+                    "GetDeviceInformation answered without credentials".to_string(),
+                    start,
+                )
+            }
+            Ok(response) => {
+                let detail = format!(
+                    "GetDeviceInformation failed with HTTP {}",
+                    response.status().as_u16()
+                );
+                return Check::new("auth", CheckStatus::Fail, detail, start);
+            }
+            Err(e) => match e.downcast_ref() {
+                Some(client::Error::AuthRequired { schemes, .. }) => schemes.clone(),
+                _ => {
+                    let detail = format!("No answer to GetDeviceInformation: {e}");
+                    return Check::new("auth", CheckStatus::Fail, detail, start);
+                }
+            },
+        };
+
+    let http: Vec<String> = schemes
+        .iter()
+        .filter(|scheme| **scheme != AuthScheme::UsernameToken)
+        .map(|scheme| format!("{scheme:?}"))
+        .collect();
+    let required = match schemes.contains(&AuthScheme::UsernameToken) {
+        _ if !http.is_empty() => format!("HTTP {} authentication", http.join("/")),
+        true => "WS-UsernameToken authentication".to_string(),
+        false => "HTTP authentication".to_string(),
+    };
+
+    let given = match credentials {
+        Some(_) => "credentials are only used for RTSP",
+        None => "no credentials given",
+    };
+
+    Check::new(
+        "auth",
+        CheckStatus::Warn,
+        format!("Device requires {required}, {given}"),
+        start,
+    )
+}
+
+/// OPTIONS and DESCRIBE on the stream URI of the camera
+async fn check_stream(camera: &Camera, credentials: Option<&Credentials>) -> Check {
+    let start = Instant::now();
+    let mut stream = camera.stream.clone();
+
+    let Some(uri) = stream.uri.as_deref() else {
+        return Check::skip("stream", "No stream URI");
+    };
+
+    if let (Some(credentials), Ok(mut url)) = (credentials, uri.parse::<url::Url>()) {
+        let _ = url.set_username(&credentials.username);
+        let _ = url.set_password(Some(&credentials.password));
+        stream.uri = Some(url.to_string());
+    }
+
+    match stream.probe(CHECK_TIMEOUT).await {
+        Ok(probe) => Check::new(
+            "stream",
+            CheckStatus::Pass,
+            format!(
+                "{} answered DESCRIBE with {}",
+                probe.server.as_deref().unwrap_or("RTSP server"),
+                probe.video_codec().unwrap_or("no video")
+            ),
+            start,
+        ),
+        Err(e) => Check::new("stream", CheckStatus::Fail, format!("{e}"), start),
+    }
+}
+
+async fn check_snapshot(camera: &Camera) -> Check {
+    let start = Instant::now();
+
+    let (Ok(url), Some(token)) = (
+        media::media_url(camera),
+        camera.profile().map(|p| p.token.clone()),
+    ) else {
+        return Check::skip("snapshot", "No media service or profile");
+    };
+
+    match media::get_snapshot_uri(url, &token).await {
+        Ok(uri) => Check::new(
+            "snapshot",
+            CheckStatus::Pass,
+            uri.uri.unwrap_or_default(),
+            start,
+        ),
+        Err(e) => Check::new(
+            "snapshot",
+            CheckStatus::Warn,
+            format!("GetSnapshotUri failed: {e}"),
+            start,
+        ),
+    }
+}
+
+/// Creates a pull point subscription and removes it again
+async fn check_events(camera: &Camera) -> Check {
+    let start = Instant::now();
+
+    let Ok(url) = events::event_url(camera) else {
+        return Check::skip("events", "No event service");
+    };
+
+    let result = async {
+        let subscription = events::create_pull_point_subscription(url).await?;
+        subscription.pull(Duration::from_secs(1), 10).await?;
+        subscription.unsubscribe().await
+    };
+
+    match result.await {
+        Ok(()) => Check::new(
+            "events",
+            CheckStatus::Pass,
+            "Pull point subscription created, pulled and removed".to_string(),
+            start,
+        ),
+        Err(e) => Check::new("events", CheckStatus::Warn, format!("{e}"), start),
+    }
+}
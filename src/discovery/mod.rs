@@ -0,0 +1,317 @@
+use crate::rtsp::multicast::{MulticastGroup, MulticastInterface};
+use crate::utils::xml::XmlNode;
+use crate::utils::xml_escape;
+
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::net::UdpSocket;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+/// Port and group WS-Discovery messages are sent to
+pub const DISCOVERY_PORT: u16 = 3702;
+pub const DISCOVERY_GROUP: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
+
+const NS_DISCOVERY: &str = "http://schemas.xmlsoap.org/ws/2005/04/discovery";
+const TO_DISCOVERY: &str = "urn:schemas-xmlsoap-org:ws:2005:04:discovery";
+const TO_ANONYMOUS: &str = "http://schemas.xmlsoap.org/ws/2004/08/addressing/role/anonymous";
+
+/// The server side of WS-Discovery: announces a device with Hello,
+/// answers Probe and Resolve messages and says Bye when it goes away.
+/// Lets the mock camera, or any virtual camera written in Rust, be found
+/// by ONVIF clients including client::discover().
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::discovery::DiscoveryResponder;
+/// # async fn run() -> anyhow::Result<()> {
+/// let responder = DiscoveryResponder::new("http://192.168.1.10:8080/onvif/device_service".parse()?)
+///     .scope("onvif://www.onvif.org/name/VirtualCam")
+///     .start()
+///     .await?;
+///
+/// // ... serve ONVIF requests ...
+///
+/// responder.bye().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct DiscoveryResponder {
+    /// Stable EndpointReference address, "urn:uuid:..."
+    pub endpoint:            String,
+    /// Qualified names in the dn: (ONVIF network) or tds: namespace
+    pub types:               Vec<String>,
+    pub scopes:              Vec<String>,
+    /// Device service URLs
+    pub xaddrs:              Vec<url::Url>,
+    /// Increase when the scopes or addresses change
+    pub metadata_version:    u32,
+    pub interface:           MulticastInterface,
+    /// Replies are cut after this many bytes, to imitate broken devices
+    pub(crate) truncate_replies: Option<usize>,
+}
+
+impl DiscoveryResponder {
+    /// A NetworkVideoTransmitter answering at `xaddr`, with a new endpoint
+    /// reference and the basic ONVIF scopes
+    pub fn new(xaddr: url::Url) -> Self {
+        DiscoveryResponder {
+            endpoint: format!("urn:uuid:{}", Uuid::new_v4()),
+            types: vec!["dn:NetworkVideoTransmitter".to_string()],
+            scopes: vec![
+                "onvif://www.onvif.org/type/video_encoder".to_string(),
+                "onvif://www.onvif.org/Profile/Streaming".to_string(),
+            ],
+            xaddrs: vec![xaddr],
+            metadata_version: 1,
+            interface: MulticastInterface::Any,
+            truncate_replies: None,
+        }
+    }
+
+    pub fn endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = endpoint.to_string();
+        self
+    }
+
+    pub fn scope(mut self, scope: &str) -> Self {
+        self.scopes.push(scope.to_string());
+        self
+    }
+
+    /// Replaces all scopes
+    pub fn scopes(mut self, scopes: Vec<String>) -> Self {
+        self.scopes = scopes;
+        self
+    }
+
+    pub fn interface(mut self, interface: MulticastInterface) -> Self {
+        self.interface = interface;
+        self
+    }
+
+    /// Joins the discovery group, sends Hello and answers probes until
+    /// the handle is dropped or bye() is called
+    pub async fn start(self) -> Result<ResponderHandle> {
+        let group = MulticastGroup::new(DISCOVERY_GROUP.into(), DISCOVERY_PORT);
+        let socket = group.join(self.interface).await?;
+        let reply_socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+            .await
+            .context("[Discovery][start] Unable to bind reply socket")?;
+
+        let state = Arc::new(State {
+            responder: self,
+            instance_id: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            message_number: AtomicU32::new(1),
+            reply_socket,
+        });
+
+        state.announce("Hello").await?;
+        let task = tokio::spawn(answer(socket, state.clone()));
+
+        Ok(ResponderHandle { state, task })
+    }
+}
+
+/// A running responder, stops answering when dropped. Dropping does not
+/// send Bye, clients then forget the device once it stops answering.
+pub struct ResponderHandle {
+    state: Arc<State>,
+    task: JoinHandle<()>,
+}
+
+impl ResponderHandle {
+    pub fn endpoint(&self) -> &str {
+        &self.state.responder.endpoint
+    }
+
+    /// Sends Hello again, e.g. after the network came back
+    pub async fn hello(&self) -> Result<()> {
+        self.state.announce("Hello").await
+    }
+
+    /// Sends Bye and stops answering
+    pub async fn bye(self) -> Result<()> {
+        self.task.abort();
+        self.state.announce("Bye").await
+    }
+}
+
+impl Drop for ResponderHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+struct State {
+    responder: DiscoveryResponder,
+    instance_id: u64,
+    message_number: AtomicU32,
+    reply_socket: UdpSocket,
+}
+
+impl State {
+    /// Sends Hello or Bye to the multicast group
+    async fn announce(&self, action: &str) -> Result<()> {
+        let body = match action {
+            "Bye" => format!(
+                "<d:Bye><w:EndpointReference><w:Address>{}</w:Address></w:EndpointReference></d:Bye>",
+                xml_escape(&self.responder.endpoint)
+            ),
+            _ => format!("<d:{action}>{}</d:{action}>", self.description()),
+        };
+        let message = self.envelope(action, TO_DISCOVERY, None, &body);
+        let group = SocketAddr::new(DISCOVERY_GROUP.into(), DISCOVERY_PORT);
+
+        self.reply_socket
+            .send_to(message.as_bytes(), group)
+            .await
+            .with_context(|| format!("[Discovery][announce] Unable to send {action}"))?;
+
+        debug!("Sent {action} for {}", self.responder.endpoint);
+        Ok(())
+    }
+
+    /// EndpointReference, Types, Scopes, XAddrs and MetadataVersion
+    fn description(&self) -> String {
+        let responder = &self.responder;
+        let xaddrs: Vec<String> = responder.xaddrs.iter().map(|x| x.to_string()).collect();
+
+        format!(
+            "<w:EndpointReference><w:Address>{}</w:Address></w:EndpointReference>
+            <d:Types>{}</d:Types>
+            <d:Scopes>{}</d:Scopes>
+            <d:XAddrs>{}</d:XAddrs>
+            <d:MetadataVersion>{}</d:MetadataVersion>",
+            xml_escape(&responder.endpoint),
+            xml_escape(&responder.types.join(" ")),
+            xml_escape(&responder.scopes.join(" ")),
+            xml_escape(&xaddrs.join(" ")),
+            responder.metadata_version,
+        )
+    }
+
+    fn envelope(&self, action: &str, to: &str, relates_to: Option<&str>, body: &str) -> String {
+        let relates_to = relates_to
+            .map(|id| format!("<w:RelatesTo>{}</w:RelatesTo>", xml_escape(id)))
+            .unwrap_or_default();
+
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<e:Envelope xmlns:e="http://www.w3.org/2003/05/soap-envelope"
+    xmlns:w="http://schemas.xmlsoap.org/ws/2004/08/addressing"
+    xmlns:d="{NS_DISCOVERY}"
+    xmlns:dn="http://www.onvif.org/ver10/network/wsdl"
+    xmlns:tds="http://www.onvif.org/ver10/device/wsdl">
+<e:Header>
+    <w:MessageID>uuid:{}</w:MessageID>
+    {relates_to}
+    <w:To>{to}</w:To>
+    <w:Action>{NS_DISCOVERY}/{action}</w:Action>
+    <d:AppSequence InstanceId="{}" MessageNumber="{}"/>
+</e:Header>
+<e:Body>{body}</e:Body>
+</e:Envelope>"#,
+            Uuid::new_v4(),
+            self.instance_id,
+            self.message_number.fetch_add(1, Ordering::Relaxed),
+        )
+    }
+
+    /// The answer to a Probe or Resolve, None when the message is neither
+    /// or does not match this device
+    fn reply(&self, message: &XmlNode) -> Option<String> {
+        let relates_to = message.find("MessageID").map(|m| m.text.trim().to_string());
+        let relates_to = relates_to.as_deref();
+
+        if let Some(probe) = message.find("Probe") {
+            let types = probe.child_text("Types").unwrap_or_default();
+            let scopes = probe.child_text("Scopes").unwrap_or_default();
+
+            if !self.matches_types(&types) || !self.matches_scopes(&scopes) {
+                return None;
+            }
+
+            let body = format!(
+                "<d:ProbeMatches><d:ProbeMatch>{}</d:ProbeMatch></d:ProbeMatches>",
+                self.description()
+            );
+            return Some(self.envelope("ProbeMatches", TO_ANONYMOUS, relates_to, &body));
+        }
+
+        let resolve = message.find("Resolve")?;
+        let address = resolve.find("Address")?.text.trim().to_string();
+
+        if address != self.responder.endpoint {
+            return None;
+        }
+
+        let body = format!(
+            "<d:ResolveMatches><d:ResolveMatch>{}</d:ResolveMatch></d:ResolveMatches>",
+            self.description()
+        );
+        Some(self.envelope("ResolveMatches", TO_ANONYMOUS, relates_to, &body))
+    }
+
+    /// Every probed type must be one of ours. Namespace prefixes differ
+    /// between clients so only the local names are compared.
+    fn matches_types(&self, types: &str) -> bool {
+        let local = |name: &str| name.rsplit(':').next().unwrap_or(name).to_string();
+        let ours: Vec<String> = self.responder.types.iter().map(|t| local(t)).collect();
+
+        types
+            .split_whitespace()
+            .all(|probed| ours.contains(&local(probed)))
+    }
+
+    /// Every probed scope must be a prefix, segment by segment, of one of
+    /// ours (the default RFC 3986 matching rule)
+    fn matches_scopes(&self, scopes: &str) -> bool {
+        scopes.split_whitespace().all(|probed| {
+            let probed = probed.trim_end_matches('/');
+
+            self.responder.scopes.iter().any(|scope| {
+                scope == probed
+                    || scope
+                        .strip_prefix(probed)
+                        .is_some_and(|rest| rest.starts_with('/'))
+            })
+        })
+    }
+}
+
+/// Answers the probes and resolves received on the multicast group
+async fn answer(socket: UdpSocket, state: Arc<State>) {
+    let mut buf = vec![0u8; 65536];
+
+    while let Ok((size, from)) = socket.recv_from(&mut buf).await {
+        // Some clients, this crate included, send whitespace before the
+        // XML declaration
+        let Ok(message) = XmlNode::parse(buf[..size].trim_ascii_start()) else {
+            continue;
+        };
+
+        let Some(reply) = state.reply(&message) else {
+            continue;
+        };
+
+        let reply = match state.responder.truncate_replies {
+            Some(size) => &reply.as_bytes()[..size.min(reply.len())],
+            None => reply.as_bytes(),
+        };
+
+        if let Err(e) = state.reply_socket.send_to(reply, from).await {
+            warn!("[Discovery][answer] Unable to answer {from}: {e}");
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use crate::client::{self, Messages};
+use crate::device::camera::Camera;
+use crate::events::EventMessage;
+use crate::utils::xml::XmlNode;
+
+use anyhow::{anyhow, Result};
+use log::info;
+use std::time::Duration;
+
+#[derive(Default, Debug, Clone)]
+#[rustfmt::skip]
+pub struct DoorInfo {
+    pub token:         String,
+    pub name:          String,
+    pub description:   Option<String>,
+}
+
+/// Logical state of a door as reported by the device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DoorMode {
+    Unknown,
+    Locked,
+    Unlocked,
+    /// Momentarily unlocked by access_door()
+    Accessed,
+    Blocked,
+    LockedDown,
+    LockedOpen,
+    DoubleLocked,
+}
+
+impl DoorMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DoorMode::Unknown => "Unknown",
+            DoorMode::Locked => "Locked",
+            DoorMode::Unlocked => "Unlocked",
+            DoorMode::Accessed => "Accessed",
+            DoorMode::Blocked => "Blocked",
+            DoorMode::LockedDown => "LockedDown",
+            DoorMode::LockedOpen => "LockedOpen",
+            DoorMode::DoubleLocked => "DoubleLocked",
+        }
+    }
+}
+
+impl From<&str> for DoorMode {
+    fn from(input: &str) -> Self {
+        match input.trim() {
+            "Locked" => DoorMode::Locked,
+            "Unlocked" => DoorMode::Unlocked,
+            "Accessed" => DoorMode::Accessed,
+            "Blocked" => DoorMode::Blocked,
+            "LockedDown" => DoorMode::LockedDown,
+            "LockedOpen" => DoorMode::LockedOpen,
+            "DoubleLocked" => DoorMode::DoubleLocked,
+            _ => DoorMode::Unknown,
+        }
+    }
+}
+
+/// State of a door, fields the device does not monitor are None
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct DoorState {
+    pub mode:        DoorMode,
+    /// Door physical state, e.g. "Open" or "Closed"
+    pub physical:    Option<String>,
+    /// Lock physical state, e.g. "Locked" or "Unlocked"
+    pub lock:        Option<String>,
+    /// e.g. "Normal", "DoorForcedOpen" or "DoorOpenTooLong"
+    pub alarm:       Option<String>,
+}
+
+/// A change of door state announced on a tns1:Door/State/* event topic
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct DoorEvent {
+    pub door_token:   String,
+    /// Last part of the topic, e.g. "DoorMode" or "DoorPhysicalState"
+    pub property:     String,
+    pub state:        String,
+}
+
+impl DoorEvent {
+    /// The door state change carried by an event, None for events of other
+    /// topics
+    pub fn from_event(event: &EventMessage) -> Option<DoorEvent> {
+        let topic = event.topic.rsplit(':').next().unwrap_or_default();
+        let property = topic.strip_prefix("Door/State/")?;
+
+        let value = |items: &[(String, String)], name: &str| {
+            items
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.clone())
+        };
+
+        Some(DoorEvent {
+            door_token: value(&event.source, "DoorToken")?,
+            property: property.to_string(),
+            state: value(&event.data, "State")?,
+        })
+    }
+
+    /// The new mode when this is a DoorMode event
+    pub fn mode(&self) -> Option<DoorMode> {
+        match self.property.as_str() {
+            "DoorMode" => Some(DoorMode::from(self.state.as_str())),
+            _ => None,
+        }
+    }
+}
+
+/// Door Control service URL of a built camera
+pub fn door_control_url(camera: &Camera) -> Result<url::Url> {
+    match &camera.services.door_control {
+        Some(url) => Ok(camera.base.authorize(&url.parse()?)),
+        None => Err(anyhow!("[DoorControl] Device has no Door Control service")),
+    }
+}
+
+/// Lists every door of the device, following the pages the device splits
+/// the list into
+///
+/// # Arguments
+///
+/// * `onvif_url` - The Door Control service URL (see `Services::door_control`)
+pub async fn get_door_info_list(onvif_url: url::Url) -> Result<Vec<DoorInfo>> {
+    let mut doors = Vec::new();
+    let mut start_reference = None;
+
+    loop {
+        let msg = Messages::GetDoorInfoList { start_reference };
+
+        let response = client::send(onvif_url.clone(), msg).await?;
+        let response = response.error_for_status()?.bytes().await?;
+        let root = XmlNode::parse(&response[..])?;
+
+        doors.extend(root.find_all("DoorInfo").into_iter().map(|d| DoorInfo {
+            token: d.attr("token").unwrap_or_default().to_string(),
+            name: d.child_text("Name").unwrap_or_default(),
+            description: d.child_text("Description"),
+        }));
+
+        start_reference = root
+            .find("NextStartReference")
+            .map(|n| n.text.trim().to_string());
+
+        if start_reference.as_deref().is_none_or(str::is_empty) {
+            break;
+        }
+    }
+
+    info!("Doors found: {}", doors.len());
+
+    Ok(doors)
+}
+
+pub async fn get_door_state(onvif_url: url::Url, token: &str) -> Result<DoorState> {
+    let msg = Messages::GetDoorState {
+        token: token.to_string(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+    let state = root
+        .find("DoorState")
+        .ok_or_else(|| anyhow!("[DoorControl][get_door_state] No DoorState in response"))?;
+
+    Ok(DoorState {
+        mode: state
+            .child_text("DoorMode")
+            .map(|m| DoorMode::from(m.as_str()))
+            .unwrap_or(DoorMode::Unknown),
+        physical: state.child_text("DoorPhysicalState"),
+        lock: state.child_text("LockPhysicalState"),
+        alarm: state.child_text("Alarm"),
+    })
+}
+
+/// Momentarily unlocks the door, for `access_time` or the device default
+/// when None, after which the device locks it again
+pub async fn access_door(
+    onvif_url: url::Url,
+    token: &str,
+    access_time: Option<Duration>,
+) -> Result<()> {
+    let msg = Messages::AccessDoor {
+        token: token.to_string(),
+        access_time,
+    };
+
+    client::send(onvif_url, msg).await?.error_for_status()?;
+    Ok(())
+}
+
+pub async fn lock_door(onvif_url: url::Url, token: &str) -> Result<()> {
+    let msg = Messages::LockDoor {
+        token: token.to_string(),
+    };
+
+    client::send(onvif_url, msg).await?.error_for_status()?;
+    Ok(())
+}
+
+/// Unlocks the door until lock_door() is called
+pub async fn unlock_door(onvif_url: url::Url, token: &str) -> Result<()> {
+    let msg = Messages::UnlockDoor {
+        token: token.to_string(),
+    };
+
+    client::send(onvif_url, msg).await?.error_for_status()?;
+    Ok(())
+}
@@ -0,0 +1,154 @@
+use crate::client::{self, Messages};
+use crate::device::camera::Camera;
+use crate::utils::parse_xsd_datetime;
+use crate::utils::xml::XmlNode;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+/// A single notification pulled from a device, e.g. a motion alarm
+/// with topic `tns1:RuleEngine/CellMotionDetector/Motion`
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct EventMessage {
+    pub topic:      String,
+    pub utc_time:   Option<DateTime<Utc>>,
+    pub source:     Vec<(String, String)>,
+    pub data:       Vec<(String, String)>,
+}
+
+impl EventMessage {
+    pub(crate) fn from_xml(node: &XmlNode) -> Self {
+        let message = node.find("Message").and_then(|m| m.child("Message"));
+        let items = |name: &str| -> Vec<(String, String)> {
+            message
+                .and_then(|m| m.child(name))
+                .map(|n| {
+                    n.children_named("SimpleItem")
+                        .map(|i| {
+                            (
+                                i.attr("Name").unwrap_or_default().to_string(),
+                                i.attr("Value").unwrap_or_default().to_string(),
+                            )
+                        })
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        EventMessage {
+            topic: node.child_text("Topic").unwrap_or_default(),
+            utc_time: message
+                .and_then(|m| m.attr("UtcTime"))
+                .and_then(parse_xsd_datetime),
+            source: items("Source"),
+            data: items("Data"),
+        }
+    }
+
+    /// Value of a data item, e.g. `data_value("IsMotion")`
+    pub fn data_value(&self, name: &str) -> Option<&str> {
+        self.data
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// A pull point created on the device event service. Messages are
+/// fetched with pull() and the subscription must be renewed before
+/// `termination_time` or the device drops it.
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct PullPointSubscription {
+    pub address:            url::Url,
+    pub termination_time:   Option<DateTime<Utc>>,
+}
+
+/// Event service URL of a built camera, from GetServices or GetCapabilities
+pub fn event_url(camera: &Camera) -> Result<url::Url> {
+    match (&camera.services.event, &camera.capabilities.url_events) {
+        (Some(url), _) => Ok(camera.base.authorize(&url.parse()?)),
+        (None, Some(url)) => Ok(camera.base.authorize(url)),
+        _ => Err(anyhow!("[Events] Device has no event service")),
+    }
+}
+
+/// Creates a pull point subscription for all events of the device
+///
+/// # Arguments
+///
+/// * `onvif_url` - The event service URL (see `Services::event`)
+pub async fn create_pull_point_subscription(onvif_url: url::Url) -> Result<PullPointSubscription> {
+    let response = client::send(onvif_url, Messages::CreatePullPointSubscriptionRequest).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    let address = root
+        .find("SubscriptionReference")
+        .and_then(|r| r.child_text("Address"))
+        .ok_or_else(|| {
+            anyhow!("[Events][create_pull_point_subscription] No subscription address in response")
+        })?;
+
+    Ok(PullPointSubscription {
+        address: address.trim().parse()?,
+        termination_time: root
+            .find("TerminationTime")
+            .and_then(|t| parse_xsd_datetime(&t.text)),
+    })
+}
+
+impl PullPointSubscription {
+    /// Waits up to `timeout` on the device for at most `limit` messages
+    pub async fn pull(&self, timeout: Duration, limit: u32) -> Result<Vec<EventMessage>> {
+        let msg = Messages::PullMessages { timeout, limit };
+
+        // Give the device a little longer than the timeout it was asked to hold the request for
+        let wait = timeout + Duration::from_secs(2);
+        let response = client::send_timeout(self.address.clone(), msg, wait).await?;
+        let response = response.error_for_status()?.bytes().await?;
+        let root = XmlNode::parse(&response[..])?;
+
+        let messages = root
+            .find_all("NotificationMessage")
+            .into_iter()
+            .map(EventMessage::from_xml)
+            .collect();
+
+        Ok(messages)
+    }
+
+    /// Extends the subscription by `termination` from now
+    pub async fn renew(&mut self, termination: Duration) -> Result<()> {
+        let msg = Messages::Renew { termination };
+
+        let response = client::send(self.address.clone(), msg).await?;
+        let response = response.error_for_status()?.bytes().await?;
+        let root = XmlNode::parse(&response[..])?;
+
+        self.termination_time = root
+            .find("TerminationTime")
+            .and_then(|t| parse_xsd_datetime(&t.text));
+
+        Ok(())
+    }
+
+    pub async fn unsubscribe(self) -> Result<()> {
+        client::send(self.address, Messages::Unsubscribe)
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// True when the subscription ends within `margin`
+    pub fn expires_within(&self, margin: Duration) -> bool {
+        match self.termination_time {
+            Some(time) => {
+                time - chrono::Duration::from_std(margin).unwrap_or_default() <= Utc::now()
+            }
+            None => false,
+        }
+    }
+}
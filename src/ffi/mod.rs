@@ -0,0 +1,416 @@
+use crate::auth::Credentials;
+use crate::builder::camera::CameraBuilder;
+use crate::client;
+use crate::device::camera::Camera;
+use crate::device::{Device, StreamTransport};
+use crate::events::{self, EventMessage};
+use crate::{media, ptz};
+
+use anyhow::{anyhow, Result};
+use std::cell::RefCell;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Wait of each PullMessages request of an event subscription, also the
+/// longest time onvif_events_free() waits for the subscription to end
+const EVENT_PULL_WAIT: Duration = Duration::from_secs(5);
+
+/// Called for every event with the topic, the event as a JSON object and
+/// the user data given to onvif_events_subscribe(). When the subscription
+/// fails, it is called a last time with a NULL topic and `{"error": ...}`.
+pub type OnvifEventCallback =
+    extern "C" fn(topic: *const c_char, event_json: *const c_char, user_data: *mut c_void);
+
+/// A camera built by onvif_camera_new(), opaque to C
+pub struct OnvifCamera {
+    camera: Camera,
+}
+
+/// A running event subscription, opaque to C
+pub struct OnvifEvents {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// Lets the user data pointer move to the event thread. C callers are
+/// told the callback runs on another thread.
+struct UserData(*mut c_void);
+
+unsafe impl Send for UserData {}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(error: &anyhow::Error) {
+    let message = CString::new(format!("{error:#}").replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+/// Runs a future to completion on a runtime of its own, panics included
+/// in the errors since they must not unwind into C
+fn block_on<T>(future: impl Future<Output = Result<T>>) -> Result<T> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    match panic::catch_unwind(AssertUnwindSafe(|| runtime.block_on(future))) {
+        Ok(result) => result,
+        Err(_) => Err(anyhow!("[Ffi] Panicked")),
+    }
+}
+
+/// A C string the caller frees with onvif_string_free(), NULL and the
+/// last error set on failure
+fn to_c_string(result: Result<String>) -> *mut c_char {
+    match result.and_then(|s| Ok(CString::new(s)?)) {
+        Ok(s) => s.into_raw(),
+        Err(e) => {
+            set_error(&e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// 0 on success, -1 with the last error set on failure
+fn to_status(result: Result<()>) -> i32 {
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            set_error(&e);
+            -1
+        }
+    }
+}
+
+/// # Safety
+///
+/// `s` must be NULL or a valid NUL terminated string
+unsafe fn optional_str<'a>(s: *const c_char) -> Result<Option<&'a str>> {
+    match s.is_null() {
+        true => Ok(None),
+        false => Ok(Some(CStr::from_ptr(s).to_str()?)),
+    }
+}
+
+/// The message of the last error of the calling thread, NULL when none.
+/// The string belongs to the library and stays valid until the next call
+/// failing on the same thread.
+#[no_mangle]
+pub extern "C" fn onvif_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+/// Frees a string returned by this library
+///
+/// # Safety
+///
+/// `s` must be NULL or a string returned by this library, not freed yet
+#[no_mangle]
+pub unsafe extern "C" fn onvif_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Discovers devices on the local network and returns their device
+/// service URLs, one per line
+#[no_mangle]
+pub extern "C" fn onvif_discover() -> *mut c_char {
+    to_c_string(block_on(async {
+        let devices = client::discover().await?;
+        let urls: Vec<String> = devices.iter().map(|d| d.url_onvif.to_string()).collect();
+        Ok(urls.join("\n"))
+    }))
+}
+
+/// Builds the camera at a device service URL, NULL and the last error set
+/// on failure
+fn new_camera(url: Option<&str>, credentials: Option<Credentials>) -> *mut OnvifCamera {
+    let camera = url
+        .ok_or_else(|| anyhow!("[Ffi][new_camera] No url"))
+        .and_then(|url| {
+            let mut camera = Camera::new(Device {
+                credentials,
+                ..Device::new(url.parse()?)
+            });
+
+            block_on(camera.build_all())?;
+            Ok(camera)
+        });
+
+    match camera {
+        Ok(camera) => Box::into_raw(Box::new(OnvifCamera { camera })),
+        Err(e) => {
+            set_error(&e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Builds the camera at a device service URL, e.g.
+/// "http://192.168.1.64/onvif/device_service". NULL on failure.
+///
+/// # Safety
+///
+/// `url` must be a valid NUL terminated string
+#[no_mangle]
+pub unsafe extern "C" fn onvif_camera_new(url: *const c_char) -> *mut OnvifCamera {
+    match optional_str(url) {
+        Ok(url) => new_camera(url, None),
+        Err(e) => {
+            set_error(&e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Builds the camera at a device service URL with a username and password,
+/// no credentials when `username` is NULL. NULL on failure.
+///
+/// # Safety
+///
+/// `url` must be a valid NUL terminated string, `username` and `password`
+/// NULL or valid strings
+#[no_mangle]
+pub unsafe extern "C" fn onvif_camera_new_with_credentials(
+    url: *const c_char,
+    username: *const c_char,
+    password: *const c_char,
+) -> *mut OnvifCamera {
+    let args = (|| -> Result<_> {
+        let password = optional_str(password)?.unwrap_or_default();
+        let credentials =
+            optional_str(username)?.map(|username| Credentials::new(username, password));
+        Ok((optional_str(url)?, credentials))
+    })();
+
+    match args {
+        Ok((url, credentials)) => new_camera(url, credentials),
+        Err(e) => {
+            set_error(&e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// # Safety
+///
+/// `camera` must be NULL or returned by onvif_camera_new(), not freed yet
+#[no_mangle]
+pub unsafe extern "C" fn onvif_camera_free(camera: *mut OnvifCamera) {
+    if !camera.is_null() {
+        drop(Box::from_raw(camera));
+    }
+}
+
+/// # Safety
+///
+/// `camera` must be a live camera, `profile` NULL or a valid string
+unsafe fn camera_and_profile<'a>(
+    camera: *const OnvifCamera,
+    profile: *const c_char,
+) -> Result<(&'a Camera, String)> {
+    let camera = &camera
+        .as_ref()
+        .ok_or_else(|| anyhow!("[Ffi] No camera"))?
+        .camera;
+
+    let profile = match optional_str(profile)? {
+        Some(profile) => profile.to_string(),
+        None => ptz::profile_token(camera)?,
+    };
+
+    Ok((camera, profile))
+}
+
+/// The RTSP URI of a media profile, the first profile when `profile` is
+/// NULL
+///
+/// # Safety
+///
+/// `camera` must be a live camera, `profile` NULL or a valid string
+#[no_mangle]
+pub unsafe extern "C" fn onvif_camera_stream_uri(
+    camera: *const OnvifCamera,
+    profile: *const c_char,
+) -> *mut c_char {
+    to_c_string(
+        camera_and_profile(camera, profile).and_then(|(camera, profile)| {
+            block_on(async {
+                media::get_stream_uri(
+                    media::media_url(camera)?,
+                    &profile,
+                    StreamTransport::RtspOverTcp,
+                )
+                .await?
+                .uri
+                .ok_or_else(|| anyhow!("[Ffi][onvif_camera_stream_uri] No uri"))
+            })
+        }),
+    )
+}
+
+/// Moves the camera at the given speeds, -1.0 to 1.0, for `seconds` and
+/// stops it. Blocks while the camera moves.
+///
+/// # Safety
+///
+/// `camera` must be a live camera, `profile` NULL or a valid string
+#[no_mangle]
+pub unsafe extern "C" fn onvif_camera_ptz_move(
+    camera: *const OnvifCamera,
+    profile: *const c_char,
+    pan: f32,
+    tilt: f32,
+    zoom: f32,
+    seconds: f64,
+) -> i32 {
+    // Checked before moving, a move must not be left without its stop
+    let wait = match seconds.is_finite() {
+        true => Duration::try_from_secs_f64(seconds.max(0.0)).ok(),
+        false => None,
+    };
+    let Some(wait) = wait else {
+        return to_status(Err(anyhow!(
+            "[Ffi][onvif_camera_ptz_move] Invalid seconds {seconds}"
+        )));
+    };
+
+    to_status(
+        camera_and_profile(camera, profile).and_then(|(camera, profile)| {
+            block_on(async {
+                let ptz_url = ptz::ptz_url(camera)?;
+
+                // Once asked to move, the camera is asked to stop whatever
+                // the move answered
+                let moved = ptz::continuous_move(ptz_url.clone(), &profile, pan, tilt, zoom).await;
+                if moved.is_ok() {
+                    tokio::time::sleep(wait).await;
+                }
+                let stopped = ptz::stop(ptz_url, &profile).await;
+
+                moved.and(stopped)
+            })
+        }),
+    )
+}
+
+/// Subscribes to the events of the camera. `callback` is called from a
+/// thread of the library for every event until onvif_events_free().
+/// NULL on failure.
+///
+/// # Safety
+///
+/// `camera` must be a live camera. `user_data` is handed to `callback`
+/// untouched and must stay valid until onvif_events_free() returns.
+#[no_mangle]
+pub unsafe extern "C" fn onvif_events_subscribe(
+    camera: *const OnvifCamera,
+    callback: OnvifEventCallback,
+    user_data: *mut c_void,
+) -> *mut OnvifEvents {
+    let subscription = camera
+        .as_ref()
+        .ok_or_else(|| anyhow!("[Ffi][onvif_events_subscribe] No camera"))
+        .and_then(|c| {
+            block_on(events::create_pull_point_subscription(events::event_url(
+                &c.camera,
+            )?))
+        });
+
+    let mut subscription = match subscription {
+        Ok(subscription) => subscription,
+        Err(e) => {
+            set_error(&e);
+            return ptr::null_mut();
+        }
+    };
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let stopped = stop.clone();
+    let user_data = UserData(user_data);
+
+    let thread = std::thread::spawn(move || {
+        let user_data = user_data;
+
+        let pulled = block_on(async {
+            while !stopped.load(Ordering::SeqCst) {
+                if subscription.expires_within(Duration::from_secs(20)) {
+                    subscription.renew(Duration::from_secs(60)).await?;
+                }
+
+                for event in subscription.pull(EVENT_PULL_WAIT, 100).await? {
+                    if stopped.load(Ordering::SeqCst) {
+                        break;
+                    }
+
+                    let topic = CString::new(event.topic.clone()).unwrap_or_default();
+                    let json = CString::new(event_json(&event)).unwrap_or_default();
+                    callback(topic.as_ptr(), json.as_ptr(), user_data.0);
+                }
+            }
+
+            Ok(())
+        });
+
+        // Also after a failure, the device may still hold the subscription
+        let _ = block_on(subscription.unsubscribe());
+
+        // Not reported when onvif_events_free() ended the subscription
+        if let (Err(e), false) = (pulled, stopped.load(Ordering::SeqCst)) {
+            let json = serde_json::json!({ "error": format!("{e:#}") }).to_string();
+            let json = CString::new(json).unwrap_or_default();
+            callback(ptr::null(), json.as_ptr(), user_data.0);
+        }
+    });
+
+    Box::into_raw(Box::new(OnvifEvents {
+        stop,
+        thread: Some(thread),
+    }))
+}
+
+/// Ends the subscription, waiting for the pull in progress. No callback
+/// is made once this returns.
+///
+/// # Safety
+///
+/// `events` must be NULL or returned by onvif_events_subscribe(), not
+/// freed yet, and must not be freed from within the callback
+#[no_mangle]
+pub unsafe extern "C" fn onvif_events_free(events: *mut OnvifEvents) {
+    if events.is_null() {
+        return;
+    }
+
+    let mut events = Box::from_raw(events);
+    events.stop.store(true, Ordering::SeqCst);
+
+    if let Some(thread) = events.thread.take() {
+        let _ = thread.join();
+    }
+}
+
+fn event_json(event: &EventMessage) -> String {
+    let items = |items: &[(String, String)]| -> serde_json::Map<String, serde_json::Value> {
+        items
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone().into()))
+            .collect()
+    };
+
+    serde_json::json!({
+        "topic": event.topic,
+        "utc_time": event.utc_time.map(|t| t.to_rfc3339()),
+        "source": items(&event.source),
+        "data": items(&event.data),
+    })
+    .to_string()
+}
@@ -0,0 +1,150 @@
+use crate::device::camera::Camera;
+
+use anyhow::{anyhow, Result};
+use ffmpeg::format::{context::Input, Pixel};
+use ffmpeg::software::scaling::{self, Flags};
+use ffmpeg::util::frame::video::Video;
+use ffmpeg_next as ffmpeg;
+
+/// A decoded picture as packed RGB24, `width * height * 3` bytes
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct DecodedFrame {
+    pub width:    u32,
+    pub height:   u32,
+    pub pts:      Option<i64>,
+    pub data:     Vec<u8>,
+}
+
+/// Reads and decodes the video of an RTSP stream with FFmpeg
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::device::camera::Camera;
+/// # fn run(camera: Camera) -> anyhow::Result<()> {
+/// let mut reader = camera.open_ffmpeg(true)?;
+///
+/// while let Some(frame) = reader.next_frame()? {
+///     println!("{}x{} frame", frame.width, frame.height);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct FrameReader {
+    input: Input,
+    stream_index: usize,
+    decoder: ffmpeg::decoder::Video,
+    scaler: Option<scaling::Context>,
+}
+
+impl FrameReader {
+    /// Opens `uri`, asking for RTP over the RTSP connection when
+    /// `force_tcp` is set. Credentials can be part of the URI.
+    pub fn open(uri: &str, force_tcp: bool) -> Result<Self> {
+        ffmpeg::init()?;
+
+        let mut options = ffmpeg::Dictionary::new();
+        if force_tcp {
+            options.set("rtsp_transport", "tcp");
+        }
+
+        let input = ffmpeg::format::input_with_dictionary(uri, options)?;
+        let stream = input
+            .streams()
+            .best(ffmpeg::media::Type::Video)
+            .ok_or_else(|| anyhow!("[Ffmpeg][open] No video stream in {uri}"))?;
+
+        let stream_index = stream.index();
+        let decoder = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?
+            .decoder()
+            .video()?;
+
+        Ok(FrameReader {
+            input,
+            stream_index,
+            decoder,
+            scaler: None,
+        })
+    }
+
+    /// Waits for the next decoded frame, None at the end of the stream
+    pub fn next_frame(&mut self) -> Result<Option<DecodedFrame>> {
+        let mut decoded = Video::empty();
+
+        loop {
+            if self.decoder.receive_frame(&mut decoded).is_ok() {
+                return Ok(Some(self.convert(&decoded)?));
+            }
+
+            let mut packet = ffmpeg::Packet::empty();
+
+            match packet.read(&mut self.input) {
+                Ok(()) if packet.stream() == self.stream_index => {
+                    self.decoder.send_packet(&packet)?
+                }
+                Ok(()) => continue,
+                Err(ffmpeg::Error::Eof) => return Ok(None),
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    fn convert(&mut self, frame: &Video) -> Result<DecodedFrame> {
+        let (width, height) = (frame.width(), frame.height());
+
+        // The scaler is rebuilt when the camera changes resolution mid stream
+        let stale = self
+            .scaler
+            .as_ref()
+            .is_none_or(|s| s.input().width != width || s.input().height != height);
+
+        if stale {
+            self.scaler = Some(scaling::Context::get(
+                frame.format(),
+                width,
+                height,
+                Pixel::RGB24,
+                width,
+                height,
+                Flags::BILINEAR,
+            )?);
+        }
+
+        let mut rgb = Video::empty();
+        if let Some(scaler) = self.scaler.as_mut() {
+            scaler.run(frame, &mut rgb)?;
+        }
+
+        // Rows may be padded, copy them into a packed buffer
+        let row = width as usize * 3;
+        let stride = rgb.stride(0);
+        let data = rgb
+            .data(0)
+            .chunks(stride)
+            .take(height as usize)
+            .flat_map(|line| &line[..row])
+            .copied()
+            .collect();
+
+        Ok(DecodedFrame {
+            width,
+            height,
+            pts: frame.pts(),
+            data,
+        })
+    }
+}
+
+impl Camera {
+    /// Opens the stream URI found by build_all() with FFmpeg
+    pub fn open_ffmpeg(&self, force_tcp: bool) -> Result<FrameReader> {
+        let uri = self
+            .stream
+            .uri
+            .as_ref()
+            .ok_or_else(|| anyhow!("[Ffmpeg][open_ffmpeg] Camera has no stream uri"))?;
+
+        FrameReader::open(uri, force_tcp)
+    }
+}
@@ -0,0 +1,124 @@
+use crate::device::camera::Camera;
+
+use anyhow::{anyhow, Result};
+
+/// Settings of the generated GStreamer pipeline
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct PipelineOptions {
+    /// Jitter buffer size of rtspsrc in milliseconds
+    pub latency_ms:   u32,
+    /// Ask for RTP over the RTSP connection instead of UDP
+    pub force_tcp:    bool,
+    pub username:     Option<String>,
+    pub password:     Option<String>,
+    /// Decode and convert the video before the sink, otherwise the
+    /// parsed elementary stream is handed to the sink as is
+    pub decode:       bool,
+    pub sink:         String,
+}
+
+impl Default for PipelineOptions {
+    fn default() -> Self {
+        PipelineOptions {
+            latency_ms: 200,
+            force_tcp: true,
+            username: None,
+            password: None,
+            decode: true,
+            sink: "autovideosink".to_string(),
+        }
+    }
+}
+
+impl PipelineOptions {
+    pub fn with_credentials(mut self, username: &str, password: &str) -> Self {
+        self.username = Some(username.to_string());
+        self.password = Some(password.to_string());
+        self
+    }
+}
+
+/// Depayloader, parser and decoder elements for a profile video encoding
+fn codec_elements(encoding: &str) -> Option<[&'static str; 3]> {
+    match encoding.to_ascii_uppercase().as_str() {
+        "H264" => Some(["rtph264depay", "h264parse", "avdec_h264"]),
+        "H265" | "HEVC" => Some(["rtph265depay", "h265parse", "avdec_h265"]),
+        "JPEG" | "MJPEG" => Some(["rtpjpegdepay", "jpegparse", "jpegdec"]),
+        "MPEG4" | "MP4V-ES" => Some(["rtpmp4vdepay", "mpeg4videoparse", "avdec_mpeg4"]),
+        _ => None,
+    }
+}
+
+/// Quotes a property value for gst-launch
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Builds a gst-launch pipeline playing the stream of a built camera, with
+/// the depayloader and parser chosen from the profile video encoding.
+/// When the encoding is unknown, decodebin picks the elements itself, which
+/// only works when decoding.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::device::camera::Camera;
+/// # use onvif_cam_rs::gstreamer::{self, PipelineOptions};
+/// # fn run(camera: Camera) -> anyhow::Result<()> {
+/// let options = PipelineOptions::default().with_credentials("admin", "secret");
+/// let pipeline = gstreamer::launch_string(&camera, &options)?;
+///
+/// // rtspsrc location="rtsp://..." latency=200 protocols=tcp ... ! rtph264depay ! h264parse ! ...
+/// println!("gst-launch-1.0 {pipeline}");
+/// # Ok(())
+/// # }
+/// ```
+pub fn launch_string(camera: &Camera, options: &PipelineOptions) -> Result<String> {
+    let uri = camera
+        .stream
+        .uri
+        .as_ref()
+        .ok_or_else(|| anyhow!("[Gstreamer][launch_string] Camera has no stream uri"))?;
+
+    let mut source = format!(
+        "rtspsrc location={} latency={}",
+        quote(uri),
+        options.latency_ms
+    );
+
+    if options.force_tcp {
+        source.push_str(" protocols=tcp");
+    }
+
+    if let Some(username) = &options.username {
+        source.push_str(&format!(" user-id={}", quote(username)));
+    }
+
+    if let Some(password) = &options.password {
+        source.push_str(&format!(" user-pw={}", quote(password)));
+    }
+
+    let encoding = camera
+        .profile()
+        .and_then(|profile| profile.video_codec())
+        .unwrap_or_default();
+    let mut elements = vec![source];
+
+    match (codec_elements(encoding), options.decode) {
+        (Some([depay, parse, decoder]), true) => {
+            elements.extend([depay, parse, decoder, "videoconvert"].map(String::from))
+        }
+        (Some([depay, parse, _]), false) => elements.extend([depay, parse].map(String::from)),
+        (None, true) => elements.extend(["decodebin", "videoconvert"].map(String::from)),
+        (None, false) => {
+            return Err(anyhow!(
+                "[Gstreamer][launch_string] Unknown video encoding '{encoding}', enable decode to let decodebin pick elements"
+            ))
+        }
+    }
+
+    elements.push(options.sink.clone());
+
+    Ok(elements.join(" ! "))
+}
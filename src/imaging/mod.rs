@@ -0,0 +1,230 @@
+use crate::client::{self, Messages};
+use crate::device::camera::Camera;
+use crate::utils::xml::XmlNode;
+
+use anyhow::{anyhow, Result};
+
+pub use crate::device::{
+    FocusMove, FocusMoveOptions, ImagingOptions, ImagingSettings, IrCutFilterMode,
+};
+
+/// Imaging service URL of a built camera, from GetServices or
+/// GetCapabilities
+pub fn imaging_url(camera: &Camera) -> Result<url::Url> {
+    match (&camera.services.imaging, &camera.capabilities.url_imaging) {
+        (Some(url), _) => Ok(camera.base.authorize(&url.parse()?)),
+        (None, Some(url)) => Ok(camera.base.authorize(url)),
+        _ => Err(anyhow!("[Imaging] Device has no imaging service")),
+    }
+}
+
+/// Token of the video source of the first media profile of a built
+/// camera, which imaging requests are about
+pub fn video_source_token(camera: &Camera) -> Result<String> {
+    camera
+        .profile()
+        .and_then(|profile| profile.video_source_token.clone())
+        .ok_or_else(|| {
+            anyhow!("[Imaging] Camera has no video source token, call build_all() first")
+        })
+}
+
+/// Moves the focus lens of a video source, for varifocal and motorized
+/// lenses. Continuous moves go on until stop_focus().
+///
+/// # Arguments
+///
+/// * `onvif_url` - The imaging service URL (see `imaging_url()`)
+/// * `video_source_token` - Token of the video source
+/// * `focus` - The move, see get_move_options() for the moves the lens
+///   supports and their ranges
+///
+/// # Examples
+///
+/// Focuses towards far for half a second:
+///
+/// ```no_run
+/// # use onvif_cam_rs::device::camera::Camera;
+/// # use onvif_cam_rs::imaging::{self, FocusMove};
+/// # use std::time::Duration;
+/// # async fn run(camera: Camera) -> anyhow::Result<()> {
+/// let url = imaging::imaging_url(&camera)?;
+/// let source = imaging::video_source_token(&camera)?;
+///
+/// imaging::move_focus(url.clone(), &source, FocusMove::Continuous { speed: 0.5 }).await?;
+/// tokio::time::sleep(Duration::from_millis(500)).await;
+/// imaging::stop_focus(url, &source).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn move_focus(
+    onvif_url: url::Url,
+    video_source_token: &str,
+    focus: FocusMove,
+) -> Result<()> {
+    let msg = Messages::ImagingMove {
+        video_source_token: video_source_token.to_string(),
+        focus,
+    };
+
+    client::send(onvif_url, msg).await?.error_for_status()?;
+    Ok(())
+}
+
+/// Stops any focus move of a video source
+///
+/// # Arguments
+///
+/// * `onvif_url` - The imaging service URL (see `imaging_url()`)
+/// * `video_source_token` - Token of the video source
+pub async fn stop_focus(onvif_url: url::Url, video_source_token: &str) -> Result<()> {
+    let msg = Messages::ImagingStop {
+        video_source_token: video_source_token.to_string(),
+    };
+
+    client::send(onvif_url, msg).await?.error_for_status()?;
+    Ok(())
+}
+
+/// Reads the focus moves a video source supports and their ranges
+///
+/// # Arguments
+///
+/// * `onvif_url` - The imaging service URL (see `imaging_url()`)
+/// * `video_source_token` - Token of the video source
+pub async fn get_move_options(
+    onvif_url: url::Url,
+    video_source_token: &str,
+) -> Result<FocusMoveOptions> {
+    let msg = Messages::GetImagingMoveOptions {
+        video_source_token: video_source_token.to_string(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    root.find("MoveOptions")
+        .map(FocusMoveOptions::from_xml)
+        .ok_or_else(|| anyhow!("[Imaging][get_move_options] No MoveOptions in response"))
+}
+
+/// Reads the ranges of the imaging settings of a video source, e.g. for
+/// sliders with the bounds of the device instead of a fixed 0 to 100
+///
+/// # Arguments
+///
+/// * `onvif_url` - The imaging service URL (see `imaging_url()`)
+/// * `video_source_token` - Token of the video source
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::device::camera::Camera;
+/// # use onvif_cam_rs::imaging;
+/// # async fn run(camera: Camera) -> anyhow::Result<()> {
+/// let url = imaging::imaging_url(&camera)?;
+/// let options = imaging::get_options(url, &imaging::video_source_token(&camera)?).await?;
+///
+/// if let Some(brightness) = options.brightness {
+///     println!("Brightness from {} to {}", brightness.start(), brightness.end());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn get_options(onvif_url: url::Url, video_source_token: &str) -> Result<ImagingOptions> {
+    let msg = Messages::GetImagingOptions {
+        video_source_token: video_source_token.to_string(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    root.find("ImagingOptions")
+        .map(ImagingOptions::from_xml)
+        .ok_or_else(|| anyhow!("[Imaging][get_options] No ImagingOptions in response"))
+}
+
+/// Reads the imaging settings of a video source
+///
+/// # Arguments
+///
+/// * `onvif_url` - The imaging service URL (see `imaging_url()`)
+/// * `video_source_token` - Token of the video source
+pub async fn get_imaging_settings(
+    onvif_url: url::Url,
+    video_source_token: &str,
+) -> Result<ImagingSettings> {
+    let msg = Messages::GetImagingSettings {
+        video_source_token: video_source_token.to_string(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    root.find("ImagingSettings")
+        .map(ImagingSettings::from_xml)
+        .ok_or_else(|| anyhow!("[Imaging][get_imaging_settings] No ImagingSettings in response"))
+}
+
+/// Changes the imaging settings of a video source. Settings that are None
+/// keep their value, see get_options() for the ranges the device accepts.
+///
+/// # Arguments
+///
+/// * `onvif_url` - The imaging service URL (see `imaging_url()`)
+/// * `video_source_token` - Token of the video source
+/// * `settings` - The settings to change
+pub async fn set_imaging_settings(
+    onvif_url: url::Url,
+    video_source_token: &str,
+    settings: &ImagingSettings,
+) -> Result<()> {
+    let msg = Messages::SetImagingSettings {
+        video_source_token: video_source_token.to_string(),
+        settings: settings.clone(),
+    };
+
+    client::send(onvif_url, msg).await?.error_for_status()?;
+    Ok(())
+}
+
+/// Forces day or night mode, or gives the switching back to the camera.
+/// Cameras behind glass often strobe in AUTO, as their IR light reflects
+/// back into the lens and looks like daylight.
+///
+/// # Arguments
+///
+/// * `onvif_url` - The imaging service URL (see `imaging_url()`)
+/// * `video_source_token` - Token of the video source
+/// * `mode` - The IR cut filter mode, see `ImagingOptions::ir_cut_filter_modes`
+///   for the modes the camera supports
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::device::camera::Camera;
+/// # use onvif_cam_rs::imaging::{self, IrCutFilterMode};
+/// # async fn run(camera: Camera) -> anyhow::Result<()> {
+/// let url = imaging::imaging_url(&camera)?;
+/// let source = imaging::video_source_token(&camera)?;
+///
+/// // Stay in colour day mode
+/// imaging::set_ir_cut_filter(url, &source, IrCutFilterMode::On).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn set_ir_cut_filter(
+    onvif_url: url::Url,
+    video_source_token: &str,
+    mode: IrCutFilterMode,
+) -> Result<()> {
+    let settings = ImagingSettings {
+        ir_cut_filter: Some(mode),
+        ..Default::default()
+    };
+
+    set_imaging_settings(onvif_url, video_source_token, &settings).await
+}
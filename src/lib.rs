@@ -40,7 +40,55 @@ async fn main() -> Result<()> {
 
 */
 
+// Without the HTTP client only the SOAP messages and parsers are built,
+// the helpers they share with the requests are then partly unused
+#![cfg_attr(not(feature = "http"), allow(dead_code, unused_imports))]
+
+#[cfg(feature = "http")]
+pub mod access_control;
+#[cfg(feature = "http")]
+pub mod analyze;
+pub mod auth;
+#[cfg(feature = "http")]
 pub mod builder;
+#[cfg(all(feature = "discovery", feature = "http", feature = "persistence"))]
+pub mod cache;
 pub mod client;
 pub mod device;
+#[cfg(feature = "http")]
+pub mod diagnose;
+#[cfg(feature = "discovery")]
+pub mod discovery;
+#[cfg(feature = "http")]
+pub mod door_control;
+#[cfg(feature = "http")]
+pub mod events;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "ffmpeg")]
+pub mod ffmpeg;
+pub mod gstreamer;
+#[cfg(feature = "http")]
+pub mod imaging;
+#[cfg(all(feature = "discovery", feature = "http"))]
+pub mod manager;
+#[cfg(feature = "http")]
+pub mod media;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod playlist;
+pub mod provisioning;
+#[cfg(feature = "http")]
+pub mod ptz;
+pub mod recording;
+pub mod replay;
+#[cfg(feature = "persistence")]
+pub mod restream;
+pub mod rtsp;
+pub mod search;
+pub mod soap;
+#[cfg(feature = "http")]
+pub mod system;
+#[cfg(feature = "metrics")]
+pub mod telemetry;
 pub(crate) mod utils;
@@ -0,0 +1,199 @@
+use super::{CameraManager, ManagerConfig, ProbeMethod};
+use crate::auth::Credentials;
+use crate::device::camera::Camera;
+use crate::device::{Device, StreamTransport};
+
+use anyhow::{anyhow, Context, Result};
+use log::{info, warn};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// A fleet described in a TOML or YAML file, e.g.
+///
+/// ```toml
+/// discover = true
+///
+/// [manager]
+/// check_interval = 15
+/// probe_method = "tcp_connect"
+///
+/// [[cameras]]
+/// url = "http://192.168.1.20/onvif/device_service"
+/// labels = ["garage"]
+/// credentials = "GARAGE_CAM_CREDENTIALS"
+/// transport = "rtsp_over_tcp"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[rustfmt::skip]
+pub struct FleetConfig {
+    #[serde(default)]
+    pub discover:   bool,
+    #[serde(default)]
+    pub manager:    ManagerSection,
+    #[serde(default)]
+    pub cameras:    Vec<CameraEntry>,
+}
+
+/// Overrides of ManagerConfig defaults, durations in seconds
+#[derive(Debug, Clone, Default, Deserialize)]
+#[rustfmt::skip]
+pub struct ManagerSection {
+    pub check_interval:        Option<f64>,
+    pub probe_timeout:         Option<f64>,
+    pub probe_method:          Option<ProbeMethod>,
+    pub failure_threshold:     Option<u32>,
+    pub rediscover_interval:   Option<f64>,
+    pub missed_discoveries:    Option<u32>,
+    pub heal_on_offline:       Option<bool>,
+    pub event_pull_timeout:    Option<f64>,
+    pub event_retry:           Option<f64>,
+}
+
+/// A camera at a known address. `credentials` names the environment
+/// variable holding `username:password`, so the secret itself stays out
+/// of the file.
+#[derive(Debug, Clone, Deserialize)]
+#[rustfmt::skip]
+pub struct CameraEntry {
+    pub url:           url::Url,
+    #[serde(default)]
+    pub labels:        Vec<String>,
+    pub credentials:   Option<String>,
+    pub transport:     Option<StreamTransport>,
+}
+
+impl ManagerSection {
+    pub fn to_config(&self) -> Result<ManagerConfig> {
+        let secs = |value: Option<f64>, default: Duration| -> Result<Duration> {
+            match value {
+                Some(secs) => Duration::try_from_secs_f64(secs)
+                    .map_err(|_| anyhow!("[Manager][config] Invalid duration {secs}")),
+                None => Ok(default),
+            }
+        };
+
+        let default = ManagerConfig::default();
+
+        Ok(ManagerConfig {
+            check_interval: secs(self.check_interval, default.check_interval)?,
+            probe_timeout: secs(self.probe_timeout, default.probe_timeout)?,
+            probe_method: self.probe_method.unwrap_or(default.probe_method),
+            failure_threshold: self.failure_threshold.unwrap_or(default.failure_threshold),
+            rediscover_interval: match self.rediscover_interval {
+                Some(interval) => Some(secs(Some(interval), Duration::ZERO)?),
+                None => default.rediscover_interval,
+            },
+            missed_discoveries: self
+                .missed_discoveries
+                .unwrap_or(default.missed_discoveries),
+            heal_on_offline: self.heal_on_offline.unwrap_or(default.heal_on_offline),
+            event_pull_timeout: secs(self.event_pull_timeout, default.event_pull_timeout)?,
+            event_retry: secs(self.event_retry, default.event_retry)?,
+        })
+    }
+}
+
+impl CameraEntry {
+    /// The credentials in the environment variable `credentials` names,
+    /// None when the camera needs none
+    pub fn resolve_credentials(&self) -> Result<Option<Credentials>> {
+        let Some(name) = &self.credentials else {
+            return Ok(None);
+        };
+
+        let value = std::env::var(name).map_err(|_| {
+            anyhow!(
+                "[Manager][config] Environment variable {name} for {} is not set",
+                self.url
+            )
+        })?;
+        let (username, password) = value.split_once(':').ok_or_else(|| {
+            anyhow!("[Manager][config] Environment variable {name} is not username:password")
+        })?;
+
+        Ok(Some(Credentials::new(username, password)))
+    }
+}
+
+impl FleetConfig {
+    pub fn from_toml(contents: &str) -> Result<Self> {
+        toml::from_str(contents).map_err(|e| anyhow!("[Manager][config] Malformed TOML: {e}"))
+    }
+
+    pub fn from_yaml(contents: &str) -> Result<Self> {
+        serde_yaml::from_str(contents).map_err(|e| anyhow!("[Manager][config] Malformed YAML: {e}"))
+    }
+
+    /// Reads a config file, choosing the format from the extension
+    /// (`.toml`, `.yaml` or `.yml`)
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("[Manager][config] Unable to read {}", path.display()))?;
+
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => Self::from_toml(&contents),
+            Some("yaml") | Some("yml") => Self::from_yaml(&contents),
+            _ => Err(anyhow!(
+                "[Manager][config] Unknown config format for {}",
+                path.display()
+            )),
+        }
+    }
+}
+
+impl CameraManager {
+    /// Creates a manager from a fleet config. Cameras listed in the config
+    /// are pinned, then discovery results are merged in when `discover` is
+    /// set. A failed discovery is logged rather than returned, so the
+    /// static part of the fleet is always available, while credentials
+    /// that cannot be resolved fail it.
+    pub async fn from_config(config: &FleetConfig) -> Result<Self> {
+        let manager = CameraManager::new(config.manager.to_config()?);
+
+        for entry in &config.cameras {
+            let mut camera = Camera::new(Device::new(entry.url.clone()));
+
+            if let Some(credentials) = entry.resolve_credentials()? {
+                camera.set_credentials(credentials);
+            }
+
+            for label in &entry.labels {
+                camera.add_label(label);
+            }
+
+            if let Some(transport) = entry.transport {
+                camera.set_stream_transport(transport);
+            }
+
+            let id = manager.add(camera).await;
+            let mut registry = manager.inner.registry.write().await;
+
+            if let Some(managed) = registry.cameras.get_mut(&id) {
+                managed.pinned = true;
+                managed.credentials_ref = entry.credentials.clone();
+                managed.transport = entry.transport;
+            }
+        }
+
+        info!(
+            "[Manager] Loaded {} cameras from config",
+            config.cameras.len()
+        );
+
+        if config.discover {
+            if let Err(e) = manager.rediscover().await {
+                warn!("[Manager] Discovery failed: {e}");
+            }
+        }
+
+        Ok(manager)
+    }
+
+    /// Loads a fleet config file, see FleetConfig::load()
+    pub async fn from_config_file(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_config(&FleetConfig::load(path)?).await
+    }
+}
@@ -0,0 +1,118 @@
+use super::{CameraId, CameraManager};
+
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Number of latencies kept per camera for the percentiles
+const LATENCY_SAMPLES: usize = 256;
+
+/// Window over which the event rate is computed
+const EVENT_WINDOW: Duration = Duration::from_secs(60);
+
+/// Counters of one managed camera at the time metrics() was called.
+/// Latency percentiles cover the last requests only, counters cover the
+/// whole time the camera has been managed.
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct CameraMetrics {
+    pub id:                  CameraId,
+    pub requests:            u64,
+    pub errors:              u64,
+    pub latency_p50:         Option<Duration>,
+    pub latency_p90:         Option<Duration>,
+    pub latency_p99:         Option<Duration>,
+    pub events:              u64,
+    pub events_per_minute:   f64,
+    pub last_contact:        Option<DateTime<Utc>>,
+}
+
+#[derive(Default)]
+pub(crate) struct Recorder {
+    requests: u64,
+    errors: u64,
+    latencies: VecDeque<Duration>,
+    events: u64,
+    recent_events: VecDeque<Instant>,
+    last_contact: Option<DateTime<Utc>>,
+}
+
+impl Recorder {
+    pub(crate) fn success(&mut self, latency: Duration) {
+        self.requests += 1;
+        self.last_contact = Some(Utc::now());
+
+        if self.latencies.len() == LATENCY_SAMPLES {
+            self.latencies.pop_front();
+        }
+        self.latencies.push_back(latency);
+    }
+
+    pub(crate) fn error(&mut self) {
+        self.requests += 1;
+        self.errors += 1;
+    }
+
+    pub(crate) fn events(&mut self, count: usize) {
+        let now = Instant::now();
+
+        self.events += count as u64;
+        self.last_contact = Some(Utc::now());
+        self.recent_events.extend(std::iter::repeat_n(now, count));
+        self.expire_events(now);
+    }
+
+    fn expire_events(&mut self, now: Instant) {
+        while let Some(first) = self.recent_events.front() {
+            match now.duration_since(*first) > EVENT_WINDOW {
+                true => self.recent_events.pop_front(),
+                false => break,
+            };
+        }
+    }
+
+    fn snapshot(&mut self, id: CameraId) -> CameraMetrics {
+        self.expire_events(Instant::now());
+
+        let mut sorted: Vec<Duration> = self.latencies.iter().copied().collect();
+        sorted.sort();
+
+        let percentile = |p: usize| match sorted.is_empty() {
+            true => None,
+            false => Some(sorted[(sorted.len() - 1) * p / 100]),
+        };
+
+        CameraMetrics {
+            id,
+            requests: self.requests,
+            errors: self.errors,
+            latency_p50: percentile(50),
+            latency_p90: percentile(90),
+            latency_p99: percentile(99),
+            events: self.events,
+            events_per_minute: self.recent_events.len() as f64 * 60.0 / EVENT_WINDOW.as_secs_f64(),
+            last_contact: self.last_contact,
+        }
+    }
+}
+
+impl CameraManager {
+    /// Counters of every managed camera, for dashboards over the fleet.
+    /// Health probes, builds and event subscriptions are all counted.
+    pub fn metrics(&self) -> Vec<CameraMetrics> {
+        self.inner
+            .metrics
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .map(|(id, recorder)| recorder.snapshot(*id))
+            .collect()
+    }
+
+    pub(crate) fn record<F>(&self, id: CameraId, update: F)
+    where
+        F: FnOnce(&mut Recorder),
+    {
+        update(self.inner.metrics.lock().unwrap().entry(id).or_default());
+    }
+}
@@ -0,0 +1,1016 @@
+#[cfg(feature = "persistence")]
+pub mod config;
+pub mod metrics;
+
+use crate::auth::Credentials;
+use crate::builder::camera::CameraBuilder;
+use crate::client;
+use crate::device::camera::{Camera, CameraQuery};
+use crate::device::{Device, StreamTransport, StreamUri};
+use crate::events::{self, EventMessage};
+use crate::{playlist, ptz, system};
+
+use futures::future::join_all;
+use log::{info, warn};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, RwLock, Semaphore};
+use tokio::task::JoinHandle;
+
+/// Identifier assigned to a camera when it is added to the manager
+pub type CameraId = u64;
+
+/// Liveness of a managed camera as seen by the last health check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Health {
+    Unknown,
+    Online,
+    Offline,
+}
+
+/// Changes to the fleet announced to subscribers
+#[derive(Debug, Clone)]
+pub enum ManagerEvent {
+    Added(CameraId),
+    Removed(CameraId),
+    AddressChanged {
+        id: CameraId,
+        old: url::Url,
+        new: url::Url,
+    },
+    HealthChanged {
+        id: CameraId,
+        old: Health,
+        new: Health,
+    },
+}
+
+/// Outcome of building one camera with CameraManager::build_all()
+#[derive(Debug)]
+#[rustfmt::skip]
+pub struct BuildReport {
+    pub id:        CameraId,
+    pub result:    anyhow::Result<()>,
+    pub elapsed:   Duration,
+}
+
+/// An event pulled from one of the managed cameras
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct FleetEvent {
+    pub id:      CameraId,
+    pub event:   EventMessage,
+}
+
+/// Maximum number of messages fetched by a single PullMessages request
+const EVENT_PULL_LIMIT: u32 = 32;
+
+/// Lifetime requested when renewing a pull point subscription
+const EVENT_TERMINATION: Duration = Duration::from_secs(60);
+
+/// How the manager decides whether a camera is alive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeMethod {
+    /// A GetSystemDateAndTime SOAP request, which needs no credentials
+    SystemDateAndTime,
+    /// A plain TCP connect to the ONVIF port
+    TcpConnect,
+    /// An RTSP OPTIONS request to the stream URI, checking the media server
+    RtspOptions,
+}
+
+impl ProbeMethod {
+    async fn probe(self, camera: &Camera, wait: Duration) -> anyhow::Result<Duration> {
+        // The public address of cameras behind NAT, no credentials needed
+        let url = camera.base.reachable(&camera.base.url_onvif);
+
+        match self {
+            ProbeMethod::SystemDateAndTime => client::ping(url, wait).await,
+            ProbeMethod::TcpConnect => client::tcp_ping(url, wait).await,
+            ProbeMethod::RtspOptions => {
+                let uri =
+                    camera.stream.uri.as_ref().ok_or_else(|| {
+                        anyhow::anyhow!("[Manager][probe] Camera has no stream uri")
+                    })?;
+
+                client::rtsp_options(uri.parse()?, wait).await
+            }
+        }
+    }
+}
+
+/// `check_interval` is the time between probes and `failure_threshold` the
+/// number of failed probes in a row before a camera is considered offline.
+/// `rediscover_interval` enables background discovery. Cameras missing
+/// from `missed_discoveries` rounds in a row are removed from the fleet.
+/// `heal_on_offline` runs discovery to find cameras that went offline
+/// at a new address.
+/// Event subscriptions long poll for `event_pull_timeout` and are
+/// recreated `event_retry` after failing.
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct ManagerConfig {
+    pub check_interval:        Duration,
+    pub probe_timeout:         Duration,
+    pub probe_method:          ProbeMethod,
+    pub failure_threshold:     u32,
+    pub rediscover_interval:   Option<Duration>,
+    pub missed_discoveries:    u32,
+    pub heal_on_offline:       bool,
+    pub event_pull_timeout:    Duration,
+    pub event_retry:           Duration,
+}
+
+impl Default for ManagerConfig {
+    fn default() -> Self {
+        ManagerConfig {
+            check_interval: Duration::from_secs(30),
+            probe_timeout: Duration::from_secs(2),
+            probe_method: ProbeMethod::SystemDateAndTime,
+            failure_threshold: 1,
+            rediscover_interval: None,
+            missed_discoveries: 3,
+            heal_on_offline: true,
+            event_pull_timeout: Duration::from_secs(10),
+            event_retry: Duration::from_secs(5),
+        }
+    }
+}
+
+/// A camera owned by the manager along with its health state.
+/// Pinned cameras come from a config file and are never removed by
+/// rediscovery.
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct ManagedCamera {
+    pub id:                CameraId,
+    pub camera:            Camera,
+    pub health:            Health,
+    pub last_seen:         Option<Instant>,
+    pub missed:            u32,
+    pub failures:          u32,
+    pub tags:              BTreeSet<String>,
+    pub pinned:            bool,
+    pub credentials_ref:   Option<String>,
+    pub transport:         Option<StreamTransport>,
+}
+
+struct Registry {
+    cameras: BTreeMap<CameraId, ManagedCamera>,
+    next_id: CameraId,
+}
+
+struct Inner {
+    config: ManagerConfig,
+    registry: RwLock<Registry>,
+    events: broadcast::Sender<ManagerEvent>,
+    tasks: Mutex<Vec<JoinHandle<()>>>,
+    fleet_events: broadcast::Sender<FleetEvent>,
+    events_started: AtomicBool,
+    event_tasks: Mutex<BTreeMap<CameraId, JoinHandle<()>>>,
+    metrics: Mutex<BTreeMap<CameraId, metrics::Recorder>>,
+}
+
+/// Owns a fleet of cameras, checks that they are alive and refreshes
+/// their stream URIs when they come back after an outage.
+///
+/// The manager is a cheap handle: clones share the same fleet, so it can
+/// be handed to as many tasks as needed.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::device::camera::Camera;
+/// # use onvif_cam_rs::manager::{CameraManager, ManagerConfig};
+/// # async fn run(cameras: Vec<Camera>) {
+/// let manager = CameraManager::new(ManagerConfig::default());
+///
+/// for camera in cameras {
+///     manager.add(camera).await;
+/// }
+///
+/// // Check the fleet every 30 seconds in the background
+/// manager.start();
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct CameraManager {
+    inner: Arc<Inner>,
+}
+
+impl CameraManager {
+    pub fn new(config: ManagerConfig) -> Self {
+        CameraManager {
+            inner: Arc::new(Inner {
+                config,
+                registry: RwLock::new(Registry {
+                    cameras: BTreeMap::new(),
+                    next_id: 0,
+                }),
+                events: broadcast::channel(64).0,
+                tasks: Mutex::new(Vec::new()),
+                fleet_events: broadcast::channel(256).0,
+                events_started: AtomicBool::new(false),
+                event_tasks: Mutex::new(BTreeMap::new()),
+                metrics: Mutex::new(BTreeMap::new()),
+            }),
+        }
+    }
+
+    pub fn config(&self) -> &ManagerConfig {
+        &self.inner.config
+    }
+
+    /// Receives fleet changes (cameras added, removed or readdressed)
+    pub fn subscribe(&self) -> broadcast::Receiver<ManagerEvent> {
+        self.inner.events.subscribe()
+    }
+
+    fn notify(&self, event: ManagerEvent) {
+        // No subscribers is not an error
+        let _ = self.inner.events.send(event);
+    }
+
+    /// Adds a camera to the fleet and returns its id
+    pub async fn add(&self, camera: Camera) -> CameraId {
+        let id = {
+            let mut registry = self.inner.registry.write().await;
+            let id = registry.next_id;
+            registry.next_id += 1;
+
+            registry.cameras.insert(
+                id,
+                ManagedCamera {
+                    id,
+                    camera,
+                    health: Health::Unknown,
+                    last_seen: None,
+                    missed: 0,
+                    failures: 0,
+                    tags: BTreeSet::new(),
+                    pinned: false,
+                    credentials_ref: None,
+                    transport: None,
+                },
+            );
+
+            id
+        };
+
+        if self.inner.events_started.load(Ordering::SeqCst) {
+            self.spawn_event_supervisor(id);
+        }
+
+        self.notify(ManagerEvent::Added(id));
+        id
+    }
+
+    pub async fn remove(&self, id: CameraId) -> Option<Camera> {
+        let removed = {
+            let mut registry = self.inner.registry.write().await;
+            registry.cameras.remove(&id).map(|m| m.camera)
+        };
+
+        if removed.is_some() {
+            self.stop_events_of(id);
+            self.inner.metrics.lock().unwrap().remove(&id);
+            self.notify(ManagerEvent::Removed(id));
+        }
+
+        removed
+    }
+
+    /// Runs discovery once and merges the result into the fleet
+    pub async fn rediscover(&self) -> anyhow::Result<()> {
+        let devices = client::discover().await?;
+        self.merge(devices).await;
+
+        Ok(())
+    }
+
+    /// Merges discovered devices into the fleet: unknown devices are added,
+    /// known devices answering on a new XAddr get their URL updated, and
+    /// devices missing from too many rounds in a row are removed.
+    /// Devices are matched by discovery endpoint reference when both sides
+    /// have one, otherwise by host, so a new port or path is an update.
+    pub async fn merge(&self, devices: Vec<Device>) {
+        let mut added = Vec::new();
+        let mut events = Vec::new();
+        let mut moved = Vec::new();
+
+        {
+            let mut registry = self.inner.registry.write().await;
+            let mut seen = Vec::new();
+
+            for device in devices {
+                let known = registry
+                    .cameras
+                    .values_mut()
+                    .find(|m| is_same_device(&m.camera.base, &device));
+
+                match known {
+                    Some(managed) => {
+                        managed.missed = 0;
+                        seen.push(managed.id);
+
+                        if managed.camera.base.url_onvif != device.url_onvif {
+                            events.push(ManagerEvent::AddressChanged {
+                                id: managed.id,
+                                old: managed.camera.base.url_onvif.clone(),
+                                new: device.url_onvif.clone(),
+                            });
+                            let credentials = managed.camera.base.credentials.take();
+                            let address_override = managed.camera.base.address_override.take();
+                            managed.camera.base = Device {
+                                credentials,
+                                address_override,
+                                ..device
+                            };
+                            moved.push((managed.id, managed.camera.base.onvif_url()));
+                        } else if managed.camera.base.endpoint.is_none() {
+                            managed.camera.base.endpoint = device.endpoint;
+                        }
+                    }
+                    None => added.push(device),
+                }
+            }
+
+            let limit = self.inner.config.missed_discoveries;
+            let mut removed = Vec::new();
+
+            for managed in registry.cameras.values_mut() {
+                if !seen.contains(&managed.id) && !managed.pinned {
+                    managed.missed += 1;
+
+                    if managed.missed >= limit {
+                        removed.push(managed.id);
+                    }
+                }
+            }
+
+            for id in removed {
+                info!("[Manager] Camera {id} missing from discovery, removing");
+                registry.cameras.remove(&id);
+                self.stop_events_of(id);
+                self.inner.metrics.lock().unwrap().remove(&id);
+                events.push(ManagerEvent::Removed(id));
+            }
+        }
+
+        for event in events {
+            self.notify(event);
+        }
+
+        for (id, url) in moved {
+            self.rebuild_services(id, url.clone()).await;
+            self.rebuild_stream(id, url).await;
+        }
+
+        for device in added {
+            info!("[Manager] Discovered new device {}", device.url_onvif);
+            self.add(Camera::new(device)).await;
+        }
+    }
+
+    pub async fn get(&self, id: CameraId) -> Option<ManagedCamera> {
+        let registry = self.inner.registry.read().await;
+        registry.cameras.get(&id).cloned()
+    }
+
+    /// A snapshot of every managed camera
+    pub async fn cameras(&self) -> Vec<ManagedCamera> {
+        let registry = self.inner.registry.read().await;
+        registry.cameras.values().cloned().collect()
+    }
+
+    /// Writes the stream URIs of every managed camera to an M3U or JSON
+    /// playlist, see playlist::save()
+    pub async fn export_playlist(&self, path: impl AsRef<Path>) -> anyhow::Result<usize> {
+        let cameras: Vec<Camera> = self.cameras().await.into_iter().map(|m| m.camera).collect();
+        playlist::save(&cameras, path)
+    }
+
+    /// Runs build_all() on every managed camera in parallel, with at most
+    /// `concurrency` builds in flight, and stores the built cameras back
+    /// in the fleet. One report is returned per camera, in id order.
+    pub async fn build_all(&self, concurrency: usize) -> Vec<BuildReport> {
+        let targets: Vec<(CameraId, Camera)> = {
+            let registry = self.inner.registry.read().await;
+            registry
+                .cameras
+                .values()
+                .map(|m| (m.id, m.camera.clone()))
+                .collect()
+        };
+
+        let permits = Semaphore::new(concurrency.max(1));
+        let permits = &permits;
+        let builds = targets.into_iter().map(|(id, mut camera)| async move {
+            let _permit = permits.acquire().await;
+            let start = Instant::now();
+            let result = camera.build_all().await;
+            (id, camera, result, start.elapsed())
+        });
+
+        let mut reports = Vec::new();
+
+        for (id, camera, result, elapsed) in join_all(builds).await {
+            match &result {
+                Ok(()) => self.record(id, |r| r.success(elapsed)),
+                Err(_) => self.record(id, |r| r.error()),
+            }
+
+            match &result {
+                Ok(()) => {
+                    let mut registry = self.inner.registry.write().await;
+                    if let Some(managed) = registry.cameras.get_mut(&id) {
+                        // Keep any address change merged in while building
+                        let base = managed.camera.base.clone();
+                        managed.camera = camera;
+                        managed.camera.base = base;
+                    }
+                }
+                Err(e) => warn!("[Manager] Unable to build camera {id}: {e}"),
+            }
+
+            reports.push(BuildReport {
+                id,
+                result,
+                elapsed,
+            });
+        }
+
+        reports
+    }
+
+    /// Adds a tag to a camera, returns false if the camera is unknown
+    pub async fn tag(&self, id: CameraId, tag: &str) -> bool {
+        let mut registry = self.inner.registry.write().await;
+
+        match registry.cameras.get_mut(&id) {
+            Some(managed) => {
+                managed.tags.insert(tag.to_string());
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub async fn untag(&self, id: CameraId, tag: &str) -> bool {
+        let mut registry = self.inner.registry.write().await;
+
+        match registry.cameras.get_mut(&id) {
+            Some(managed) => managed.tags.remove(tag),
+            None => false,
+        }
+    }
+
+    /// Credentials for one camera of the fleet, used by every request made
+    /// for it from now on, so cameras can each have their own password.
+    /// A running event subscription is restarted with them. Returns false
+    /// if the camera is unknown.
+    pub async fn set_credentials(&self, id: CameraId, credentials: Credentials) -> bool {
+        {
+            let mut registry = self.inner.registry.write().await;
+
+            match registry.cameras.get_mut(&id) {
+                Some(managed) => managed.camera.set_credentials(credentials),
+                None => return false,
+            }
+        }
+
+        if self.inner.event_tasks.lock().unwrap().contains_key(&id) {
+            self.spawn_event_supervisor(id);
+        }
+
+        true
+    }
+
+    /// The cameras currently carrying `tag`. Membership is resolved here,
+    /// so cameras tagged afterwards are not part of the returned group.
+    pub async fn group(&self, tag: &str) -> CameraGroup {
+        let registry = self.inner.registry.read().await;
+
+        CameraGroup {
+            cameras: registry
+                .cameras
+                .values()
+                .filter(|m| m.tags.contains(tag))
+                .map(|m| (m.id, m.camera.clone()))
+                .collect(),
+        }
+    }
+
+    /// Every managed camera matching `query`. Manager tags are checked
+    /// along with camera labels for CameraQuery::Label.
+    pub async fn find(&self, query: &CameraQuery) -> Vec<ManagedCamera> {
+        let registry = self.inner.registry.read().await;
+
+        registry
+            .cameras
+            .values()
+            .filter(|m| match query {
+                CameraQuery::Label(label) => m.tags.contains(label) || m.camera.matches(query),
+                _ => m.camera.matches(query),
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub async fn health(&self, id: CameraId) -> Option<Health> {
+        let registry = self.inner.registry.read().await;
+        registry.cameras.get(&id).map(|m| m.health)
+    }
+
+    /// Runs one round of health checks over the whole fleet with the
+    /// configured probe method. Cameras that come back online after being
+    /// offline get their stream URI rebuilt, since many devices hand out
+    /// new session URIs after a reboot.
+    pub async fn check_all(&self) {
+        let targets: Vec<(CameraId, Camera)> = {
+            let registry = self.inner.registry.read().await;
+            registry
+                .cameras
+                .values()
+                .map(|m| (m.id, m.camera.clone()))
+                .collect()
+        };
+
+        let wait = self.inner.config.probe_timeout;
+        let method = self.inner.config.probe_method;
+        let probes = targets.into_iter().map(|(id, camera)| async move {
+            let result = method.probe(&camera, wait).await;
+            (id, camera.base.onvif_url(), result)
+        });
+
+        let mut went_offline = Vec::new();
+
+        for (id, url, result) in join_all(probes).await {
+            let online = match result {
+                Ok(latency) => {
+                    self.record(id, |r| r.success(latency));
+                    true
+                }
+                Err(_) => {
+                    self.record(id, |r| r.error());
+                    false
+                }
+            };
+
+            let Some((old, new)) = self.set_health(id, online).await else {
+                continue;
+            };
+
+            if old == new {
+                continue;
+            }
+
+            match new {
+                Health::Offline => warn!("[Manager] Camera {id} went offline"),
+                _ => info!("[Manager] Camera {id} is {new:?}"),
+            }
+
+            self.notify(ManagerEvent::HealthChanged { id, old, new });
+
+            if new == Health::Online && old == Health::Offline {
+                info!("[Manager] Camera {id} is back online, rebuilding stream uri");
+                self.rebuild_stream(id, url).await;
+            }
+
+            if new == Health::Offline {
+                went_offline.push(id);
+            }
+        }
+
+        if self.inner.config.heal_on_offline && !went_offline.is_empty() {
+            self.heal(&went_offline).await;
+        }
+    }
+
+    /// Looks for cameras that stopped answering at a new address, which is
+    /// what usually happens when DHCP renumbers a device. A single discovery
+    /// is run and each camera is matched by its discovery endpoint reference
+    /// or, failing that, by asking unknown devices for their serial number.
+    /// Found cameras get their URL and stream URI updated and are marked
+    /// online again. Returns the ids of the healed cameras.
+    pub async fn heal(&self, ids: &[CameraId]) -> Vec<CameraId> {
+        let devices = match client::discover().await {
+            Ok(devices) => devices,
+            Err(e) => {
+                warn!("[Manager] Discovery for healing failed: {e}");
+                return Vec::new();
+            }
+        };
+
+        // Devices already answering for a managed camera cannot be a moved one
+        let candidates: Vec<Device> = {
+            let registry = self.inner.registry.read().await;
+            devices
+                .into_iter()
+                .filter(|d| {
+                    !registry
+                        .cameras
+                        .values()
+                        .any(|m| m.camera.base.url_onvif == d.url_onvif)
+                })
+                .collect()
+        };
+
+        let mut healed = Vec::new();
+
+        for &id in ids {
+            let Some(managed) = self.get(id).await else {
+                continue;
+            };
+
+            let Some(device) = find_moved(&managed.camera, &candidates).await else {
+                info!("[Manager] No new address found for camera {id}");
+                continue;
+            };
+
+            let old = managed.camera.base.url_onvif.clone();
+            let new = device.url_onvif.clone();
+            let device = Device {
+                credentials: managed.camera.base.credentials.clone(),
+                address_override: managed.camera.base.address_override.clone(),
+                ..device
+            };
+            let authorized = device.onvif_url();
+
+            info!("[Manager] Camera {id} moved from {old} to {new}");
+
+            {
+                let mut registry = self.inner.registry.write().await;
+                if let Some(managed) = registry.cameras.get_mut(&id) {
+                    managed.camera.base = device;
+                }
+            }
+
+            self.notify(ManagerEvent::AddressChanged {
+                id,
+                old,
+                new: new.clone(),
+            });
+
+            self.rebuild_services(id, authorized.clone()).await;
+            self.rebuild_stream(id, authorized).await;
+
+            if let Some((old, new)) = self.set_health(id, true).await {
+                self.notify(ManagerEvent::HealthChanged { id, old, new });
+            }
+
+            healed.push(id);
+        }
+
+        healed
+    }
+
+    /// Records the result of a probe and returns the previous and new
+    /// health. A camera only goes offline once `failure_threshold` probes
+    /// in a row have failed.
+    async fn set_health(&self, id: CameraId, online: bool) -> Option<(Health, Health)> {
+        let threshold = self.inner.config.failure_threshold.max(1);
+        let mut registry = self.inner.registry.write().await;
+        let managed = registry.cameras.get_mut(&id)?;
+        let previous = managed.health;
+
+        match online {
+            true => {
+                managed.health = Health::Online;
+                managed.failures = 0;
+                managed.last_seen = Some(Instant::now());
+            }
+            false => {
+                managed.failures += 1;
+
+                if managed.failures >= threshold {
+                    managed.health = Health::Offline;
+                }
+            }
+        }
+
+        Some((previous, managed.health))
+    }
+
+    /// Reads the service URLs of a camera that moved to a new address,
+    /// as media, PTZ and events use them before the device URL. Service
+    /// URLs that cannot be read are dropped rather than left on the old
+    /// address.
+    async fn rebuild_services(&self, id: CameraId, url: url::Url) {
+        let capabilities = Camera::set_capabilities(url.clone()).await;
+        let services = Camera::set_services(url).await;
+
+        let mut registry = self.inner.registry.write().await;
+        let Some(managed) = registry.cameras.get_mut(&id) else {
+            return;
+        };
+
+        managed.camera.capabilities = capabilities.unwrap_or_else(|e| {
+            warn!("[Manager] Unable to rebuild capabilities of camera {id}: {e}");
+            Default::default()
+        });
+        managed.camera.services = services.unwrap_or_else(|e| {
+            warn!("[Manager] Unable to rebuild services of camera {id}: {e}");
+            Default::default()
+        });
+    }
+
+    async fn rebuild_stream(&self, id: CameraId, url: url::Url) {
+        let transport = match self.inner.registry.read().await.cameras.get(&id) {
+            Some(managed) => managed.camera.stream_transport(),
+            None => return,
+        };
+
+        match Camera::set_stream_uri(url, transport).await {
+            Ok(stream) => {
+                let mut registry = self.inner.registry.write().await;
+                if let Some(managed) = registry.cameras.get_mut(&id) {
+                    managed.camera.stream = stream;
+                }
+            }
+            Err(e) => warn!("[Manager] Unable to rebuild stream uri of camera {id}: {e}"),
+        }
+    }
+
+    /// Starts the periodic health checks, and rediscovery when configured,
+    /// in background tasks. The tasks end on stop() or once every handle
+    /// has been dropped.
+    pub fn start(&self) {
+        self.stop();
+
+        let mut tasks = vec![self.spawn_periodic(self.inner.config.check_interval, |m| {
+            Box::pin(async move { m.check_all().await })
+        })];
+
+        if let Some(interval) = self.inner.config.rediscover_interval {
+            tasks.push(self.spawn_periodic(interval, |m| {
+                Box::pin(async move {
+                    if let Err(e) = m.rediscover().await {
+                        warn!("[Manager] Rediscovery failed: {e}");
+                    }
+                })
+            }));
+        }
+
+        *self.inner.tasks.lock().unwrap() = tasks;
+    }
+
+    fn spawn_periodic<F>(&self, interval: Duration, round: F) -> JoinHandle<()>
+    where
+        F: Fn(CameraManager) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + 'static,
+    {
+        let weak: Weak<Inner> = Arc::downgrade(&self.inner);
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+
+            loop {
+                ticker.tick().await;
+
+                match weak.upgrade() {
+                    Some(inner) => round(CameraManager { inner }).await,
+                    None => break,
+                }
+            }
+        })
+    }
+
+    pub fn stop(&self) {
+        for task in self.inner.tasks.lock().unwrap().drain(..) {
+            task.abort();
+        }
+    }
+
+    /// Ends every event subscription started by events()
+    pub fn stop_events(&self) {
+        self.inner.events_started.store(false, Ordering::SeqCst);
+
+        for (_, task) in std::mem::take(&mut *self.inner.event_tasks.lock().unwrap()) {
+            task.abort();
+        }
+    }
+
+    /// Receives the events of every managed camera, tagged with the camera
+    /// id. The first call subscribes to the event service of each camera,
+    /// cameras added later are subscribed as they join. A subscription that
+    /// fails (device reboot, expired pull point...) is recreated after
+    /// `event_retry`.
+    pub async fn events(&self) -> broadcast::Receiver<FleetEvent> {
+        let receiver = self.inner.fleet_events.subscribe();
+
+        if !self.inner.events_started.swap(true, Ordering::SeqCst) {
+            let ids: Vec<CameraId> = {
+                let registry = self.inner.registry.read().await;
+                registry.cameras.keys().copied().collect()
+            };
+
+            for id in ids {
+                self.spawn_event_supervisor(id);
+            }
+        }
+
+        receiver
+    }
+
+    fn spawn_event_supervisor(&self, id: CameraId) {
+        let weak: Weak<Inner> = Arc::downgrade(&self.inner);
+
+        let task = tokio::spawn(async move {
+            loop {
+                let (camera, retry) = match weak.upgrade() {
+                    Some(inner) => {
+                        let manager = CameraManager { inner };
+                        match manager.get(id).await {
+                            Some(managed) => (managed.camera, manager.inner.config.event_retry),
+                            None => break,
+                        }
+                    }
+                    None => break,
+                };
+
+                match pull_events(&weak, id, &camera).await {
+                    Ok(()) => break,
+                    Err(e) => {
+                        if let Some(inner) = weak.upgrade() {
+                            CameraManager { inner }.record(id, |r| r.error());
+                        }
+
+                        warn!("[Manager] Event subscription of camera {id} failed, restarting: {e}")
+                    }
+                }
+
+                tokio::time::sleep(retry).await;
+            }
+        });
+
+        if let Some(old) = self.inner.event_tasks.lock().unwrap().insert(id, task) {
+            old.abort();
+        }
+    }
+
+    fn stop_events_of(&self, id: CameraId) {
+        if let Some(task) = self.inner.event_tasks.lock().unwrap().remove(&id) {
+            task.abort();
+        }
+    }
+}
+
+/// Devices are the same when their discovery endpoint references match or,
+/// when either one is unknown, when they answer on the same host
+fn is_same_device(a: &Device, b: &Device) -> bool {
+    match (&a.endpoint, &b.endpoint) {
+        (Some(a), Some(b)) => a == b,
+        _ => a.url_onvif.host() == b.url_onvif.host(),
+    }
+}
+
+/// Finds the device a camera moved to among `candidates`, first by
+/// endpoint reference, then by serial number from GetDeviceInformation
+async fn find_moved(camera: &Camera, candidates: &[Device]) -> Option<Device> {
+    if let Some(endpoint) = &camera.base.endpoint {
+        if let Some(device) = candidates
+            .iter()
+            .find(|d| d.endpoint.as_ref() == Some(endpoint))
+        {
+            return Some(device.clone());
+        }
+    }
+
+    let serial = camera.device_info.serial_num.as_ref()?;
+    let credentials = &camera.base.credentials;
+    let lookups = candidates.iter().map(|d| async move {
+        let device = Device {
+            credentials: credentials.clone(),
+            ..d.clone()
+        };
+        let info = Camera::set_device_info(device.onvif_url()).await.ok()?;
+        (info.serial_num.as_ref() == Some(serial)).then(|| d.clone())
+    });
+
+    join_all(lookups).await.into_iter().flatten().next()
+}
+
+/// Pulls events of one camera into the fleet channel until the manager is
+/// dropped. Returns an error whenever the subscription has to be recreated.
+async fn pull_events(weak: &Weak<Inner>, id: CameraId, camera: &Camera) -> anyhow::Result<()> {
+    let url = events::event_url(camera)?;
+    let mut subscription = events::create_pull_point_subscription(url).await?;
+
+    loop {
+        let (wait, sender) = match weak.upgrade() {
+            Some(inner) => (inner.config.event_pull_timeout, inner.fleet_events.clone()),
+            None => break,
+        };
+
+        let events = subscription.pull(wait, EVENT_PULL_LIMIT).await?;
+
+        #[cfg(feature = "metrics")]
+        {
+            let url = &camera.device().url_onvif;
+            let host = format!(
+                "{}:{}",
+                url.host_str().unwrap_or_default(),
+                url.port_or_known_default().unwrap_or_default()
+            );
+            crate::telemetry::events(&host, events.len());
+        }
+
+        if let Some(inner) = weak.upgrade() {
+            CameraManager { inner }.record(id, |r| r.events(events.len()));
+        }
+
+        for event in events {
+            // No subscribers is not an error
+            let _ = sender.send(FleetEvent { id, event });
+        }
+
+        if subscription.expires_within(wait * 2) {
+            subscription.renew(EVENT_TERMINATION).await?;
+        }
+    }
+
+    subscription.unsubscribe().await
+}
+
+/// A set of managed cameras selected by tag. Every operation runs on all
+/// cameras concurrently and reports one result per camera, so a single
+/// unreachable device does not fail the whole group.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::manager::CameraManager;
+/// # async fn run(manager: CameraManager) {
+/// let lobby = manager.group("lobby").await;
+///
+/// for (id, result) in lobby.goto_preset("entrance").await {
+///     if let Err(e) = result {
+///         println!("Camera {id} did not move: {e}");
+///     }
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CameraGroup {
+    cameras: Vec<(CameraId, Camera)>,
+}
+
+impl CameraGroup {
+    pub fn ids(&self) -> Vec<CameraId> {
+        self.cameras.iter().map(|(id, _)| *id).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cameras.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cameras.is_empty()
+    }
+
+    /// Sets the clock of every camera to the current UTC time, keeping
+    /// their time zones. Cameras on NTP are skipped with an error, see
+    /// system::set_system_date_and_time().
+    pub async fn set_time(&self) -> Vec<(CameraId, anyhow::Result<()>)> {
+        let now = chrono::Utc::now();
+
+        self.run(|camera| async move {
+            system::set_system_date_and_time(camera.base.onvif_url(), now).await
+        })
+        .await
+    }
+
+    /// Moves every camera to its preset called `name`
+    pub async fn goto_preset(&self, name: &str) -> Vec<(CameraId, anyhow::Result<()>)> {
+        self.run(|camera| async move { ptz::goto_preset_named(&camera, name).await })
+            .await
+    }
+
+    /// Requests a fresh stream URI from every camera
+    pub async fn stream_uris(&self) -> Vec<(CameraId, anyhow::Result<StreamUri>)> {
+        self.run(|camera| async move {
+            Camera::set_stream_uri(camera.base.onvif_url(), camera.transport).await
+        })
+        .await
+    }
+
+    async fn run<T, F, Fut>(&self, op: F) -> Vec<(CameraId, anyhow::Result<T>)>
+    where
+        F: Fn(Camera) -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        let ops = self.cameras.iter().map(|(id, camera)| {
+            let op = op(camera.clone());
+            async move { (*id, op.await) }
+        });
+
+        join_all(ops).await
+    }
+}
@@ -0,0 +1,99 @@
+use crate::client::{self, Messages};
+use crate::device::MetadataConfig;
+use crate::utils::xml::XmlNode;
+
+use anyhow::{anyhow, Result};
+
+/// Lists the metadata configurations of the device, see
+/// MediaProfile::metadata_config_token for the one of a profile
+///
+/// # Arguments
+///
+/// * `onvif_url` - The media service URL (see `media_url()`)
+pub async fn get_metadata_configurations(onvif_url: url::Url) -> Result<Vec<MetadataConfig>> {
+    let response = client::send(onvif_url, Messages::GetMetadataConfigurations).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    Ok(root
+        .find_all("Configurations")
+        .into_iter()
+        .filter_map(MetadataConfig::from_xml)
+        .collect())
+}
+
+/// Attaches a metadata configuration to a media profile, so its RTSP
+/// session carries a metadata stream
+///
+/// # Arguments
+///
+/// * `onvif_url` - The media service URL (see `media_url()`)
+/// * `profile_token` - Token of the media profile
+/// * `configuration_token` - Token of the metadata configuration
+///
+/// # Examples
+///
+/// Events in the stream of the first profile:
+///
+/// ```no_run
+/// # use onvif_cam_rs::device::camera::Camera;
+/// # use onvif_cam_rs::media;
+/// # async fn run(camera: Camera) -> anyhow::Result<()> {
+/// let url = media::media_url(&camera)?;
+/// let profile = camera.profile().unwrap();
+///
+/// let mut config = media::get_metadata_configurations(url.clone()).await?.remove(0);
+/// config.events = true;
+/// media::set_metadata_configuration(url.clone(), &config).await?;
+/// media::add_metadata_configuration(url, &profile.token, &config.token).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn add_metadata_configuration(
+    onvif_url: url::Url,
+    profile_token: &str,
+    configuration_token: &str,
+) -> Result<()> {
+    let msg = Messages::AddMetadataConfiguration {
+        profile_token: profile_token.to_string(),
+        configuration_token: configuration_token.to_string(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    response.error_for_status()?;
+
+    Ok(())
+}
+
+/// Writes a metadata configuration, for every profile using it
+///
+/// # Arguments
+///
+/// * `onvif_url` - The media service URL (see `media_url()`)
+/// * `configuration` - The configuration as it should be, usually one of
+///   get_metadata_configurations() changed
+pub async fn set_metadata_configuration(
+    onvif_url: url::Url,
+    configuration: &MetadataConfig,
+) -> Result<()> {
+    if configuration.token.is_empty() {
+        return Err(anyhow!(
+            "[Media][set_metadata_configuration] The configuration has no token"
+        ));
+    }
+
+    if configuration.event_filter.is_some() && !configuration.events {
+        return Err(anyhow!(
+            "[Media][set_metadata_configuration] An event filter needs events enabled"
+        ));
+    }
+
+    let msg = Messages::SetMetadataConfiguration {
+        configuration: configuration.clone(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    response.error_for_status()?;
+
+    Ok(())
+}
@@ -0,0 +1,268 @@
+use crate::client::{self, Messages};
+use crate::device::VideoEncoderConfig;
+use crate::utils::xml::XmlNode;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+
+/// What a video encoder configuration accepts, per encoding, as answered
+/// by GetVideoEncoderConfigurationOptions. validate() checks a
+/// configuration against it before it is set.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VideoEncoderOptions {
+    pub encodings: Vec<EncodingOptions>,
+}
+
+/// Ranges of one encoding. Empty lists and None ranges are not limited by
+/// the device, or at least not reported.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[rustfmt::skip]
+pub struct EncodingOptions {
+    /// e.g. "H264", "H265" or "JPEG"
+    pub encoding:            String,
+    pub resolutions:         Vec<(u32, u32)>,
+    pub quality:             Option<RangeInclusive<f32>>,
+    /// Frames per second
+    pub frame_rate:          Option<RangeInclusive<u32>>,
+    pub encoding_interval:   Option<RangeInclusive<u32>>,
+    /// Kbit/s
+    pub bitrate:             Option<RangeInclusive<u32>>,
+    pub gov_length:          Option<RangeInclusive<u32>>,
+    /// e.g. "Baseline", "Main" and "High"
+    pub profiles:            Vec<String>,
+}
+
+impl VideoEncoderOptions {
+    /// The options of `encoding`, None when the encoder does not support it
+    pub fn encoding(&self, encoding: &str) -> Option<&EncodingOptions> {
+        self.encodings
+            .iter()
+            .find(|options| options.encoding.eq_ignore_ascii_case(encoding))
+    }
+
+    /// Fails naming the first value of `config` the encoder does not
+    /// accept, which set_video_encoder_configuration() would otherwise get
+    /// back as a fault that seldom tells which one
+    pub fn validate(&self, config: &VideoEncoderConfig) -> Result<()> {
+        let encoding = config.encoding.as_deref().unwrap_or_default();
+        let Some(options) = self.encoding(encoding) else {
+            let supported: Vec<&str> = self.encodings.iter().map(|o| o.encoding.as_str()).collect();
+            return Err(anyhow!(
+                "[Media][validate] Encoding {encoding:?} is not supported, only {supported:?}"
+            ));
+        };
+
+        if let Some(resolution) = config.resolution {
+            if !options.resolutions.is_empty() && !options.resolutions.contains(&resolution) {
+                return Err(anyhow!(
+                    "[Media][validate] Resolution {}x{} is not supported for {encoding}, only {:?}",
+                    resolution.0,
+                    resolution.1,
+                    options.resolutions
+                ));
+            }
+        }
+
+        check_range("Quality", config.quality, &options.quality)?;
+        check_range(
+            "Frame rate limit",
+            config.frame_rate_limit,
+            &options.frame_rate,
+        )?;
+        check_range(
+            "Encoding interval",
+            config.encoding_interval,
+            &options.encoding_interval,
+        )?;
+        check_range("Bitrate limit", config.bitrate_limit, &options.bitrate)?;
+        check_range("GOV length", config.gov_length, &options.gov_length)?;
+
+        if let Some(profile) = &config.profile {
+            if !options.profiles.is_empty() && !options.profiles.contains(profile) {
+                return Err(anyhow!(
+                    "[Media][validate] Profile {profile} is not supported for {encoding}, only {:?}",
+                    options.profiles
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parses a Media GetVideoEncoderConfigurationOptionsResponse, which has
+    /// an element per encoding and the bitrate ranges in its extension
+    fn from_xml(root: &XmlNode) -> Result<Self> {
+        let options = root
+            .find("Options")
+            .ok_or_else(|| anyhow!("[Media][get_video_encoder_options] No Options in response"))?;
+        let quality = options.child("QualityRange").and_then(range);
+        let extension = options.child("Extension");
+
+        let encodings = ["JPEG", "MPEG4", "H264"]
+            .into_iter()
+            .filter_map(|encoding| {
+                let codec = options.child(encoding)?;
+                let bitrate = extension
+                    .and_then(|e| e.child(encoding))
+                    .and_then(|c| c.child("BitrateRange"))
+                    .and_then(range);
+
+                Some(EncodingOptions {
+                    encoding: encoding.to_string(),
+                    resolutions: resolutions(codec),
+                    quality: quality.clone(),
+                    frame_rate: codec.child("FrameRateRange").and_then(range),
+                    encoding_interval: codec.child("EncodingIntervalRange").and_then(range),
+                    bitrate,
+                    gov_length: codec.child("GovLengthRange").and_then(range),
+                    profiles: codec
+                        .children
+                        .iter()
+                        .filter(|c| c.name.ends_with("ProfilesSupported"))
+                        .map(|c| c.text.trim().to_string())
+                        .collect(),
+                })
+            })
+            .collect();
+
+        Ok(VideoEncoderOptions { encodings })
+    }
+
+    /// Parses a Media2 GetVideoEncoderConfigurationOptionsResponse, which
+    /// has an Options element per encoding with the GOV length, frame rates
+    /// and profiles as attributes
+    fn from_xml2(root: &XmlNode) -> Self {
+        let encodings = root
+            .find_all("Options")
+            .into_iter()
+            .map(|options| {
+                let list = |name: &str| -> Vec<String> {
+                    options
+                        .attr(name)
+                        .unwrap_or_default()
+                        .split_whitespace()
+                        .map(str::to_string)
+                        .collect()
+                };
+
+                let numbers = |name: &str| -> Vec<f32> {
+                    list(name).iter().filter_map(|n| n.parse().ok()).collect()
+                };
+
+                EncodingOptions {
+                    encoding: options.child_text("Encoding").unwrap_or_default(),
+                    resolutions: resolutions(options),
+                    quality: options.child("QualityRange").and_then(range),
+                    frame_rate: span(&numbers("FrameRatesSupported")),
+                    encoding_interval: None,
+                    bitrate: options.child("BitrateRange").and_then(range),
+                    gov_length: span(&numbers("GovLengthRange")),
+                    profiles: list("ProfilesSupported"),
+                }
+            })
+            .collect();
+
+        VideoEncoderOptions { encodings }
+    }
+}
+
+/// Reads what the video encoder configuration with `configuration_token`
+/// accepts, see VideoEncoderOptions::validate()
+///
+/// # Arguments
+///
+/// * `onvif_url` - The media service URL (see `media_url()`)
+/// * `configuration_token` - Token of the video encoder configuration
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::media;
+/// # async fn run(onvif_url: url::Url) -> anyhow::Result<()> {
+/// let options = media::get_video_encoder_options(onvif_url.clone(), "vec_1").await?;
+///
+/// let mut config = media::get_video_encoder_configuration(onvif_url.clone(), "vec_1").await?;
+/// config.resolution = Some((1280, 720));
+/// options.validate(&config)?;
+/// media::set_video_encoder_configuration(onvif_url, &config).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn get_video_encoder_options(
+    onvif_url: url::Url,
+    configuration_token: &str,
+) -> Result<VideoEncoderOptions> {
+    let msg = Messages::GetVideoEncoderConfigurationOptions {
+        configuration_token: configuration_token.to_string(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    let response = response.error_for_status()?.bytes().await?;
+
+    VideoEncoderOptions::from_xml(&XmlNode::parse(&response[..])?)
+}
+
+/// Reads what the video encoder configuration with `configuration_token`
+/// accepts through the Media2 service, H265 included
+///
+/// # Arguments
+///
+/// * `onvif_url` - The Media2 service URL (see `media2_url()`)
+/// * `configuration_token` - Token of the video encoder configuration
+pub async fn get_video_encoder_options2(
+    onvif_url: url::Url,
+    configuration_token: &str,
+) -> Result<VideoEncoderOptions> {
+    let msg = Messages::GetMedia2VideoEncoderConfigurationOptions {
+        configuration_token: configuration_token.to_string(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    let response = response.error_for_status()?.bytes().await?;
+
+    Ok(VideoEncoderOptions::from_xml2(&XmlNode::parse(
+        &response[..],
+    )?))
+}
+
+fn check_range<T>(name: &str, value: Option<T>, range: &Option<RangeInclusive<T>>) -> Result<()>
+where
+    T: PartialOrd + std::fmt::Display,
+{
+    match (value, range) {
+        (Some(value), Some(range)) if !range.contains(&value) => Err(anyhow!(
+            "[Media][validate] {name} {value} is out of {}..={}",
+            range.start(),
+            range.end()
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// The Min and Max children of a range element
+fn range<T: std::str::FromStr>(node: &XmlNode) -> Option<RangeInclusive<T>> {
+    let min = node.child_text("Min")?.parse().ok()?;
+    let max = node.child_text("Max")?.parse().ok()?;
+
+    Some(min..=max)
+}
+
+/// The smallest to the largest of `values`, rounded outwards
+fn span(values: &[f32]) -> Option<RangeInclusive<u32>> {
+    let min = values.iter().copied().reduce(f32::min)?;
+    let max = values.iter().copied().reduce(f32::max)?;
+
+    Some(min.floor() as u32..=max.ceil() as u32)
+}
+
+fn resolutions(node: &XmlNode) -> Vec<(u32, u32)> {
+    node.children_named("ResolutionsAvailable")
+        .filter_map(|r| {
+            Some((
+                r.child_text("Width")?.parse().ok()?,
+                r.child_text("Height")?.parse().ok()?,
+            ))
+        })
+        .collect()
+}
@@ -0,0 +1,103 @@
+use crate::client::{self, Messages};
+use crate::device::Osd;
+use crate::utils::xml::XmlNode;
+
+use anyhow::{anyhow, Result};
+
+/// Lists the OSDs of the device through the Media2 service. OSD types
+/// other than text and image are left out.
+///
+/// # Arguments
+///
+/// * `onvif_url` - The Media2 service URL (see `media2_url()`)
+/// * `configuration_token` - Only the OSDs of this video source
+///   configuration, or every OSD
+pub async fn get_osds(onvif_url: url::Url, configuration_token: Option<&str>) -> Result<Vec<Osd>> {
+    let msg = Messages::GetOSDs {
+        configuration_token: configuration_token.map(str::to_string),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    Ok(root
+        .find_all("OSDs")
+        .into_iter()
+        .filter_map(Osd::from_xml)
+        .collect())
+}
+
+/// Creates an OSD and returns the token the device gave it
+///
+/// # Arguments
+///
+/// * `onvif_url` - The Media2 service URL (see `media2_url()`)
+/// * `osd` - The OSD to create, its token is ignored
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::device::camera::Camera;
+/// # use onvif_cam_rs::device::{Osd, OsdContent, OsdPosition};
+/// # use onvif_cam_rs::media;
+/// # async fn run(camera: Camera) -> anyhow::Result<()> {
+/// let url = media::media2_url(&camera)?;
+/// let source = camera.profile().and_then(|p| p.video_source_config_token.clone()).unwrap();
+///
+/// let mut osd = Osd::text(&source, "", OsdPosition::UpperRight);
+/// osd.content = OsdContent::DateAndTime {
+///     date_format: Some("yyyy-MM-dd".to_string()),
+///     time_format: Some("HH:mm:ss".to_string()),
+/// };
+/// let token = media::create_osd(url, &osd).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn create_osd(onvif_url: url::Url, osd: &Osd) -> Result<String> {
+    let msg = Messages::CreateOSD { osd: osd.clone() };
+
+    let response = client::send(onvif_url, msg).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    root.find("OSDToken")
+        .map(|t| t.text.trim().to_string())
+        .ok_or_else(|| anyhow!("[Media][create_osd] No OSDToken in response"))
+}
+
+/// Changes the OSD with the token of `osd`
+///
+/// # Arguments
+///
+/// * `onvif_url` - The Media2 service URL (see `media2_url()`)
+/// * `osd` - The OSD as it should be, usually one of get_osds() changed
+pub async fn set_osd(onvif_url: url::Url, osd: &Osd) -> Result<()> {
+    if osd.token.is_empty() {
+        return Err(anyhow!("[Media][set_osd] The OSD has no token"));
+    }
+
+    let msg = Messages::SetOSD { osd: osd.clone() };
+
+    let response = client::send(onvif_url, msg).await?;
+    response.error_for_status()?;
+
+    Ok(())
+}
+
+/// Removes the OSD with `osd_token`
+///
+/// # Arguments
+///
+/// * `onvif_url` - The Media2 service URL (see `media2_url()`)
+/// * `osd_token` - Token of the OSD
+pub async fn delete_osd(onvif_url: url::Url, osd_token: &str) -> Result<()> {
+    let msg = Messages::DeleteOSD {
+        osd_token: osd_token.to_string(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    response.error_for_status()?;
+
+    Ok(())
+}
@@ -0,0 +1,212 @@
+use crate::client::{self, Messages};
+use crate::device::{ConfigurationType, MediaProfile, VideoEncoderConfig};
+use crate::soap::parse_media_profile;
+use crate::utils::xml::XmlNode;
+
+use anyhow::{anyhow, Result};
+
+/// Creates an empty media profile. A profile streams once it has a video
+/// source and a video encoder configuration, see
+/// add_video_source_configuration() and add_video_encoder_configuration().
+///
+/// # Arguments
+///
+/// * `onvif_url` - The media service URL (see `media_url()`)
+/// * `name` - Name of the profile
+/// * `token` - Token of the profile, or None for one picked by the device
+///
+/// # Examples
+///
+/// A low resolution profile for analytics, sharing the video source of the
+/// main profile:
+///
+/// ```no_run
+/// # use onvif_cam_rs::device::camera::Camera;
+/// # use onvif_cam_rs::media;
+/// # async fn run(camera: Camera) -> anyhow::Result<()> {
+/// let url = media::media_url(&camera)?;
+/// let source = camera.profile().and_then(|p| p.video_source_config_token.clone()).unwrap();
+///
+/// let profile = media::create_profile(url.clone(), "analytics", None).await?;
+/// media::add_video_source_configuration(url.clone(), &profile.token, &source).await?;
+/// media::add_video_encoder_configuration(url.clone(), &profile.token, "vec_sub").await?;
+///
+/// let mut config = media::get_video_encoder_configuration(url.clone(), "vec_sub").await?;
+/// config.resolution = Some((640, 360));
+/// media::set_video_encoder_configuration(url, &config).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn create_profile(
+    onvif_url: url::Url,
+    name: &str,
+    token: Option<&str>,
+) -> Result<MediaProfile> {
+    let msg = Messages::CreateProfile {
+        name: name.to_string(),
+        token: token.map(str::to_string),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    root.find("Profile")
+        .and_then(parse_media_profile)
+        .ok_or_else(|| anyhow!("[Media][create_profile] No Profile in response"))
+}
+
+/// Deletes a media profile. Profiles that are `fixed` cannot be deleted.
+///
+/// # Arguments
+///
+/// * `onvif_url` - The media service URL (see `media_url()`)
+/// * `profile_token` - Token of the media profile
+pub async fn delete_profile(onvif_url: url::Url, profile_token: &str) -> Result<()> {
+    let msg = Messages::DeleteProfile {
+        profile_token: profile_token.to_string(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    response.error_for_status()?;
+
+    Ok(())
+}
+
+/// Attaches a video source configuration to a media profile, replacing
+/// the one it had
+///
+/// # Arguments
+///
+/// * `onvif_url` - The media service URL (see `media_url()`)
+/// * `profile_token` - Token of the media profile
+/// * `configuration_token` - Token of the video source configuration
+pub async fn add_video_source_configuration(
+    onvif_url: url::Url,
+    profile_token: &str,
+    configuration_token: &str,
+) -> Result<()> {
+    let msg = Messages::AddVideoSourceConfiguration {
+        profile_token: profile_token.to_string(),
+        configuration_token: configuration_token.to_string(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    response.error_for_status()?;
+
+    Ok(())
+}
+
+/// Attaches a video encoder configuration to a media profile, replacing
+/// the one it had. Most devices want a video source configuration in the
+/// profile first.
+///
+/// # Arguments
+///
+/// * `onvif_url` - The media service URL (see `media_url()`)
+/// * `profile_token` - Token of the media profile
+/// * `configuration_token` - Token of the video encoder configuration
+pub async fn add_video_encoder_configuration(
+    onvif_url: url::Url,
+    profile_token: &str,
+    configuration_token: &str,
+) -> Result<()> {
+    let msg = Messages::AddVideoEncoderConfiguration {
+        profile_token: profile_token.to_string(),
+        configuration_token: configuration_token.to_string(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    response.error_for_status()?;
+
+    Ok(())
+}
+
+/// Lists the video encoder configurations that can be added to a media
+/// profile. Compatibility depends on the video source configuration of the
+/// profile, so add that first.
+///
+/// # Arguments
+///
+/// * `onvif_url` - The media service URL (see `media_url()`)
+/// * `profile_token` - Token of the media profile
+///
+/// # Examples
+///
+/// Picks an H264 encoder for a new profile:
+///
+/// ```no_run
+/// # use onvif_cam_rs::media;
+/// # async fn run(onvif_url: url::Url, source: &str) -> anyhow::Result<()> {
+/// let profile = media::create_profile(onvif_url.clone(), "recording", None).await?;
+/// media::add_video_source_configuration(onvif_url.clone(), &profile.token, source).await?;
+///
+/// let encoders =
+///     media::get_compatible_video_encoder_configurations(onvif_url.clone(), &profile.token)
+///         .await?;
+///
+/// if let Some(token) = encoders
+///     .iter()
+///     .find(|c| c.encoding.as_deref() == Some("H264"))
+///     .and_then(|c| c.token.as_deref())
+/// {
+///     media::add_video_encoder_configuration(onvif_url, &profile.token, token).await?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn get_compatible_video_encoder_configurations(
+    onvif_url: url::Url,
+    profile_token: &str,
+) -> Result<Vec<VideoEncoderConfig>> {
+    let msg = Messages::GetCompatibleVideoEncoderConfigurations {
+        profile_token: profile_token.to_string(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    Ok(root
+        .find_all("Configurations")
+        .into_iter()
+        .map(VideoEncoderConfig::from_xml)
+        .collect())
+}
+
+/// Attaches configurations of any type to a media profile through the
+/// Media2 service, replacing those of the same types it had
+///
+/// # Arguments
+///
+/// * `onvif_url` - The Media2 service URL (see `media2_url()`)
+/// * `profile_token` - Token of the media profile
+/// * `configurations` - Type and token of each configuration, at most one
+///   of each type
+pub async fn add_configuration(
+    onvif_url: url::Url,
+    profile_token: &str,
+    configurations: &[(ConfigurationType, &str)],
+) -> Result<()> {
+    for (i, (kind, _)) in configurations.iter().enumerate() {
+        if configurations[..i].iter().any(|(k, _)| k == kind) {
+            return Err(anyhow!(
+                "[Media][add_configuration] More than one {} configuration",
+                kind.as_str()
+            ));
+        }
+    }
+
+    let msg = Messages::AddConfiguration {
+        profile_token: profile_token.to_string(),
+        configurations: configurations
+            .iter()
+            .map(|(kind, token)| (*kind, token.to_string()))
+            .collect(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    response.error_for_status()?;
+
+    Ok(())
+}
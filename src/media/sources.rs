@@ -0,0 +1,248 @@
+use crate::client::{self, Messages};
+use crate::device::{
+    AudioSource, AudioSourceConfig, VideoSource, VideoSourceConfig, VideoSourceMode,
+};
+use crate::utils::xml::XmlNode;
+
+use anyhow::{anyhow, Result};
+
+/// Lists the video inputs of the device, one per sensor on multi-sensor
+/// cameras
+///
+/// # Arguments
+///
+/// * `onvif_url` - The media service URL (see `media_url()`)
+pub async fn get_video_sources(onvif_url: url::Url) -> Result<Vec<VideoSource>> {
+    let response = client::send(onvif_url, Messages::GetVideoSources).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    Ok(root
+        .find_all("VideoSources")
+        .into_iter()
+        .filter_map(VideoSource::from_xml)
+        .collect())
+}
+
+/// Lists the video source configurations of the device, see
+/// MediaProfile::video_source_config_token for the one of a profile
+///
+/// # Arguments
+///
+/// * `onvif_url` - The media service URL (see `media_url()`)
+pub async fn get_video_source_configurations(
+    onvif_url: url::Url,
+) -> Result<Vec<VideoSourceConfig>> {
+    let response = client::send(onvif_url, Messages::GetVideoSourceConfigurations).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    Ok(root
+        .find_all("Configurations")
+        .into_iter()
+        .filter_map(VideoSourceConfig::from_xml)
+        .collect())
+}
+
+/// Writes a video source configuration, for every profile using it
+///
+/// # Arguments
+///
+/// * `onvif_url` - The media service URL (see `media_url()`)
+/// * `configuration` - The configuration as it should be, usually one of
+///   get_video_source_configurations() changed
+///
+/// # Examples
+///
+/// Crops the center quarter of the image:
+///
+/// ```no_run
+/// # use onvif_cam_rs::device::Bounds;
+/// # use onvif_cam_rs::media;
+/// # async fn run(onvif_url: url::Url) -> anyhow::Result<()> {
+/// let mut config = media::get_video_source_configurations(onvif_url.clone()).await?.remove(0);
+/// let Bounds { width, height, .. } = config.bounds;
+///
+/// config.bounds = Bounds { x: width / 4, y: height / 4, width: width / 2, height: height / 2 };
+/// media::set_video_source_configuration(onvif_url, &config).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn set_video_source_configuration(
+    onvif_url: url::Url,
+    configuration: &VideoSourceConfig,
+) -> Result<()> {
+    if configuration.token.is_empty() || configuration.source_token.is_empty() {
+        return Err(anyhow!(
+            "[Media][set_video_source_configuration] The configuration needs a token and a source token"
+        ));
+    }
+
+    if configuration.bounds.width == 0 || configuration.bounds.height == 0 {
+        return Err(anyhow!(
+            "[Media][set_video_source_configuration] The bounds {:?} are empty",
+            configuration.bounds
+        ));
+    }
+
+    let msg = Messages::SetVideoSourceConfiguration {
+        configuration: configuration.clone(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    response.error_for_status()?;
+
+    Ok(())
+}
+
+/// Lists the sensor modes of a video source through the Media2 service,
+/// the one it runs in being `enabled`
+///
+/// # Arguments
+///
+/// * `onvif_url` - The Media2 service URL (see `media2_url()`)
+/// * `video_source_token` - Token of the VideoSource
+pub async fn get_video_source_modes(
+    onvif_url: url::Url,
+    video_source_token: &str,
+) -> Result<Vec<VideoSourceMode>> {
+    let msg = Messages::GetVideoSourceModes {
+        video_source_token: video_source_token.to_string(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    Ok(root
+        .find_all("VideoSourceModes")
+        .into_iter()
+        .filter_map(VideoSourceMode::from_xml)
+        .collect())
+}
+
+/// Switches a video source to another sensor mode. Returns whether the
+/// device reboots to apply it, in which case it is unreachable for a while.
+///
+/// # Arguments
+///
+/// * `onvif_url` - The Media2 service URL (see `media2_url()`)
+/// * `video_source_token` - Token of the VideoSource
+/// * `mode_token` - Token of the VideoSourceMode
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::media;
+/// # async fn run(onvif_url: url::Url) -> anyhow::Result<()> {
+/// let modes = media::get_video_source_modes(onvif_url.clone(), "video_source_1").await?;
+///
+/// if let Some(mode) = modes.iter().find(|m| m.max_resolution == Some((2048, 1536))) {
+///     if media::set_video_source_mode(onvif_url, "video_source_1", &mode.token).await? {
+///         // The camera reboots, wait before sending it anything else
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn set_video_source_mode(
+    onvif_url: url::Url,
+    video_source_token: &str,
+    mode_token: &str,
+) -> Result<bool> {
+    let msg = Messages::SetVideoSourceMode {
+        video_source_token: video_source_token.to_string(),
+        mode_token: mode_token.to_string(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    let reboot = root
+        .find("Reboot")
+        .ok_or_else(|| anyhow!("[Media][set_video_source_mode] No Reboot in response"))?;
+
+    Ok(reboot.text.trim().eq_ignore_ascii_case("true"))
+}
+
+/// Lists the audio inputs of the device, e.g. the microphone inputs of a
+/// multi-input encoder
+///
+/// # Arguments
+///
+/// * `onvif_url` - The media service URL (see `media_url()`)
+pub async fn get_audio_sources(onvif_url: url::Url) -> Result<Vec<AudioSource>> {
+    let response = client::send(onvif_url, Messages::GetAudioSources).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    Ok(root
+        .find_all("AudioSources")
+        .into_iter()
+        .filter_map(AudioSource::from_xml)
+        .collect())
+}
+
+/// Lists the audio source configurations of the device, see
+/// MediaProfile::audio_source for the one of a profile
+///
+/// # Arguments
+///
+/// * `onvif_url` - The media service URL (see `media_url()`)
+pub async fn get_audio_source_configurations(
+    onvif_url: url::Url,
+) -> Result<Vec<AudioSourceConfig>> {
+    let response = client::send(onvif_url, Messages::GetAudioSourceConfigurations).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    Ok(root
+        .find_all("Configurations")
+        .into_iter()
+        .filter_map(AudioSourceConfig::from_xml)
+        .collect())
+}
+
+/// Attaches an audio source configuration to a media profile, replacing
+/// the one it had, which selects the microphone input the profile records
+///
+/// # Arguments
+///
+/// * `onvif_url` - The media service URL (see `media_url()`)
+/// * `profile_token` - Token of the media profile
+/// * `configuration_token` - Token of the audio source configuration
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::device::camera::Camera;
+/// # use onvif_cam_rs::media;
+/// # async fn run(camera: Camera) -> anyhow::Result<()> {
+/// let url = media::media_url(&camera)?;
+/// let inputs = media::get_audio_sources(url.clone()).await?;
+///
+/// let config = media::get_audio_source_configurations(url.clone())
+///     .await?
+///     .into_iter()
+///     .find(|c| c.source_token == inputs[1].token)
+///     .unwrap();
+/// media::add_audio_source_configuration(url, &camera.profile().unwrap().token, &config.token).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn add_audio_source_configuration(
+    onvif_url: url::Url,
+    profile_token: &str,
+    configuration_token: &str,
+) -> Result<()> {
+    let msg = Messages::AddAudioSourceConfiguration {
+        profile_token: profile_token.to_string(),
+        configuration_token: configuration_token.to_string(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    response.error_for_status()?;
+
+    Ok(())
+}
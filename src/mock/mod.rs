@@ -0,0 +1,1291 @@
+use crate::device::Device;
+use crate::discovery::{DiscoveryResponder, ResponderHandle};
+use crate::utils::xml::XmlNode;
+use crate::utils::{parse_xsd_duration, xml_escape, xsd_datetime, xsd_duration};
+
+use anyhow::{anyhow, Result};
+use chrono::{Duration as ChronoDuration, Utc};
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+/// Lifetime of a pull point subscription when it is created or renewed
+/// without a termination time
+const SUBSCRIPTION_TIME: Duration = Duration::from_secs(60);
+
+const SOAP_CONTENT_TYPE: &str = "application/soap+xml; charset=utf-8";
+const SOAP11_CONTENT_TYPE: &str = "text/xml; charset=utf-8";
+
+const SOAP_NAMESPACE: &str = "http://www.w3.org/2003/05/soap-envelope";
+const SOAP11_NAMESPACE: &str = "http://schemas.xmlsoap.org/soap/envelope/";
+
+/// Served for every snapshot URI, just the JPEG start and end markers
+const SNAPSHOT: &[u8] = &[0xFF, 0xD8, 0xFF, 0xD9];
+
+/// Longest time a PullMessages request is held open
+const MAX_PULL_WAIT: Duration = Duration::from_secs(10);
+
+/// A media profile served by the mock camera
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct MockProfile {
+    pub token:      String,
+    pub name:       String,
+    /// "H264" or "H265"
+    pub encoding:   String,
+    pub width:      u32,
+    pub height:     u32,
+}
+
+/// Misbehaviour of real devices the mock can take on, so that the way
+/// the crate copes with them can be tested without the device
+#[derive(Debug, Clone, PartialEq)]
+pub enum Quirk {
+    /// Answers these operations, e.g. "GetProfiles", with a NotAuthorized
+    /// fault unless the request carries a WS-Security UsernameToken or an
+    /// HTTP Authorization header
+    RejectUnauthenticated(Vec<String>),
+    /// Answers in SOAP 1.1 and faults SOAP 1.2 requests with
+    /// VersionMismatch, like some older NVRs
+    Soap11Only,
+    /// Cuts WS-Discovery replies after this many bytes, like devices whose
+    /// ProbeMatches do not fit the datagram
+    TruncatedDiscoveryReply(usize),
+    /// Waits this long before answering each HTTP request
+    SlowResponse(Duration),
+    /// Hands out stream URIs that expire after this long, like cameras
+    /// with short lived URIs
+    ExpiringStreamUri(Duration),
+}
+
+/// A fake ONVIF camera answering the requests build_all() sends, plus
+/// stream URIs, PTZ presets, the system clock and pull point events.
+/// Start it with start() and point the crate at MockHandle::device().
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct MockCamera {
+    /// Address the device service listens on, port 0 picks a free one
+    pub address:        SocketAddr,
+    pub manufacturer:   String,
+    pub model:          String,
+    pub firmware:       String,
+    pub serial:         String,
+    pub hardware_id:    String,
+    /// Announced in the onvif://www.onvif.org/name/ scope
+    pub name:           String,
+    pub profiles:       Vec<MockProfile>,
+    /// Stream URI handed out for every profile, `{token}` is replaced by
+    /// the profile token
+    pub stream_uri:     String,
+    /// Preset tokens and names
+    pub presets:        Vec<(String, String)>,
+    /// Answer WS-Discovery probes sent to 239.255.255.250:3702
+    pub discoverable:   bool,
+    /// Emit a motion event toggling IsMotion at this interval
+    pub motion_every:   Option<Duration>,
+    /// Known bad behaviours to reproduce, none by default
+    pub quirks:         Vec<Quirk>,
+    /// Door tokens and names, the Access Control and Door Control
+    /// services are offered when there is at least one
+    pub doors:          Vec<(String, String)>,
+}
+
+impl Default for MockCamera {
+    fn default() -> Self {
+        MockCamera {
+            address: SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+            manufacturer: "Mock".to_string(),
+            model: "MockCam 1000".to_string(),
+            firmware: "1.0.0".to_string(),
+            serial: "MOCK0001".to_string(),
+            hardware_id: "1000".to_string(),
+            name: "Mock Camera".to_string(),
+            profiles: vec![
+                MockProfile {
+                    token: "profile_1".to_string(),
+                    name: "mainStream".to_string(),
+                    encoding: "H264".to_string(),
+                    width: 1920,
+                    height: 1080,
+                },
+                MockProfile {
+                    token: "profile_2".to_string(),
+                    name: "subStream".to_string(),
+                    encoding: "H264".to_string(),
+                    width: 640,
+                    height: 360,
+                },
+            ],
+            stream_uri: "rtsp://127.0.0.1:8554/{token}".to_string(),
+            presets: vec![
+                ("1".to_string(), "Home".to_string()),
+                ("2".to_string(), "Door".to_string()),
+            ],
+            discoverable: false,
+            motion_every: None,
+            quirks: Vec::new(),
+            doors: Vec::new(),
+        }
+    }
+}
+
+/// Shared between the server tasks of a running mock
+struct State {
+    camera: MockCamera,
+    url: url::Url,
+    endpoint: String,
+    events: Mutex<Vec<String>>,
+    cursors: Mutex<HashMap<u32, usize>>,
+    /// DoorMode of each door by token
+    door_modes: Mutex<HashMap<String, &'static str>>,
+    next_subscription: AtomicU32,
+    new_event: Notify,
+}
+
+/// A running mock camera. The server stops when the handle is dropped.
+pub struct MockHandle {
+    state: Arc<State>,
+    tasks: Vec<JoinHandle<()>>,
+    responder: Option<ResponderHandle>,
+}
+
+impl Drop for MockHandle {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+impl MockCamera {
+    /// Binds the device service and starts answering requests
+    pub async fn start(self) -> Result<MockHandle> {
+        let listener = TcpListener::bind(self.address).await?;
+        let local = listener.local_addr()?;
+        let host = match local.ip().is_unspecified() {
+            true => Ipv4Addr::LOCALHOST.into(),
+            false => local.ip(),
+        };
+
+        let state = Arc::new(State {
+            url: format!(
+                "http://{}/onvif/device_service",
+                SocketAddr::new(host, local.port())
+            )
+            .parse()?,
+            endpoint: format!("urn:uuid:{}", Uuid::new_v4()),
+            events: Mutex::new(Vec::new()),
+            cursors: Mutex::new(HashMap::new()),
+            door_modes: Mutex::new(
+                self.doors
+                    .iter()
+                    .map(|(token, _)| (token.clone(), "Locked"))
+                    .collect(),
+            ),
+            next_subscription: AtomicU32::new(1),
+            new_event: Notify::new(),
+            camera: self,
+        });
+
+        let mut tasks = vec![tokio::spawn(serve(listener, state.clone()))];
+
+        let responder = match state.camera.discoverable {
+            true => {
+                let mut responder = DiscoveryResponder::new(state.url.clone())
+                    .endpoint(&state.endpoint)
+                    .scopes(state.scopes());
+                responder.types.push("tds:Device".to_string());
+
+                responder.truncate_replies = state.camera.quirks.iter().find_map(|q| match q {
+                    Quirk::TruncatedDiscoveryReply(size) => Some(*size),
+                    _ => None,
+                });
+
+                Some(responder.start().await?)
+            }
+            false => None,
+        };
+
+        if let Some(every) = state.camera.motion_every {
+            let state = state.clone();
+
+            tasks.push(tokio::spawn(async move {
+                let mut motion = false;
+
+                loop {
+                    tokio::time::sleep(every).await;
+                    motion = !motion;
+                    state.push_event(
+                        "tns1:RuleEngine/CellMotionDetector/Motion",
+                        &[("VideoSourceConfigurationToken", "video_source_1")],
+                        &[("IsMotion", if motion { "true" } else { "false" })],
+                    );
+                }
+            }));
+        }
+
+        Ok(MockHandle {
+            state,
+            tasks,
+            responder,
+        })
+    }
+}
+
+impl MockHandle {
+    /// Device service URL of the mock
+    pub fn url(&self) -> &url::Url {
+        &self.state.url
+    }
+
+    /// The mock as discovery would have found it, ready for Camera::new()
+    pub fn device(&self) -> Device {
+        Device {
+            scopes: self.state.scopes(),
+            endpoint: Some(self.state.endpoint.clone()),
+            metadata_version: Some(1),
+            xaddrs: vec![self.state.url.clone()],
+            ..Device::new(self.state.url.clone())
+        }
+    }
+
+    /// Queues an event for every pull point subscription, e.g.
+    /// `push_event("tns1:VideoSource/MotionAlarm", &[("Source", "vs1")], &[("State", "true")])`
+    pub fn push_event(&self, topic: &str, source: &[(&str, &str)], data: &[(&str, &str)]) {
+        self.state.push_event(topic, source, data);
+    }
+
+    /// Stops the server, same as dropping the handle
+    pub fn stop(self) {}
+
+    /// Stops the server after announcing WS-Discovery Bye when the mock
+    /// is discoverable
+    pub async fn shutdown(mut self) -> Result<()> {
+        match self.responder.take() {
+            Some(responder) => responder.bye().await,
+            None => Ok(()),
+        }
+    }
+}
+
+impl State {
+    fn soap11(&self) -> bool {
+        self.camera.quirks.contains(&Quirk::Soap11Only)
+    }
+
+    /// True when a RejectUnauthenticated quirk covers the operation and
+    /// the request brings no credentials
+    fn rejects(&self, request: &Request, operation: &str) -> bool {
+        let covered = self.camera.quirks.iter().any(|q| match q {
+            Quirk::RejectUnauthenticated(operations) => operations.iter().any(|o| o == operation),
+            _ => false,
+        });
+
+        covered && request.authorization.is_none() && !contains(&request.body, b"UsernameToken")
+    }
+
+    fn scopes(&self) -> Vec<String> {
+        vec![
+            "onvif://www.onvif.org/type/video_encoder".to_string(),
+            "onvif://www.onvif.org/Profile/Streaming".to_string(),
+            format!(
+                "onvif://www.onvif.org/name/{}",
+                self.camera.name.replace(' ', "%20")
+            ),
+            format!(
+                "onvif://www.onvif.org/hardware/{}",
+                self.camera.model.replace(' ', "%20")
+            ),
+        ]
+    }
+
+    fn service_url(&self, service: &str) -> String {
+        let mut url = self.url.clone();
+        url.set_path(&format!("/onvif/{service}"));
+        url.to_string()
+    }
+
+    fn push_event(&self, topic: &str, source: &[(&str, &str)], data: &[(&str, &str)]) {
+        let items = |items: &[(&str, &str)]| -> String {
+            items
+                .iter()
+                .map(|(name, value)| {
+                    format!(
+                        r#"<tt:SimpleItem Name="{}" Value="{}"/>"#,
+                        xml_escape(name),
+                        xml_escape(value)
+                    )
+                })
+                .collect()
+        };
+
+        let message = format!(
+            r#"<wsnt:NotificationMessage>
+                <wsnt:Topic Dialect="http://www.onvif.org/ver10/tev/topicExpression/ConcreteSet">{}</wsnt:Topic>
+                <wsnt:Message>
+                    <tt:Message UtcTime="{}" PropertyOperation="Changed">
+                        <tt:Source>{}</tt:Source>
+                        <tt:Data>{}</tt:Data>
+                    </tt:Message>
+                </wsnt:Message>
+            </wsnt:NotificationMessage>"#,
+            xml_escape(topic),
+            xsd_datetime(&Utc::now()),
+            items(source),
+            items(data)
+        );
+
+        self.events.lock().unwrap().push(message);
+        self.new_event.notify_waiters();
+    }
+
+    /// Changes the mode of a door and announces it on the door state topic
+    fn set_door_mode(&self, token: &str, mode: &'static str) -> Result<()> {
+        match self.door_modes.lock().unwrap().get_mut(token) {
+            Some(current) => *current = mode,
+            None => return Err(anyhow!("[Mock] No door {token}")),
+        }
+
+        self.push_event(
+            "tns1:Door/State/DoorMode",
+            &[("DoorToken", token)],
+            &[("State", mode)],
+        );
+        Ok(())
+    }
+
+    /// Events not yet pulled by a subscription, at most `limit`
+    fn take_events(&self, subscription: u32, limit: usize) -> Vec<String> {
+        let events = self.events.lock().unwrap();
+        let mut cursors = self.cursors.lock().unwrap();
+        let cursor = cursors.entry(subscription).or_insert(events.len());
+        let end = events.len().min(*cursor + limit);
+        let taken = events[*cursor..end].to_vec();
+        *cursor = end;
+
+        taken
+    }
+}
+
+async fn serve(listener: TcpListener, state: Arc<State>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tokio::spawn(handle_connection(stream, state.clone()));
+            }
+            Err(e) => warn!("[Mock][serve] Unable to accept: {e}"),
+        }
+    }
+}
+
+/// Reads one HTTP request, answers it and closes the connection
+async fn handle_connection(mut stream: TcpStream, state: Arc<State>) {
+    let request = match read_request(&mut stream).await {
+        Ok(request) => request,
+        Err(e) => {
+            debug!("{e}");
+            return;
+        }
+    };
+
+    for quirk in &state.camera.quirks {
+        if let Quirk::SlowResponse(delay) = quirk {
+            tokio::time::sleep(*delay).await;
+        }
+    }
+
+    let soap11 = state.soap11();
+    let content_type = match soap11 {
+        true => SOAP11_CONTENT_TYPE,
+        false => SOAP_CONTENT_TYPE,
+    };
+
+    let (status, content_type, reply) = match request.path.starts_with("/onvif/snapshot/") {
+        true => ("200 OK", "image/jpeg", SNAPSHOT.to_vec()),
+        false => {
+            let (status, reply) = soap_reply(&state, &request).await;
+            (status, content_type, envelope(&reply, soap11).into_bytes())
+        }
+    };
+
+    let head = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        reply.len()
+    );
+
+    let _ = stream.write_all(head.as_bytes()).await;
+    let _ = stream.write_all(&reply).await;
+    let _ = stream.shutdown().await;
+}
+
+/// The parts of an HTTP request the mock looks at
+struct Request {
+    path: String,
+    authorization: Option<String>,
+    body: Vec<u8>,
+}
+
+async fn read_request(stream: &mut TcpStream) -> Result<Request> {
+    let mut buf = Vec::with_capacity(4096);
+
+    let end = loop {
+        if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end;
+        }
+
+        if stream.read_buf(&mut buf).await? == 0 {
+            return Err(anyhow!(
+                "[Mock][read] Connection closed before request head"
+            ));
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buf[..end]).into_owned();
+    let path = head
+        .lines()
+        .next()
+        .and_then(|l| l.split_whitespace().nth(1))
+        .unwrap_or("/")
+        .to_string();
+
+    let header = |name: &str| {
+        head.lines()
+            .filter_map(|l| l.split_once(':'))
+            .find(|(n, _)| n.trim().eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.trim().to_string())
+    };
+
+    let length = header("Content-Length")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0usize);
+    let authorization = header("Authorization");
+
+    while buf.len() < end + 4 + length {
+        if stream.read_buf(&mut buf).await? == 0 {
+            return Err(anyhow!(
+                "[Mock][read] Connection closed before request body"
+            ));
+        }
+    }
+
+    Ok(Request {
+        path,
+        authorization,
+        body: buf[end + 4..end + 4 + length].to_vec(),
+    })
+}
+
+/// HTTP status and SOAP body answering a request, after the quirks that
+/// stop the request from being answered normally
+async fn soap_reply(state: &State, request: &Request) -> (&'static str, String) {
+    let soap11 = state.soap11();
+
+    if soap11 && contains(&request.body, SOAP_NAMESPACE.as_bytes()) {
+        return (
+            "500 Internal Server Error",
+            fault(true, "VersionMismatch", None, "Only SOAP 1.1 is supported"),
+        );
+    }
+
+    let operation = XmlNode::parse(&request.body)
+        .ok()
+        .and_then(|root| Some(root.find("Body")?.children.first()?.name.clone()))
+        .unwrap_or_default();
+
+    if state.rejects(request, &operation) {
+        return (
+            "400 Bad Request",
+            fault(
+                soap11,
+                "Sender",
+                Some("ter:NotAuthorized"),
+                "Sender not authorized",
+            ),
+        );
+    }
+
+    match answer(state, &request.path, &request.body).await {
+        Ok(reply) => ("200 OK", reply),
+        Err(e) => (
+            "400 Bad Request",
+            fault(
+                soap11,
+                "Sender",
+                Some("ter:ActionNotSupported"),
+                &e.to_string(),
+            ),
+        ),
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+/// The response body for a SOAP request, keyed on the first element of
+/// the SOAP body and, where services share operation names, the path
+async fn answer(state: &State, path: &str, body: &[u8]) -> Result<String> {
+    let root = XmlNode::parse(body)?;
+    let request = root
+        .find("Body")
+        .and_then(|b| b.children.first())
+        .ok_or_else(|| anyhow!("[Mock] Request has no SOAP body"))?;
+
+    let camera = &state.camera;
+    let profile = |token: Option<String>| -> Result<&MockProfile> {
+        match token {
+            Some(token) => camera
+                .profiles
+                .iter()
+                .find(|p| p.token == token)
+                .ok_or_else(|| anyhow!("[Mock] No profile {token}")),
+            None => camera
+                .profiles
+                .first()
+                .ok_or_else(|| anyhow!("[Mock] No profiles")),
+        }
+    };
+
+    let reply = match (request.name.as_str(), path) {
+        ("GetCapabilities", _) => format!(
+            "<tds:GetCapabilitiesResponse><tds:Capabilities>
+                <tt:Analytics><tt:XAddr>{}</tt:XAddr></tt:Analytics>
+                <tt:Device><tt:XAddr>{}</tt:XAddr></tt:Device>
+                <tt:Events><tt:XAddr>{}</tt:XAddr></tt:Events>
+                <tt:Imaging><tt:XAddr>{}</tt:XAddr></tt:Imaging>
+                <tt:Media><tt:XAddr>{}</tt:XAddr></tt:Media>
+                <tt:PTZ><tt:XAddr>{}</tt:XAddr></tt:PTZ>
+            </tds:Capabilities></tds:GetCapabilitiesResponse>",
+            state.service_url("analytics_service"),
+            state.url,
+            state.service_url("event_service"),
+            state.service_url("imaging_service"),
+            state.service_url("media_service"),
+            state.service_url("ptz_service"),
+        ),
+        ("GetServices", _) => {
+            let mut services = vec![
+                ("http://www.onvif.org/ver10/device/wsdl", "device_service"),
+                ("http://www.onvif.org/ver10/media/wsdl", "media_service"),
+                ("http://www.onvif.org/ver20/media/wsdl", "media2_service"),
+                ("http://www.onvif.org/ver10/events/wsdl", "event_service"),
+                ("http://www.onvif.org/ver20/ptz/wsdl", "ptz_service"),
+                ("http://www.onvif.org/ver20/imaging/wsdl", "imaging_service"),
+            ];
+
+            if !camera.doors.is_empty() {
+                services.extend([
+                    ("http://www.onvif.org/ver10/accesscontrol/wsdl", "accesscontrol_service"),
+                    ("http://www.onvif.org/ver10/doorcontrol/wsdl", "doorcontrol_service"),
+                ]);
+            }
+
+            let services: String = services
+                .iter()
+                .map(|(namespace, service)| {
+                    format!(
+                        "<tds:Service><tds:Namespace>{namespace}</tds:Namespace><tds:XAddr>{}</tds:XAddr></tds:Service>",
+                        state.service_url(service)
+                    )
+                })
+                .collect();
+
+            format!("<tds:GetServicesResponse>{services}</tds:GetServicesResponse>")
+        }
+        ("GetDeviceInformation", _) => format!(
+            "<tds:GetDeviceInformationResponse>
+                <tds:Manufacturer>{}</tds:Manufacturer>
+                <tds:Model>{}</tds:Model>
+                <tds:FirmwareVersion>{}</tds:FirmwareVersion>
+                <tds:SerialNumber>{}</tds:SerialNumber>
+                <tds:HardwareId>{}</tds:HardwareId>
+            </tds:GetDeviceInformationResponse>",
+            xml_escape(&camera.manufacturer),
+            xml_escape(&camera.model),
+            xml_escape(&camera.firmware),
+            xml_escape(&camera.serial),
+            xml_escape(&camera.hardware_id),
+        ),
+        ("GetSystemDateAndTime", _) => {
+            let now = Utc::now();
+            format!(
+                "<tds:GetSystemDateAndTimeResponse><tds:SystemDateAndTime>
+                    <tt:DateTimeType>Manual</tt:DateTimeType>
+                    <tt:DaylightSavings>true</tt:DaylightSavings>
+                    <tt:TimeZone><tt:TZ>CET-1CEST,M3.5.0,M10.5.0/3</tt:TZ></tt:TimeZone>
+                    <tt:UTCDateTime>
+                        <tt:Time><tt:Hour>{}</tt:Hour><tt:Minute>{}</tt:Minute><tt:Second>{}</tt:Second></tt:Time>
+                        <tt:Date><tt:Year>{}</tt:Year><tt:Month>{}</tt:Month><tt:Day>{}</tt:Day></tt:Date>
+                    </tt:UTCDateTime>
+                </tds:SystemDateAndTime></tds:GetSystemDateAndTimeResponse>",
+                now.format("%H"),
+                now.format("%M"),
+                now.format("%S"),
+                now.format("%Y"),
+                now.format("%m"),
+                now.format("%d"),
+            )
+        }
+        ("SetSystemDateAndTime", _) => {
+            "<tds:SetSystemDateAndTimeResponse/>".to_string()
+        }
+        ("GetProfiles", _) => {
+            let profiles: String = camera
+                .profiles
+                .iter()
+                .map(|p| {
+                    format!(
+                        r#"<trt:Profiles token="{}" fixed="true">
+                            <tt:Name>{}</tt:Name>
+                            <tt:VideoSourceConfiguration token="video_source_1">
+                                <tt:SourceToken>video_source_1</tt:SourceToken>
+                            </tt:VideoSourceConfiguration>
+                            <tt:VideoEncoderConfiguration token="vec_{}">
+                                <tt:Encoding>{}</tt:Encoding>
+                                <tt:Resolution><tt:Width>{}</tt:Width><tt:Height>{}</tt:Height></tt:Resolution>
+                                <tt:H264><tt:GovLength>50</tt:GovLength><tt:H264Profile>Main</tt:H264Profile></tt:H264>
+                            </tt:VideoEncoderConfiguration>
+                            <tt:AudioSourceConfiguration token="audio_source_1">
+                                <tt:SourceToken>audio_source_1</tt:SourceToken>
+                            </tt:AudioSourceConfiguration>
+                            <tt:AudioEncoderConfiguration token="aec_{}">
+                                <tt:Encoding>G711</tt:Encoding>
+                            </tt:AudioEncoderConfiguration>
+                            <tt:PTZConfiguration token="ptz_config_1">
+                                <tt:NodeToken>ptz_node_1</tt:NodeToken>
+                            </tt:PTZConfiguration>
+                        </trt:Profiles>"#,
+                        xml_escape(&p.token),
+                        xml_escape(&p.name),
+                        xml_escape(&p.token),
+                        xml_escape(&p.encoding),
+                        p.width,
+                        p.height,
+                        xml_escape(&p.token),
+                    )
+                })
+                .collect();
+
+            format!("<trt:GetProfilesResponse>{profiles}</trt:GetProfilesResponse>")
+        }
+        ("GetStreamUri", path) if path.contains("media2") => {
+            let profile = profile(request.child_text("ProfileToken"))?;
+            format!(
+                "<tr2:GetStreamUriResponse><tr2:Uri>{}</tr2:Uri></tr2:GetStreamUriResponse>",
+                xml_escape(&camera.stream_uri.replace("{token}", &profile.token))
+            )
+        }
+        ("GetStreamUri", _) => {
+            let profile = profile(request.child_text("ProfileToken"))?;
+            let timeout = camera.quirks.iter().find_map(|q| match q {
+                Quirk::ExpiringStreamUri(timeout) => Some(*timeout),
+                _ => None,
+            });
+
+            format!(
+                "<trt:GetStreamUriResponse><trt:MediaUri>
+                    <tt:Uri>{}</tt:Uri>
+                    <tt:InvalidAfterConnect>false</tt:InvalidAfterConnect>
+                    <tt:InvalidAfterReboot>false</tt:InvalidAfterReboot>
+                    <tt:Timeout>{}</tt:Timeout>
+                </trt:MediaUri></trt:GetStreamUriResponse>",
+                xml_escape(&camera.stream_uri.replace("{token}", &profile.token)),
+                xsd_duration(timeout.unwrap_or_default())
+            )
+        }
+        ("GetSnapshotUri", _) => {
+            let profile = profile(request.child_text("ProfileToken"))?;
+            format!(
+                "<trt:GetSnapshotUriResponse><trt:MediaUri>
+                    <tt:Uri>{}</tt:Uri>
+                    <tt:InvalidAfterConnect>false</tt:InvalidAfterConnect>
+                    <tt:InvalidAfterReboot>false</tt:InvalidAfterReboot>
+                    <tt:Timeout>PT0S</tt:Timeout>
+                </trt:MediaUri></trt:GetSnapshotUriResponse>",
+                state.service_url(&format!("snapshot/{}.jpg", profile.token))
+            )
+        }
+        ("GetVideoSources", _) => {
+            let profile = profile(None)?;
+            format!(
+                r#"<trt:GetVideoSourcesResponse><trt:VideoSources token="video_source_1">
+                    <tt:Framerate>25</tt:Framerate>
+                    <tt:Resolution><tt:Width>{}</tt:Width><tt:Height>{}</tt:Height></tt:Resolution>
+                </trt:VideoSources></trt:GetVideoSourcesResponse>"#,
+                profile.width, profile.height
+            )
+        }
+        ("GetVideoSourceConfigurations", _) => {
+            let profile = profile(None)?;
+            format!(
+                r#"<trt:GetVideoSourceConfigurationsResponse><trt:Configurations token="video_source_1">
+                    <tt:Name>videoSource</tt:Name>
+                    <tt:UseCount>{}</tt:UseCount>
+                    <tt:SourceToken>video_source_1</tt:SourceToken>
+                    <tt:Bounds x="0" y="0" width="{}" height="{}"/>
+                    <tt:Extension><tt:Rotate><tt:Mode>OFF</tt:Mode></tt:Rotate></tt:Extension>
+                </trt:Configurations></trt:GetVideoSourceConfigurationsResponse>"#,
+                camera.profiles.len(),
+                profile.width,
+                profile.height
+            )
+        }
+        ("GetVideoEncoderConfigurations", path) if !path.contains("media2") => format!(
+            "<trt:GetVideoEncoderConfigurationsResponse>{}</trt:GetVideoEncoderConfigurationsResponse>",
+            video_encoder_configurations(&camera.profiles)
+        ),
+        ("GetCompatibleVideoEncoderConfigurations", _) => {
+            profile(request.child_text("ProfileToken"))?;
+            format!(
+                "<trt:GetCompatibleVideoEncoderConfigurationsResponse>{}</trt:GetCompatibleVideoEncoderConfigurationsResponse>",
+                video_encoder_configurations(&camera.profiles)
+            )
+        }
+        ("SetVideoSourceConfiguration", _) => {
+            "<trt:SetVideoSourceConfigurationResponse/>".to_string()
+        }
+        ("GetVideoSourceModes", _) => {
+            let profile = profile(None)?;
+            format!(
+                r#"<tr2:GetVideoSourceModesResponse>
+                    <tr2:VideoSourceModes token="mode_1" Enabled="true">
+                        <tt:MaxFramerate>30</tt:MaxFramerate>
+                        <tt:MaxResolution><tt:Width>{}</tt:Width><tt:Height>{}</tt:Height></tt:MaxResolution>
+                        <tt:Encodings>H264 JPEG</tt:Encodings>
+                        <tt:Reboot>false</tt:Reboot>
+                    </tr2:VideoSourceModes>
+                </tr2:GetVideoSourceModesResponse>"#,
+                profile.width, profile.height
+            )
+        }
+        ("SetVideoSourceMode", _) => {
+            if request.child_text("VideoSourceModeToken").as_deref() != Some("mode_1") {
+                return Err(anyhow!("[Mock] No video source mode"));
+            }
+
+            "<tr2:SetVideoSourceModeResponse><tr2:Reboot>false</tr2:Reboot></tr2:SetVideoSourceModeResponse>".to_string()
+        }
+        ("GetAudioSources", _) => r#"<trt:GetAudioSourcesResponse>
+                <trt:AudioSources token="audio_source_1"><tt:Channels>1</tt:Channels></trt:AudioSources>
+            </trt:GetAudioSourcesResponse>"#
+            .to_string(),
+        ("GetAudioSourceConfigurations", _) => format!(
+            r#"<trt:GetAudioSourceConfigurationsResponse><trt:Configurations token="audio_source_1">
+                <tt:Name>audioSource</tt:Name>
+                <tt:UseCount>{}</tt:UseCount>
+                <tt:SourceToken>audio_source_1</tt:SourceToken>
+            </trt:Configurations></trt:GetAudioSourceConfigurationsResponse>"#,
+            camera.profiles.len()
+        ),
+        ("AddAudioSourceConfiguration", _) => {
+            profile(request.child_text("ProfileToken"))?;
+            "<trt:AddAudioSourceConfigurationResponse/>".to_string()
+        }
+        ("GetMetadataConfigurations", _) => {
+            r#"<trt:GetMetadataConfigurationsResponse><trt:Configurations token="metadata_1">
+                <tt:Name>metadata</tt:Name>
+                <tt:UseCount>0</tt:UseCount>
+                <tt:Analytics>false</tt:Analytics>
+                <tt:SessionTimeout>PT60S</tt:SessionTimeout>
+            </trt:Configurations></trt:GetMetadataConfigurationsResponse>"#
+                .to_string()
+        }
+        ("AddMetadataConfiguration", _) => {
+            profile(request.child_text("ProfileToken"))?;
+            "<trt:AddMetadataConfigurationResponse/>".to_string()
+        }
+        ("SetMetadataConfiguration", _) => "<trt:SetMetadataConfigurationResponse/>".to_string(),
+        ("StartMulticastStreaming", _) => {
+            profile(request.child_text("ProfileToken"))?;
+            "<trt:StartMulticastStreamingResponse/>".to_string()
+        }
+        ("StopMulticastStreaming", _) => {
+            profile(request.child_text("ProfileToken"))?;
+            "<trt:StopMulticastStreamingResponse/>".to_string()
+        }
+        ("GetServiceCapabilities", path) if path.contains("media2") => {
+            r#"<tr2:GetServiceCapabilitiesResponse><tr2:Capabilities SnapshotUri="false">
+                <tr2:ProfileCapabilities MaximumNumberOfProfiles="4"/>
+                <tr2:StreamingCapabilities RTSPStreaming="true" RTPMulticast="false" RTP_RTSP_TCP="true" SecureRTSPStreaming="false"/>
+            </tr2:Capabilities></tr2:GetServiceCapabilitiesResponse>"#
+                .to_string()
+        }
+        ("GetServiceCapabilities", _) => {
+            r#"<tds:GetServiceCapabilitiesResponse><tds:Capabilities WSSubscriptionPolicySupport="false" WSPausableSubscriptionManagerInterfaceSupport="false" MaxNotificationProducers="4" MaxPullPoints="4" PersistentNotificationStorage="false"/></tds:GetServiceCapabilitiesResponse>"#
+                .to_string()
+        }
+        ("GetPresets", _) => {
+            let presets: String = camera
+                .presets
+                .iter()
+                .map(|(token, name)| {
+                    format!(
+                        r#"<tptz:Preset token="{}"><tt:Name>{}</tt:Name></tptz:Preset>"#,
+                        xml_escape(token),
+                        xml_escape(name)
+                    )
+                })
+                .collect();
+
+            format!("<tptz:GetPresetsResponse>{presets}</tptz:GetPresetsResponse>")
+        }
+        ("GotoPreset", _) => {
+            let token = request.child_text("PresetToken").unwrap_or_default();
+
+            if !camera.presets.iter().any(|(t, _)| *t == token) {
+                return Err(anyhow!("[Mock] No preset {token}"));
+            }
+
+            "<tptz:GotoPresetResponse/>".to_string()
+        }
+        ("Move", _) => "<timg:MoveResponse/>".to_string(),
+        ("Stop", path) if path.contains("imaging") => "<timg:StopResponse/>".to_string(),
+        ("GetMoveOptions", _) => {
+            r#"<timg:GetMoveOptionsResponse><timg:MoveOptions>
+                <tt:Absolute>
+                    <tt:Position><tt:Min>0</tt:Min><tt:Max>1</tt:Max></tt:Position>
+                    <tt:Speed><tt:Min>0</tt:Min><tt:Max>1</tt:Max></tt:Speed>
+                </tt:Absolute>
+                <tt:Continuous>
+                    <tt:Speed><tt:Min>-1</tt:Min><tt:Max>1</tt:Max></tt:Speed>
+                </tt:Continuous>
+            </timg:MoveOptions></timg:GetMoveOptionsResponse>"#
+                .to_string()
+        }
+        ("GetOptions", _) => {
+            r#"<timg:GetOptionsResponse><timg:ImagingOptions>
+                <tt:BacklightCompensation>
+                    <tt:Mode>OFF</tt:Mode>
+                    <tt:Mode>ON</tt:Mode>
+                    <tt:Level><tt:Min>0</tt:Min><tt:Max>100</tt:Max></tt:Level>
+                </tt:BacklightCompensation>
+                <tt:Brightness><tt:Min>0</tt:Min><tt:Max>255</tt:Max></tt:Brightness>
+                <tt:ColorSaturation><tt:Min>0</tt:Min><tt:Max>255</tt:Max></tt:ColorSaturation>
+                <tt:Contrast><tt:Min>0</tt:Min><tt:Max>255</tt:Max></tt:Contrast>
+                <tt:Exposure>
+                    <tt:Mode>AUTO</tt:Mode>
+                    <tt:Mode>MANUAL</tt:Mode>
+                    <tt:ExposureTime><tt:Min>10</tt:Min><tt:Max>40000</tt:Max></tt:ExposureTime>
+                    <tt:Gain><tt:Min>0</tt:Min><tt:Max>100</tt:Max></tt:Gain>
+                </tt:Exposure>
+                <tt:Focus>
+                    <tt:AutoFocusModes>AUTO</tt:AutoFocusModes>
+                    <tt:AutoFocusModes>MANUAL</tt:AutoFocusModes>
+                    <tt:DefaultSpeed><tt:Min>0</tt:Min><tt:Max>1</tt:Max></tt:DefaultSpeed>
+                </tt:Focus>
+                <tt:IrCutFilterModes>ON</tt:IrCutFilterModes>
+                <tt:IrCutFilterModes>OFF</tt:IrCutFilterModes>
+                <tt:IrCutFilterModes>AUTO</tt:IrCutFilterModes>
+                <tt:Sharpness><tt:Min>0</tt:Min><tt:Max>15</tt:Max></tt:Sharpness>
+                <tt:WideDynamicRange>
+                    <tt:Mode>OFF</tt:Mode>
+                    <tt:Mode>ON</tt:Mode>
+                    <tt:Level><tt:Min>0</tt:Min><tt:Max>100</tt:Max></tt:Level>
+                </tt:WideDynamicRange>
+                <tt:WhiteBalance>
+                    <tt:Mode>AUTO</tt:Mode>
+                    <tt:Mode>MANUAL</tt:Mode>
+                    <tt:YrGain><tt:Min>0</tt:Min><tt:Max>255</tt:Max></tt:YrGain>
+                    <tt:YbGain><tt:Min>0</tt:Min><tt:Max>255</tt:Max></tt:YbGain>
+                </tt:WhiteBalance>
+            </timg:ImagingOptions></timg:GetOptionsResponse>"#
+                .to_string()
+        }
+        ("GetImagingSettings", _) => {
+            r#"<timg:GetImagingSettingsResponse><timg:ImagingSettings>
+                <tt:Brightness>128</tt:Brightness>
+                <tt:ColorSaturation>128</tt:ColorSaturation>
+                <tt:Contrast>128</tt:Contrast>
+                <tt:IrCutFilter>AUTO</tt:IrCutFilter>
+                <tt:Sharpness>8</tt:Sharpness>
+            </timg:ImagingSettings></timg:GetImagingSettingsResponse>"#
+                .to_string()
+        }
+        ("SetImagingSettings", _) => "<timg:SetImagingSettingsResponse/>".to_string(),
+        ("ContinuousMove", _) => "<tptz:ContinuousMoveResponse/>".to_string(),
+        ("Stop", _) => "<tptz:StopResponse/>".to_string(),
+        ("GetNodes", _) => format!(
+            r#"<tptz:GetNodesResponse><tptz:PTZNode token="ptz_node_1" FixedHomePosition="false">
+                <tt:Name>PTZ</tt:Name>
+                <tt:SupportedPTZSpaces>
+                    <tt:AbsolutePanTiltPositionSpace>
+                        <tt:URI>http://www.onvif.org/ver10/tptz/PanTiltSpaces/PositionGenericSpace</tt:URI>
+                        <tt:XRange><tt:Min>-1</tt:Min><tt:Max>1</tt:Max></tt:XRange>
+                        <tt:YRange><tt:Min>-1</tt:Min><tt:Max>1</tt:Max></tt:YRange>
+                    </tt:AbsolutePanTiltPositionSpace>
+                    <tt:AbsolutePanTiltPositionSpace>
+                        <tt:URI>http://www.onvif.org/ver10/tptz/PanTiltSpaces/SphericalPositionSpaceDegrees</tt:URI>
+                        <tt:XRange><tt:Min>-170</tt:Min><tt:Max>170</tt:Max></tt:XRange>
+                        <tt:YRange><tt:Min>-90</tt:Min><tt:Max>0</tt:Max></tt:YRange>
+                    </tt:AbsolutePanTiltPositionSpace>
+                    <tt:AbsoluteZoomPositionSpace>
+                        <tt:URI>http://www.onvif.org/ver10/tptz/ZoomSpaces/PositionGenericSpace</tt:URI>
+                        <tt:XRange><tt:Min>0</tt:Min><tt:Max>1</tt:Max></tt:XRange>
+                    </tt:AbsoluteZoomPositionSpace>
+                    <tt:RelativePanTiltTranslationSpace>
+                        <tt:URI>http://www.onvif.org/ver10/tptz/PanTiltSpaces/TranslationGenericSpace</tt:URI>
+                        <tt:XRange><tt:Min>-1</tt:Min><tt:Max>1</tt:Max></tt:XRange>
+                        <tt:YRange><tt:Min>-1</tt:Min><tt:Max>1</tt:Max></tt:YRange>
+                    </tt:RelativePanTiltTranslationSpace>
+                    <tt:ContinuousPanTiltVelocitySpace>
+                        <tt:URI>http://www.onvif.org/ver10/tptz/PanTiltSpaces/VelocityGenericSpace</tt:URI>
+                        <tt:XRange><tt:Min>-1</tt:Min><tt:Max>1</tt:Max></tt:XRange>
+                        <tt:YRange><tt:Min>-1</tt:Min><tt:Max>1</tt:Max></tt:YRange>
+                    </tt:ContinuousPanTiltVelocitySpace>
+                    <tt:ContinuousZoomVelocitySpace>
+                        <tt:URI>http://www.onvif.org/ver10/tptz/ZoomSpaces/VelocityGenericSpace</tt:URI>
+                        <tt:XRange><tt:Min>-1</tt:Min><tt:Max>1</tt:Max></tt:XRange>
+                    </tt:ContinuousZoomVelocitySpace>
+                    <tt:PanTiltSpeedSpace>
+                        <tt:URI>http://www.onvif.org/ver10/tptz/PanTiltSpaces/GenericSpeedSpace</tt:URI>
+                        <tt:XRange><tt:Min>0</tt:Min><tt:Max>1</tt:Max></tt:XRange>
+                    </tt:PanTiltSpeedSpace>
+                </tt:SupportedPTZSpaces>
+                <tt:MaximumNumberOfPresets>{}</tt:MaximumNumberOfPresets>
+                <tt:HomeSupported>true</tt:HomeSupported>
+            </tptz:PTZNode></tptz:GetNodesResponse>"#,
+            camera.presets.len().max(16)
+        ),
+        ("GetConfigurations", path) if path.contains("ptz") => {
+            r#"<tptz:GetConfigurationsResponse><tptz:PTZConfiguration token="ptz_config_1">
+                <tt:Name>PTZ</tt:Name>
+                <tt:UseCount>1</tt:UseCount>
+                <tt:NodeToken>ptz_node_1</tt:NodeToken>
+                <tt:DefaultPTZSpeed><tt:PanTilt x="0.5" y="0.5"/><tt:Zoom x="0.5"/></tt:DefaultPTZSpeed>
+                <tt:DefaultPTZTimeout>PT5S</tt:DefaultPTZTimeout>
+                <tt:PanTiltLimits><tt:Range>
+                    <tt:URI>http://www.onvif.org/ver10/tptz/PanTiltSpaces/PositionGenericSpace</tt:URI>
+                    <tt:XRange><tt:Min>-1</tt:Min><tt:Max>1</tt:Max></tt:XRange>
+                    <tt:YRange><tt:Min>-1</tt:Min><tt:Max>1</tt:Max></tt:YRange>
+                </tt:Range></tt:PanTiltLimits>
+            </tptz:PTZConfiguration></tptz:GetConfigurationsResponse>"#
+                .to_string()
+        }
+        ("SetConfiguration", path) if path.contains("ptz") => {
+            let token = request
+                .find("PTZConfiguration")
+                .and_then(|c| c.attr("token"))
+                .unwrap_or_default();
+
+            if token != "ptz_config_1" {
+                return Err(anyhow!("[Mock] No PTZ configuration {token}"));
+            }
+
+            "<tptz:SetConfigurationResponse/>".to_string()
+        }
+        ("GetConfigurationOptions", path) if path.contains("ptz") => {
+            let token = request.child_text("ConfigurationToken").unwrap_or_default();
+
+            if token != "ptz_config_1" {
+                return Err(anyhow!("[Mock] No PTZ configuration {token}"));
+            }
+
+            r#"<tptz:GetConfigurationOptionsResponse><tptz:PTZConfigurationOptions>
+                <tt:Spaces>
+                    <tt:AbsolutePanTiltPositionSpace>
+                        <tt:URI>http://www.onvif.org/ver10/tptz/PanTiltSpaces/PositionGenericSpace</tt:URI>
+                        <tt:XRange><tt:Min>-1</tt:Min><tt:Max>1</tt:Max></tt:XRange>
+                        <tt:YRange><tt:Min>-1</tt:Min><tt:Max>1</tt:Max></tt:YRange>
+                    </tt:AbsolutePanTiltPositionSpace>
+                    <tt:AbsolutePanTiltPositionSpace>
+                        <tt:URI>http://www.onvif.org/ver10/tptz/PanTiltSpaces/SphericalPositionSpaceDegrees</tt:URI>
+                        <tt:XRange><tt:Min>-170</tt:Min><tt:Max>170</tt:Max></tt:XRange>
+                        <tt:YRange><tt:Min>-90</tt:Min><tt:Max>0</tt:Max></tt:YRange>
+                    </tt:AbsolutePanTiltPositionSpace>
+                    <tt:AbsoluteZoomPositionSpace>
+                        <tt:URI>http://www.onvif.org/ver10/tptz/ZoomSpaces/PositionGenericSpace</tt:URI>
+                        <tt:XRange><tt:Min>0</tt:Min><tt:Max>1</tt:Max></tt:XRange>
+                    </tt:AbsoluteZoomPositionSpace>
+                    <tt:RelativePanTiltTranslationSpace>
+                        <tt:URI>http://www.onvif.org/ver10/tptz/PanTiltSpaces/TranslationGenericSpace</tt:URI>
+                        <tt:XRange><tt:Min>-1</tt:Min><tt:Max>1</tt:Max></tt:XRange>
+                        <tt:YRange><tt:Min>-1</tt:Min><tt:Max>1</tt:Max></tt:YRange>
+                    </tt:RelativePanTiltTranslationSpace>
+                    <tt:ContinuousPanTiltVelocitySpace>
+                        <tt:URI>http://www.onvif.org/ver10/tptz/PanTiltSpaces/VelocityGenericSpace</tt:URI>
+                        <tt:XRange><tt:Min>-1</tt:Min><tt:Max>1</tt:Max></tt:XRange>
+                        <tt:YRange><tt:Min>-1</tt:Min><tt:Max>1</tt:Max></tt:YRange>
+                    </tt:ContinuousPanTiltVelocitySpace>
+                    <tt:ContinuousZoomVelocitySpace>
+                        <tt:URI>http://www.onvif.org/ver10/tptz/ZoomSpaces/VelocityGenericSpace</tt:URI>
+                        <tt:XRange><tt:Min>-1</tt:Min><tt:Max>1</tt:Max></tt:XRange>
+                    </tt:ContinuousZoomVelocitySpace>
+                    <tt:PanTiltSpeedSpace>
+                        <tt:URI>http://www.onvif.org/ver10/tptz/PanTiltSpaces/GenericSpeedSpace</tt:URI>
+                        <tt:XRange><tt:Min>0</tt:Min><tt:Max>1</tt:Max></tt:XRange>
+                    </tt:PanTiltSpeedSpace>
+                </tt:Spaces>
+                <tt:PTZTimeout><tt:Min>PT1S</tt:Min><tt:Max>PT60S</tt:Max></tt:PTZTimeout>
+            </tptz:PTZConfigurationOptions></tptz:GetConfigurationOptionsResponse>"#
+                .to_string()
+        }
+        ("AbsoluteMove", _) => "<tptz:AbsoluteMoveResponse/>".to_string(),
+        ("RelativeMove", _) => "<tptz:RelativeMoveResponse/>".to_string(),
+        ("CreatePullPointSubscription", _) => {
+            let id = state.next_subscription.fetch_add(1, Ordering::Relaxed);
+            state.take_events(id, 0);
+            let now = Utc::now();
+
+            format!(
+                "<tev:CreatePullPointSubscriptionResponse>
+                    <tev:SubscriptionReference><wsa:Address>{}</wsa:Address></tev:SubscriptionReference>
+                    <wsnt:CurrentTime>{}</wsnt:CurrentTime>
+                    <wsnt:TerminationTime>{}</wsnt:TerminationTime>
+                </tev:CreatePullPointSubscriptionResponse>",
+                state.service_url(&format!("subscription/{id}")),
+                xsd_datetime(&now),
+                xsd_datetime(&(now + termination(None))),
+            )
+        }
+        ("PullMessages", path) => {
+            let limit = request
+                .child_text("MessageLimit")
+                .and_then(|l| l.parse().ok())
+                .unwrap_or(10);
+            let wait = request
+                .child_text("Timeout")
+                .as_deref()
+                .and_then(parse_xsd_duration)
+                .unwrap_or_default()
+                .min(MAX_PULL_WAIT);
+
+            // Pulls on the event service itself, as build_all() sends, get
+            // an immediate empty answer
+            let messages = match subscription_id(path) {
+                Some(id) => pull(state, id, limit, wait).await,
+                None => Vec::new(),
+            };
+            let now = Utc::now();
+
+            format!(
+                "<tev:PullMessagesResponse>
+                    <tev:CurrentTime>{}</tev:CurrentTime>
+                    <tev:TerminationTime>{}</tev:TerminationTime>
+                    {}
+                </tev:PullMessagesResponse>",
+                xsd_datetime(&now),
+                xsd_datetime(&(now + termination(None))),
+                messages.concat()
+            )
+        }
+        ("Renew", _) => {
+            let now = Utc::now();
+            let requested = request
+                .child_text("TerminationTime")
+                .as_deref()
+                .and_then(parse_xsd_duration);
+
+            format!(
+                "<wsnt:RenewResponse>
+                    <wsnt:TerminationTime>{}</wsnt:TerminationTime>
+                    <wsnt:CurrentTime>{}</wsnt:CurrentTime>
+                </wsnt:RenewResponse>",
+                xsd_datetime(&(now + termination(requested))),
+                xsd_datetime(&now),
+            )
+        }
+        ("Unsubscribe", path) => {
+            if let Some(id) = subscription_id(path) {
+                state.cursors.lock().unwrap().remove(&id);
+            }
+
+            "<wsnt:UnsubscribeResponse/>".to_string()
+        }
+        // One access point per door, all enabled
+        ("GetAccessPointInfoList", _) => {
+            let access_points: String = camera
+                .doors
+                .iter()
+                .map(|(token, name)| {
+                    format!(
+                        r#"<tac:AccessPointInfo token="ap_{}">
+                            <tac:Name>{}</tac:Name>
+                            <tac:EntityType>tdc:Door</tac:EntityType>
+                            <tac:Entity>{}</tac:Entity>
+                        </tac:AccessPointInfo>"#,
+                        xml_escape(token),
+                        xml_escape(name),
+                        xml_escape(token),
+                    )
+                })
+                .collect();
+
+            format!("<tac:GetAccessPointInfoListResponse>{access_points}</tac:GetAccessPointInfoListResponse>")
+        }
+        ("GetAccessPointState", _) => {
+            "<tac:GetAccessPointStateResponse><tac:AccessPointState><tac:Enabled>true</tac:Enabled></tac:AccessPointState></tac:GetAccessPointStateResponse>"
+                .to_string()
+        }
+        ("EnableAccessPoint", _) => "<tac:EnableAccessPointResponse/>".to_string(),
+        ("DisableAccessPoint", _) => "<tac:DisableAccessPointResponse/>".to_string(),
+        ("GetDoorInfoList", _) => {
+            let doors: String = camera
+                .doors
+                .iter()
+                .map(|(token, name)| {
+                    format!(
+                        r#"<tdc:DoorInfo token="{}"><tdc:Name>{}</tdc:Name></tdc:DoorInfo>"#,
+                        xml_escape(token),
+                        xml_escape(name)
+                    )
+                })
+                .collect();
+
+            format!("<tdc:GetDoorInfoListResponse>{doors}</tdc:GetDoorInfoListResponse>")
+        }
+        ("GetDoorState", _) => {
+            let token = request.child_text("Token").unwrap_or_default();
+            let mode = *state
+                .door_modes
+                .lock()
+                .unwrap()
+                .get(&token)
+                .ok_or_else(|| anyhow!("[Mock] No door {token}"))?;
+
+            format!(
+                "<tdc:GetDoorStateResponse><tdc:DoorState>
+                    <tdc:DoorPhysicalState>Closed</tdc:DoorPhysicalState>
+                    <tdc:LockPhysicalState>{}</tdc:LockPhysicalState>
+                    <tdc:Alarm>Normal</tdc:Alarm>
+                    <tdc:DoorMode>{mode}</tdc:DoorMode>
+                </tdc:DoorState></tdc:GetDoorStateResponse>",
+                match mode {
+                    "Locked" => "Locked",
+                    _ => "Unlocked",
+                }
+            )
+        }
+        // The door stays in Accessed mode until locked again, the mock does
+        // not time momentary access out
+        ("AccessDoor", _) => {
+            state.set_door_mode(&request.child_text("Token").unwrap_or_default(), "Accessed")?;
+            "<tdc:AccessDoorResponse/>".to_string()
+        }
+        ("LockDoor", _) => {
+            state.set_door_mode(&request.child_text("Token").unwrap_or_default(), "Locked")?;
+            "<tdc:LockDoorResponse/>".to_string()
+        }
+        ("UnlockDoor", _) => {
+            state.set_door_mode(&request.child_text("Token").unwrap_or_default(), "Unlocked")?;
+            "<tdc:UnlockDoorResponse/>".to_string()
+        }
+        (name, _) => return Err(anyhow!("[Mock] {name} is not supported")),
+    };
+
+    Ok(reply)
+}
+
+/// A Configurations element for the encoder of each profile
+fn video_encoder_configurations(profiles: &[MockProfile]) -> String {
+    profiles
+        .iter()
+        .map(|p| {
+            format!(
+                r#"<trt:Configurations token="vec_{}">
+                    <tt:Name>{}</tt:Name>
+                    <tt:UseCount>1</tt:UseCount>
+                    <tt:Encoding>{}</tt:Encoding>
+                    <tt:Resolution><tt:Width>{}</tt:Width><tt:Height>{}</tt:Height></tt:Resolution>
+                    <tt:H264><tt:GovLength>50</tt:GovLength><tt:H264Profile>Main</tt:H264Profile></tt:H264>
+                </trt:Configurations>"#,
+                xml_escape(&p.token),
+                xml_escape(&p.name),
+                xml_escape(&p.encoding),
+                p.width,
+                p.height,
+            )
+        })
+        .collect()
+}
+
+fn subscription_id(path: &str) -> Option<u32> {
+    path.strip_prefix("/onvif/subscription/")?.parse().ok()
+}
+
+fn termination(requested: Option<Duration>) -> ChronoDuration {
+    ChronoDuration::from_std(requested.unwrap_or(SUBSCRIPTION_TIME)).unwrap_or_default()
+}
+
+/// Waits up to `wait` for events of a subscription
+async fn pull(state: &State, subscription: u32, limit: usize, wait: Duration) -> Vec<String> {
+    let deadline = tokio::time::Instant::now() + wait;
+
+    loop {
+        let notified = state.new_event.notified();
+        let messages = state.take_events(subscription, limit);
+
+        if !messages.is_empty() {
+            return messages;
+        }
+
+        if tokio::time::timeout_at(deadline, notified).await.is_err() {
+            return Vec::new();
+        }
+    }
+}
+
+fn envelope(body: &str, soap11: bool) -> String {
+    let namespace = match soap11 {
+        true => SOAP11_NAMESPACE,
+        false => SOAP_NAMESPACE,
+    };
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<SOAP-ENV:Envelope xmlns:SOAP-ENV="{namespace}"
+    xmlns:wsa="http://www.w3.org/2005/08/addressing"
+    xmlns:tac="http://www.onvif.org/ver10/accesscontrol/wsdl"
+    xmlns:tdc="http://www.onvif.org/ver10/doorcontrol/wsdl"
+    xmlns:tds="http://www.onvif.org/ver10/device/wsdl"
+    xmlns:tev="http://www.onvif.org/ver10/events/wsdl"
+    xmlns:timg="http://www.onvif.org/ver20/imaging/wsdl"
+    xmlns:tptz="http://www.onvif.org/ver20/ptz/wsdl"
+    xmlns:tr2="http://www.onvif.org/ver20/media/wsdl"
+    xmlns:trt="http://www.onvif.org/ver10/media/wsdl"
+    xmlns:tt="http://www.onvif.org/ver10/schema"
+    xmlns:wsnt="http://docs.oasis-open.org/wsn/b-2"
+    xmlns:tns1="http://www.onvif.org/ver10/topics">
+<SOAP-ENV:Body>{body}</SOAP-ENV:Body>
+</SOAP-ENV:Envelope>"#
+    )
+}
+
+/// A SOAP 1.2 fault, or a SOAP 1.1 one with the subcode, when given, as
+/// the faultcode
+fn fault(soap11: bool, code: &str, subcode: Option<&str>, reason: &str) -> String {
+    if soap11 {
+        let code = subcode
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| format!("SOAP-ENV:{code}"));
+
+        return format!(
+            "<SOAP-ENV:Fault><faultcode>{code}</faultcode><faultstring>{}</faultstring></SOAP-ENV:Fault>",
+            xml_escape(reason)
+        );
+    }
+
+    let subcode = subcode
+        .map(|s| {
+            format!("<SOAP-ENV:Subcode><SOAP-ENV:Value>{s}</SOAP-ENV:Value></SOAP-ENV:Subcode>")
+        })
+        .unwrap_or_default();
+
+    format!(
+        r#"<SOAP-ENV:Fault>
+            <SOAP-ENV:Code>
+                <SOAP-ENV:Value>SOAP-ENV:{code}</SOAP-ENV:Value>
+                {subcode}
+            </SOAP-ENV:Code>
+            <SOAP-ENV:Reason><SOAP-ENV:Text xml:lang="en">{}</SOAP-ENV:Text></SOAP-ENV:Reason>
+        </SOAP-ENV:Fault>"#,
+        xml_escape(reason)
+    )
+}
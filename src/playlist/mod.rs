@@ -0,0 +1,121 @@
+use crate::device::camera::Camera;
+
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+/// File formats a playlist can be written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaylistFormat {
+    /// Extended M3U, opened by VLC, mpv and most media servers
+    M3u,
+    /// JSON array of PlaylistEntry
+    Json,
+}
+
+impl PlaylistFormat {
+    /// Picks the format from a `.m3u`, `.m3u8` or `.json` extension
+    pub fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("m3u") | Some("m3u8") => Ok(PlaylistFormat::M3u),
+            Some("json") => Ok(PlaylistFormat::Json),
+            _ => Err(anyhow!(
+                "[Playlist][from_path] Unknown playlist format for {}",
+                path.display()
+            )),
+        }
+    }
+}
+
+/// One stream of the playlist
+#[derive(Debug, Clone, Serialize)]
+#[rustfmt::skip]
+pub struct PlaylistEntry {
+    pub name:            String,
+    pub uri:             String,
+    pub onvif_url:       String,
+    pub manufacturer:    Option<String>,
+    pub model:           Option<String>,
+    pub serial:          Option<String>,
+    pub labels:          BTreeSet<String>,
+}
+
+impl PlaylistEntry {
+    /// Entry for a built camera, None when it has no stream URI. The name
+    /// comes from the ONVIF name scope, then the labels, the model and
+    /// finally the host.
+    pub fn from_camera(camera: &Camera) -> Option<Self> {
+        let uri = camera.stream.uri.clone()?;
+        let info = camera.device_info();
+        let host = camera.device().url_onvif.host_str().unwrap_or_default();
+
+        let name = camera
+            .scope_name()
+            .or_else(|| {
+                (!camera.labels().is_empty()).then(|| {
+                    camera
+                        .labels()
+                        .iter()
+                        .cloned()
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                })
+            })
+            .or_else(|| info.model.as_ref().map(|model| format!("{model} ({host})")))
+            .unwrap_or_else(|| host.to_string());
+
+        Some(PlaylistEntry {
+            name,
+            uri,
+            onvif_url: camera.device().url_onvif.to_string(),
+            manufacturer: info.manufacturer.clone(),
+            model: info.model.clone(),
+            serial: info.serial_num.clone(),
+            labels: camera.labels().clone(),
+        })
+    }
+}
+
+/// Entries for every camera with a stream URI, in the given order
+pub fn entries(cameras: &[Camera]) -> Vec<PlaylistEntry> {
+    cameras
+        .iter()
+        .filter_map(PlaylistEntry::from_camera)
+        .collect()
+}
+
+/// Extended M3U playlist of the cameras' streams
+pub fn to_m3u(cameras: &[Camera]) -> String {
+    let mut playlist = String::from("#EXTM3U\n");
+
+    for entry in entries(cameras) {
+        // Commas and line breaks would end the title early
+        let title = entry.name.replace([',', '\r', '\n'], " ");
+        playlist.push_str(&format!("#EXTINF:-1,{title}\n{}\n", entry.uri));
+    }
+
+    playlist
+}
+
+/// JSON playlist of the cameras' streams, with their device details
+pub fn to_json(cameras: &[Camera]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&entries(cameras))?)
+}
+
+/// Writes the playlist in the format given by the file extension,
+/// see PlaylistFormat::from_path(). Returns the number of streams written.
+pub fn save(cameras: &[Camera], path: impl AsRef<Path>) -> Result<usize> {
+    let path = path.as_ref();
+
+    let contents = match PlaylistFormat::from_path(path)? {
+        PlaylistFormat::M3u => to_m3u(cameras),
+        PlaylistFormat::Json => to_json(cameras)?,
+    };
+
+    fs::write(path, contents)
+        .with_context(|| format!("[Playlist][save] Unable to write {}", path.display()))?;
+
+    Ok(entries(cameras).len())
+}
@@ -0,0 +1,168 @@
+use crate::client::{self, Messages};
+use crate::utils::parse_soap;
+
+use anyhow::Result;
+use log::info;
+use std::time::Duration;
+
+/// Direction for a provisioning pan move
+#[derive(Debug, Clone, Copy)]
+pub enum PanDirection {
+    Left,
+    Right,
+}
+
+/// Direction for a provisioning tilt move
+#[derive(Debug, Clone, Copy)]
+pub enum TiltDirection {
+    Up,
+    Down,
+}
+
+/// Direction for a provisioning focus move
+#[derive(Debug, Clone, Copy)]
+pub enum FocusDirection {
+    Near,
+    Far,
+    Auto,
+}
+
+impl PanDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PanDirection::Left => "left",
+            PanDirection::Right => "right",
+        }
+    }
+}
+
+impl TiltDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TiltDirection::Up => "up",
+            TiltDirection::Down => "down",
+        }
+    }
+}
+
+impl FocusDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FocusDirection::Near => "near",
+            FocusDirection::Far => "far",
+            FocusDirection::Auto => "auto",
+        }
+    }
+}
+
+/// Lifetime movement counters reported by the device for a video source.
+/// Each counter is the number of seconds the actuator has been moved.
+#[derive(Default, Debug)]
+#[rustfmt::skip]
+pub struct Usage {
+    pub pan:     Option<u64>,
+    pub tilt:    Option<u64>,
+    pub zoom:    Option<u64>,
+    pub roll:    Option<u64>,
+    pub focus:   Option<u64>,
+}
+
+/// Moves the pan actuator of a video source during installation.
+/// The move stops after `timeout`, on `stop()`, or when the device
+/// reaches its mechanical limit.
+///
+/// # Arguments
+///
+/// * `onvif_url` - The Provisioning service URL (see `Services::provisioning`)
+/// * `video_source` - Token of the video source to adjust
+/// * `direction` - Which way to pan
+/// * `timeout` - Optional duration after which the move stops
+#[cfg(feature = "http")]
+pub async fn pan_move(
+    onvif_url: url::Url,
+    video_source: &str,
+    direction: PanDirection,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    let msg = Messages::PanMove {
+        video_source: video_source.to_string(),
+        direction,
+        timeout,
+    };
+
+    client::send(onvif_url, msg).await?.error_for_status()?;
+    Ok(())
+}
+
+/// Moves the tilt actuator of a video source during installation
+#[cfg(feature = "http")]
+pub async fn tilt_move(
+    onvif_url: url::Url,
+    video_source: &str,
+    direction: TiltDirection,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    let msg = Messages::TiltMove {
+        video_source: video_source.to_string(),
+        direction,
+        timeout,
+    };
+
+    client::send(onvif_url, msg).await?.error_for_status()?;
+    Ok(())
+}
+
+/// Moves the focus lens of a video source during installation
+#[cfg(feature = "http")]
+pub async fn focus_move(
+    onvif_url: url::Url,
+    video_source: &str,
+    direction: FocusDirection,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    let msg = Messages::FocusMove {
+        video_source: video_source.to_string(),
+        direction,
+        timeout,
+    };
+
+    client::send(onvif_url, msg).await?.error_for_status()?;
+    Ok(())
+}
+
+/// Stops every provisioning move in progress on a video source
+#[cfg(feature = "http")]
+pub async fn stop(onvif_url: url::Url, video_source: &str) -> Result<()> {
+    let msg = Messages::ProvisioningStop {
+        video_source: video_source.to_string(),
+    };
+
+    client::send(onvif_url, msg).await?.error_for_status()?;
+    Ok(())
+}
+
+/// Returns the lifetime usage counters of the actuators of a video source
+#[rustfmt::skip]
+#[cfg(feature = "http")]
+pub async fn get_usage(onvif_url: url::Url, video_source: &str) -> Result<Usage> {
+    let msg      = Messages::GetUsage { video_source: video_source.to_string() };
+    let response = client::send(onvif_url, msg).await?.error_for_status()?;
+    let response = response.bytes().await?;
+    let counter  = |name: &str| {
+        parse_soap(&response[..], name, Some("Usage"), true, false)
+            .first()
+            .and_then(|v| v.trim().parse().ok())
+    };
+
+    let result = Usage {
+        pan:     counter("Pan"),
+        tilt:    counter("Tilt"),
+        zoom:    counter("Zoom"),
+        roll:    counter("Roll"),
+        focus:   counter("Focus"),
+    };
+
+    info!("Provisioning usage: {result:?}");
+
+    Ok(result)
+}
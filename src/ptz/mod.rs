@@ -0,0 +1,416 @@
+use crate::client::{self, Messages};
+use crate::device::camera::Camera;
+use crate::utils::xml::XmlNode;
+
+use anyhow::{anyhow, Result};
+
+pub use crate::device::{
+    rescale, PtzConfig, PtzConfigOptions, PtzNode, PtzSpace, PtzSpaces, PtzVector,
+};
+
+/// A saved PTZ position
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct Preset {
+    pub token:   String,
+    pub name:    String,
+}
+
+/// PTZ control of one media profile of a built camera, see Camera::ptz()
+///
+/// # Examples
+///
+/// Pans right at half speed for two seconds:
+///
+/// ```no_run
+/// # use onvif_cam_rs::device::camera::Camera;
+/// # use std::time::Duration;
+/// # async fn run(camera: Camera) -> anyhow::Result<()> {
+/// let ptz = camera.ptz()?;
+///
+/// ptz.move_continuous(0.5, 0.0, 0.0).await?;
+/// tokio::time::sleep(Duration::from_secs(2)).await;
+/// ptz.stop().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Ptz {
+    onvif_url: url::Url,
+    profile_token: String,
+}
+
+impl Ptz {
+    /// Controls the first media profile with a PTZ configuration, or the
+    /// first profile when none reports one
+    pub fn new(camera: &Camera) -> Result<Self> {
+        let profile_token = camera
+            .profiles()
+            .iter()
+            .find(|profile| profile.ptz_config_token.is_some())
+            .map(|profile| profile.token.clone())
+            .map_or_else(|| profile_token(camera), Ok)?;
+
+        Ok(Ptz {
+            onvif_url: ptz_url(camera)?,
+            profile_token,
+        })
+    }
+
+    /// Controls another media profile of the camera
+    pub fn with_profile(mut self, profile_token: &str) -> Self {
+        self.profile_token = profile_token.to_string();
+        self
+    }
+
+    pub fn profile_token(&self) -> &str {
+        &self.profile_token
+    }
+
+    /// Starts moving at the given pan, tilt and zoom velocities (-1.0 to
+    /// 1.0) until stop() is sent, see continuous_move()
+    pub async fn move_continuous(&self, pan: f32, tilt: f32, zoom: f32) -> Result<()> {
+        continuous_move(self.onvif_url.clone(), &self.profile_token, pan, tilt, zoom).await
+    }
+
+    /// Moves to a position, see absolute_move()
+    pub async fn move_absolute(&self, position: PtzVector, speed: Option<PtzVector>) -> Result<()> {
+        absolute_move(self.onvif_url.clone(), &self.profile_token, position, speed).await
+    }
+
+    /// Moves by a translation from the current position, see relative_move()
+    pub async fn move_relative(
+        &self,
+        translation: PtzVector,
+        speed: Option<PtzVector>,
+    ) -> Result<()> {
+        relative_move(
+            self.onvif_url.clone(),
+            &self.profile_token,
+            translation,
+            speed,
+        )
+        .await
+    }
+
+    /// Stops any pan, tilt and zoom movement
+    pub async fn stop(&self) -> Result<()> {
+        stop(self.onvif_url.clone(), &self.profile_token).await
+    }
+
+    /// Lists the presets saved for the profile
+    pub async fn presets(&self) -> Result<Vec<Preset>> {
+        get_presets(self.onvif_url.clone(), &self.profile_token).await
+    }
+
+    /// Moves the camera to a saved preset
+    pub async fn goto_preset(&self, preset_token: &str) -> Result<()> {
+        goto_preset(self.onvif_url.clone(), &self.profile_token, preset_token).await
+    }
+}
+
+/// PTZ service URL of a built camera, from GetServices or GetCapabilities
+pub fn ptz_url(camera: &Camera) -> Result<url::Url> {
+    match (&camera.services.ptz, &camera.capabilities.url_ptz) {
+        (Some(url), _) => Ok(camera.base.authorize(&url.parse()?)),
+        (None, Some(url)) => Ok(camera.base.authorize(url)),
+        _ => Err(anyhow!("[Ptz] Device has no PTZ service")),
+    }
+}
+
+/// Media profile token used for PTZ requests of a built camera
+pub fn profile_token(camera: &Camera) -> Result<String> {
+    camera
+        .profile()
+        .map(|profile| profile.token.clone())
+        .ok_or_else(|| anyhow!("[Ptz] Camera has no media profile token, call build_all() first"))
+}
+
+/// Lists the presets saved for a media profile
+///
+/// # Arguments
+///
+/// * `onvif_url` - The PTZ service URL (see `Services::ptz`)
+/// * `profile_token` - Token of the media profile with a PTZ configuration
+pub async fn get_presets(onvif_url: url::Url, profile_token: &str) -> Result<Vec<Preset>> {
+    let msg = Messages::GetPresets {
+        profile_token: profile_token.to_string(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    let presets = root
+        .find_all("Preset")
+        .into_iter()
+        .map(|p| Preset {
+            token: p.attr("token").unwrap_or_default().to_string(),
+            name: p.child_text("Name").unwrap_or_default(),
+        })
+        .collect();
+
+    Ok(presets)
+}
+
+/// Moves the camera to a saved preset
+pub async fn goto_preset(
+    onvif_url: url::Url,
+    profile_token: &str,
+    preset_token: &str,
+) -> Result<()> {
+    let msg = Messages::GotoPreset {
+        profile_token: profile_token.to_string(),
+        preset_token: preset_token.to_string(),
+    };
+
+    client::send(onvif_url, msg).await?.error_for_status()?;
+    Ok(())
+}
+
+/// Starts moving at the given pan, tilt and zoom velocities (-1.0 to 1.0)
+/// until stop() is sent
+///
+/// # Arguments
+///
+/// * `onvif_url` - The PTZ service URL (see `ptz_url()`)
+/// * `profile_token` - Token of the media profile with a PTZ configuration
+pub async fn continuous_move(
+    onvif_url: url::Url,
+    profile_token: &str,
+    pan: f32,
+    tilt: f32,
+    zoom: f32,
+) -> Result<()> {
+    let msg = Messages::ContinuousMove {
+        profile_token: profile_token.to_string(),
+        pan: pan.clamp(-1.0, 1.0),
+        tilt: tilt.clamp(-1.0, 1.0),
+        zoom: zoom.clamp(-1.0, 1.0),
+    };
+
+    client::send(onvif_url, msg).await?.error_for_status()?;
+    Ok(())
+}
+
+/// Lists the PTZ nodes of the device with the coordinate spaces each
+/// supports, which tell whether it can move to absolute positions and the
+/// ranges of its coordinates
+///
+/// # Arguments
+///
+/// * `onvif_url` - The PTZ service URL (see `ptz_url()`)
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::ptz;
+/// # async fn run(onvif_url: url::Url) -> anyhow::Result<()> {
+/// for node in ptz::get_nodes(onvif_url).await? {
+///     match node.spaces.absolute_pan_tilt.first() {
+///         Some(space) => println!("{} pans from {:?} in {}", node.token, space.x, space.uri),
+///         None => println!("{} cannot move to positions", node.token),
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn get_nodes(onvif_url: url::Url) -> Result<Vec<PtzNode>> {
+    let response = client::send(onvif_url, Messages::GetPtzNodes).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    Ok(root
+        .find_all("PTZNode")
+        .into_iter()
+        .filter_map(PtzNode::from_xml)
+        .collect())
+}
+
+/// Lists the PTZ configurations of the device, see
+/// MediaProfile::ptz_config_token for the one of a profile
+///
+/// # Arguments
+///
+/// * `onvif_url` - The PTZ service URL (see `ptz_url()`)
+pub async fn get_configurations(onvif_url: url::Url) -> Result<Vec<PtzConfig>> {
+    let response = client::send(onvif_url, Messages::GetPtzConfigurations).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    Ok(root
+        .find_all("PTZConfiguration")
+        .into_iter()
+        .filter_map(PtzConfig::from_xml)
+        .collect())
+}
+
+/// Reads the coordinate spaces and timeouts a PTZ configuration accepts
+///
+/// # Arguments
+///
+/// * `onvif_url` - The PTZ service URL (see `ptz_url()`)
+/// * `configuration_token` - Token of the PTZ configuration
+pub async fn get_configuration_options(
+    onvif_url: url::Url,
+    configuration_token: &str,
+) -> Result<PtzConfigOptions> {
+    let msg = Messages::GetPtzConfigurationOptions {
+        configuration_token: configuration_token.to_string(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    root.find("PTZConfigurationOptions")
+        .map(PtzConfigOptions::from_xml)
+        .ok_or_else(|| {
+            anyhow!("[Ptz][get_configuration_options] No PTZConfigurationOptions in response")
+        })
+}
+
+/// Writes a PTZ configuration, for every profile using it: its default
+/// speed and timeout and the pan, tilt and zoom limits
+///
+/// # Arguments
+///
+/// * `onvif_url` - The PTZ service URL (see `ptz_url()`)
+/// * `configuration` - The configuration as it should be, usually one of
+///   get_configurations() changed
+///
+/// # Examples
+///
+/// Keeps the camera from panning over the neighbour's garden:
+///
+/// ```no_run
+/// # use onvif_cam_rs::ptz::{self, PtzSpace};
+/// # async fn run(onvif_url: url::Url) -> anyhow::Result<()> {
+/// let mut config = ptz::get_configurations(onvif_url.clone()).await?.remove(0);
+///
+/// config.pan_tilt_limits = Some(PtzSpace {
+///     uri: "http://www.onvif.org/ver10/tptz/PanTiltSpaces/PositionGenericSpace".into(),
+///     x: -1.0..=0.3,
+///     y: Some(-1.0..=1.0),
+/// });
+/// ptz::set_configuration(onvif_url, &config).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn set_configuration(onvif_url: url::Url, configuration: &PtzConfig) -> Result<()> {
+    if configuration.token.is_empty() || configuration.node_token.is_empty() {
+        return Err(anyhow!(
+            "[Ptz][set_configuration] The configuration needs a token and a node token"
+        ));
+    }
+
+    let limits = [&configuration.pan_tilt_limits, &configuration.zoom_limits];
+    for limit in limits.into_iter().flatten() {
+        let ranges = std::iter::once(&limit.x).chain(&limit.y);
+
+        if let Some(range) = ranges.into_iter().find(|r| r.start() > r.end()) {
+            return Err(anyhow!(
+                "[Ptz][set_configuration] The limits {range:?} of {} are empty",
+                limit.uri
+            ));
+        }
+    }
+
+    let msg = Messages::SetPtzConfiguration {
+        configuration: configuration.clone(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    response.error_for_status()?;
+
+    Ok(())
+}
+
+/// Moves to a position, in the generic space pan and tilt from -1.0 to 1.0
+/// and zoom from 0.0 (wide) to 1.0 (tele)
+///
+/// # Arguments
+///
+/// * `onvif_url` - The PTZ service URL (see `ptz_url()`)
+/// * `profile_token` - Token of the media profile with a PTZ configuration
+/// * `position` - The position to move to
+/// * `speed` - Speed of the move, None for the default speed of the
+///   PTZ configuration
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::ptz::{self, PtzVector};
+/// # async fn run(onvif_url: url::Url) -> anyhow::Result<()> {
+/// let center = PtzVector::new(0.0, 0.0, 0.0);
+/// let slow = PtzVector::new(0.2, 0.2, 0.2);
+///
+/// ptz::absolute_move(onvif_url, "profile_1", center, Some(slow)).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn absolute_move(
+    onvif_url: url::Url,
+    profile_token: &str,
+    position: PtzVector,
+    speed: Option<PtzVector>,
+) -> Result<()> {
+    let msg = Messages::AbsoluteMove {
+        profile_token: profile_token.to_string(),
+        position,
+        speed,
+    };
+
+    client::send(onvif_url, msg).await?.error_for_status()?;
+    Ok(())
+}
+
+/// Moves by a translation from the current position, in the generic space
+/// each axis from -1.0 to 1.0
+///
+/// # Arguments
+///
+/// * `onvif_url` - The PTZ service URL (see `ptz_url()`)
+/// * `profile_token` - Token of the media profile with a PTZ configuration
+/// * `translation` - How far to move along each axis, 0.0 to stay
+/// * `speed` - Speed of the move, None for the default speed of the
+///   PTZ configuration
+pub async fn relative_move(
+    onvif_url: url::Url,
+    profile_token: &str,
+    translation: PtzVector,
+    speed: Option<PtzVector>,
+) -> Result<()> {
+    let msg = Messages::RelativeMove {
+        profile_token: profile_token.to_string(),
+        translation,
+        speed,
+    };
+
+    client::send(onvif_url, msg).await?.error_for_status()?;
+    Ok(())
+}
+
+/// Stops any pan, tilt and zoom movement
+pub async fn stop(onvif_url: url::Url, profile_token: &str) -> Result<()> {
+    let msg = Messages::Stop {
+        profile_token: profile_token.to_string(),
+    };
+
+    client::send(onvif_url, msg).await?.error_for_status()?;
+    Ok(())
+}
+
+/// Moves a built camera to the preset with the given name
+pub async fn goto_preset_named(camera: &Camera, name: &str) -> Result<()> {
+    let url = ptz_url(camera)?;
+    let profile = profile_token(camera)?;
+
+    let preset = get_presets(url.clone(), &profile)
+        .await?
+        .into_iter()
+        .find(|p| p.name == name)
+        .ok_or_else(|| anyhow!("[Ptz] No preset named {name}"))?;
+
+    goto_preset(url, &profile, &preset.token).await
+}
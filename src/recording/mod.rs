@@ -0,0 +1,894 @@
+use crate::client::{self, Messages};
+use crate::device::camera::Camera;
+use crate::utils::xml::XmlNode;
+use crate::utils::{parse_soap, parse_xsd_duration, xml_escape, xsd_datetime, xsd_duration};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use std::time::Duration;
+
+/// Kind of data stored in a recording track
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrackType {
+    Video,
+    Audio,
+    Metadata,
+    Extended,
+}
+
+impl TrackType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TrackType::Video => "Video",
+            TrackType::Audio => "Audio",
+            TrackType::Metadata => "Metadata",
+            TrackType::Extended => "Extended",
+        }
+    }
+}
+
+impl From<&str> for TrackType {
+    fn from(input: &str) -> Self {
+        match input.trim() {
+            "Video" => TrackType::Video,
+            "Audio" => TrackType::Audio,
+            "Metadata" => TrackType::Metadata,
+            _ => TrackType::Extended,
+        }
+    }
+}
+
+/// Describes where the recorded data comes from
+#[derive(Default, Debug, Clone)]
+#[rustfmt::skip]
+pub struct RecordingSource {
+    pub source_id:     String,
+    pub name:          String,
+    pub location:      String,
+    pub description:   String,
+    pub address:       String,
+}
+
+#[derive(Default, Debug, Clone)]
+#[rustfmt::skip]
+pub struct RecordingConfiguration {
+    pub source:                   RecordingSource,
+    pub content:                  String,
+    pub maximum_retention_time:   Duration,
+}
+
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct Track {
+    pub token:         String,
+    pub track_type:    TrackType,
+    pub description:   String,
+}
+
+/// A recording container on the device's edge storage
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct Recording {
+    pub token:           String,
+    pub configuration:   RecordingConfiguration,
+    pub tracks:          Vec<Track>,
+}
+
+#[rustfmt::skip]
+impl RecordingConfiguration {
+    pub(crate) fn from_xml(node: &XmlNode) -> Self {
+        let source = node.child("Source");
+        let text   = |name: &str| source.and_then(|s| s.child_text(name)).unwrap_or_default();
+
+        RecordingConfiguration {
+            source: RecordingSource {
+                source_id:     text("SourceId"),
+                name:          text("Name"),
+                location:      text("Location"),
+                description:   text("Description"),
+                address:       text("Address"),
+            },
+            content:                  node.child_text("Content").unwrap_or_default(),
+            maximum_retention_time:   node
+                .child_text("MaximumRetentionTime")
+                .and_then(|t| parse_xsd_duration(&t))
+                .unwrap_or_default(),
+        }
+    }
+
+    pub(crate) fn to_soap(&self) -> String {
+        format!(
+            "<tt:Source>
+                <tt:SourceId>{}</tt:SourceId>
+                <tt:Name>{}</tt:Name>
+                <tt:Location>{}</tt:Location>
+                <tt:Description>{}</tt:Description>
+                <tt:Address>{}</tt:Address>
+            </tt:Source>
+            <tt:Content>{}</tt:Content>
+            <tt:MaximumRetentionTime>{}</tt:MaximumRetentionTime>",
+            xml_escape(&self.source.source_id),
+            xml_escape(&self.source.name),
+            xml_escape(&self.source.location),
+            xml_escape(&self.source.description),
+            xml_escape(&self.source.address),
+            xml_escape(&self.content),
+            xsd_duration(self.maximum_retention_time),
+        )
+    }
+}
+
+impl Track {
+    pub(crate) fn from_xml(node: &XmlNode) -> Self {
+        let config = node.child("Configuration");
+
+        Track {
+            token: node.child_text("TrackToken").unwrap_or_default(),
+            track_type: config
+                .and_then(|c| c.child_text("TrackType"))
+                .map(|t| TrackType::from(t.as_str()))
+                .unwrap_or(TrackType::Extended),
+            description: config
+                .and_then(|c| c.child_text("Description"))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Returns the type and description of a single track of a recording
+#[cfg(feature = "http")]
+pub async fn get_track_configuration(
+    onvif_url: url::Url,
+    recording_token: &str,
+    track_token: &str,
+) -> Result<Track> {
+    let msg = Messages::GetTrackConfiguration {
+        recording_token: recording_token.to_string(),
+        track_token: track_token.to_string(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+    let config = root.find("TrackConfiguration").ok_or_else(|| {
+        anyhow!("[Recording][get_track_configuration] No TrackConfiguration in response")
+    })?;
+
+    Ok(Track {
+        token: track_token.to_string(),
+        track_type: config
+            .child_text("TrackType")
+            .map(|t| TrackType::from(t.as_str()))
+            .unwrap_or(TrackType::Extended),
+        description: config.child_text("Description").unwrap_or_default(),
+    })
+}
+
+/// Lists every recording on the device with its configuration and tracks
+///
+/// # Arguments
+///
+/// * `onvif_url` - The Recording service URL (see `Services::recording`)
+#[cfg(feature = "http")]
+pub async fn get_recordings(onvif_url: url::Url) -> Result<Vec<Recording>> {
+    let response = client::send(onvif_url, Messages::GetRecordings).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    let recordings: Vec<Recording> = root
+        .find_all("RecordingItem")
+        .into_iter()
+        .map(|item| Recording {
+            token: item.child_text("RecordingToken").unwrap_or_default(),
+            configuration: item
+                .child("Configuration")
+                .map(RecordingConfiguration::from_xml)
+                .unwrap_or_default(),
+            tracks: item
+                .child("Tracks")
+                .map(|t| t.children_named("Track").map(Track::from_xml).collect())
+                .unwrap_or_default(),
+        })
+        .collect();
+
+    info!("Recordings found: {}", recordings.len());
+
+    Ok(recordings)
+}
+
+/// Creates a new recording container and returns its token.
+/// The device creates one track per media type it supports.
+#[cfg(feature = "http")]
+pub async fn create_recording(
+    onvif_url: url::Url,
+    configuration: &RecordingConfiguration,
+) -> Result<String> {
+    let msg = Messages::CreateRecording {
+        configuration: configuration.clone(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let mut token = parse_soap(&response[..], "RecordingToken", None, true, false);
+
+    if token.is_empty() {
+        return Err(anyhow!(
+            "[Recording][create_recording] No RecordingToken in response"
+        ));
+    }
+
+    Ok(token.remove(0))
+}
+
+/// Deletes a recording and all of the data it contains
+#[cfg(feature = "http")]
+pub async fn delete_recording(onvif_url: url::Url, recording_token: &str) -> Result<()> {
+    let msg = Messages::DeleteRecording {
+        recording_token: recording_token.to_string(),
+    };
+
+    client::send(onvif_url, msg).await?.error_for_status()?;
+    Ok(())
+}
+
+#[cfg(feature = "http")]
+pub async fn get_recording_configuration(
+    onvif_url: url::Url,
+    recording_token: &str,
+) -> Result<RecordingConfiguration> {
+    let msg = Messages::GetRecordingConfiguration {
+        recording_token: recording_token.to_string(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    root.find("RecordingConfiguration")
+        .map(RecordingConfiguration::from_xml)
+        .ok_or_else(|| {
+            anyhow!(
+                "[Recording][get_recording_configuration] No RecordingConfiguration in response"
+            )
+        })
+}
+
+/// Whether a recording job is currently writing to its recording
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingJobMode {
+    Idle,
+    Active,
+}
+
+impl RecordingJobMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecordingJobMode::Idle => "Idle",
+            RecordingJobMode::Active => "Active",
+        }
+    }
+}
+
+impl From<&str> for RecordingJobMode {
+    fn from(input: &str) -> Self {
+        match input.trim() {
+            "Active" => RecordingJobMode::Active,
+            _ => RecordingJobMode::Idle,
+        }
+    }
+}
+
+/// Maps a track of the source to a track of the recording
+#[derive(Default, Debug, Clone)]
+#[rustfmt::skip]
+pub struct RecordingJobTrack {
+    pub source_tag:    String,
+    pub destination:   String,
+}
+
+/// A media source feeding a recording job. `source_token` is the token
+/// of one of the device's own media profiles.
+#[derive(Default, Debug, Clone)]
+#[rustfmt::skip]
+pub struct RecordingJobSource {
+    pub source_token:           Option<String>,
+    pub auto_create_receiver:   bool,
+    pub tracks:                 Vec<RecordingJobTrack>,
+}
+
+/// Makes a job record only around matching events instead of continuously.
+/// `before` and `after` are the pre and post event buffers.
+#[derive(Default, Debug, Clone)]
+#[rustfmt::skip]
+pub struct RecordingEventFilter {
+    pub topic:    String,
+    pub before:   Duration,
+    pub after:    Duration,
+}
+
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct RecordingJobConfiguration {
+    pub recording_token:   String,
+    pub mode:              RecordingJobMode,
+    pub priority:          u32,
+    pub sources:           Vec<RecordingJobSource>,
+    pub event_filter:      Option<RecordingEventFilter>,
+}
+
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct RecordingJob {
+    pub token:           String,
+    pub configuration:   RecordingJobConfiguration,
+}
+
+impl RecordingJobConfiguration {
+    pub(crate) fn from_xml(node: &XmlNode) -> Self {
+        let sources = node
+            .children_named("Source")
+            .map(|s| RecordingJobSource {
+                source_token: s.child("SourceToken").and_then(|t| t.child_text("Token")),
+                auto_create_receiver: s
+                    .child_text("AutoCreateReceiver")
+                    .map(|v| v == "true")
+                    .unwrap_or(false),
+                tracks: s
+                    .children_named("Tracks")
+                    .map(|t| RecordingJobTrack {
+                        source_tag: t.child_text("SourceTag").unwrap_or_default(),
+                        destination: t.child_text("Destination").unwrap_or_default(),
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let event_filter = node.child("EventFilter").map(|f| RecordingEventFilter {
+            topic: f
+                .find("TopicExpression")
+                .map(|t| t.text.trim().to_string())
+                .unwrap_or_default(),
+            before: f
+                .child_text("Before")
+                .and_then(|t| parse_xsd_duration(&t))
+                .unwrap_or_default(),
+            after: f
+                .child_text("After")
+                .and_then(|t| parse_xsd_duration(&t))
+                .unwrap_or_default(),
+        });
+
+        RecordingJobConfiguration {
+            recording_token: node.child_text("RecordingToken").unwrap_or_default(),
+            mode: node
+                .child_text("Mode")
+                .map(|m| RecordingJobMode::from(m.as_str()))
+                .unwrap_or(RecordingJobMode::Idle),
+            priority: node
+                .child_text("Priority")
+                .and_then(|p| p.parse().ok())
+                .unwrap_or_default(),
+            sources,
+            event_filter,
+        }
+    }
+
+    pub(crate) fn to_soap(&self) -> String {
+        let sources: String = self
+            .sources
+            .iter()
+            .map(|s| {
+                let token = match &s.source_token {
+                    Some(token) => format!(
+                        "<tt:SourceToken Type=\"http://www.onvif.org/ver10/schema/Profile\"><tt:Token>{}</tt:Token></tt:SourceToken>",
+                        xml_escape(token)
+                    ),
+                    None => String::new(),
+                };
+
+                let tracks: String = s
+                    .tracks
+                    .iter()
+                    .map(|t| {
+                        format!(
+                            "<tt:Tracks>
+                                <tt:SourceTag>{}</tt:SourceTag>
+                                <tt:Destination>{}</tt:Destination>
+                            </tt:Tracks>",
+                            xml_escape(&t.source_tag),
+                            xml_escape(&t.destination)
+                        )
+                    })
+                    .collect();
+
+                format!(
+                    "<tt:Source>
+                        {token}
+                        <tt:AutoCreateReceiver>{}</tt:AutoCreateReceiver>
+                        {tracks}
+                    </tt:Source>",
+                    s.auto_create_receiver
+                )
+            })
+            .collect();
+
+        let event_filter = match &self.event_filter {
+            Some(f) => format!(
+                "<tt:EventFilter>
+                    <tt:Filter>
+                        <wsnt:TopicExpression Dialect=\"http://www.onvif.org/ver10/tev/topicExpression/ConcreteSet\">{}</wsnt:TopicExpression>
+                    </tt:Filter>
+                    <tt:Before>{}</tt:Before>
+                    <tt:After>{}</tt:After>
+                </tt:EventFilter>",
+                xml_escape(&f.topic),
+                xsd_duration(f.before),
+                xsd_duration(f.after)
+            ),
+            None => String::new(),
+        };
+
+        format!(
+            "<tt:RecordingToken>{}</tt:RecordingToken>
+            <tt:Mode>{}</tt:Mode>
+            <tt:Priority>{}</tt:Priority>
+            {sources}
+            {event_filter}",
+            xml_escape(&self.recording_token),
+            self.mode.as_str(),
+            self.priority
+        )
+    }
+}
+
+/// Creates a recording job that feeds a recording and returns the job
+/// as stored by the device (which may adjust the requested configuration)
+#[cfg(feature = "http")]
+pub async fn create_recording_job(
+    onvif_url: url::Url,
+    configuration: &RecordingJobConfiguration,
+) -> Result<RecordingJob> {
+    let msg = Messages::CreateRecordingJob {
+        configuration: configuration.clone(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    let token = root
+        .find("JobToken")
+        .map(|t| t.text.trim().to_string())
+        .ok_or_else(|| anyhow!("[Recording][create_recording_job] No JobToken in response"))?;
+
+    let configuration = root
+        .find("JobConfiguration")
+        .map(RecordingJobConfiguration::from_xml)
+        .unwrap_or_else(|| configuration.clone());
+
+    Ok(RecordingJob {
+        token,
+        configuration,
+    })
+}
+
+/// Switches a recording job between Idle and Active
+#[cfg(feature = "http")]
+pub async fn set_recording_job_mode(
+    onvif_url: url::Url,
+    job_token: &str,
+    mode: RecordingJobMode,
+) -> Result<()> {
+    let msg = Messages::SetRecordingJobMode {
+        job_token: job_token.to_string(),
+        mode,
+    };
+
+    client::send(onvif_url, msg).await?.error_for_status()?;
+    Ok(())
+}
+
+/// Lists every recording job on the device
+#[cfg(feature = "http")]
+pub async fn get_recording_jobs(onvif_url: url::Url) -> Result<Vec<RecordingJob>> {
+    let response = client::send(onvif_url, Messages::GetRecordingJobs).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    let jobs: Vec<RecordingJob> = root
+        .find_all("JobItem")
+        .into_iter()
+        .filter_map(|item| {
+            Some(RecordingJob {
+                token: item.child_text("JobToken")?,
+                configuration: RecordingJobConfiguration::from_xml(item.child("JobConfiguration")?),
+            })
+        })
+        .collect();
+
+    info!("Recording jobs found: {}", jobs.len());
+
+    Ok(jobs)
+}
+
+/// Limits a search or export to a set of sources and recordings.
+/// Empty lists mean no restriction.
+#[derive(Default, Debug, Clone)]
+#[rustfmt::skip]
+pub struct SearchScope {
+    pub included_sources:                Vec<String>,
+    pub included_recordings:             Vec<String>,
+    pub recording_information_filter:    Option<String>,
+}
+
+impl SearchScope {
+    pub(crate) fn to_soap(&self) -> String {
+        let sources: String = self
+            .included_sources
+            .iter()
+            .map(|s| {
+                format!(
+                    "<tt:IncludedSources><tt:Token>{}</tt:Token></tt:IncludedSources>",
+                    xml_escape(s)
+                )
+            })
+            .collect();
+
+        let recordings: String = self
+            .included_recordings
+            .iter()
+            .map(|r| {
+                format!(
+                    "<tt:IncludedRecordings>{}</tt:IncludedRecordings>",
+                    xml_escape(r)
+                )
+            })
+            .collect();
+
+        let filter = match &self.recording_information_filter {
+            Some(f) => format!(
+                "<tt:RecordingInformationFilter>{}</tt:RecordingInformationFilter>",
+                xml_escape(f)
+            ),
+            None => String::new(),
+        };
+
+        format!("{sources}{recordings}{filter}")
+    }
+}
+
+/// Storage configuration and path the exported files are written to
+#[derive(Default, Debug, Clone)]
+#[rustfmt::skip]
+pub struct StorageReferencePath {
+    pub storage_token:   String,
+    pub relative_path:   Option<String>,
+}
+
+/// Parameters of an ExportRecordedData request
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct RecordedDataExport {
+    pub start_point:           DateTime<Utc>,
+    pub end_point:             DateTime<Utc>,
+    pub search_scope:          SearchScope,
+    pub file_format:           String,
+    pub storage_destination:   StorageReferencePath,
+}
+
+impl RecordedDataExport {
+    pub(crate) fn to_soap(&self) -> String {
+        let relative_path = match &self.storage_destination.relative_path {
+            Some(p) => format!("<tt:RelativePath>{}</tt:RelativePath>", xml_escape(p)),
+            None => String::new(),
+        };
+
+        format!(
+            "<trc:StartPoint>{}</trc:StartPoint>
+            <trc:EndPoint>{}</trc:EndPoint>
+            <trc:SearchScope>{}</trc:SearchScope>
+            <trc:FileFormat>{}</trc:FileFormat>
+            <trc:StorageDestination>
+                <tt:StorageToken>{}</tt:StorageToken>
+                {relative_path}
+            </trc:StorageDestination>",
+            xsd_datetime(&self.start_point),
+            xsd_datetime(&self.end_point),
+            self.search_scope.to_soap(),
+            xml_escape(&self.file_format),
+            xml_escape(&self.storage_destination.storage_token),
+        )
+    }
+}
+
+/// A running export started by export_recorded_data()
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct ExportOperation {
+    pub token:        String,
+    pub file_names:   Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct FileProgress {
+    pub file_name:   String,
+    pub progress:    f32,
+}
+
+/// Overall (0.0 - 1.0) and per file progress of an export
+#[derive(Default, Debug, Clone)]
+#[rustfmt::skip]
+pub struct ExportStatus {
+    pub progress:   f32,
+    pub files:      Vec<FileProgress>,
+}
+
+impl ExportStatus {
+    pub(crate) fn from_xml(node: &XmlNode) -> Self {
+        ExportStatus {
+            progress: node
+                .find("Progress")
+                .and_then(|p| p.text.trim().parse().ok())
+                .unwrap_or_default(),
+            files: node
+                .find_all("FileProgress")
+                .into_iter()
+                .map(|f| FileProgress {
+                    file_name: f.child_text("FileName").unwrap_or_default(),
+                    progress: f
+                        .child_text("Progress")
+                        .and_then(|p| p.parse().ok())
+                        .unwrap_or_default(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.progress >= 1.0
+    }
+}
+
+/// Starts exporting recorded data to the given storage destination
+/// (typically the SD card or a network share configured on the device).
+/// Poll get_export_recorded_data_status() to follow the export.
+#[cfg(feature = "http")]
+pub async fn export_recorded_data(
+    onvif_url: url::Url,
+    export: &RecordedDataExport,
+) -> Result<ExportOperation> {
+    let msg = Messages::ExportRecordedData {
+        export: export.clone(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    let token = root
+        .find("OperationToken")
+        .map(|t| t.text.trim().to_string())
+        .ok_or_else(|| {
+            anyhow!("[Recording][export_recorded_data] No OperationToken in response")
+        })?;
+
+    let file_names = root
+        .find_all("FileNames")
+        .into_iter()
+        .map(|f| f.text.trim().to_string())
+        .collect();
+
+    Ok(ExportOperation { token, file_names })
+}
+
+#[cfg(feature = "http")]
+pub async fn get_export_recorded_data_status(
+    onvif_url: url::Url,
+    operation_token: &str,
+) -> Result<ExportStatus> {
+    let msg = Messages::GetExportRecordedDataStatus {
+        operation_token: operation_token.to_string(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    Ok(ExportStatus::from_xml(&root))
+}
+
+/// Cancels an export and returns the progress reached before it stopped
+#[cfg(feature = "http")]
+pub async fn stop_export_recorded_data(
+    onvif_url: url::Url,
+    operation_token: &str,
+) -> Result<ExportStatus> {
+    let msg = Messages::StopExportRecordedData {
+        operation_token: operation_token.to_string(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    Ok(ExportStatus::from_xml(&root))
+}
+
+/// A storage location configured on the device (SD card, NAS share...)
+#[derive(Default, Debug, Clone)]
+#[rustfmt::skip]
+pub struct StorageConfiguration {
+    pub token:          String,
+    pub storage_type:   String,
+    pub local_path:     Option<String>,
+    pub storage_uri:    Option<String>,
+}
+
+/// Lists the storage configurations of the device
+///
+/// # Arguments
+///
+/// * `onvif_url` - The main ONVIF device service URL
+#[cfg(feature = "http")]
+pub async fn get_storage_configurations(onvif_url: url::Url) -> Result<Vec<StorageConfiguration>> {
+    let response = client::send(onvif_url, Messages::GetStorageConfigurations).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    let storages = root
+        .find_all("StorageConfigurations")
+        .into_iter()
+        .map(|s| {
+            let data = s.child("Data");
+
+            StorageConfiguration {
+                token: s.attr("token").unwrap_or_default().to_string(),
+                storage_type: data
+                    .and_then(|d| d.attr("type"))
+                    .unwrap_or_default()
+                    .to_string(),
+                local_path: data.and_then(|d| d.child_text("LocalPath")),
+                storage_uri: data.and_then(|d| d.child_text("StorageUri")),
+            }
+        })
+        .collect();
+
+    Ok(storages)
+}
+
+/// What makes a job created by setup_recording() write to its recording
+#[derive(Debug, Clone)]
+pub enum RecordingTrigger {
+    /// Record all the time
+    Continuous,
+    /// Record around motion alarms of the video source
+    Motion { before: Duration, after: Duration },
+    /// Record around any event matching the filter
+    Event(RecordingEventFilter),
+}
+
+/// Describes a complete recording setup for setup_recording(), e.g.
+/// "record the main stream profile on motion and keep 7 days"
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct RecordingSetup {
+    pub profile_token:   String,
+    pub name:            String,
+    pub retention:       Duration,
+    pub trigger:         RecordingTrigger,
+}
+
+impl RecordingSetup {
+    /// Records `profile_token` on motion (5s before, 10s after) for `days`
+    pub fn motion(profile_token: &str, days: u64) -> Self {
+        RecordingSetup {
+            profile_token: profile_token.to_string(),
+            name: format!("{profile_token} motion"),
+            retention: Duration::from_secs(days.saturating_mul(86400)),
+            trigger: RecordingTrigger::Motion {
+                before: Duration::from_secs(5),
+                after: Duration::from_secs(10),
+            },
+        }
+    }
+
+    /// Records `profile_token` continuously for `days`
+    pub fn continuous(profile_token: &str, days: u64) -> Self {
+        RecordingSetup {
+            profile_token: profile_token.to_string(),
+            name: format!("{profile_token} continuous"),
+            retention: Duration::from_secs(days.saturating_mul(86400)),
+            trigger: RecordingTrigger::Continuous,
+        }
+    }
+}
+
+/// Recording service URL of a built camera, from GetServices
+#[cfg(feature = "http")]
+pub fn recording_url(camera: &Camera) -> Result<url::Url> {
+    match &camera.services.recording {
+        Some(url) => Ok(camera.base.authorize(&url.parse()?)),
+        None => Err(anyhow!("[Recording] Device has no Recording service")),
+    }
+}
+
+/// Sets up edge recording on a built camera in one call: checks that the
+/// device has storage, creates a recording for the profile and starts an
+/// active recording job fed by that profile. Which storage the recording
+/// goes to is up to the device. The recording is deleted again when the
+/// job cannot be created.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::device::camera::Camera;
+/// # use onvif_cam_rs::recording::{self, RecordingSetup};
+/// # async fn run(camera: &Camera) -> anyhow::Result<()> {
+/// // Record the main stream on motion and keep 7 days
+/// let job = recording::setup_recording(camera, &RecordingSetup::motion("Profile_1", 7)).await?;
+/// println!("Recording job: {}", job.token);
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "http")]
+pub async fn setup_recording(camera: &Camera, setup: &RecordingSetup) -> Result<RecordingJob> {
+    let device_url = camera.base.onvif_url();
+    let recording_url = recording_url(camera)?;
+
+    let storages = get_storage_configurations(device_url).await?;
+    if storages.is_empty() {
+        return Err(anyhow!(
+            "[Recording][setup_recording] Device has no storage configured"
+        ));
+    }
+
+    let configuration = RecordingConfiguration {
+        source: RecordingSource {
+            source_id: setup.profile_token.clone(),
+            name: setup.name.clone(),
+            ..Default::default()
+        },
+        content: setup.name.clone(),
+        maximum_retention_time: setup.retention,
+    };
+
+    let recording_token = create_recording(recording_url.clone(), &configuration).await?;
+
+    let event_filter = match &setup.trigger {
+        RecordingTrigger::Continuous => None,
+        RecordingTrigger::Motion { before, after } => Some(RecordingEventFilter {
+            topic: "tns1:VideoSource/MotionAlarm".to_string(),
+            before: *before,
+            after: *after,
+        }),
+        RecordingTrigger::Event(filter) => Some(filter.clone()),
+    };
+
+    let job = RecordingJobConfiguration {
+        recording_token,
+        mode: RecordingJobMode::Active,
+        priority: 1,
+        sources: vec![RecordingJobSource {
+            source_token: Some(setup.profile_token.clone()),
+            auto_create_receiver: false,
+            tracks: Vec::new(),
+        }],
+        event_filter,
+    };
+
+    match create_recording_job(recording_url.clone(), &job).await {
+        Ok(job) => Ok(job),
+        Err(e) => {
+            // Not left behind unused
+            if let Err(delete) = delete_recording(recording_url, &job.recording_token).await {
+                warn!(
+                    "Unable to delete recording {}: {delete}",
+                    job.recording_token
+                );
+            }
+            Err(e)
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use chrono::{DateTime, Utc};
+
+/// RTSP PLAY parameters for ONVIF replay of a recording, as defined by
+/// the ONVIF Streaming Specification. The recording URI itself comes
+/// from the Replay service, these headers tell the device where to seek
+/// and how fast to deliver the data.
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct ReplayParams {
+    pub start:          Option<DateTime<Utc>>,
+    pub end:            Option<DateTime<Utc>>,
+    pub scale:          Option<f32>,
+    pub rate_control:   bool,
+    pub immediate:      bool,
+}
+
+impl Default for ReplayParams {
+    fn default() -> Self {
+        ReplayParams {
+            start: None,
+            end: None,
+            scale: None,
+            rate_control: true,
+            immediate: false,
+        }
+    }
+}
+
+impl ReplayParams {
+    /// Plays back from `start` in real time
+    pub fn from_time(start: DateTime<Utc>) -> Self {
+        ReplayParams {
+            start: Some(start),
+            ..Default::default()
+        }
+    }
+
+    /// Value of the Range header, e.g. "clock=20230801T120000.000Z-".
+    /// For reverse playback (negative scale) the start is the later time.
+    pub fn range(&self) -> Option<String> {
+        if self.start.is_none() && self.end.is_none() {
+            return None;
+        }
+
+        let start = self.start.as_ref().map(replay_clock).unwrap_or_default();
+        let end = self.end.as_ref().map(replay_clock).unwrap_or_default();
+
+        Some(format!("clock={start}-{end}"))
+    }
+
+    /// All headers to add to the RTSP PLAY request
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        let mut headers = vec![("Require", "onvif-replay".to_string())];
+
+        if let Some(range) = self.range() {
+            headers.push(("Range", range));
+        }
+
+        if let Some(scale) = self.scale {
+            headers.push(("Scale", format!("{scale}")));
+        }
+
+        if !self.rate_control {
+            headers.push(("Rate-Control", "no".to_string()));
+        }
+
+        if self.immediate {
+            headers.push(("Immediate", "yes".to_string()));
+        }
+
+        headers
+    }
+
+    /// Headers formatted as RTSP header lines, each ending in CRLF
+    pub fn header_lines(&self) -> String {
+        self.headers()
+            .iter()
+            .map(|(name, value)| format!("{name}: {value}\r\n"))
+            .collect()
+    }
+}
+
+/// Formats a time in the compact UTC form used by the replay Range header
+pub fn replay_clock(time: &DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%S%.3fZ").to_string()
+}
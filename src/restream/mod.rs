@@ -0,0 +1,143 @@
+use crate::device::camera::Camera;
+use crate::playlist::PlaylistEntry;
+
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Settings shared by the go2rtc and MediaMTX exporters
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct RestreamOptions {
+    /// Credentials put in every source URL
+    pub username:    Option<String>,
+    pub password:    Option<String>,
+    /// Pull RTP over the RTSP connection instead of UDP
+    pub force_tcp:   bool,
+    /// Only connect to the camera while someone is watching (MediaMTX)
+    pub on_demand:   bool,
+}
+
+impl Default for RestreamOptions {
+    fn default() -> Self {
+        RestreamOptions {
+            username: None,
+            password: None,
+            force_tcp: true,
+            on_demand: true,
+        }
+    }
+}
+
+impl RestreamOptions {
+    pub fn with_credentials(mut self, username: &str, password: &str) -> Self {
+        self.username = Some(username.to_string());
+        self.password = Some(password.to_string());
+        self
+    }
+}
+
+/// Stream name and source URL of every camera with a stream URI. Names
+/// are made from the playlist name of the camera and made unique.
+pub fn sources(cameras: &[Camera], options: &RestreamOptions) -> Vec<(String, String)> {
+    let mut taken = BTreeSet::new();
+
+    cameras
+        .iter()
+        .filter_map(PlaylistEntry::from_camera)
+        .map(|entry| {
+            let base = stream_name(&entry.name);
+            let mut name = base.clone();
+            let mut n = 1;
+
+            while !taken.insert(name.clone()) {
+                n += 1;
+                name = format!("{base}_{n}");
+            }
+
+            (name, source_url(&entry.uri, options))
+        })
+        .collect()
+}
+
+/// "Front Door (192.168.1.20)" -> "front_door_192_168_1_20"
+fn stream_name(name: &str) -> String {
+    let name = name
+        .to_ascii_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_");
+
+    match name.is_empty() {
+        true => "camera".to_string(),
+        false => name,
+    }
+}
+
+/// The stream URI with the credentials of the options, if any
+fn source_url(uri: &str, options: &RestreamOptions) -> String {
+    let Ok(mut url) = uri.parse::<url::Url>() else {
+        return uri.to_string();
+    };
+
+    if let Some(username) = &options.username {
+        let _ = url.set_username(username);
+        let _ = url.set_password(options.password.as_deref());
+    }
+
+    url.to_string()
+}
+
+#[derive(Serialize)]
+struct Go2rtcConfig {
+    streams: BTreeMap<String, Vec<String>>,
+}
+
+/// A go2rtc `streams:` section for the cameras. With `force_tcp` the
+/// sources get go2rtc's `#transport=tcp` suffix.
+pub fn go2rtc_config(cameras: &[Camera], options: &RestreamOptions) -> Result<String> {
+    let streams = sources(cameras, options)
+        .into_iter()
+        .map(|(name, url)| match options.force_tcp {
+            true => (name, vec![format!("{url}#transport=tcp")]),
+            false => (name, vec![url]),
+        })
+        .collect();
+
+    Ok(serde_yaml::to_string(&Go2rtcConfig { streams })?)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MediamtxPath {
+    source: String,
+    rtsp_transport: &'static str,
+    source_on_demand: bool,
+}
+
+#[derive(Serialize)]
+struct MediamtxConfig {
+    paths: BTreeMap<String, MediamtxPath>,
+}
+
+/// A MediaMTX `paths:` section pulling every camera stream
+pub fn mediamtx_config(cameras: &[Camera], options: &RestreamOptions) -> Result<String> {
+    let paths = sources(cameras, options)
+        .into_iter()
+        .map(|(name, source)| {
+            let path = MediamtxPath {
+                source,
+                rtsp_transport: match options.force_tcp {
+                    true => "tcp",
+                    false => "automatic",
+                },
+                source_on_demand: options.on_demand,
+            };
+
+            (name, path)
+        })
+        .collect();
+
+    Ok(serde_yaml::to_string(&MediamtxConfig { paths })?)
+}
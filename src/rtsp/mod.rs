@@ -0,0 +1,422 @@
+#[cfg(feature = "discovery")]
+pub mod multicast;
+pub mod sdp;
+#[cfg(feature = "rtsp")]
+pub mod stream;
+
+use sdp::{MediaDescription, Sdp};
+
+use crate::auth::{Credentials, DigestChallenge};
+use crate::device::StreamUri;
+
+use anyhow::{anyhow, Context, Result};
+use bytes::{Buf, Bytes, BytesMut};
+use log::trace;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+/// Port used when an rtsp:// URL has none
+pub const DEFAULT_PORT: u16 = 554;
+
+/// Answer to an RTSP request
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct Response {
+    pub status:    u16,
+    pub reason:    String,
+    pub headers:   Vec<(String, String)>,
+    pub body:      Bytes,
+}
+
+impl Response {
+    /// Value of a header, ignoring case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// What the server sent next on the connection
+#[derive(Debug)]
+pub enum Message {
+    Response(Response),
+    /// RTP or RTCP data interleaved on the RTSP connection (RFC 2326 10.12)
+    Interleaved {
+        channel: u8,
+        data: Bytes,
+    },
+}
+
+/// A single RTSP control connection. Credentials in the URL answer the
+/// Digest or Basic challenge of the server, and are sent with every
+/// request after it.
+pub struct RtspConnection {
+    stream: TcpStream,
+    buf: BytesMut,
+    uri: url::Url,
+    cseq: u32,
+    session: Option<String>,
+    credentials: Option<Credentials>,
+    /// The scheme the server asked for, None until it has
+    auth: Option<RtspAuth>,
+    wait: Duration,
+}
+
+/// Authentication scheme of an RTSP connection
+enum RtspAuth {
+    Basic,
+    Digest(DigestChallenge),
+}
+
+impl RtspConnection {
+    /// Connects to the host of `uri`, waiting up to `wait` for each step
+    pub async fn connect(uri: &url::Url, wait: Duration) -> Result<Self> {
+        if uri.scheme() == "rtsps" {
+            return Err(anyhow!(
+                "[Rtsp][connect] RTSP over TLS is not supported by the built-in client, hand {uri} to a player"
+            ));
+        }
+
+        let host = uri
+            .host_str()
+            .ok_or_else(|| anyhow!("[Rtsp][connect] No host in {uri}"))?;
+        let port = uri.port().unwrap_or(DEFAULT_PORT);
+
+        let stream = timeout(wait, TcpStream::connect((host, port)))
+            .await
+            .map_err(|_| anyhow!("[Rtsp][connect] Timed out connecting to {host}:{port}"))?
+            .with_context(|| format!("[Rtsp][connect] Unable to connect to {host}:{port}"))?;
+
+        let credentials = Credentials::from_url(uri);
+
+        // Credentials are never sent in the request line
+        let mut clean = uri.clone();
+        let _ = clean.set_username("");
+        let _ = clean.set_password(None);
+
+        Ok(RtspConnection {
+            stream,
+            buf: BytesMut::with_capacity(64 * 1024),
+            uri: clean,
+            cseq: 0,
+            session: None,
+            credentials,
+            auth: None,
+            wait,
+        })
+    }
+
+    /// The stream URL without credentials
+    pub fn uri(&self) -> &url::Url {
+        &self.uri
+    }
+
+    pub fn session(&self) -> Option<&str> {
+        self.session.as_deref()
+    }
+
+    /// Sends a request and waits for its response, skipping any
+    /// interleaved data that arrives first. A 401 answer is retried once
+    /// with the URL credentials, in the scheme of its challenge.
+    pub async fn request(
+        &mut self,
+        method: &str,
+        url: &url::Url,
+        headers: &[(&str, String)],
+    ) -> Result<Response> {
+        let mut response = self.request_once(method, url, headers).await?;
+
+        // Also a stale nonce of an earlier challenge
+        if response.status == 401 && self.credentials.is_some() {
+            if let Some(auth) = RtspAuth::from_response(&response) {
+                self.auth = Some(auth);
+                response = self.request_once(method, url, headers).await?;
+            }
+        }
+
+        if !response.is_success() {
+            return Err(anyhow!(
+                "[Rtsp][{method}] {url} answered {} {}",
+                response.status,
+                response.reason
+            ));
+        }
+
+        if let Some(session) = response.header("Session") {
+            // "12345678;timeout=60"
+            let id = session.split(';').next().unwrap_or_default().trim();
+            self.session = Some(id.to_string());
+        }
+
+        Ok(response)
+    }
+
+    async fn request_once(
+        &mut self,
+        method: &str,
+        url: &url::Url,
+        headers: &[(&str, String)],
+    ) -> Result<Response> {
+        let cseq = self.send(method, url, headers, true).await?;
+
+        loop {
+            match timeout(self.wait, self.read_message()).await {
+                Ok(Ok(Some(Message::Response(response)))) => {
+                    match response
+                        .header("CSeq")
+                        .and_then(|c| c.trim().parse::<u32>().ok())
+                    {
+                        Some(c) if c != cseq => continue,
+                        _ => return Ok(response),
+                    }
+                }
+                Ok(Ok(Some(Message::Interleaved { .. }))) => continue,
+                Ok(Ok(None)) => {
+                    return Err(anyhow!("[Rtsp][{method}] Connection closed by server"))
+                }
+                Ok(Err(e)) => return Err(e),
+                Err(_) => return Err(anyhow!("[Rtsp][{method}] No response from {url}")),
+            }
+        }
+    }
+
+    /// Writes a request without waiting for the response and returns its CSeq
+    pub async fn send(
+        &mut self,
+        method: &str,
+        url: &url::Url,
+        headers: &[(&str, String)],
+        authorize: bool,
+    ) -> Result<u32> {
+        self.cseq += 1;
+
+        let mut request = format!(
+            "{method} {url} RTSP/1.0\r\nCSeq: {}\r\nUser-Agent: onvif-cam-rs\r\n",
+            self.cseq
+        );
+
+        if let Some(session) = &self.session {
+            request.push_str(&format!("Session: {session}\r\n"));
+        }
+
+        if let (true, Some(authorization)) = (authorize, self.authorization(method, url)) {
+            request.push_str(&format!("Authorization: {authorization}\r\n"));
+        }
+
+        for (name, value) in headers {
+            request.push_str(&format!("{name}: {value}\r\n"));
+        }
+
+        request.push_str("\r\n");
+        trace!("RTSP request:\n{request}");
+
+        timeout(self.wait, self.stream.write_all(request.as_bytes()))
+            .await
+            .map_err(|_| anyhow!("[Rtsp][{method}] Timed out sending to {url}"))??;
+
+        Ok(self.cseq)
+    }
+
+    /// Authorization header of a request, None until the server has
+    /// asked for credentials
+    fn authorization(&self, method: &str, url: &url::Url) -> Option<String> {
+        let credentials = self.credentials.as_ref()?;
+
+        match self.auth.as_ref()? {
+            RtspAuth::Basic => Some(credentials.basic_authorization()),
+            // RTSP digests the whole request URL
+            RtspAuth::Digest(challenge) => {
+                let cnonce = uuid::Uuid::new_v4().simple().to_string();
+                Some(credentials.digest_authorization(challenge, method, url.as_str(), &cnonce))
+            }
+        }
+    }
+
+    /// Reads the next response or interleaved packet, None once the
+    /// server has closed the connection
+    pub async fn read_message(&mut self) -> Result<Option<Message>> {
+        loop {
+            if let Some(message) = self.parse_message()? {
+                return Ok(Some(message));
+            }
+
+            if self.stream.read_buf(&mut self.buf).await? == 0 {
+                return Ok(None);
+            }
+        }
+    }
+
+    fn parse_message(&mut self) -> Result<Option<Message>> {
+        if self.buf.first() == Some(&b'$') {
+            if self.buf.len() < 4 {
+                return Ok(None);
+            }
+
+            let channel = self.buf[1];
+            let len = u16::from_be_bytes([self.buf[2], self.buf[3]]) as usize;
+
+            if self.buf.len() < 4 + len {
+                return Ok(None);
+            }
+
+            self.buf.advance(4);
+            let data = self.buf.split_to(len).freeze();
+
+            return Ok(Some(Message::Interleaved { channel, data }));
+        }
+
+        let Some(end) = self.buf.windows(4).position(|w| w == b"\r\n\r\n") else {
+            return Ok(None);
+        };
+
+        let head = String::from_utf8_lossy(&self.buf[..end]).into_owned();
+        let mut lines = head.lines();
+        let status_line = lines.next().unwrap_or_default();
+
+        if !status_line.starts_with("RTSP/") {
+            return Err(anyhow!("[Rtsp][read] Unexpected data: {status_line}"));
+        }
+
+        let mut parts = status_line.splitn(3, ' ');
+        let status = parts.nth(1).and_then(|s| s.parse().ok()).unwrap_or(0);
+        let reason = parts.next().unwrap_or_default().to_string();
+
+        let headers: Vec<(String, String)> = lines
+            .filter_map(|l| l.split_once(':'))
+            .map(|(n, v)| (n.trim().to_string(), v.trim().to_string()))
+            .collect();
+
+        let body_len = headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case("Content-Length"))
+            .and_then(|(_, v)| v.parse().ok())
+            .unwrap_or(0usize);
+
+        if self.buf.len() < end + 4 + body_len {
+            return Ok(None);
+        }
+
+        self.buf.advance(end + 4);
+        let body = self.buf.split_to(body_len).freeze();
+        trace!("RTSP response:\n{head}");
+
+        Ok(Some(Message::Response(Response {
+            status,
+            reason,
+            headers,
+            body,
+        })))
+    }
+
+    pub async fn options(&mut self) -> Result<Response> {
+        let uri = self.uri.clone();
+        self.request("OPTIONS", &uri, &[]).await
+    }
+
+    /// Sends DESCRIBE and returns the parsed SDP with the base URL that
+    /// control attributes are relative to
+    pub async fn describe(&mut self) -> Result<(Sdp, url::Url)> {
+        let uri = self.uri.clone();
+        let response = self
+            .request(
+                "DESCRIBE",
+                &uri,
+                &[("Accept", "application/sdp".to_string())],
+            )
+            .await?;
+
+        let base = response
+            .header("Content-Base")
+            .or_else(|| response.header("Content-Location"))
+            .and_then(|b| b.parse().ok())
+            .unwrap_or(uri);
+
+        let sdp = Sdp::parse(&String::from_utf8_lossy(&response.body))?;
+
+        Ok((sdp, base))
+    }
+}
+
+/// What a stream URI answered to OPTIONS and DESCRIBE
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct StreamProbe {
+    /// Time taken to connect and get both answers
+    pub latency:   Duration,
+    pub server:    Option<String>,
+    /// Methods listed in the Public header of the OPTIONS answer
+    pub methods:   Vec<String>,
+    pub sdp:       Sdp,
+}
+
+impl StreamProbe {
+    /// Encoding of the first video media, e.g. "H264" or "H265"
+    pub fn video_codec(&self) -> Option<&str> {
+        self.sdp.video().map(|m| m.encoding.as_str())
+    }
+
+    pub fn media(&self) -> &[MediaDescription] {
+        &self.sdp.media
+    }
+}
+
+impl StreamUri {
+    /// Checks that the stream URI actually works by sending OPTIONS and
+    /// DESCRIBE, and returns the codecs the server announces. Each request
+    /// waits up to `wait`. Credentials in the URI are used when asked for.
+    pub async fn probe(&self, wait: Duration) -> Result<StreamProbe> {
+        let uri: url::Url = self
+            .uri
+            .as_ref()
+            .ok_or_else(|| anyhow!("[Rtsp][probe] No stream uri"))?
+            .parse()?;
+
+        let start = Instant::now();
+        let mut conn = RtspConnection::connect(&uri, wait).await?;
+        let options = conn.options().await?;
+        let (sdp, _) = conn.describe().await?;
+
+        Ok(StreamProbe {
+            latency: start.elapsed(),
+            server: options.header("Server").map(str::to_string),
+            methods: options
+                .header("Public")
+                .unwrap_or_default()
+                .split(',')
+                .map(|m| m.trim().to_string())
+                .filter(|m| !m.is_empty())
+                .collect(),
+            sdp,
+        })
+    }
+}
+
+impl RtspAuth {
+    /// The scheme of the WWW-Authenticate challenges of a 401 answer,
+    /// Digest when the server offers it
+    fn from_response(response: &Response) -> Option<RtspAuth> {
+        let challenges = response
+            .headers
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("WWW-Authenticate"))
+            .map(|(_, value)| value.as_str());
+
+        let mut basic = false;
+        for challenge in challenges {
+            if let Some(digest) = DigestChallenge::parse(challenge) {
+                return Some(RtspAuth::Digest(digest));
+            }
+            basic |= challenge.trim().to_ascii_lowercase().starts_with("basic");
+        }
+
+        basic.then_some(RtspAuth::Basic)
+    }
+}
@@ -0,0 +1,149 @@
+use super::sdp::{MediaDescription, Sdp};
+
+use anyhow::{anyhow, Context, Result};
+use bytes::Bytes;
+use futures::Stream;
+use log::debug;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use tokio::net::UdpSocket;
+
+/// A multicast group and port a camera sends RTP to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[rustfmt::skip]
+pub struct MulticastGroup {
+    pub address:   IpAddr,
+    pub port:      u16,
+    pub ttl:       Option<u8>,
+}
+
+/// Network interface the group is joined on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MulticastInterface {
+    /// Let the OS choose, usually the interface of the default route
+    #[default]
+    Any,
+    /// The IPv4 interface with this address
+    Address(Ipv4Addr),
+    /// The interface with this index, required to pick one for IPv6
+    Index(u32),
+}
+
+impl MulticastGroup {
+    pub fn new(address: IpAddr, port: u16) -> Self {
+        MulticastGroup {
+            address,
+            port,
+            ttl: None,
+        }
+    }
+
+    /// The group announced for a media in the SDP, None when the media is
+    /// not sent to a multicast address
+    pub fn from_media(media: &MediaDescription) -> Option<Self> {
+        let connection = media.connection.as_ref().filter(|c| c.is_multicast())?;
+
+        Some(MulticastGroup {
+            address: connection.address,
+            port: media.port,
+            ttl: connection.ttl,
+        })
+    }
+
+    /// Joins the group and returns a socket receiving its datagrams. The
+    /// port is shared with other sockets joined to the same group, so
+    /// several readers on one host each get every packet.
+    pub async fn join(&self, interface: MulticastInterface) -> Result<UdpSocket> {
+        if !self.address.is_multicast() {
+            return Err(anyhow!(
+                "[Rtsp][multicast] {} is not a multicast address",
+                self.address
+            ));
+        }
+
+        let domain = match self.address {
+            IpAddr::V4(_) => Domain::IPV4,
+            IpAddr::V6(_) => Domain::IPV6,
+        };
+
+        let socket = Socket::new(domain, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_reuse_address(true)?;
+        // BSD and macOS only let several sockets share the port with SO_REUSEPORT
+        #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+        socket.set_reuse_port(true)?;
+        socket.set_nonblocking(true)?;
+
+        // Binding the group address filters out other groups sent to the
+        // same port, Windows only allows binding a local address
+        let bind_ip = match (cfg!(windows), self.address) {
+            (true, IpAddr::V4(_)) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            (true, IpAddr::V6(_)) => IpAddr::V6(std::net::Ipv6Addr::UNSPECIFIED),
+            (false, address) => address,
+        };
+
+        socket
+            .bind(&SocketAddr::new(bind_ip, self.port).into())
+            .with_context(|| format!("[Rtsp][multicast] Unable to bind port {}", self.port))?;
+
+        match (self.address, interface) {
+            (IpAddr::V4(group), MulticastInterface::Any) => {
+                socket.join_multicast_v4(&group, &Ipv4Addr::UNSPECIFIED)
+            }
+            (IpAddr::V4(group), MulticastInterface::Address(local)) => {
+                socket.join_multicast_v4(&group, &local)
+            }
+            (IpAddr::V6(group), MulticastInterface::Any) => socket.join_multicast_v6(&group, 0),
+            (IpAddr::V6(group), MulticastInterface::Index(index)) => {
+                socket.join_multicast_v6(&group, index)
+            }
+            (address, interface) => {
+                return Err(anyhow!(
+                    "[Rtsp][multicast] Cannot join {address} on {interface:?}"
+                ))
+            }
+        }
+        .with_context(|| format!("[Rtsp][multicast] Unable to join {}", self.address))?;
+
+        debug!("Joined multicast group {}:{}", self.address, self.port);
+
+        Ok(UdpSocket::from_std(socket.into())?)
+    }
+
+    /// Joins the group and returns the received datagrams as a Stream.
+    /// The group is left when the stream is dropped.
+    pub async fn packets(
+        &self,
+        interface: MulticastInterface,
+    ) -> Result<impl Stream<Item = Result<Bytes>>> {
+        let socket = self.join(interface).await?;
+
+        Ok(futures::stream::try_unfold(socket, |socket| async move {
+            let mut buf = vec![0u8; 65536];
+            let size = socket.recv(&mut buf).await?;
+            buf.truncate(size);
+
+            Ok(Some((Bytes::from(buf), socket)))
+        }))
+    }
+}
+
+/// Joins the multicast group of every media in the SDP that has one and
+/// returns each media with the socket receiving its RTP packets
+pub async fn join_sdp(
+    sdp: &Sdp,
+    interface: MulticastInterface,
+) -> Result<Vec<(MediaDescription, UdpSocket)>> {
+    let mut joined = Vec::new();
+
+    for media in &sdp.media {
+        if let Some(group) = MulticastGroup::from_media(media) {
+            joined.push((media.clone(), group.join(interface).await?));
+        }
+    }
+
+    if joined.is_empty() {
+        return Err(anyhow!("[Rtsp][multicast] No multicast media in session"));
+    }
+
+    Ok(joined)
+}
@@ -0,0 +1,192 @@
+use anyhow::{anyhow, Result};
+use std::net::IpAddr;
+
+/// One `m=` section of a session description
+#[derive(Debug, Clone, Default)]
+#[rustfmt::skip]
+pub struct MediaDescription {
+    /// "video", "audio" or "application"
+    pub media:          String,
+    pub payload_type:   u8,
+    /// Encoding name from a=rtpmap, e.g. "H264", "H265" or "vnd.onvif.metadata"
+    pub encoding:       String,
+    pub clock_rate:     u32,
+    pub control:        Option<String>,
+    /// Format parameters from a=fmtp as key/value pairs
+    pub fmtp:           Vec<(String, String)>,
+    /// Port from the m= line, the multicast port for multicast sessions
+    pub port:           u16,
+    /// Transport protocol from the m= line, "RTP/AVP" or "RTP/SAVP" for SRTP
+    pub protocol:       String,
+    /// Address from the c= line of the media or, if it has none, the session
+    pub connection:     Option<Connection>,
+}
+
+/// Connection data from a `c=` line, e.g. `c=IN IP4 239.0.0.1/64`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[rustfmt::skip]
+pub struct Connection {
+    pub address:   IpAddr,
+    /// Multicast TTL, only given for IPv4 multicast addresses
+    pub ttl:       Option<u8>,
+}
+
+impl Connection {
+    fn parse(value: &str) -> Option<Self> {
+        let mut fields = value.split_whitespace();
+        let (_net, _kind) = (fields.next()?, fields.next()?);
+        let mut parts = fields.next()?.split('/');
+        let address = parts.next()?.parse().ok()?;
+        let ttl = match address {
+            IpAddr::V4(_) => parts.next().and_then(|t| t.parse().ok()),
+            IpAddr::V6(_) => None,
+        };
+
+        Some(Connection { address, ttl })
+    }
+
+    pub fn is_multicast(&self) -> bool {
+        self.address.is_multicast()
+    }
+}
+
+impl MediaDescription {
+    /// True when the media is sent as SRTP (RTP/SAVP or RTP/SAVPF)
+    pub fn is_srtp(&self) -> bool {
+        self.protocol.contains("SAVP")
+    }
+
+    pub fn fmtp_value(&self, key: &str) -> Option<&str> {
+        self.fmtp
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// The parts of an SDP answer to DESCRIBE needed to set up a stream
+#[derive(Debug, Clone, Default)]
+#[rustfmt::skip]
+pub struct Sdp {
+    pub session_name:   Option<String>,
+    pub control:        Option<String>,
+    pub connection:     Option<Connection>,
+    pub media:          Vec<MediaDescription>,
+}
+
+impl Sdp {
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut sdp = Sdp::default();
+
+        for line in input.lines().map(str::trim) {
+            let Some((kind, value)) = line.split_once('=') else {
+                continue;
+            };
+
+            match kind {
+                "s" => sdp.session_name = Some(value.to_string()),
+                "m" => {
+                    let mut fields = value.split_whitespace();
+                    let media = fields.next().unwrap_or_default().to_string();
+                    // "5000/2" announces a range of ports, the first is RTP
+                    let port = fields
+                        .next()
+                        .and_then(|p| p.split('/').next())
+                        .and_then(|p| p.parse().ok())
+                        .unwrap_or(0);
+                    let protocol = fields.next().unwrap_or_default().to_string();
+                    let payload_type = fields.next().and_then(|p| p.parse().ok()).unwrap_or(0);
+
+                    sdp.media.push(MediaDescription {
+                        media,
+                        payload_type,
+                        port,
+                        protocol,
+                        ..Default::default()
+                    });
+                }
+                "c" => {
+                    let connection = Connection::parse(value);
+
+                    match sdp.media.last_mut() {
+                        Some(media) => media.connection = connection,
+                        None => sdp.connection = connection,
+                    }
+                }
+                "a" => {
+                    let (name, attr) = value.split_once(':').unwrap_or((value, ""));
+
+                    match sdp.media.last_mut() {
+                        Some(media) => media.set_attribute(name, attr),
+                        None if name == "control" => sdp.control = Some(attr.to_string()),
+                        None => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if sdp.media.is_empty() {
+            return Err(anyhow!("[Rtsp][sdp] Session description has no media"));
+        }
+
+        for media in sdp.media.iter_mut().filter(|m| m.connection.is_none()) {
+            media.connection = sdp.connection.clone();
+        }
+
+        Ok(sdp)
+    }
+
+    pub fn video(&self) -> Option<&MediaDescription> {
+        self.media.iter().find(|m| m.media == "video")
+    }
+}
+
+impl MediaDescription {
+    fn set_attribute(&mut self, name: &str, value: &str) {
+        match name {
+            "control" => self.control = Some(value.to_string()),
+            "rtpmap" => {
+                // a=rtpmap:96 H264/90000
+                let mut parts = value
+                    .split_whitespace()
+                    .nth(1)
+                    .unwrap_or_default()
+                    .split('/');
+                self.encoding = parts.next().unwrap_or_default().to_string();
+                self.clock_rate = parts.next().and_then(|r| r.parse().ok()).unwrap_or(0);
+            }
+            "fmtp" => {
+                // a=fmtp:96 packetization-mode=1;sprop-parameter-sets=...
+                let params = value.split_once(' ').map(|(_, p)| p).unwrap_or_default();
+                self.fmtp = params
+                    .split(';')
+                    .filter_map(|p| p.trim().split_once('='))
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Resolves a control attribute against the base URL of the session
+/// (Content-Base of the DESCRIBE answer, or the request URL)
+pub fn control_url(base: &url::Url, control: Option<&str>) -> Result<url::Url> {
+    match control {
+        None | Some("*") | Some("") => Ok(base.clone()),
+        Some(control) if control.starts_with("rtsp://") || control.starts_with("rtsps://") => {
+            Ok(control.parse()?)
+        }
+        Some(control) => {
+            let mut base = base.clone();
+
+            if !base.path().ends_with('/') {
+                let path = format!("{}/", base.path());
+                base.set_path(&path);
+            }
+
+            Ok(base.join(control)?)
+        }
+    }
+}
@@ -0,0 +1,980 @@
+use super::sdp::{control_url, MediaDescription, Sdp};
+use super::{Message, RtspConnection};
+use crate::device::camera::Camera;
+use crate::device::StreamTransport;
+use crate::media;
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytes::{Bytes, BytesMut};
+use futures::Stream;
+use log::{debug, info, warn};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Time allowed for each RTSP request and for the first packet
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Session timeout assumed when the server does not announce one
+const DEFAULT_SESSION_TIMEOUT: Duration = Duration::from_secs(60);
+
+const START_CODE: [u8; 4] = [0, 0, 0, 1];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoCodec {
+    H264,
+    H265,
+}
+
+/// One complete access unit in Annex B format (start code delimited NAL
+/// units), ready for a decoder. Parameter sets from the SDP are prepended
+/// to keyframes that do not carry their own.
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct VideoFrame {
+    pub codec:       VideoCodec,
+    pub timestamp:   u32,
+    pub keyframe:    bool,
+    pub data:        Bytes,
+}
+
+/// An ONVIF metadata document (tt:MetadataStream XML)
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct MetadataFrame {
+    pub timestamp:   u32,
+    pub data:        Bytes,
+}
+
+#[derive(Debug, Clone)]
+pub enum Frame {
+    Video(VideoFrame),
+    Metadata(MetadataFrame),
+}
+
+/// An RTP packet with the header stripped
+struct RtpPacket {
+    sequence: u16,
+    timestamp: u32,
+    marker: bool,
+    payload: Bytes,
+}
+
+impl RtpPacket {
+    fn parse(data: Bytes) -> Result<Self> {
+        if data.len() < 12 || data[0] >> 6 != 2 {
+            return Err(anyhow!("[Rtsp][rtp] Invalid RTP packet"));
+        }
+
+        let csrc_count = (data[0] & 0x0F) as usize;
+        let mut start = 12 + 4 * csrc_count;
+
+        if data[0] & 0x10 != 0 {
+            let ext = data
+                .get(start + 2..start + 4)
+                .ok_or_else(|| anyhow!("[Rtsp][rtp] Truncated header extension"))?;
+            start += 4 + 4 * u16::from_be_bytes([ext[0], ext[1]]) as usize;
+        }
+
+        let mut end = data.len();
+
+        if data[0] & 0x20 != 0 {
+            end = end.saturating_sub(data[data.len() - 1] as usize);
+        }
+
+        if start > end {
+            return Err(anyhow!("[Rtsp][rtp] Truncated RTP packet"));
+        }
+
+        Ok(RtpPacket {
+            sequence: u16::from_be_bytes([data[2], data[3]]),
+            timestamp: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+            marker: data[1] & 0x80 != 0,
+            payload: data.slice(start..end),
+        })
+    }
+}
+
+enum Kind {
+    Video(VideoCodec),
+    Metadata,
+}
+
+/// Reassembles RTP payloads of one media into frames
+struct Depacketizer {
+    kind: Kind,
+    parameter_sets: Vec<Bytes>,
+    nals: Vec<Bytes>,
+    fragment: Option<BytesMut>,
+    timestamp: u32,
+    next_sequence: Option<u16>,
+    corrupt: bool,
+    received: u64,
+    lost: u64,
+}
+
+impl Depacketizer {
+    fn new(media: &MediaDescription) -> Option<Self> {
+        let kind = match media.encoding.to_ascii_uppercase().as_str() {
+            "H264" => Kind::Video(VideoCodec::H264),
+            "H265" => Kind::Video(VideoCodec::H265),
+            _ if media.media == "application" => Kind::Metadata,
+            _ => return None,
+        };
+
+        let keys: &[&str] = match kind {
+            Kind::Video(VideoCodec::H264) => &["sprop-parameter-sets"],
+            Kind::Video(VideoCodec::H265) => &["sprop-vps", "sprop-sps", "sprop-pps"],
+            Kind::Metadata => &[],
+        };
+
+        let parameter_sets = keys
+            .iter()
+            .filter_map(|k| media.fmtp_value(k))
+            .flat_map(|v| v.split(','))
+            .filter_map(|p| STANDARD.decode(p.trim()).ok())
+            .map(Bytes::from)
+            .collect();
+
+        Some(Depacketizer {
+            kind,
+            parameter_sets,
+            nals: Vec::new(),
+            fragment: None,
+            timestamp: 0,
+            next_sequence: None,
+            corrupt: false,
+            received: 0,
+            lost: 0,
+        })
+    }
+
+    fn push(&mut self, packet: RtpPacket) -> Option<Frame> {
+        // A new timestamp starts a new access unit
+        if packet.timestamp != self.timestamp {
+            self.nals.clear();
+            self.fragment = None;
+            self.corrupt = false;
+            self.timestamp = packet.timestamp;
+        }
+
+        if let Some(expected) = self.next_sequence.filter(|s| *s != packet.sequence) {
+            debug!("RTP packet loss before sequence {}", packet.sequence);
+            self.lost += packet.sequence.wrapping_sub(expected) as u64;
+            self.corrupt = true;
+            self.fragment = None;
+        }
+        self.next_sequence = Some(packet.sequence.wrapping_add(1));
+        self.received += 1;
+
+        match self.kind {
+            Kind::Video(VideoCodec::H264) => self.push_h264(packet.payload),
+            Kind::Video(VideoCodec::H265) => self.push_h265(packet.payload),
+            Kind::Metadata => self.nals.push(packet.payload),
+        }
+
+        if !packet.marker {
+            return None;
+        }
+
+        let nals = std::mem::take(&mut self.nals);
+
+        if std::mem::take(&mut self.corrupt) || nals.is_empty() {
+            return None;
+        }
+
+        Some(self.frame(nals))
+    }
+
+    fn push_h264(&mut self, payload: Bytes) {
+        let Some(&header) = payload.first() else {
+            return;
+        };
+
+        match header & 0x1F {
+            // STAP-A: 16 bit size before each NAL unit
+            24 => self.push_aggregated(payload.slice(1..)),
+            // FU-A
+            28 if payload.len() > 2 => {
+                let fu = payload[1];
+
+                if fu & 0x80 != 0 {
+                    let mut nal = BytesMut::new();
+                    nal.extend_from_slice(&[(header & 0xE0) | (fu & 0x1F)]);
+                    self.fragment = Some(nal);
+                }
+
+                self.push_fragment(&payload[2..], fu & 0x40 != 0);
+            }
+            1..=23 => self.nals.push(payload),
+            _ => {}
+        }
+    }
+
+    fn push_h265(&mut self, payload: Bytes) {
+        if payload.len() < 3 {
+            return;
+        }
+
+        match (payload[0] >> 1) & 0x3F {
+            // Aggregation packet
+            48 => self.push_aggregated(payload.slice(2..)),
+            // Fragmentation unit
+            49 => {
+                let fu = payload[2];
+
+                if fu & 0x80 != 0 {
+                    let mut nal = BytesMut::new();
+                    nal.extend_from_slice(&[(payload[0] & 0x81) | ((fu & 0x3F) << 1), payload[1]]);
+                    self.fragment = Some(nal);
+                }
+
+                self.push_fragment(&payload[3..], fu & 0x40 != 0);
+            }
+            _ => self.nals.push(payload),
+        }
+    }
+
+    fn push_aggregated(&mut self, mut data: Bytes) {
+        while data.len() > 2 {
+            let size = u16::from_be_bytes([data[0], data[1]]) as usize;
+
+            if data.len() < 2 + size {
+                self.corrupt = true;
+                return;
+            }
+
+            // An empty unit carries nothing to decode
+            if size > 0 {
+                self.nals.push(data.slice(2..2 + size));
+            }
+            data = data.slice(2 + size..);
+        }
+    }
+
+    fn push_fragment(&mut self, data: &[u8], end: bool) {
+        // A fragment without its start was lost
+        let Some(nal) = self.fragment.as_mut() else {
+            self.corrupt = true;
+            return;
+        };
+
+        nal.extend_from_slice(data);
+
+        if end {
+            if let Some(nal) = self.fragment.take() {
+                self.nals.push(nal.freeze());
+            }
+        }
+    }
+
+    fn frame(&self, nals: Vec<Bytes>) -> Frame {
+        let codec = match self.kind {
+            Kind::Video(codec) => codec,
+            Kind::Metadata => {
+                return Frame::Metadata(MetadataFrame {
+                    timestamp: self.timestamp,
+                    data: nals.concat().into(),
+                })
+            }
+        };
+
+        let nal_type = |nal: &Bytes| match (codec, nal.first()) {
+            (VideoCodec::H264, Some(header)) => header & 0x1F,
+            (VideoCodec::H265, Some(header)) => (header >> 1) & 0x3F,
+            (_, None) => 0,
+        };
+
+        let keyframe = nals.iter().any(|n| match codec {
+            VideoCodec::H264 => nal_type(n) == 5,
+            VideoCodec::H265 => (16..=21).contains(&nal_type(n)),
+        });
+
+        // SPS is type 7 for H.264 and 33 for H.265
+        let has_parameter_sets = nals.iter().any(|n| match codec {
+            VideoCodec::H264 => nal_type(n) == 7,
+            VideoCodec::H265 => nal_type(n) == 33,
+        });
+
+        let mut data = BytesMut::new();
+
+        if keyframe && !has_parameter_sets {
+            for set in &self.parameter_sets {
+                data.extend_from_slice(&START_CODE);
+                data.extend_from_slice(set);
+            }
+        }
+
+        for nal in nals.iter().filter(|n| !n.is_empty()) {
+            data.extend_from_slice(&START_CODE);
+            data.extend_from_slice(nal);
+        }
+
+        Frame::Video(VideoFrame {
+            codec,
+            timestamp: self.timestamp,
+            keyframe,
+            data: data.freeze(),
+        })
+    }
+}
+
+/// A playing RTSP session delivering frames over RTP interleaved on the
+/// RTSP connection (RTP/AVP/TCP), which works through NAT and firewalls.
+/// The session is kept alive while frames are read.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use onvif_cam_rs::device::camera::Camera;
+/// # use onvif_cam_rs::rtsp::stream::Frame;
+/// # async fn run(camera: Camera) -> anyhow::Result<()> {
+/// let mut stream = camera.open_stream(None).await?;
+///
+/// while let Some(frame) = stream.next_frame().await? {
+///     if let Frame::Video(video) = frame {
+///         println!("{} bytes, keyframe: {}", video.data.len(), video.keyframe);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct MediaStream {
+    conn: RtspConnection,
+    sdp: Sdp,
+    transport: RtpTransport,
+    channels: Vec<(u8, Depacketizer)>,
+    udp: Option<UdpReceiver>,
+    pending: VecDeque<Frame>,
+    /// TCP URI, credentials included, and wait for UDP data of a stream
+    /// opened with fallback
+    fallback: Option<(url::Url, Duration)>,
+    session_timeout: Duration,
+    last_keepalive: Instant,
+}
+
+/// How RTP packets reach the client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RtpTransport {
+    /// RTP/AVP over UDP, lowest latency but blocked by many NATs
+    Udp,
+    /// RTP interleaved on the RTSP connection
+    Tcp,
+}
+
+/// Packets received on the UDP ports of every media, tagged with the
+/// channel id of the media they belong to
+struct UdpReceiver {
+    packets: mpsc::Receiver<(u8, Bytes)>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl Drop for UdpReceiver {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// Binds an even RTP port and the RTCP port right above it
+async fn bind_rtp_pair() -> Result<(UdpSocket, UdpSocket)> {
+    for _ in 0..16 {
+        let rtp = UdpSocket::bind("0.0.0.0:0").await?;
+        let port = rtp.local_addr()?.port();
+
+        if port % 2 != 0 {
+            continue;
+        }
+
+        if let Ok(rtcp) = UdpSocket::bind(("0.0.0.0", port + 1)).await {
+            return Ok((rtp, rtcp));
+        }
+    }
+
+    Err(anyhow!("[Rtsp][udp] Unable to bind an RTP/RTCP port pair"))
+}
+
+/// Settings of MediaStream::open_with_fallback()
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct FallbackOptions {
+    /// Time allowed for each request and for UDP packets to start flowing
+    pub wait:           Duration,
+    /// Frames read over UDP before deciding it works
+    pub probe_frames:   usize,
+    /// Highest acceptable share of lost packets during the probe
+    pub max_loss:       f32,
+}
+
+impl Default for FallbackOptions {
+    fn default() -> Self {
+        FallbackOptions {
+            wait: DEFAULT_TIMEOUT,
+            probe_frames: 30,
+            max_loss: 0.02,
+        }
+    }
+}
+
+impl MediaStream {
+    /// Sets up every H.264/H.265 video and metadata media of the stream
+    /// over TCP and starts playing
+    pub async fn open(uri: &url::Url, wait: Duration) -> Result<Self> {
+        Self::open_with(uri, RtpTransport::Tcp, wait).await
+    }
+
+    /// Sets up every H.264/H.265 video and metadata media of the stream
+    /// over the given transport and starts playing
+    pub async fn open_with(
+        uri: &url::Url,
+        transport: RtpTransport,
+        wait: Duration,
+    ) -> Result<Self> {
+        let mut conn = RtspConnection::connect(uri, wait).await?;
+        let (sdp, base) = conn.describe().await?;
+
+        let mut channels = Vec::new();
+        let mut sockets = Vec::new();
+        let mut session_timeout = DEFAULT_SESSION_TIMEOUT;
+
+        for media in &sdp.media {
+            let Some(depacketizer) = Depacketizer::new(media) else {
+                debug!(
+                    "Skipping unsupported media {} {}",
+                    media.media, media.encoding
+                );
+                continue;
+            };
+
+            let channel = 2 * channels.len() as u8;
+            let url = control_url(&base, media.control.as_deref())?;
+            let header = match transport {
+                RtpTransport::Tcp => format!(
+                    "RTP/AVP/TCP;unicast;interleaved={}-{}",
+                    channel,
+                    channel + 1
+                ),
+                RtpTransport::Udp => {
+                    let (rtp, rtcp) = bind_rtp_pair().await?;
+                    let port = rtp.local_addr()?.port();
+                    sockets.push((channel, rtp, rtcp));
+
+                    format!("RTP/AVP;unicast;client_port={}-{}", port, port + 1)
+                }
+            };
+
+            let response = conn
+                .request("SETUP", &url, &[("Transport", header)])
+                .await?;
+
+            if let Some(timeout) = response
+                .header("Session")
+                .and_then(|s| s.split_once("timeout="))
+                .and_then(|(_, t)| t.trim().parse().ok())
+            {
+                session_timeout = Duration::from_secs(timeout);
+            }
+
+            // The server may pick other channels than the ones asked for
+            let channel = match transport {
+                RtpTransport::Tcp => response
+                    .header("Transport")
+                    .and_then(|t| t.split_once("interleaved="))
+                    .and_then(|(_, i)| i.split(['-', ';']).next())
+                    .and_then(|c| c.trim().parse().ok())
+                    .unwrap_or(channel),
+                RtpTransport::Udp => channel,
+            };
+
+            channels.push((channel, depacketizer));
+        }
+
+        if channels.is_empty() {
+            return Err(anyhow!("[Rtsp][open] No supported media in {uri}"));
+        }
+
+        let udp = match transport {
+            RtpTransport::Tcp => None,
+            RtpTransport::Udp => Some(Self::receive_udp(sockets)),
+        };
+
+        let play_url = control_url(&base, sdp.control.as_deref())?;
+        conn.request("PLAY", &play_url, &[("Range", "npt=0.000-".to_string())])
+            .await?;
+
+        Ok(MediaStream {
+            conn,
+            sdp,
+            transport,
+            channels,
+            udp,
+            pending: VecDeque::new(),
+            fallback: None,
+            session_timeout,
+            last_keepalive: Instant::now(),
+        })
+    }
+
+    fn receive_udp(sockets: Vec<(u8, UdpSocket, UdpSocket)>) -> UdpReceiver {
+        let (sender, packets) = mpsc::channel(1024);
+
+        let tasks = sockets
+            .into_iter()
+            .map(|(channel, rtp, _rtcp)| {
+                let sender = sender.clone();
+
+                tokio::spawn(async move {
+                    // Keep the RTCP socket bound so the server can reach it
+                    let _rtcp = _rtcp;
+                    let mut buf = vec![0u8; 65536];
+
+                    while let Ok(size) = rtp.recv(&mut buf).await {
+                        let data = Bytes::copy_from_slice(&buf[..size]);
+
+                        if sender.send((channel, data)).await.is_err() {
+                            break;
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        UdpReceiver { packets, tasks }
+    }
+
+    /// Tries RTP over UDP first and falls back to RTP over the RTSP
+    /// connection when no frames arrive in time (typically NAT or a
+    /// firewall) or too many packets are lost. `tcp_uri` is used for the
+    /// fallback, for cameras handing out a different URI per transport.
+    /// UDP streams that stall later on also switch to TCP by themselves.
+    pub async fn open_with_fallback(
+        udp_uri: &url::Url,
+        tcp_uri: &url::Url,
+        options: &FallbackOptions,
+    ) -> Result<Self> {
+        match Self::probe_udp(udp_uri, options).await {
+            Ok(mut stream) => {
+                stream.fallback = Some((tcp_uri.clone(), options.wait));
+                Ok(stream)
+            }
+            Err(e) => {
+                info!("RTP over UDP unusable ({e}), falling back to TCP");
+                Self::open_with(tcp_uri, RtpTransport::Tcp, options.wait).await
+            }
+        }
+    }
+
+    async fn probe_udp(uri: &url::Url, options: &FallbackOptions) -> Result<Self> {
+        let mut stream = Self::open_with(uri, RtpTransport::Udp, options.wait).await?;
+        let mut frames = VecDeque::new();
+
+        while frames.len() < options.probe_frames {
+            match tokio::time::timeout(options.wait, stream.next_frame()).await {
+                Ok(Ok(Some(frame))) => frames.push_back(frame),
+                Ok(Ok(None)) => return Err(anyhow!("[Rtsp][fallback] Session ended")),
+                Ok(Err(e)) => return Err(e),
+                Err(_) => {
+                    let _ = stream.teardown().await;
+                    return Err(anyhow!("[Rtsp][fallback] No frames over UDP"));
+                }
+            }
+        }
+
+        let loss = stream.loss_ratio();
+
+        if loss > options.max_loss {
+            let _ = stream.teardown().await;
+            return Err(anyhow!("[Rtsp][fallback] {:.1}% packet loss", loss * 100.0));
+        }
+
+        stream.pending = frames;
+
+        Ok(stream)
+    }
+
+    /// The transport the stream ended up using
+    pub fn transport(&self) -> RtpTransport {
+        self.transport
+    }
+
+    /// Share of RTP packets lost since the stream was opened
+    pub fn loss_ratio(&self) -> f32 {
+        let (received, lost) = self
+            .channels
+            .iter()
+            .fold((0, 0), |(r, l), (_, d)| (r + d.received, l + d.lost));
+
+        match received + lost {
+            0 => 0.0,
+            total => lost as f32 / total as f32,
+        }
+    }
+
+    /// The session description returned by DESCRIBE
+    pub fn sdp(&self) -> &Sdp {
+        &self.sdp
+    }
+
+    /// Waits for the next complete frame. Returns None once the server
+    /// ends the session.
+    pub async fn next_frame(&mut self) -> Result<Option<Frame>> {
+        if let Some(frame) = self.pending.pop_front() {
+            return Ok(Some(frame));
+        }
+
+        loop {
+            if self.last_keepalive.elapsed() > self.session_timeout / 2 {
+                // The answer arrives with the stream data and is skipped below
+                let uri = self.conn.uri().clone();
+                self.conn.send("GET_PARAMETER", &uri, &[], true).await?;
+                self.last_keepalive = Instant::now();
+            }
+
+            let wait = match (self.transport, &self.fallback) {
+                (RtpTransport::Udp, Some((_, wait))) => *wait,
+                _ => self.session_timeout,
+            };
+
+            let received = match self.udp.as_mut() {
+                Some(udp) => tokio::select! {
+                    packet = udp.packets.recv() => Ok(packet.map(|(channel, data)| Message::Interleaved { channel, data })),
+                    message = self.conn.read_message() => message,
+                    _ = tokio::time::sleep(wait) => return self.stalled().await,
+                },
+                None => match tokio::time::timeout(wait, self.conn.read_message()).await {
+                    Ok(message) => message,
+                    Err(_) => return self.stalled().await,
+                },
+            };
+
+            let (channel, data) = match received? {
+                Some(Message::Interleaved { channel, data }) => (channel, data),
+                Some(Message::Response(response)) => {
+                    if !response.is_success() {
+                        warn!("RTSP keepalive answered {}", response.status);
+                    }
+                    continue;
+                }
+                None => return Ok(None),
+            };
+
+            // Odd channels carry RTCP, which is not needed to receive frames
+            let Some((_, depacketizer)) = self.channels.iter_mut().find(|(c, _)| *c == channel)
+            else {
+                continue;
+            };
+
+            match RtpPacket::parse(data) {
+                Ok(packet) => {
+                    if let Some(frame) = depacketizer.push(packet) {
+                        return Ok(Some(frame));
+                    }
+                }
+                Err(e) => debug!("{e}"),
+            }
+        }
+    }
+
+    /// No data within the wait time. A UDP stream opened with fallback is
+    /// reopened over TCP, anything else is an error.
+    async fn stalled(&mut self) -> Result<Option<Frame>> {
+        let (tcp_uri, wait) = match (self.transport, self.fallback.take()) {
+            (RtpTransport::Udp, Some(fallback)) => fallback,
+            _ => return Err(anyhow!("[Rtsp][next_frame] Stream stalled")),
+        };
+
+        info!("RTP over UDP stalled, switching to TCP");
+
+        let uri = self.conn.uri().clone();
+        let _ = self.conn.request("TEARDOWN", &uri, &[]).await;
+
+        // The connection URI lost its credentials, the TCP one has them
+        *self = Self::open_with(&tcp_uri, RtpTransport::Tcp, wait).await?;
+
+        Box::pin(self.next_frame()).await
+    }
+
+    /// The frames as a futures Stream
+    pub fn frames(self) -> impl Stream<Item = Result<Frame>> {
+        futures::stream::try_unfold(self, |mut stream| async move {
+            Ok(stream.next_frame().await?.map(|frame| (frame, stream)))
+        })
+    }
+
+    /// Ends the session on the server
+    pub async fn teardown(mut self) -> Result<()> {
+        let uri = self.conn.uri().clone();
+        self.conn.request("TEARDOWN", &uri, &[]).await?;
+        Ok(())
+    }
+}
+
+impl Camera {
+    /// Opens the camera stream over UDP, falling back to TCP when UDP
+    /// does not get through, see MediaStream::open_with_fallback(). The
+    /// URI is requested per transport when a profile is given.
+    pub async fn open_stream_with_fallback(
+        &self,
+        profile: Option<&str>,
+        options: &FallbackOptions,
+    ) -> Result<MediaStream> {
+        let uri_for = |transport| async move {
+            let uri = match profile {
+                None => self.stream.uri.clone(),
+                Some(token) => {
+                    let uri = media::get_stream_uri(media::media_url(self)?, token, transport);
+                    self.reachable_stream(uri.await?).uri
+                }
+            };
+
+            uri.ok_or_else(|| anyhow!("[Rtsp][open_stream] Camera has no stream uri"))?
+                .parse::<url::Url>()
+                .map_err(anyhow::Error::from)
+        };
+
+        let udp_uri = uri_for(StreamTransport::RtpUnicast).await?;
+        let tcp_uri = uri_for(StreamTransport::RtspOverTcp).await?;
+
+        MediaStream::open_with_fallback(&udp_uri, &tcp_uri, options).await
+    }
+
+    /// Opens the camera stream with the built-in RTSP client. With no
+    /// profile the stream URI found by build_all() is used, or requested
+    /// again when it has expired, otherwise the URI of the given media
+    /// profile is requested first.
+    pub async fn open_stream(&self, profile: Option<&str>) -> Result<MediaStream> {
+        let uri = match profile {
+            None if !self.stream.is_expired() => self.stream.uri.clone(),
+            None => {
+                let token = self.profile_token()?;
+                let uri = media::get_stream_uri(media::media_url(self)?, token, self.transport);
+                self.reachable_stream(uri.await?).uri
+            }
+            Some(token) => {
+                let uri = media::get_stream_uri(
+                    media::media_url(self)?,
+                    token,
+                    StreamTransport::RtspOverTcp,
+                );
+                self.reachable_stream(uri.await?).uri
+            }
+        }
+        .ok_or_else(|| anyhow!("[Rtsp][open_stream] Camera has no stream uri"))?;
+
+        MediaStream::open(&uri.parse()?, DEFAULT_TIMEOUT).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // SPS 67 42 and PPS 68 CE
+    const H264_SDP: &str = "v=0\r\n\
+        m=video 0 RTP/AVP 96\r\n\
+        a=rtpmap:96 H264/90000\r\n\
+        a=fmtp:96 packetization-mode=1;sprop-parameter-sets=Z0I=,aM4=\r\n";
+
+    const H265_SDP: &str = "v=0\r\n\
+        m=video 0 RTP/AVP 96\r\n\
+        a=rtpmap:96 H265/90000\r\n";
+
+    const METADATA_SDP: &str = "v=0\r\n\
+        m=application 0 RTP/AVP 107\r\n\
+        a=rtpmap:107 vnd.onvif.metadata/90000\r\n";
+
+    fn depacketizer(sdp: &str) -> Depacketizer {
+        Depacketizer::new(&Sdp::parse(sdp).unwrap().media[0]).unwrap()
+    }
+
+    fn packet(sequence: u16, timestamp: u32, marker: bool, payload: &[u8]) -> RtpPacket {
+        let mut data = vec![0x80, 96 | if marker { 0x80 } else { 0 }];
+        data.extend_from_slice(&sequence.to_be_bytes());
+        data.extend_from_slice(&timestamp.to_be_bytes());
+        data.extend_from_slice(&[0, 0, 0, 1]);
+        data.extend_from_slice(payload);
+        RtpPacket::parse(Bytes::from(data)).unwrap()
+    }
+
+    fn video(frame: Option<Frame>) -> VideoFrame {
+        match frame {
+            Some(Frame::Video(video)) => video,
+            other => panic!("Expected a video frame, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn rtp_packet_parse_csrc_extension_and_padding() {
+        let mut data = vec![0xB1, 0xE0, 0x00, 0x07, 0, 0, 0x03, 0xE8, 0, 0, 0, 1];
+        // One CSRC, then an extension of one word
+        data.extend_from_slice(&[0, 0, 0, 2]);
+        data.extend_from_slice(&[0xBE, 0xDE, 0x00, 0x01, 1, 2, 3, 4]);
+        data.extend_from_slice(&[0x41, 0x9A]);
+        // Two bytes of padding, the last one giving the count
+        data.extend_from_slice(&[0, 2]);
+
+        let packet = RtpPacket::parse(Bytes::from(data)).unwrap();
+
+        assert_eq!(packet.sequence, 7);
+        assert_eq!(packet.timestamp, 1000);
+        assert!(packet.marker);
+        assert_eq!(&packet.payload[..], &[0x41, 0x9A]);
+    }
+
+    #[test]
+    fn rtp_packet_parse_rejects() {
+        assert!(RtpPacket::parse(Bytes::from_static(&[0x80, 96, 0, 1])).is_err());
+        assert!(RtpPacket::parse(Bytes::from_static(&[0x40; 12])).is_err());
+        // Extension announced past the end of the packet
+        assert!(RtpPacket::parse(Bytes::from_static(&[
+            0x90, 96, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0xBE, 0xDE, 0, 4
+        ]))
+        .is_err());
+    }
+
+    #[test]
+    fn h264_single_nal_units() {
+        let mut depacketizer = depacketizer(H264_SDP);
+
+        assert!(depacketizer
+            .push(packet(1, 3000, false, &[0x09, 0xF0]))
+            .is_none());
+        let frame = video(depacketizer.push(packet(2, 3000, true, &[0x41, 0x9A])));
+
+        assert_eq!(frame.codec, VideoCodec::H264);
+        assert_eq!(frame.timestamp, 3000);
+        assert!(!frame.keyframe);
+        assert_eq!(
+            &frame.data[..],
+            &[0, 0, 0, 1, 0x09, 0xF0, 0, 0, 0, 1, 0x41, 0x9A]
+        );
+    }
+
+    #[test]
+    fn h264_keyframe_gets_parameter_sets() {
+        let mut depacketizer = depacketizer(H264_SDP);
+        let frame = video(depacketizer.push(packet(1, 3000, true, &[0x65, 0x88])));
+
+        assert!(frame.keyframe);
+        assert_eq!(
+            &frame.data[..],
+            &[0, 0, 0, 1, 0x67, 0x42, 0, 0, 0, 1, 0x68, 0xCE, 0, 0, 0, 1, 0x65, 0x88]
+        );
+    }
+
+    #[test]
+    fn h264_keyframe_keeps_its_own_parameter_sets() {
+        let mut depacketizer = depacketizer(H264_SDP);
+
+        depacketizer.push(packet(1, 3000, false, &[0x67, 0x64]));
+        let frame = video(depacketizer.push(packet(2, 3000, true, &[0x65, 0x88])));
+
+        assert_eq!(
+            &frame.data[..],
+            &[0, 0, 0, 1, 0x67, 0x64, 0, 0, 0, 1, 0x65, 0x88]
+        );
+    }
+
+    #[test]
+    fn h264_fu_a() {
+        let mut depacketizer = depacketizer(H264_SDP);
+
+        // FU indicator 0x7C (NRI 3, type 28), FU headers start/middle/end of an IDR
+        assert!(depacketizer
+            .push(packet(1, 3000, false, &[0x7C, 0x85, 1, 2]))
+            .is_none());
+        assert!(depacketizer
+            .push(packet(2, 3000, false, &[0x7C, 0x05, 3]))
+            .is_none());
+        let frame = video(depacketizer.push(packet(3, 3000, true, &[0x7C, 0x45, 4])));
+
+        assert!(frame.keyframe);
+        assert!(frame.data.ends_with(&[0, 0, 0, 1, 0x65, 1, 2, 3, 4]));
+    }
+
+    #[test]
+    fn h264_stap_a_skips_empty_units() {
+        let mut depacketizer = depacketizer(H264_SDP);
+        let payload = [
+            0x78, 0, 2, 0x67, 0x4D, 0, 0, 0, 2, 0x68, 0xEE, 0, 2, 0x65, 0x88,
+        ];
+        let frame = video(depacketizer.push(packet(1, 3000, true, &payload)));
+
+        assert!(frame.keyframe);
+        assert_eq!(
+            &frame.data[..],
+            &[0, 0, 0, 1, 0x67, 0x4D, 0, 0, 0, 1, 0x68, 0xEE, 0, 0, 0, 1, 0x65, 0x88]
+        );
+    }
+
+    #[test]
+    fn truncated_stap_a_drops_the_frame() {
+        let mut depacketizer = depacketizer(H264_SDP);
+
+        assert!(depacketizer
+            .push(packet(1, 3000, true, &[0x78, 0, 9, 0x67]))
+            .is_none());
+    }
+
+    #[test]
+    fn packet_loss_drops_the_frame() {
+        let mut depacketizer = depacketizer(H264_SDP);
+
+        assert!(depacketizer
+            .push(packet(1, 3000, true, &[0x41, 0x9A]))
+            .is_some());
+        assert!(depacketizer
+            .push(packet(3, 6000, true, &[0x41, 0x9A]))
+            .is_none());
+        assert_eq!(depacketizer.lost, 1);
+        assert_eq!(depacketizer.received, 2);
+
+        // The next access unit is complete again
+        assert!(depacketizer
+            .push(packet(4, 9000, true, &[0x41, 0x9A]))
+            .is_some());
+    }
+
+    #[test]
+    fn fragment_without_start_drops_the_frame() {
+        let mut depacketizer = depacketizer(H264_SDP);
+
+        assert!(depacketizer
+            .push(packet(1, 3000, true, &[0x7C, 0x45, 4]))
+            .is_none());
+    }
+
+    #[test]
+    fn h265_fragmentation_unit() {
+        let mut depacketizer = depacketizer(H265_SDP);
+
+        // Payload header type 49, FU header start/end of an IDR_W_RADL (19)
+        assert!(depacketizer
+            .push(packet(1, 3000, false, &[0x62, 0x01, 0x93, 1]))
+            .is_none());
+        let frame = video(depacketizer.push(packet(2, 3000, true, &[0x62, 0x01, 0x53, 2])));
+
+        assert_eq!(frame.codec, VideoCodec::H265);
+        assert!(frame.keyframe);
+        assert_eq!(&frame.data[..], &[0, 0, 0, 1, 0x26, 0x01, 1, 2]);
+    }
+
+    #[test]
+    fn metadata_payloads_concatenated() {
+        let mut depacketizer = depacketizer(METADATA_SDP);
+
+        assert!(depacketizer
+            .push(packet(1, 3000, false, b"<tt:Metadata"))
+            .is_none());
+        match depacketizer.push(packet(2, 3000, true, b"Stream/>")) {
+            Some(Frame::Metadata(metadata)) => {
+                assert_eq!(&metadata.data[..], b"<tt:MetadataStream/>")
+            }
+            other => panic!("Expected a metadata frame, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,385 @@
+use crate::client::{self, Messages};
+use crate::recording::{SearchScope, TrackType};
+use crate::utils::xml::XmlNode;
+use crate::utils::{parse_xsd_datetime, xml_escape, xsd_datetime, xsd_duration};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use log::{debug, info};
+use std::time::Duration;
+
+/// Time span covered by all recordings on the device
+#[derive(Default, Debug, Clone)]
+#[rustfmt::skip]
+pub struct RecordingSummary {
+    pub data_from:           Option<DateTime<Utc>>,
+    pub data_until:          Option<DateTime<Utc>>,
+    pub number_recordings:   u32,
+}
+
+#[derive(Default, Debug, Clone)]
+#[rustfmt::skip]
+pub struct VideoAttributes {
+    pub width:       u32,
+    pub height:      u32,
+    pub encoding:    String,
+    pub framerate:   f32,
+    pub bitrate:     Option<u32>,
+}
+
+/// Time range and media properties of a single recorded track
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct TrackAttributes {
+    pub token:         String,
+    pub track_type:    TrackType,
+    pub description:   String,
+    pub data_from:     Option<DateTime<Utc>>,
+    pub data_to:       Option<DateTime<Utc>>,
+    pub video:         Option<VideoAttributes>,
+}
+
+/// Media attributes of a recording at a given point in time
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct MediaAttributes {
+    pub recording_token:   String,
+    pub tracks:            Vec<TrackAttributes>,
+    pub from:              Option<DateTime<Utc>>,
+    pub until:             Option<DateTime<Utc>>,
+}
+
+impl TrackAttributes {
+    pub(crate) fn from_xml(node: &XmlNode) -> Self {
+        let info = node.child("TrackInformation");
+        let text = |name: &str| info.and_then(|i| i.child_text(name));
+
+        TrackAttributes {
+            token: text("TrackToken").unwrap_or_default(),
+            track_type: text("TrackType")
+                .map(|t| TrackType::from(t.as_str()))
+                .unwrap_or(TrackType::Extended),
+            description: text("Description").unwrap_or_default(),
+            data_from: text("DataFrom").and_then(|t| parse_xsd_datetime(&t)),
+            data_to: text("DataTo").and_then(|t| parse_xsd_datetime(&t)),
+            video: node.child("VideoAttributes").map(|v| VideoAttributes {
+                width: v
+                    .child_text("Width")
+                    .and_then(|w| w.parse().ok())
+                    .unwrap_or_default(),
+                height: v
+                    .child_text("Height")
+                    .and_then(|h| h.parse().ok())
+                    .unwrap_or_default(),
+                encoding: v.child_text("Encoding").unwrap_or_default(),
+                framerate: v
+                    .child_text("Framerate")
+                    .and_then(|f| f.parse().ok())
+                    .unwrap_or_default(),
+                bitrate: v.child_text("Bitrate").and_then(|b| b.parse().ok()),
+            }),
+        }
+    }
+}
+
+/// Returns the overall time span and number of recordings on the device
+///
+/// # Arguments
+///
+/// * `onvif_url` - The Search service URL (see `Services::search`)
+#[cfg(feature = "http")]
+pub async fn get_recording_summary(onvif_url: url::Url) -> Result<RecordingSummary> {
+    let response = client::send(onvif_url, Messages::GetRecordingSummary).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+    let summary = root.find("Summary");
+    let text = |name: &str| summary.and_then(|s| s.child_text(name));
+
+    let result = RecordingSummary {
+        data_from: text("DataFrom").and_then(|t| parse_xsd_datetime(&t)),
+        data_until: text("DataUntil").and_then(|t| parse_xsd_datetime(&t)),
+        number_recordings: text("NumberRecordings")
+            .and_then(|n| n.parse().ok())
+            .unwrap_or_default(),
+    };
+
+    info!("Recording summary: {result:?}");
+
+    Ok(result)
+}
+
+/// Returns the media attributes of the given recordings at `time`,
+/// including the time range each track actually holds data for
+#[cfg(feature = "http")]
+pub async fn get_media_attributes(
+    onvif_url: url::Url,
+    recording_tokens: &[String],
+    time: DateTime<Utc>,
+) -> Result<Vec<MediaAttributes>> {
+    let msg = Messages::GetMediaAttributes {
+        recording_tokens: recording_tokens.to_vec(),
+        time,
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    let result = root
+        .find_all("MediaAttributes")
+        .into_iter()
+        .map(|m| MediaAttributes {
+            recording_token: m.child_text("RecordingToken").unwrap_or_default(),
+            tracks: m
+                .children_named("TrackAttributes")
+                .map(TrackAttributes::from_xml)
+                .collect(),
+            from: m.child_text("From").and_then(|t| parse_xsd_datetime(&t)),
+            until: m.child_text("Until").and_then(|t| parse_xsd_datetime(&t)),
+        })
+        .collect();
+
+    Ok(result)
+}
+
+/// Which recorded metadata a FindMetadata search should match
+#[derive(Debug, Clone)]
+pub enum MetadataFilter {
+    /// Any metadata at all
+    Any,
+    /// Motion alarms (IsMotion / State set to true in a metadata event)
+    Motion,
+    /// Analytics objects classified as the given type, e.g. "Human" or "Vehicle"
+    ObjectClass(String),
+    /// A raw XPath expression applied to the metadata stream
+    XPath(String),
+}
+
+impl MetadataFilter {
+    /// The XPath expression sent as MetadataStreamFilter
+    pub fn xpath(&self) -> String {
+        match self {
+            MetadataFilter::Any => "boolean(//tt:MetadataStream)".to_string(),
+            MetadataFilter::Motion => concat!(
+                "boolean(//tt:Event//tt:SimpleItem[(@Name=\"IsMotion\" or @Name=\"State\")",
+                " and @Value=\"true\"])"
+            )
+            .to_string(),
+            MetadataFilter::ObjectClass(class) => format!(
+                "boolean(//tt:Object/tt:Appearance/tt:Class/tt:Type[text()={}])",
+                xpath_literal(class)
+            ),
+            MetadataFilter::XPath(xpath) => xpath.clone(),
+        }
+    }
+}
+
+/// Parameters of a FindMetadata request
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct MetadataSearch {
+    pub start_point:   DateTime<Utc>,
+    pub end_point:     Option<DateTime<Utc>>,
+    pub scope:         SearchScope,
+    pub filter:        MetadataFilter,
+    pub max_matches:   Option<u32>,
+    pub keep_alive:    Duration,
+    /// Longest search_metadata() waits for the device to complete the search
+    pub timeout:       Duration,
+}
+
+impl MetadataSearch {
+    pub(crate) fn to_soap(&self) -> String {
+        let end_point = match &self.end_point {
+            Some(end) => format!("<tse:EndPoint>{}</tse:EndPoint>", xsd_datetime(end)),
+            None => String::new(),
+        };
+
+        let max_matches = match self.max_matches {
+            Some(max) => format!("<tse:MaxMatches>{max}</tse:MaxMatches>"),
+            None => String::new(),
+        };
+
+        format!(
+            "<tse:StartPoint>{}</tse:StartPoint>
+            {end_point}
+            <tse:Scope>{}</tse:Scope>
+            <tse:MetadataFilter>
+                <tt:MetadataStreamFilter>{}</tt:MetadataStreamFilter>
+            </tse:MetadataFilter>
+            {max_matches}
+            <tse:KeepAliveTime>{}</tse:KeepAliveTime>",
+            xsd_datetime(&self.start_point),
+            self.scope.to_soap(),
+            xml_escape(&self.filter.xpath()),
+            xsd_duration(self.keep_alive),
+        )
+    }
+}
+
+/// State of a search session on the device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchState {
+    Queued,
+    Searching,
+    Completed,
+    Unknown,
+}
+
+impl From<&str> for SearchState {
+    fn from(input: &str) -> Self {
+        match input.trim() {
+            "Queued" => SearchState::Queued,
+            "Searching" => SearchState::Searching,
+            "Completed" => SearchState::Completed,
+            _ => SearchState::Unknown,
+        }
+    }
+}
+
+/// A point in a recording where the metadata matched the filter
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct FindMetadataResult {
+    pub recording_token:   String,
+    pub track_token:       String,
+    pub time:              Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone)]
+#[rustfmt::skip]
+pub struct MetadataSearchResults {
+    pub state:     SearchState,
+    pub results:   Vec<FindMetadataResult>,
+}
+
+/// Starts a metadata search and returns the search token used to
+/// fetch results with get_metadata_search_results()
+#[cfg(feature = "http")]
+pub async fn find_metadata(onvif_url: url::Url, search: &MetadataSearch) -> Result<String> {
+    let msg = Messages::FindMetadata {
+        search: search.clone(),
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    root.find("SearchToken")
+        .map(|t| t.text.trim().to_string())
+        .ok_or_else(|| anyhow!("[Search][find_metadata] No SearchToken in response"))
+}
+
+/// Fetches the next batch of results of a metadata search, waiting up to
+/// `wait_time` on the device for new matches
+#[cfg(feature = "http")]
+pub async fn get_metadata_search_results(
+    onvif_url: url::Url,
+    search_token: &str,
+    max_results: Option<u32>,
+    wait_time: Option<Duration>,
+) -> Result<MetadataSearchResults> {
+    let msg = Messages::GetMetadataSearchResults {
+        search_token: search_token.to_string(),
+        max_results,
+        wait_time,
+    };
+
+    let response = client::send(onvif_url, msg).await?;
+    let response = response.error_for_status()?.bytes().await?;
+    let root = XmlNode::parse(&response[..])?;
+
+    let state = root
+        .find("SearchState")
+        .map(|s| SearchState::from(s.text.as_str()))
+        .unwrap_or(SearchState::Unknown);
+
+    let results = root
+        .find_all("Result")
+        .into_iter()
+        .map(|r| FindMetadataResult {
+            recording_token: r.child_text("RecordingToken").unwrap_or_default(),
+            track_token: r.child_text("TrackToken").unwrap_or_default(),
+            time: r.child_text("Time").and_then(|t| parse_xsd_datetime(&t)),
+        })
+        .collect();
+
+    Ok(MetadataSearchResults { state, results })
+}
+
+/// Ends a search session and frees it on the device
+#[cfg(feature = "http")]
+pub async fn end_search(onvif_url: url::Url, search_token: &str) -> Result<()> {
+    let msg = Messages::EndSearch {
+        search_token: search_token.to_string(),
+    };
+
+    client::send(onvif_url, msg).await?.error_for_status()?;
+    Ok(())
+}
+
+/// Runs a complete metadata search: starts it, collects every result
+/// until the device reports the search completed or `search.timeout` has
+/// passed, then ends the session. The session is ended on errors too.
+#[cfg(feature = "http")]
+pub async fn search_metadata(
+    onvif_url: url::Url,
+    search: &MetadataSearch,
+) -> Result<Vec<FindMetadataResult>> {
+    let token = find_metadata(onvif_url.clone(), search).await?;
+
+    let found = tokio::time::timeout(search.timeout, collect_results(onvif_url.clone(), &token))
+        .await
+        .unwrap_or_else(|_| {
+            Err(anyhow!(
+                "[Search][search_metadata] Search not completed within {:?}",
+                search.timeout
+            ))
+        });
+    let ended = end_search(onvif_url, &token).await;
+
+    let found = found?;
+    ended?;
+    Ok(found)
+}
+
+/// Results of a search session until the device reports it completed
+#[cfg(feature = "http")]
+async fn collect_results(onvif_url: url::Url, token: &str) -> Result<Vec<FindMetadataResult>> {
+    let mut found = Vec::new();
+
+    loop {
+        let batch = get_metadata_search_results(
+            onvif_url.clone(),
+            token,
+            None,
+            Some(Duration::from_secs(1)),
+        )
+        .await?;
+
+        debug!(
+            "Metadata search {:?}: {} results",
+            batch.state,
+            batch.results.len()
+        );
+        found.extend(batch.results);
+
+        if batch.state != SearchState::Queued && batch.state != SearchState::Searching {
+            return Ok(found);
+        }
+    }
+}
+
+/// `value` as an XPath 1.0 string literal, which has no escapes: quoted
+/// with the quote it does not contain, or pieced together with concat()
+fn xpath_literal(value: &str) -> String {
+    if !value.contains('"') {
+        return format!("\"{value}\"");
+    }
+    if !value.contains('\'') {
+        return format!("'{value}'");
+    }
+
+    let parts: Vec<String> = value.split('"').map(|part| format!("\"{part}\"")).collect();
+    format!("concat({})", parts.join(", '\"', "))
+}
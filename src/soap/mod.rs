@@ -0,0 +1,399 @@
+use crate::device::{
+    parse_device_type, AudioEncoderConfig, AudioSourceConfig, Capabilities, Device, DeviceInfo,
+    MediaProfile, ServiceCapabilities, Services, StreamUri, VideoEncoderConfig,
+};
+use crate::utils::xml::XmlNode;
+use crate::utils::{parse_soap, parse_xsd_duration};
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use log::{error, info};
+
+/// The reason of a SOAP 1.2 or 1.1 fault, None when the response is not
+/// a fault or not well formed
+pub fn parse_fault(response: &[u8]) -> Option<String> {
+    let root = XmlNode::parse(response).ok()?;
+    let fault = root.find("Fault")?;
+
+    let reason = fault
+        .find("Reason")
+        .and_then(|r| r.child_text("Text"))
+        .or_else(|| fault.child_text("faultstring"))
+        .unwrap_or_default();
+
+    Some(reason)
+}
+
+/// Fails with the fault reason when the device answered with a fault
+fn check_fault(response: &[u8], parser: &str) -> Result<()> {
+    match parse_fault(response) {
+        Some(reason) => {
+            #[cfg(feature = "metrics")]
+            crate::telemetry::fault(&fault_code(response).unwrap_or_default());
+
+            Err(anyhow!("[Soap][{parser}] Device answered with a fault: {reason}"))
+        }
+        None => Ok(()),
+    }
+}
+
+/// The most specific code of a fault: the SOAP 1.2 subcode, else the code
+/// or the SOAP 1.1 faultcode, e.g. "ter:NotAuthorized"
+pub fn fault_code(response: &[u8]) -> Option<String> {
+    let root = XmlNode::parse(response).ok()?;
+    let fault = root.find("Fault")?;
+
+    match fault.find("Code") {
+        Some(code) => code
+            .find("Subcode")
+            .and_then(|s| s.child_text("Value"))
+            .or_else(|| code.child_text("Value")),
+        None => fault.child_text("faultcode"),
+    }
+}
+
+/// The device announced in a WS-Discovery ProbeMatch
+///
+/// Like the other parsers of this module it needs no network and never
+/// panics, whatever the bytes, which makes it suitable for fuzzing:
+///
+/// ```
+/// # use onvif_cam_rs::soap::parse_probe_match;
+/// assert!(parse_probe_match(b"<Envelope><Body>").is_err());
+/// ```
+pub fn parse_probe_match(response: &[u8]) -> Result<Device> {
+    // The SOAP response should provide an XAddrs which will be the
+    // ONVIF URL of the device that responded
+    // Devices often list several, e.g. IPv4, IPv6 and host name ones
+    let xaddrs: Vec<url::Url> = parse_soap(response, "XAddrs", None, true, false)
+        .first()
+        .map(|x| x.split_whitespace().filter_map(|x| x.parse().ok()).collect())
+        .unwrap_or_default();
+    let url_onvif = xaddrs
+        .first()
+        .cloned()
+        .ok_or_else(|| anyhow!("[Soap][parse_probe_match] No XAddrs in ProbeMatch"))?;
+
+    // Get device type
+    let device_type = parse_soap(response, "Types", None, true, false);
+    let device_type = parse_device_type(device_type.into_iter().next().unwrap_or_default());
+
+    // Get scope list
+    let scopes = parse_soap(response, "Scopes", None, true, false);
+    let scopes = scopes
+        .first()
+        .map(|s| s.split(' ').map(|s| s.to_string()).collect::<Vec<String>>())
+        .unwrap_or_default();
+
+    // Endpoint reference stays the same when the device gets a new address
+    let endpoint = parse_soap(response, "Address", Some("EndpointReference"), true, false)
+        .pop()
+        .map(|e| e.trim().to_string());
+
+    let metadata_version = parse_soap(response, "MetadataVersion", None, true, false)
+        .first()
+        .and_then(|v| v.trim().parse().ok());
+
+    Ok(Device {
+        device_type,
+        scopes,
+        endpoint,
+        metadata_version,
+        xaddrs,
+        probe_match: Some(String::from_utf8_lossy(response).into_owned()),
+        ..Device::new(url_onvif)
+    })
+}
+
+/// The MessageID of the request a WS-Discovery reply answers, from its
+/// RelatesTo header
+pub fn parse_relates_to(response: &[u8]) -> Option<String> {
+    parse_soap(response, "RelatesTo", None, true, false)
+        .pop()
+        .map(|id| id.trim().to_string())
+}
+
+/// Every device of a ProbeMatches or ResolveMatches message. Discovery
+/// Proxies answer a probe with all the devices they know in one message.
+pub fn parse_probe_matches(response: &[u8]) -> Result<Vec<Device>> {
+    check_fault(response, "parse_probe_matches")?;
+
+    let root = XmlNode::parse(response.trim_ascii_start())?;
+    let matches = root
+        .find_all("ProbeMatch")
+        .into_iter()
+        .chain(root.find_all("ResolveMatch"));
+
+    let mut devices = Vec::new();
+
+    for node in matches {
+        let xaddrs: Vec<url::Url> = node
+            .child_text("XAddrs")
+            .unwrap_or_default()
+            .split_whitespace()
+            .filter_map(|x| x.parse().ok())
+            .collect();
+
+        // A match without a usable address is of no use to the caller
+        let Some(url_onvif) = xaddrs.first().cloned() else {
+            continue;
+        };
+
+        devices.push(Device {
+            device_type: parse_device_type(node.child_text("Types").unwrap_or_default()),
+            scopes: node
+                .child_text("Scopes")
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(|s| s.to_string())
+                .collect(),
+            endpoint: node
+                .child("EndpointReference")
+                .and_then(|e| e.child_text("Address"))
+                .map(|e| e.trim().to_string()),
+            metadata_version: node
+                .child_text("MetadataVersion")
+                .and_then(|v| v.trim().parse().ok()),
+            xaddrs,
+            probe_match: Some(String::from_utf8_lossy(response).into_owned()),
+            ..Device::new(url_onvif)
+        });
+    }
+
+    Ok(devices)
+}
+
+/// A WS-Discovery message about a device, as sent to the multicast group
+/// or in answer to a probe
+#[derive(Debug, Clone)]
+pub enum DiscoveryMessage {
+    /// The device joined the network
+    Hello(Device),
+    /// The device is leaving the network, only its endpoint reference is
+    /// sent
+    Bye(String),
+    ProbeMatch(Device),
+}
+
+/// A Hello, Bye or ProbeMatches message. Probes and Resolves of other
+/// clients and Hellos without XAddrs are errors.
+pub fn parse_discovery_message(message: &[u8]) -> Result<DiscoveryMessage> {
+    let root = XmlNode::parse(message.trim_ascii_start())?;
+    let body = root
+        .find("Body")
+        .and_then(|b| b.children.first())
+        .ok_or_else(|| anyhow!("[Soap][parse_discovery_message] No SOAP body"))?;
+
+    match body.name.as_str() {
+        "Hello" => Ok(DiscoveryMessage::Hello(parse_probe_match(message)?)),
+        "ProbeMatches" => Ok(DiscoveryMessage::ProbeMatch(parse_probe_match(message)?)),
+        "Bye" => body
+            .find("EndpointReference")
+            .and_then(|e| e.child_text("Address"))
+            .map(|e| DiscoveryMessage::Bye(e.trim().to_string()))
+            .ok_or_else(|| anyhow!("[Soap][parse_discovery_message] No EndpointReference in Bye")),
+        name => Err(anyhow!(
+            "[Soap][parse_discovery_message] Not about a device: {name}"
+        )),
+    }
+}
+
+/// Service addresses of a GetCapabilities response, services the device
+/// does not list are None
+#[rustfmt::skip]
+pub fn parse_capabilities(response: &[u8]) -> Result<Capabilities> {
+    check_fault(response, "parse_capabilities")?;
+
+    let xaddr = |service: &str| -> Result<Option<url::Url>> {
+        let xaddr = parse_soap(response, "XAddr", Some(service), true, false);
+
+        match xaddr.first() {
+            Some(xaddr) => {
+                info!("{service} service: {xaddr}");
+                Ok(Some(xaddr.trim().parse()?))
+            }
+            None => Ok(None),
+        }
+    };
+
+    let result = Capabilities {
+        url_media:       xaddr("Media")?,
+        url_events:      xaddr("Events")?,
+        url_analytics:   xaddr("Analytics")?,
+        url_ptz:         xaddr("PTZ")?,
+        url_imaging:     xaddr("Imaging")?,
+    };
+
+    Ok(result)
+}
+
+/// A GetDeviceInformation response
+#[rustfmt::skip]
+pub fn parse_device_info(response: &[u8]) -> Result<DeviceInfo> {
+    check_fault(response, "parse_device_info")?;
+
+    let text = |element: &str| parse_soap(response, element, None, true, false).into_iter().next();
+
+    let result = DeviceInfo {
+        firmware_version:    text("FirmwareVersion"),
+        serial_num:          text("SerialNumber"),
+        hardware_id:         text("HardwareId"),
+        model:               text("Model"),
+        manufacturer:        text("Manufacturer"),
+    };
+
+    info!("Manufacturer: {}", result.manufacturer.as_deref().unwrap_or_default());
+    info!("Model: {}", result.model.as_deref().unwrap_or_default());
+
+    Ok(result)
+}
+
+/// The media profiles of a GetProfiles response, in the order of the
+/// device, which usually lists the main stream first
+pub fn parse_profiles(response: &[u8]) -> Result<Vec<MediaProfile>> {
+    check_fault(response, "parse_profiles")?;
+
+    let root = XmlNode::parse(response)?;
+    let profiles: Vec<MediaProfile> = root
+        .find_all("Profiles")
+        .into_iter()
+        .filter_map(parse_media_profile)
+        .collect();
+
+    for profile in &profiles {
+        info!(
+            "Profile {}: {} {:?}, audio {}",
+            profile.token,
+            profile.video_codec().unwrap_or_default(),
+            profile.video_dim().unwrap_or_default(),
+            profile.audio_codec().unwrap_or("none")
+        );
+    }
+
+    Ok(profiles)
+}
+
+/// A Profiles element, None without a token
+pub(crate) fn parse_media_profile(profile: &XmlNode) -> Option<MediaProfile> {
+    fn number<T: std::str::FromStr>(node: &XmlNode, name: &str) -> Option<T> {
+        node.child_text(name)?.parse().ok()
+    }
+
+    let video_encoder = profile
+        .child("VideoEncoderConfiguration")
+        .map(VideoEncoderConfig::from_xml);
+
+    let audio_encoder = profile
+        .child("AudioEncoderConfiguration")
+        .map(|audio| AudioEncoderConfig {
+            token: audio.attr("token").map(str::to_string),
+            name: audio.child_text("Name"),
+            encoding: audio.child_text("Encoding"),
+            bitrate: number(audio, "Bitrate"),
+            sample_rate: number(audio, "SampleRate"),
+        });
+
+    Some(MediaProfile {
+        token: profile.attr("token")?.to_string(),
+        name: profile.child_text("Name"),
+        fixed: profile
+            .attr("fixed")
+            .is_some_and(|f| f.trim().eq_ignore_ascii_case("true")),
+        video_source_config_token: profile
+            .child("VideoSourceConfiguration")
+            .and_then(|source| source.attr("token"))
+            .map(str::to_string),
+        video_source_token: profile
+            .child("VideoSourceConfiguration")
+            .and_then(|source| source.child_text("SourceToken")),
+        video_encoder,
+        audio_source: profile
+            .child("AudioSourceConfiguration")
+            .and_then(AudioSourceConfig::from_xml),
+        audio_encoder,
+        ptz_config_token: profile
+            .child("PTZConfiguration")
+            .and_then(|ptz| ptz.attr("token"))
+            .map(str::to_string),
+        metadata_config_token: profile
+            .child("MetadataConfiguration")
+            .and_then(|metadata| metadata.attr("token"))
+            .map(str::to_string),
+    })
+}
+
+/// A GetStreamUri response, issued now
+#[rustfmt::skip]
+pub fn parse_stream_uri(response: &[u8]) -> Result<StreamUri> {
+    check_fault(response, "parse_stream_uri")?;
+
+    let first = |element: &str| parse_soap(response, element, None, true, false).into_iter().next();
+
+    let uri = first("Uri").ok_or_else(|| anyhow!("[Soap][parse_stream_uri] No Uri in response"))?;
+    info!("RTSP URL: {uri}");
+
+    let result = StreamUri {
+        invalid_after_connect:   first("InvalidAfterConnect").is_some_and(|i| i.trim().eq_ignore_ascii_case("true")),
+        uri:                     Some(uri),
+        timeout:                 first("Timeout").and_then(|t| parse_xsd_duration(&t)),
+        issued:                  Some(Utc::now()),
+    };
+
+    Ok(result)
+}
+
+/// Service addresses of a GetServices response, matched on keywords of
+/// their URL
+#[rustfmt::skip]
+pub fn parse_services(response: &[u8]) -> Result<Services> {
+    check_fault(response, "parse_services")?;
+
+    let services         = parse_soap(response, "XAddr", None, false, false);
+    let mut result       = Services::default();
+
+    for service in services {
+        info!("Service: {}", service);
+
+        // Vendors spell the newer services e.g. "AccessControl" or "access_control"
+        let keyword = service.to_lowercase().replace('_', "");
+
+        // Match Service URL Address by keywords
+        match &service {
+            s if s.contains("device_service")    =>(),
+            _ if keyword.contains("accesscontrol") => result.access_control = Some(service.clone()),
+            _ if keyword.contains("doorcontrol")   => result.door_control   = Some(service.clone()),
+            s if s.contains("provisioning")      => result.provisioning = Some(service.clone()),
+            s if s.contains("recording")         => result.recording    = Some(service.clone()),
+            s if s.contains("search")            => result.search       = Some(service.clone()),
+            s if s.contains("analytics")         => result.analytics    = Some(service.clone()),
+            s if s.contains("event")             => result.event        = Some(service.clone()),
+            s if s.contains("deviceIO")          => result.io           = Some(service.clone()),
+            s if s.contains("imaging")           => result.imaging      = Some(service.clone()),
+            s if s.contains("media_service")     => result.media        = Some(service.clone()),
+            s if s.contains("media2")            => result.media2       = Some(service.clone()),
+            s if s.contains("ptz")               => result.ptz          = Some(service.clone()),
+            _ => error!("Encountered unknown Service"),
+        }
+    }
+
+    Ok(result)
+}
+
+/// The attributes of the Capabilities element of a GetServiceCapabilities
+/// response, values keep the quotes parse_soap() puts around them
+pub fn parse_service_capabilities<T>(response: &[u8]) -> Result<T>
+where
+    T: ServiceCapabilities + Default,
+{
+    check_fault(response, "parse_service_capabilities")?;
+
+    let capabilities = parse_soap(response, "Capabilities", None, true, true);
+    let mut result = T::default();
+
+    capabilities
+        .iter()
+        .filter_map(|c| c.split_once('='))
+        .for_each(|pair| result.set_prop_with_pair(pair));
+
+    Ok(result)
+}
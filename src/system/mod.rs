@@ -0,0 +1,123 @@
+use crate::client::{self, Messages};
+use crate::utils::xml::XmlNode;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// Clock settings of a device, as answered by GetSystemDateAndTime
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[rustfmt::skip]
+pub struct DateTimeSettings {
+    /// The device keeps its clock with NTP
+    pub ntp:                bool,
+    pub daylight_savings:   bool,
+    /// POSIX TZ string, e.g. "CET-1CEST,M3.5.0,M10.5.0/3"
+    pub time_zone:          Option<String>,
+    pub utc:                DateTime<Utc>,
+}
+
+/// Sets the device clock manually to the given UTC time. The daylight
+/// saving and time zone of the device are read first and kept. Devices
+/// that keep their clock with NTP are left alone, as a manual time would
+/// turn NTP off; fix their NTP server instead.
+///
+/// # Arguments
+///
+/// * `onvif_url` - The main ONVIF device service URL
+/// * `time` - The time to set, usually `Utc::now()`
+pub async fn set_system_date_and_time(onvif_url: url::Url, time: DateTime<Utc>) -> Result<()> {
+    let settings = get_date_time_settings(onvif_url.clone()).await?;
+    if settings.ntp {
+        return Err(anyhow!(
+            "[System][set_system_date_and_time] Device keeps its clock with NTP, not setting it manually"
+        ));
+    }
+
+    let msg = Messages::SetSystemDateAndTime {
+        time,
+        daylight_savings: settings.daylight_savings,
+        time_zone: settings.time_zone,
+    };
+
+    client::send(onvif_url.clone(), msg)
+        .await?
+        .error_for_status()?;
+
+    // The clock skew measured before no longer holds
+    client::forget_clock_offset(&onvif_url);
+    Ok(())
+}
+
+/// Reads the clock settings of a device, NTP, daylight saving and time
+/// zone along with its time
+///
+/// # Arguments
+///
+/// * `onvif_url` - The main ONVIF device service URL
+pub async fn get_date_time_settings(onvif_url: url::Url) -> Result<DateTimeSettings> {
+    let response = client::send(onvif_url, Messages::GetSystemDateAndTime)
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+
+    let utc = parse_system_date_and_time(&response)?;
+    let root = XmlNode::parse(&response[..])?;
+    let settings = root.find("SystemDateAndTime");
+    let text = |name: &str| settings.and_then(|s| s.child_text(name));
+
+    Ok(DateTimeSettings {
+        ntp: text("DateTimeType").is_some_and(|t| t.eq_ignore_ascii_case("NTP")),
+        daylight_savings: text("DaylightSavings").is_some_and(|d| d == "true"),
+        time_zone: settings
+            .and_then(|s| s.child("TimeZone"))
+            .and_then(|tz| tz.child_text("TZ"))
+            .filter(|tz| !tz.is_empty()),
+        utc,
+    })
+}
+
+/// Reads the device clock. No credentials are needed, which makes it the
+/// usual way to measure the clock skew before authenticating.
+///
+/// # Arguments
+///
+/// * `onvif_url` - The main ONVIF device service URL
+pub async fn get_system_date_and_time(onvif_url: url::Url) -> Result<DateTime<Utc>> {
+    let response = client::send(onvif_url, Messages::GetSystemDateAndTime)
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+
+    parse_system_date_and_time(&response)
+}
+
+/// The UTCDateTime of a GetSystemDateAndTimeResponse
+pub(crate) fn parse_system_date_and_time(response: &[u8]) -> Result<DateTime<Utc>> {
+    let root = XmlNode::parse(response)?;
+    let utc = root
+        .find("UTCDateTime")
+        .ok_or_else(|| anyhow!("[System][get_system_date_and_time] No UTCDateTime in reply"))?;
+
+    let field = |parent: &str, name: &str| -> Option<u32> {
+        utc.child(parent)?.child_text(name)?.parse().ok()
+    };
+
+    let time = || {
+        NaiveDate::from_ymd_opt(
+            field("Date", "Year")? as i32,
+            field("Date", "Month")?,
+            field("Date", "Day")?,
+        )?
+        .and_hms_opt(
+            field("Time", "Hour")?,
+            field("Time", "Minute")?,
+            field("Time", "Second")?,
+        )
+    };
+
+    time()
+        .map(|time| time.and_utc())
+        .ok_or_else(|| anyhow!("[System][get_system_date_and_time] Malformed UTCDateTime"))
+}
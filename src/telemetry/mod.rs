@@ -0,0 +1,80 @@
+use metrics::{
+    counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram, Unit,
+};
+use std::time::Duration;
+
+/// SOAP requests sent, by `operation`
+pub const REQUESTS: &str = "onvif_requests_total";
+/// Requests without a successful answer, by `operation` and `reason`,
+/// the HTTP status code or "no_response"
+pub const REQUEST_FAILURES: &str = "onvif_request_failures_total";
+/// Time until the answer, retries included, by `operation`
+pub const REQUEST_DURATION: &str = "onvif_request_duration_seconds";
+/// SOAP faults seen by the parsers, by fault `code`, e.g. "ter:NotAuthorized"
+pub const SOAP_FAULTS: &str = "onvif_soap_faults_total";
+/// Events pulled by the camera manager, by `camera` host and port
+pub const EVENTS: &str = "onvif_events_total";
+/// Duration of each client::discover() round
+pub const DISCOVERY_DURATION: &str = "onvif_discovery_duration_seconds";
+/// Devices found by the last discovery round
+pub const DISCOVERED_DEVICES: &str = "onvif_discovered_devices";
+
+/// Registers units and help texts of the metrics of this crate with the
+/// installed recorder. Optional, the metrics are recorded either way.
+///
+/// # Examples
+///
+/// ```ignore
+/// metrics_exporter_prometheus::PrometheusBuilder::new().install()?;
+/// onvif_cam_rs::telemetry::describe();
+/// ```
+pub fn describe() {
+    describe_counter!(REQUESTS, Unit::Count, "SOAP requests sent to devices");
+    describe_counter!(
+        REQUEST_FAILURES,
+        Unit::Count,
+        "SOAP requests without a successful answer"
+    );
+    describe_histogram!(
+        REQUEST_DURATION,
+        Unit::Seconds,
+        "Time until a device answered, retries included"
+    );
+    describe_counter!(SOAP_FAULTS, Unit::Count, "SOAP faults answered by devices");
+    describe_counter!(EVENTS, Unit::Count, "Events pulled from managed cameras");
+    describe_histogram!(
+        DISCOVERY_DURATION,
+        Unit::Seconds,
+        "Duration of WS-Discovery rounds"
+    );
+    describe_gauge!(
+        DISCOVERED_DEVICES,
+        Unit::Count,
+        "Devices found by the last WS-Discovery round"
+    );
+}
+
+/// `failure` is None when the device answered with a success status
+pub(crate) fn request(operation: &str, elapsed: Duration, failure: Option<String>) {
+    let operation = operation.to_string();
+
+    counter!(REQUESTS, "operation" => operation.clone()).increment(1);
+    histogram!(REQUEST_DURATION, "operation" => operation.clone()).record(elapsed.as_secs_f64());
+
+    if let Some(reason) = failure {
+        counter!(REQUEST_FAILURES, "operation" => operation, "reason" => reason).increment(1);
+    }
+}
+
+pub(crate) fn fault(code: &str) {
+    counter!(SOAP_FAULTS, "code" => code.to_string()).increment(1);
+}
+
+pub(crate) fn events(camera: &str, count: usize) {
+    counter!(EVENTS, "camera" => camera.to_string()).increment(count as u64);
+}
+
+pub(crate) fn discovery(elapsed: Duration, devices: usize) {
+    histogram!(DISCOVERY_DURATION).record(elapsed.as_secs_f64());
+    gauge!(DISCOVERED_DEVICES).set(devices as f64);
+}
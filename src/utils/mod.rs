@@ -1,6 +1,10 @@
+pub mod xml;
+
+use ::xml::reader::{EventReader, XmlEvent};
+use chrono::{DateTime, SecondsFormat, Utc};
 use log::debug;
 use std::io::BufReader;
-use xml::reader::{EventReader, XmlEvent};
+use std::time::Duration;
 
 pub fn parse_soap(
     response: &[u8],
@@ -15,10 +19,7 @@ pub fn parse_soap(
     let buffer = BufReader::new(response);
     let parser = EventReader::new(buffer);
 
-    let mut parent_found = match parent {
-        Some(_) => false,
-        None => true,
-    };
+    let mut parent_found = parent.is_none();
 
     for e in parser {
         match e {
@@ -54,14 +55,12 @@ pub fn parse_soap(
                     element_found = false;
                 }
             }
-            Ok(XmlEvent::Characters(chars)) => {
-                if !is_attributes && element_found {
-                    debug!("CHARS found: {chars}");
-                    result.push(chars);
-
-                    if is_single {
-                        break;
-                    }
+            Ok(XmlEvent::Characters(chars)) if !is_attributes && element_found => {
+                debug!("CHARS found: {chars}");
+                result.push(chars);
+
+                if is_single {
+                    break;
                 }
             }
             Err(e) => {
@@ -75,3 +74,169 @@ pub fn parse_soap(
 
     result
 }
+
+/// Formats a Duration as an xs:duration string (e.g. "PT2.5S")
+/// as expected by the timeout fields of ONVIF requests
+pub fn xsd_duration(duration: Duration) -> String {
+    format!("PT{}S", duration.as_secs_f64())
+}
+
+/// Parses an xs:duration string (e.g. "PT60S", "P7D", "PT1H30M") into a Duration.
+/// Years and months are approximated as 365 and 30 days.
+pub fn parse_xsd_duration(value: &str) -> Option<Duration> {
+    let value = value.trim().strip_prefix('P')?;
+    let (date, time) = match value.split_once('T') {
+        Some((date, time)) => (date, time),
+        None => (value, ""),
+    };
+
+    let mut secs = 0f64;
+    let mut number = String::new();
+
+    for (part, is_time) in [(date, false), (time, true)] {
+        for c in part.chars() {
+            if c.is_ascii_digit() || c == '.' {
+                number.push(c);
+                continue;
+            }
+
+            let n: f64 = number.parse().ok()?;
+            number.clear();
+
+            secs += n * match (c, is_time) {
+                ('Y', false) => 365.0 * 86400.0,
+                ('M', false) => 30.0 * 86400.0,
+                ('W', false) => 7.0 * 86400.0,
+                ('D', false) => 86400.0,
+                ('H', true) => 3600.0,
+                ('M', true) => 60.0,
+                ('S', true) => 1.0,
+                _ => return None,
+            };
+        }
+    }
+
+    match number.is_empty() {
+        true => Duration::try_from_secs_f64(secs).ok(),
+        false => None,
+    }
+}
+
+/// Escapes text before it is inserted into a SOAP message
+pub fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Formats a UTC time as an xs:dateTime string (e.g. "2023-08-01T12:00:00Z")
+pub fn xsd_datetime(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Parses an xs:dateTime string into a UTC time
+pub fn parse_xsd_datetime(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value.trim())
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+/// Decodes %XX escapes, as found in ONVIF scope URIs (e.g. "Front%20Door")
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_xsd_duration_units() {
+        assert_eq!(parse_xsd_duration("PT60S"), Some(Duration::from_secs(60)));
+        assert_eq!(
+            parse_xsd_duration("PT1H30M"),
+            Some(Duration::from_secs(5400))
+        );
+        assert_eq!(
+            parse_xsd_duration("P7D"),
+            Some(Duration::from_secs(7 * 86400))
+        );
+        assert_eq!(
+            parse_xsd_duration("P1DT1S"),
+            Some(Duration::from_secs(86401))
+        );
+        assert_eq!(
+            parse_xsd_duration(" PT2.5S "),
+            Some(Duration::from_millis(2500))
+        );
+    }
+
+    #[test]
+    fn parse_xsd_duration_month_and_minute() {
+        // M is months before the T and minutes after it
+        assert_eq!(
+            parse_xsd_duration("P1M"),
+            Some(Duration::from_secs(30 * 86400))
+        );
+        assert_eq!(parse_xsd_duration("PT1M"), Some(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn parse_xsd_duration_invalid() {
+        assert_eq!(parse_xsd_duration("60S"), None);
+        assert_eq!(parse_xsd_duration("PT60"), None);
+        assert_eq!(parse_xsd_duration("P1H"), None);
+        assert_eq!(parse_xsd_duration("PTS"), None);
+    }
+
+    #[test]
+    fn parse_xsd_duration_overflow() {
+        assert_eq!(parse_xsd_duration("P99999999999999999999Y"), None);
+        assert_eq!(parse_xsd_duration(&format!("PT{}S", "9".repeat(400))), None);
+    }
+
+    #[test]
+    fn xsd_duration_round_trip() {
+        let duration = Duration::from_millis(2500);
+        assert_eq!(parse_xsd_duration(&xsd_duration(duration)), Some(duration));
+    }
+
+    #[test]
+    fn percent_decode_escapes() {
+        assert_eq!(percent_decode("Front%20Door"), "Front Door");
+        assert_eq!(percent_decode("%E6%9D%B1%E4%BA%AC"), "東京");
+        assert_eq!(percent_decode("a%2fb"), "a/b");
+    }
+
+    #[test]
+    fn percent_decode_keeps_invalid_escapes() {
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+        assert_eq!(percent_decode("%%41"), "%A");
+    }
+}
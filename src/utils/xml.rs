@@ -0,0 +1,109 @@
+use anyhow::{anyhow, Result};
+use std::io::BufReader;
+use xml::reader::{EventReader, XmlEvent};
+
+/// A very small DOM built from a SOAP response.
+/// parse_soap() is enough for flat replies, but lists of nested items
+/// (recordings, profiles, tracks) need to keep their structure.
+#[derive(Debug, Default, Clone)]
+pub struct XmlNode {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub text: String,
+    pub children: Vec<XmlNode>,
+}
+
+impl XmlNode {
+    /// Parses a complete XML document and returns its root element
+    pub fn parse(response: &[u8]) -> Result<XmlNode> {
+        let buffer = BufReader::new(response);
+        let parser = EventReader::new(buffer);
+        let mut stack: Vec<XmlNode> = Vec::new();
+
+        for e in parser {
+            match e? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => stack.push(XmlNode {
+                    name: name.local_name,
+                    attributes: attributes
+                        .into_iter()
+                        .map(|a| (a.name.local_name, a.value))
+                        .collect(),
+                    ..Default::default()
+                }),
+                XmlEvent::EndElement { .. } => {
+                    let node = stack
+                        .pop()
+                        .ok_or_else(|| anyhow!("[Utils][XmlNode] Unbalanced end element"))?;
+
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(node),
+                        None => return Ok(node),
+                    }
+                }
+                XmlEvent::Characters(chars) | XmlEvent::CData(chars) => {
+                    if let Some(node) = stack.last_mut() {
+                        node.text.push_str(&chars);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Err(anyhow!(
+            "[Utils][XmlNode] Document ended without a root element"
+        ))
+    }
+
+    /// First direct child with the given local name
+    pub fn child(&self, name: &str) -> Option<&XmlNode> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    /// All direct children with the given local name
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlNode> {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+
+    /// Trimmed text of the first direct child with the given local name
+    pub fn child_text(&self, name: &str) -> Option<String> {
+        self.child(name).map(|c| c.text.trim().to_string())
+    }
+
+    /// Value of an attribute by local name
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// First descendant (depth first, including self) with the given local name
+    pub fn find(&self, name: &str) -> Option<&XmlNode> {
+        if self.name == name {
+            return Some(self);
+        }
+
+        self.children.iter().find_map(|c| c.find(name))
+    }
+
+    /// All descendants with the given local name. Matches are not searched
+    /// for further nested matches.
+    pub fn find_all<'a>(&'a self, name: &str) -> Vec<&'a XmlNode> {
+        let mut result = Vec::new();
+        self.collect(name, &mut result);
+        result
+    }
+
+    fn collect<'a>(&'a self, name: &str, result: &mut Vec<&'a XmlNode>) {
+        if self.name == name {
+            result.push(self);
+            return;
+        }
+
+        for child in &self.children {
+            child.collect(name, result);
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use onvif_cam_rs::builder::camera::CameraBuilder;
+use onvif_cam_rs::device::camera::Camera;
+use onvif_cam_rs::device::StreamTransport;
+use onvif_cam_rs::mock::{MockCamera, MockProfile};
+use onvif_cam_rs::{events, media, ptz};
+
+use std::time::Duration;
+
+#[tokio::test]
+async fn build_all() -> anyhow::Result<()> {
+    let mock = MockCamera::default().start().await?;
+    let mut camera = Camera::new(mock.device());
+    camera.build_all().await?;
+
+    let info = camera.device_info();
+    assert_eq!(info.manufacturer.as_deref(), Some("Mock"));
+    assert_eq!(info.model.as_deref(), Some("MockCam 1000"));
+    assert_eq!(info.serial_num.as_deref(), Some("MOCK0001"));
+
+    let tokens: Vec<&str> = camera.profiles().iter().map(|p| p.token.as_str()).collect();
+    assert_eq!(tokens, ["profile_1", "profile_2"]);
+    assert_eq!(camera.scope_name().as_deref(), Some("Mock Camera"));
+    assert_eq!(
+        camera.stream.uri.as_deref(),
+        Some("rtsp://127.0.0.1:8554/profile_1")
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn get_stream_uri() -> anyhow::Result<()> {
+    let mock = MockCamera {
+        profiles: vec![MockProfile {
+            token: "main".to_string(),
+            name: "Main".to_string(),
+            encoding: "H265".to_string(),
+            width: 2560,
+            height: 1440,
+        }],
+        stream_uri: "rtsp://127.0.0.1:554/live/{token}".to_string(),
+        ..Default::default()
+    }
+    .start()
+    .await?;
+    let mut camera = Camera::new(mock.device());
+    camera.build_all().await?;
+
+    let uri = media::get_stream_uri(
+        media::media_url(&camera)?,
+        "main",
+        StreamTransport::RtspOverTcp,
+    )
+    .await?;
+
+    assert_eq!(uri.uri.as_deref(), Some("rtsp://127.0.0.1:554/live/main"));
+    assert!(!uri.invalid_after_connect);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn presets() -> anyhow::Result<()> {
+    let mock = MockCamera::default().start().await?;
+    let mut camera = Camera::new(mock.device());
+    camera.build_all().await?;
+
+    let presets = camera.ptz()?.presets().await?;
+    let presets: Vec<(&str, &str)> = presets
+        .iter()
+        .map(|p| (p.token.as_str(), p.name.as_str()))
+        .collect();
+    assert_eq!(presets, [("1", "Home"), ("2", "Door")]);
+
+    camera.ptz()?.goto_preset("2").await?;
+    ptz::goto_preset_named(&camera, "Home").await?;
+    assert!(ptz::goto_preset_named(&camera, "Garage").await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn events() -> anyhow::Result<()> {
+    let mock = MockCamera::default().start().await?;
+    let mut camera = Camera::new(mock.device());
+    camera.build_all().await?;
+
+    let subscription = events::create_pull_point_subscription(events::event_url(&camera)?).await?;
+    assert!(subscription.termination_time.is_some());
+
+    mock.push_event(
+        "tns1:VideoSource/MotionAlarm",
+        &[("Source", "vs1")],
+        &[("State", "true")],
+    );
+
+    let messages = subscription.pull(Duration::from_secs(2), 10).await?;
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].topic, "tns1:VideoSource/MotionAlarm");
+    assert_eq!(
+        messages[0].source,
+        [("Source".to_string(), "vs1".to_string())]
+    );
+    assert_eq!(messages[0].data_value("State"), Some("true"));
+
+    // Events already pulled are not delivered twice
+    let messages = subscription.pull(Duration::from_millis(200), 10).await?;
+    assert!(messages.is_empty());
+
+    subscription.unsubscribe().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn motion_events() -> anyhow::Result<()> {
+    let mock = MockCamera {
+        motion_every: Some(Duration::from_millis(100)),
+        ..Default::default()
+    }
+    .start()
+    .await?;
+    let mut camera = Camera::new(mock.device());
+    camera.build_all().await?;
+
+    let subscription = events::create_pull_point_subscription(events::event_url(&camera)?).await?;
+    let messages = subscription.pull(Duration::from_secs(2), 1).await?;
+
+    assert_eq!(
+        messages[0].topic,
+        "tns1:RuleEngine/CellMotionDetector/Motion"
+    );
+    assert!(messages[0].data_value("IsMotion").is_some());
+
+    Ok(())
+}
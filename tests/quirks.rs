@@ -0,0 +1,133 @@
+use onvif_cam_rs::auth::Credentials;
+use onvif_cam_rs::builder::camera::CameraBuilder;
+use onvif_cam_rs::client::{self, DiscoverOptions};
+use onvif_cam_rs::device::camera::Camera;
+use onvif_cam_rs::device::StreamTransport;
+use onvif_cam_rs::media;
+use onvif_cam_rs::mock::{MockCamera, Quirk};
+
+use std::time::Duration;
+
+#[tokio::test]
+async fn reject_unauthenticated() -> anyhow::Result<()> {
+    let mock = MockCamera {
+        quirks: vec![Quirk::RejectUnauthenticated(
+            vec!["GetProfiles".to_string()],
+        )],
+        ..Default::default()
+    }
+    .start()
+    .await?;
+
+    let mut camera = Camera::new(mock.device());
+    let e = camera.build_all().await.unwrap_err();
+    assert!(
+        matches!(e.downcast_ref(), Some(client::Error::AuthRequired { .. })),
+        "{e:#}"
+    );
+
+    camera.set_credentials(Credentials::new("admin", "secret"));
+    camera.build_all().await?;
+    assert_eq!(camera.profiles().len(), 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn soap11_only() -> anyhow::Result<()> {
+    let mock = MockCamera {
+        quirks: vec![Quirk::Soap11Only],
+        ..Default::default()
+    }
+    .start()
+    .await?;
+    assert!(!client::soap11(mock.url()));
+
+    // The first request is faulted with VersionMismatch, everything is
+    // then sent in SOAP 1.1
+    let mut camera = Camera::new(mock.device());
+    camera.build_all().await?;
+
+    assert!(client::soap11(mock.url()));
+    assert_eq!(camera.profiles().len(), 2);
+    assert_eq!(
+        camera.stream.uri.as_deref(),
+        Some("rtsp://127.0.0.1:8554/profile_1")
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn truncated_discovery_reply() -> anyhow::Result<()> {
+    let broken = MockCamera {
+        discoverable: true,
+        quirks: vec![Quirk::TruncatedDiscoveryReply(200)],
+        ..Default::default()
+    }
+    .start()
+    .await?;
+    let healthy = MockCamera {
+        discoverable: true,
+        ..Default::default()
+    }
+    .start()
+    .await?;
+
+    let devices = client::discover_with(DiscoverOptions {
+        timeout: Duration::from_secs(1),
+        ..Default::default()
+    })
+    .await?;
+    let urls: Vec<&url::Url> = devices.iter().map(|d| &d.url_onvif).collect();
+
+    assert!(urls.contains(&healthy.url()), "{urls:?}");
+    assert!(!urls.contains(&broken.url()), "{urls:?}");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn slow_response() -> anyhow::Result<()> {
+    let mock = MockCamera {
+        quirks: vec![Quirk::SlowResponse(Duration::from_millis(1500))],
+        ..Default::default()
+    }
+    .start()
+    .await?;
+
+    assert!(client::ping(mock.url().clone(), Duration::from_millis(500))
+        .await
+        .is_err());
+
+    let rtt = client::ping(mock.url().clone(), Duration::from_secs(5)).await?;
+    assert!(rtt >= Duration::from_millis(1500), "{rtt:?}");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn expiring_stream_uri() -> anyhow::Result<()> {
+    let mock = MockCamera {
+        quirks: vec![Quirk::ExpiringStreamUri(Duration::from_secs(30))],
+        ..Default::default()
+    }
+    .start()
+    .await?;
+
+    let mut camera = Camera::new(mock.device());
+    camera.build_all().await?;
+    assert_eq!(camera.stream.timeout, Some(Duration::from_secs(30)));
+
+    let uri = media::get_stream_uri(
+        media::media_url(&camera)?,
+        "profile_2",
+        StreamTransport::RtspOverTcp,
+    )
+    .await?;
+    assert_eq!(uri.uri.as_deref(), Some("rtsp://127.0.0.1:8554/profile_2"));
+    assert_eq!(uri.timeout, Some(Duration::from_secs(30)));
+    assert!(uri.issued.is_some());
+
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use onvif_cam_rs::client::{self, DiscoverOptions, DiscoveryEvent};
+use onvif_cam_rs::device::{Device, Scopes};
+use onvif_cam_rs::discovery::DiscoveryResponder;
+
+use futures::StreamExt;
+use std::time::Duration;
+use tokio::time::timeout;
+
+fn options() -> DiscoverOptions {
+    DiscoverOptions {
+        timeout: Duration::from_secs(1),
+        ..Default::default()
+    }
+}
+
+/// The device discovery found with this endpoint reference, other tests
+/// running responders at the same time
+fn find<'a>(devices: &'a [Device], endpoint: &str) -> Option<&'a Device> {
+    devices
+        .iter()
+        .find(|d| d.endpoint.as_deref() == Some(endpoint))
+}
+
+#[tokio::test]
+async fn discover_finds_responder() -> anyhow::Result<()> {
+    let xaddr: url::Url = "http://127.0.0.1:18080/onvif/device_service".parse()?;
+    let responder = DiscoveryResponder::new(xaddr.clone())
+        .scope("onvif://www.onvif.org/name/Virtual%20Cam")
+        .scope("onvif://www.onvif.org/hardware/VC-1")
+        .start()
+        .await?;
+
+    let devices = client::discover_with(options()).await?;
+    let device = find(&devices, responder.endpoint()).expect("responder not discovered");
+
+    assert_eq!(device.url_onvif, xaddr);
+    assert_eq!(device.xaddrs, [xaddr]);
+    assert_eq!(device.metadata_version, Some(1));
+
+    let scopes = Scopes::parse(&device.scopes);
+    assert_eq!(scopes.name.as_deref(), Some("Virtual Cam"));
+    assert_eq!(scopes.hardware.as_deref(), Some("VC-1"));
+    assert_eq!(scopes.profiles, ["Streaming"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn responder_ignores_other_types() -> anyhow::Result<()> {
+    let responder = DiscoveryResponder::new("http://127.0.0.1:18081/onvif/device_service".parse()?)
+        .start()
+        .await?;
+
+    // Finding no device at all is an error
+    let devices = client::discover_with(DiscoverOptions {
+        types: vec!["tds:Doorbell".to_string()],
+        ..options()
+    })
+    .await
+    .unwrap_or_default();
+    assert!(find(&devices, responder.endpoint()).is_none());
+
+    // An empty list of types asks every device to answer
+    let devices = client::discover_with(DiscoverOptions {
+        types: Vec::new(),
+        ..options()
+    })
+    .await?;
+    assert!(find(&devices, responder.endpoint()).is_some());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn watch_sees_hello_and_bye() -> anyhow::Result<()> {
+    let events = client::watch(options(), Duration::from_secs(60)).await?;
+    let mut events = std::pin::pin!(events);
+
+    let responder = DiscoveryResponder::new("http://127.0.0.1:18082/onvif/device_service".parse()?)
+        .start()
+        .await?;
+    let endpoint = responder.endpoint().to_string();
+
+    let joined = timeout(Duration::from_secs(5), async {
+        while let Some(event) = events.next().await {
+            match event {
+                DiscoveryEvent::Joined(device) if device.endpoint.as_deref() == Some(&endpoint) => {
+                    return Some(device);
+                }
+                _ => continue,
+            }
+        }
+        None
+    })
+    .await?;
+    assert!(joined.is_some());
+
+    responder.bye().await?;
+
+    let left = timeout(Duration::from_secs(5), async {
+        while let Some(event) = events.next().await {
+            match event {
+                DiscoveryEvent::Left {
+                    endpoint: e,
+                    device,
+                } if e == endpoint => {
+                    return device;
+                }
+                _ => continue,
+            }
+        }
+        None
+    })
+    .await?;
+    assert_eq!(
+        left.map(|d| d.url_onvif.to_string()).as_deref(),
+        Some("http://127.0.0.1:18082/onvif/device_service")
+    );
+
+    Ok(())
+}